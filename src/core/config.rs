@@ -1,11 +1,17 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-use crate::services::llm::LlmConfig;
+use crate::services::llm::{FallbackStrategy, LlmConfig};
+use crate::services::tts::azure::AzureTtsConfig;
 use crate::services::tts::edge::EdgeTtsConfig;
+use crate::services::tts::elevenlabs::ElevenLabsConfig;
+use crate::services::tts::external::ExternalTtsConfig;
 use crate::services::tts::gpt_sovits_config::GptSovitsConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::services::tts::polly::PollyConfig;
 use crate::services::tts::qwen3_tts::Qwen3TtsConfig;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -26,6 +32,286 @@ pub struct Config {
 
     #[serde(default)]
     pub audio: AudioConfig,
+
+    #[serde(default)]
+    pub workflow: WorkflowConfig,
+
+    #[serde(default)]
+    pub book_metadata: BookMetadata,
+
+    #[serde(default)]
+    pub output: OutputConfig,
+
+    #[serde(default)]
+    pub preprocessing: PreprocessingConfig,
+}
+
+/// Controls the `TextPreprocessor` chain `WorkflowManager` runs over each
+/// segment's text right before `tts.synthesize`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PreprocessingConfig {
+    /// Names of built-in `services::preprocessing::TextNormalizer`s to apply,
+    /// in listed order (e.g. `["ellipsis", "whitespace"]`). Empty by default
+    /// so existing configs keep synthesizing the script's text unchanged.
+    #[serde(default)]
+    pub enabled_normalizers: Vec<String>,
+}
+
+/// Metadata embedded as ID3 tags into each chapter's MP3 output (for
+/// MP3-based TTS providers). All fields are optional in practice — an
+/// empty title/author just means the tag isn't written for that frame.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BookMetadata {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub cover_image_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutputConfig {
+    #[serde(default)]
+    pub format: OutputFormat,
+
+    /// Write an `{chapter}.srt` subtitle file alongside each chapter's
+    /// audio, timed from the actual synthesized segment durations.
+    #[serde(default)]
+    pub generate_subtitles: bool,
+
+    /// After all chapters are processed, concatenate their MP3s into a
+    /// single `output/{book_title}.mp3` (or `output/combined.mp3` if no
+    /// title is set) via `WorkflowManager::combine_chapters`. Independent of
+    /// `format`'s M4B combination, which produces a `.m4b` with chapter
+    /// markers instead.
+    #[serde(default)]
+    pub combine: bool,
+
+    /// Template `process_chapter` renders (via `utils::template::render_template`)
+    /// to name each chapter's output file, before sanitization. Supports
+    /// `{stem}` (input filename without extension), `{ext}`, `{index}`
+    /// (1-based chapter position), `{total}` (chapter count), and `{title}`
+    /// (from `BookMetadata`, empty if unset). `combine_chapters`,
+    /// `combine_to_m4b` and `print_summary` still look chapters up by
+    /// `{stem}.{ext}` (see `WorkflowState::completed_chapters`), so a
+    /// template that renders a name other than a `{stem}`-based one will
+    /// confuse those steps; only `process_chapter`'s own per-chapter output
+    /// honors it fully today.
+    #[serde(default = "default_filename_template")]
+    pub filename_template: String,
+
+    /// Silence inserted between two chapters in `combine_chapters`'s output,
+    /// generated at the preceding chapter's own sample rate via
+    /// `utils::audio::generate_silence_wav`. `0` disables the gap.
+    #[serde(default = "default_chapter_gap_ms")]
+    pub chapter_gap_ms: u32,
+
+    /// Silence inserted before the first chapter in `combine_chapters`'s
+    /// output, e.g. to leave room for an intro. Configured separately from
+    /// `chapter_gap_ms` since a longer or shorter lead-in is common; `0`
+    /// disables it.
+    #[serde(default)]
+    pub before_first_chapter_ms: u32,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            generate_subtitles: false,
+            combine: false,
+            filename_template: default_filename_template(),
+            chapter_gap_ms: default_chapter_gap_ms(),
+            before_first_chapter_ms: 0,
+        }
+    }
+}
+
+fn default_filename_template() -> String {
+    "{stem}.{ext}".to_string()
+}
+
+fn default_chapter_gap_ms() -> u32 {
+    2000
+}
+
+/// Controls what `WorkflowManager::run` leaves behind in the output folder
+/// once all chapters are processed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// One MP3 per chapter (the default, and the only format before M4B
+    /// support was added).
+    #[default]
+    Mp3PerChapter,
+    /// A single M4B audiobook, combining all chapter MP3s with chapter
+    /// markers, produced via `WorkflowManager::combine_to_m4b`.
+    M4bSingleFile,
+}
+
+/// The audio container/codec `WorkflowManager::process_chapter` transcodes
+/// each chapter's merged audio into before writing it to the output folder.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioOutputFormat {
+    #[default]
+    Mp3,
+    Wav,
+    Ogg,
+}
+
+impl AudioOutputFormat {
+    /// File extension (without the leading dot) a chapter's merged audio is
+    /// written with in the output folder.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioOutputFormat::Mp3 => "mp3",
+            AudioOutputFormat::Wav => "wav",
+            AudioOutputFormat::Ogg => "ogg",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WorkflowConfig {
+    /// If a segment fails synthesis, log it and skip it instead of aborting
+    /// the whole chapter. Already-synthesized chunks are kept so a retry
+    /// only has to redo the segments that failed.
+    #[serde(default)]
+    pub continue_on_error: bool,
+
+    /// When a voice conflict is detected between characters in the global
+    /// character map, automatically reassign the secondary character to a
+    /// different random voice instead of just warning about it.
+    #[serde(default)]
+    pub auto_resolve_voice_conflicts: bool,
+
+    /// Maximum number of chapters to process concurrently. Values above 1
+    /// trade memory and API quota for wall-clock time; leave at 1 to process
+    /// chapters sequentially (with the usual "continue to next chapter?"
+    /// prompt when running interactively).
+    #[serde(default = "default_parallel_chapters")]
+    pub parallel_chapters: usize,
+
+    /// Invalidate a chapter's cached `segments.json` when the source `.txt`
+    /// has changed since it was generated, detected via a SHA-256 hash
+    /// stored alongside it. Disable if you've hand-edited `segments.json`
+    /// and don't want unrelated source tweaks to discard that work.
+    #[serde(default = "default_cache_validation")]
+    pub cache_validation: bool,
+
+    /// Run character analysis and script generation, write `segments.json`
+    /// and a readable `dry_run_report.txt`, then stop before spending any
+    /// TTS quota. Lets a user review the script before committing to
+    /// synthesis.
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Skip synthesis entirely and only run `WorkflowManager::combine_chapters`
+    /// over the chapters already recorded in `completed_chapters`. Useful for
+    /// re-combining after manually editing a chapter's MP3.
+    #[serde(default)]
+    pub combine_only: bool,
+
+    /// Shell command template run on each chapter's merged output, e.g. an
+    /// `ffmpeg`/`sox` filter. Supports `{input}`/`{output}` placeholders
+    /// (rendered via `utils::template::render_template`); the command must
+    /// write a complete file to `{output}`. Native-only (`process_chapter`
+    /// gates this with `#[cfg(not(target_arch = "wasm32"))]`) since there's
+    /// no subprocess support under wasm32.
+    #[serde(default)]
+    pub post_process_command: Option<String>,
+
+    /// Run `services::setup::run_character_editor` against the persisted
+    /// `character_map.json` before starting the workflow, letting a user
+    /// manually reassign character voices from a prior run's analysis.
+    /// Native-only (`main` gates the call with
+    /// `#[cfg(not(target_arch = "wasm32"))]`) since `inquire` prompts need a
+    /// real terminal.
+    #[serde(default)]
+    pub interactive_character_edit: bool,
+
+    /// POSTs a `services::notifications::WebhookPayload` to this URL on
+    /// chapter completion/failure and on overall workflow
+    /// completion/failure, so a CI pipeline running this headless can react
+    /// without polling the build folder. `None` disables notifications.
+    /// Native-only (`WorkflowManager` gates the call with
+    /// `#[cfg(not(target_arch = "wasm32"))]`) since `services::notifications`
+    /// isn't compiled for wasm32.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+
+    /// When true, pass the characters already present in `character_map` to
+    /// the analysis prompt and instruct the LLM to map detected speakers onto
+    /// those existing entries before creating new ones. Also enables a fuzzy
+    /// name-matching pass (`utils::text::find_fuzzy_character_match`) after
+    /// the analysis JSON is parsed, merging a new character whose name is
+    /// within edit distance 2 of an existing one (e.g. "英雄" vs "英雄男")
+    /// instead of letting the character map fork under slightly different
+    /// spellings across chapters.
+    #[serde(default = "default_include_existing_chars_in_analysis")]
+    pub include_existing_chars_in_analysis: bool,
+
+    /// Controls the order `WorkflowManager::run` processes chapter `.txt`
+    /// files in (epub-extracted chapters already have a natural reading
+    /// order and are left alone). See `services::workflow::sort_chapters`.
+    #[serde(default)]
+    pub chapter_sort: ChapterSort,
+
+    /// Segments whose LLM-reported `AudioSegment::confidence` falls below
+    /// this are flagged for review before synthesis: written to
+    /// `build/{chapter}/review.json`, and (unless `Config::unattended`)
+    /// presented one by one via `inquire::Confirm` so a user can decide
+    /// whether to keep or drop the speaker assignment. `0.5` (the default)
+    /// sits below the `0.7` that the script generator prompts ask the LLM to
+    /// report under when a speaker is unclear.
+    #[serde(default = "default_low_confidence_threshold")]
+    pub low_confidence_threshold: f32,
+
+    /// Forces `utils::text::decode_bytes` to decode chapter `.txt` files as
+    /// this encoding (e.g. `"gbk"`, `"big5"`) instead of auto-detecting,
+    /// for a source collection known to use one consistent legacy encoding.
+    /// `None` (the default) auto-detects each file independently.
+    #[serde(default)]
+    pub input_encoding: Option<String>,
+}
+
+fn default_include_existing_chars_in_analysis() -> bool {
+    true
+}
+
+fn default_low_confidence_threshold() -> f32 {
+    0.5
+}
+
+/// How `services::workflow::sort_chapters` orders chapter filenames before
+/// processing.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum ChapterSort {
+    /// Plain string sort. Works for zero-padded names (`chapter_001.txt`,
+    /// `chapter_002.txt`, ...) but misorders `Chapter2.txt`/`Chapter10.txt`
+    /// as 1, 10, 2.
+    #[default]
+    Lexicographic,
+    /// "Natural sort": runs of digits anywhere in the filename are compared
+    /// as numbers rather than character-by-character, so `Chapter2.txt`
+    /// sorts before `Chapter10.txt`.
+    NaturalNumeric,
+    /// An explicit ordering. Filenames listed here come first, in the order
+    /// given; any chapter file not mentioned is appended afterward, sorted
+    /// lexicographically. The order list lives on the variant itself (rather
+    /// than a separate always-present config field) since it's meaningless
+    /// for the other two variants.
+    Manual(Vec<String>),
+}
+
+fn default_cache_validation() -> bool {
+    true
+}
+
+fn default_parallel_chapters() -> usize {
+    1
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -42,6 +328,203 @@ pub struct AudioConfig {
     pub edge_tts: Option<EdgeTtsConfig>,
     pub gpt_sovits: Option<GptSovitsConfig>,
     pub qwen3_tts: Option<Qwen3TtsConfig>,
+    pub azure: Option<AzureTtsConfig>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub polly: Option<PollyConfig>,
+    pub elevenlabs: Option<ElevenLabsConfig>,
+    pub external: Option<ExternalTtsConfig>,
+
+    /// Detect natural pauses in each synthesized WAV chunk and log how many
+    /// sub-regions were found. A diagnostic aid for spotting segments that
+    /// are long enough to risk degraded TTS quality; does not yet re-split
+    /// the segment list automatically.
+    #[serde(default)]
+    pub post_synthesis_split: bool,
+
+    /// How many times to retry a single segment's `tts.synthesize` call
+    /// before giving up on the whole chapter. A transient TTS hiccup on one
+    /// segment shouldn't force a restart of the entire chapter.
+    #[serde(default = "default_max_segment_retries")]
+    pub max_segment_retries: usize,
+
+    /// Delay between segment synthesis retries.
+    #[serde(default = "default_segment_retry_delay_secs")]
+    pub segment_retry_delay_secs: u64,
+
+    /// Silence to insert between synthesized segments, for WAV-based
+    /// providers (see `TtsClient::is_mp3_output`).
+    #[serde(default)]
+    pub silence: SilenceConfig,
+
+    /// Linearly ramps the chapter's first synthesized chunk up from silence
+    /// over this many milliseconds (see `utils::audio::apply_fade_in`), for
+    /// WAV-based providers. `0` (the default) preserves the previous
+    /// behavior of no fade.
+    #[serde(default)]
+    pub fade_in_ms: u32,
+
+    /// Mirror of `fade_in_ms`: ramps the chapter's last synthesized chunk
+    /// down to silence over this many milliseconds before it's merged.
+    #[serde(default)]
+    pub fade_out_ms: u32,
+
+    /// Peak-normalize the merged chapter audio after `merge_audio_files`,
+    /// for WAV-based providers. Smooths out loudness differences between
+    /// TTS providers/voices.
+    #[serde(default)]
+    pub normalize: bool,
+
+    /// Target peak level in dBFS for `normalize`. Values above `0.0` are
+    /// clamped to `0.0` since that's full scale.
+    #[serde(default = "default_normalize_target_db")]
+    pub normalize_target_db: f32,
+
+    /// Maximum characters per segment sent to `tts.synthesize`. Segments
+    /// longer than this are split at sentence boundaries via
+    /// `services::script::split_long_segment` before synthesis, since some
+    /// providers (e.g. Edge TTS's ~3000-character SSML limit) reject or
+    /// silently mishandle long payloads. `None` disables splitting.
+    #[serde(default)]
+    pub max_segment_chars: Option<usize>,
+
+    /// Container/codec the merged chapter audio is transcoded to before
+    /// being written to the output folder. Independent of a provider's own
+    /// native output (e.g. `Qwen3TtsConfig::output_format`); this is the
+    /// final on-disk format for every provider.
+    #[serde(default)]
+    pub output_format: AudioOutputFormat,
+
+    /// Encoding quality passed to `utils::audio::encode_to_ogg` when
+    /// `output_format` is `Ogg`. Ranges `0.0`-`1.0`; higher is better
+    /// quality and larger files.
+    #[serde(default = "default_ogg_quality")]
+    pub ogg_quality: f32,
+
+    /// Maps a string that TTS engines routinely mispronounce (e.g. a
+    /// character name) to its corrected reading, applied via
+    /// `utils::text::apply_phonetic_corrections`/`apply_phonetic_corrections_ssml`
+    /// before synthesis. SSML providers (`edge-tts`, `azure`) render matches
+    /// as `<phoneme>` tags; other providers get a plain substitution.
+    #[serde(default)]
+    pub phonetic_corrections: HashMap<String, String>,
+
+    /// Explicit opt-in to `services::setup::HeadlessSetupStrategy` for voice
+    /// selection, even when `Config::unattended` is `false`. `unattended` on
+    /// its own already implies this; this field exists for users who want
+    /// unattended voice selection without also disabling every other
+    /// interactive prompt `unattended` gates elsewhere.
+    #[serde(default)]
+    pub auto_select_voices: bool,
+
+    /// Explicit Simplified/Traditional conversion applied to Chinese text
+    /// before synthesis (see `utils::text::convert_zh_variant`), currently
+    /// only read by `Qwen3TtsClient`. `None` leaves it unset; see
+    /// `resolved_zh_variant` for the derived default. Named `zh_variant`
+    /// rather than reusing `utils::text::ZhVariant` - that type describes a
+    /// *detected* script and has no "don't convert" case, while this one is
+    /// a user choice (`utils::text::ZhConversionMode`).
+    #[serde(default)]
+    pub zh_variant: Option<crate::utils::text::ZhConversionMode>,
+
+    /// Path (relative to the working directory) to an audio clip prepended to
+    /// every chapter's merged output, e.g. a recurring intro jingle. Copied
+    /// into `build_folder` once per run and reused across chapters; see
+    /// `services::workflow::prepare_clip`. `None` (the default) adds nothing.
+    #[serde(default)]
+    pub intro_clip: Option<String>,
+
+    /// Mirror of `intro_clip`, appended after the chapter's last segment
+    /// instead of prepended before the first.
+    #[serde(default)]
+    pub outro_clip: Option<String>,
+
+    /// Substrings (matched case-insensitively) identifying a voice as
+    /// suitable for `CharacterInfo::is_child` characters: Edge TTS matches
+    /// these against a voice's `friendly_name`, GPT-SoVITS against its
+    /// metadata `tags`.
+    #[serde(default = "default_child_voice_tags")]
+    pub child_voice_tags: Vec<String>,
+
+    /// Number of distinct voices in the chapter-mob pool (`章節路人(男)_1`,
+    /// `_2`, ... and the `(女)` equivalents) that unimportant same-chapter
+    /// characters are round-robin distributed across, instead of all sharing
+    /// the single `章節路人(男)`/`章節路人(女)` voice. `1` (the default)
+    /// preserves the single-voice behavior.
+    #[serde(default = "default_chapter_mob_pool_size")]
+    pub chapter_mob_pool_size: usize,
+
+    /// Secondary language codes (e.g. `"en"`) a chapter's text may switch
+    /// into besides the primary `language` - an English quote inside an
+    /// otherwise Chinese novel, for instance. `process_chapter` scans each
+    /// segment's script composition and, when it detects one of these, tags
+    /// `AudioSegment::detected_language` so providers can pick a
+    /// matching-locale voice instead of the primary one. Empty (the
+    /// default) disables detection entirely.
+    #[serde(default)]
+    pub additional_languages: Vec<String>,
+
+    /// Per-language narrator voice overrides, keyed by the same language
+    /// codes as `additional_languages` (e.g. `{"en": "en-US-JennyNeural"}`).
+    /// `process_chapter` consults this for a segment tagged with a matching
+    /// `AudioSegment::detected_language` before falling back to the active
+    /// provider's own single `narrator_voice`, so a bilingual novel can use a
+    /// distinct narrator voice for foreign-language passages. Empty (the
+    /// default) always falls back to the provider's `narrator_voice`.
+    #[serde(default)]
+    pub narrator_voices: HashMap<String, String>,
+}
+
+impl AudioConfig {
+    /// Picks the explicit `zh_variant`, or - if unset - derives a default
+    /// from `language`: `"zh-TW"` defaults to `Traditional`, anything else
+    /// (including plain `"zh"`) to `Simplified`, matching the conversion
+    /// this crate always applied before `zh_variant` existed.
+    pub fn resolved_zh_variant(&self) -> crate::utils::text::ZhConversionMode {
+        self.zh_variant.unwrap_or_else(|| {
+            if self.language == "zh-TW" {
+                crate::utils::text::ZhConversionMode::Traditional
+            } else {
+                crate::utils::text::ZhConversionMode::Simplified
+            }
+        })
+    }
+}
+
+fn default_normalize_target_db() -> f32 {
+    -3.0
+}
+
+fn default_child_voice_tags() -> Vec<String> {
+    vec!["child".to_string(), "kid".to_string(), "youth".to_string()]
+}
+
+fn default_chapter_mob_pool_size() -> usize {
+    1
+}
+
+fn default_ogg_quality() -> f32 {
+    0.5
+}
+
+/// Pause durations `WorkflowManager::process_chapter` inserts between
+/// synthesized segments before merging. All values default to `0`
+/// (no pause) so existing configs keep their current pacing.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SilenceConfig {
+    /// Gap inserted between two consecutive segments spoken by the same
+    /// speaker.
+    #[serde(default)]
+    pub between_segments_ms: u32,
+
+    /// Gap inserted between two consecutive segments with different
+    /// speakers.
+    #[serde(default)]
+    pub between_speakers_ms: u32,
+
+    /// Reserved for a future paragraph-boundary gap; `AudioSegment` doesn't
+    /// currently carry paragraph boundaries, so this isn't applied yet.
+    #[serde(default)]
+    pub paragraph_ms: u32,
 }
 
 fn default_input() -> String {
@@ -62,8 +545,32 @@ fn default_exclude_locales() -> Vec<String> {
 fn default_tts_provider() -> String {
     "edge-tts".to_string()
 }
+fn default_max_segment_retries() -> usize {
+    3
+}
+fn default_segment_retry_delay_secs() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub path: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
 
 impl Config {
+    /// Computes a flat list of differences between `self` and `other`,
+    /// serializing both to JSON and recursively comparing them. `path` is a
+    /// dotted path into the config (e.g. `"audio.edge-tts.narrator_voice"`).
+    pub fn diff(&self, other: &Config) -> Vec<ConfigDiff> {
+        let old = serde_json::to_value(self).unwrap_or(serde_json::Value::Null);
+        let new = serde_json::to_value(other).unwrap_or(serde_json::Value::Null);
+        let mut diffs = Vec::new();
+        diff_values("", &old, &new, &mut diffs);
+        diffs
+    }
+
     pub fn load() -> Result<Self> {
         let path = Path::new("config.yml");
         if !path.exists() {
@@ -76,6 +583,16 @@ impl Config {
         Ok(config)
     }
 
+    /// Loads a config from an arbitrary path, for comparing against the
+    /// active `config.yml` via `Config::diff`.
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+        let config: Config = serde_yaml_ng::from_str(&content)
+            .with_context(|| format!("Failed to parse {:?}", path))?;
+        Ok(config)
+    }
+
     pub fn save(&self) -> Result<()> {
         let content = serde_yaml_ng::to_string(self)?;
         fs::write("config.yml", content).context("Failed to write config.yml")?;
@@ -88,4 +605,209 @@ impl Config {
         fs::create_dir_all(&self.build_folder)?;
         Ok(())
     }
+
+    /// A fully-commented `config.yml` template for new users, covering every
+    /// top-level field and its default. Provider-specific TTS settings
+    /// (`edge-tts`, `azure`, etc.) are deliberately left out — `run_setup`
+    /// populates `audio.<provider>` interactively (or headlessly, see
+    /// `services::setup::HeadlessSetupStrategy`) on first run, so baking a
+    /// provider's full option list in here would just go stale. Hand-written
+    /// rather than derived from `serde_yaml_ng::to_string(&Config::default())`
+    /// since `Config` has no meaningful all-defaults value (`llm.provider`
+    /// has no default) and serialization can't carry `#` comments anyway.
+    pub fn generate_template() -> String {
+        format!(
+            r#"# novel2audiobook configuration.
+# Run `novel2audiobook` once after filling this in — it walks you through
+# picking TTS voices for whichever `audio.provider` you chose below.
+
+# Folder chapters are read from (`.txt` or `.epub` files).
+input_folder: {input_folder}
+# Folder finished chapter audio is written to.
+output_folder: {output_folder}
+# Folder for cached intermediate state (character maps, segments.json, etc.)
+# so a run can resume instead of starting over.
+build_folder: {build_folder}
+
+# Skip every interactive prompt (voice selection, "continue to next chapter?",
+# character editor) — for CI / headless environments. See also
+# `audio.auto_select_voices` if you only want headless voice selection.
+unattended: {unattended}
+
+llm:
+  # "gemini", "ollama", "openai", or "claude". Add the matching section below
+  # (e.g. `gemini: {{ api_key: "...", model: "gemini-1.5-flash" }}`).
+  provider: gemini
+  retry_count: {llm_retry_count}
+  retry_delay_seconds: {llm_retry_delay_seconds}
+  max_context_chars: {llm_max_context_chars}
+
+audio:
+  # "edge-tts" (free), "gpt_sovits", "qwen3_tts", "azure", "polly", or
+  # "elevenlabs".
+  provider: {audio_provider}
+  # BCP-47-ish prefix used to filter the provider's voice list (e.g. "zh" or
+  # "en").
+  language: {audio_language}
+  # Pick voices without prompting when `unattended` above is false.
+  auto_select_voices: {audio_auto_select_voices}
+
+workflow:
+  # How many chapters to process concurrently. 1 = sequential.
+  parallel_chapters: {workflow_parallel_chapters}
+  # Run analysis + script generation and stop before spending TTS quota, so
+  # you can review `segments.json` first.
+  dry_run: {workflow_dry_run}
+
+output:
+  # "mp3_per_chapter" or "m4b_single_file".
+  format: mp3_per_chapter
+  generate_subtitles: {output_generate_subtitles}
+  combine: {output_combine}
+
+book_metadata:
+  title: ""
+  author: ""
+
+preprocessing:
+  # Built-in `services::preprocessing::TextNormalizer` names to run over each
+  # segment's text before synthesis, in order (e.g. ["ellipsis", "whitespace"]).
+  enabled_normalizers: []
+"#,
+            input_folder = default_input(),
+            output_folder = default_output(),
+            build_folder = default_build(),
+            unattended = false,
+            // Mirrors `services::llm`'s own (private) `default_retry_count`/
+            // `default_retry_delay`/`default_max_context_chars`.
+            llm_retry_count = 3,
+            llm_retry_delay_seconds = 10,
+            llm_max_context_chars = 10000,
+            audio_provider = default_tts_provider(),
+            audio_language = default_language(),
+            audio_auto_select_voices = false,
+            workflow_parallel_chapters = default_parallel_chapters(),
+            workflow_dry_run = false,
+            output_generate_subtitles = false,
+            output_combine = false,
+        )
+    }
+}
+
+fn diff_values(
+    path: &str,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    out: &mut Vec<ConfigDiff>,
+) {
+    if old == new {
+        return;
+    }
+
+    if let (serde_json::Value::Object(old_map), serde_json::Value::Object(new_map)) = (old, new) {
+        let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let child_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            let old_child = old_map.get(key).unwrap_or(&serde_json::Value::Null);
+            let new_child = new_map.get(key).unwrap_or(&serde_json::Value::Null);
+            diff_values(&child_path, old_child, new_child, out);
+        }
+        return;
+    }
+
+    out.push(ConfigDiff {
+        path: path.to_string(),
+        old: old.clone(),
+        new: new.clone(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_config() -> Config {
+        Config {
+            input_folder: default_input(),
+            output_folder: default_output(),
+            build_folder: default_build(),
+            unattended: false,
+            llm: LlmConfig {
+                provider: "gemini".to_string(),
+                retry_count: 3,
+                retry_delay_seconds: 5,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: AudioConfig::default(),
+            workflow: WorkflowConfig::default(),
+            book_metadata: BookMetadata::default(),
+            output: OutputConfig::default(),
+            preprocessing: PreprocessingConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_diff_detects_changed_scalar_field() {
+        let old = base_config();
+        let mut new = base_config();
+        new.unattended = true;
+
+        let diffs = old.diff(&new);
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, "unattended");
+        assert_eq!(diffs[0].old, serde_json::json!(false));
+        assert_eq!(diffs[0].new, serde_json::json!(true));
+    }
+
+    #[test]
+    fn test_diff_detects_nested_field_by_dotted_path() {
+        let old = base_config();
+        let mut new = base_config();
+        new.audio.language = "en".to_string();
+
+        let diffs = old.diff(&new);
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, "audio.language");
+    }
+
+    #[test]
+    fn test_diff_is_empty_for_identical_configs() {
+        let old = base_config();
+        let new = base_config();
+
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_generate_template_parses_back_to_valid_config() {
+        let template = Config::generate_template();
+
+        let config: Config =
+            serde_yaml_ng::from_str(&template).expect("template should parse as Config");
+
+        assert_eq!(config.input_folder, default_input());
+        assert_eq!(config.llm.provider, "gemini");
+        assert_eq!(config.audio.provider, default_tts_provider());
+    }
 }
@@ -1,2 +1,8 @@
 pub mod config;
 pub mod state;
+
+// There is no `core::io` module or `Storage` trait in this codebase.
+// Chunk/segment I/O goes straight through `std`/`tokio::fs` (see
+// `services::workflow::WorkflowManager::cleanup_temp_files` and the
+// temp-file-then-rename writes in `process_chapter`), so there's no shared
+// read/write surface to add a `batch_read`/`batch_write` extension point to.
@@ -0,0 +1,126 @@
+//! Schema-version migrations for persisted `WorkflowState`/`CharacterMap`
+//! JSON files, so a `build/` directory from an older release doesn't hit a
+//! hard deserialization failure when a future schema change needs more than
+//! `#[serde(default)]` can express.
+//!
+//! Each migration step takes the raw `serde_json::Value` of one schema
+//! version and returns the `Value` for the next version up. `migrate_*`
+//! loops until the value is current, then deserializes normally.
+
+use super::{
+    CharacterMap, WorkflowState, CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+    CURRENT_WORKFLOW_STATE_SCHEMA_VERSION,
+};
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+fn schema_version_of(raw: &Value) -> u32 {
+    raw.get("schema_version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+fn set_schema_version(mut raw: Value, version: u32) -> Value {
+    if let Some(obj) = raw.as_object_mut() {
+        obj.insert("schema_version".to_string(), Value::from(version));
+    }
+    raw
+}
+
+/// Deserializes `raw` as a `WorkflowState`, migrating it to
+/// `CURRENT_WORKFLOW_STATE_SCHEMA_VERSION` first if it's older. A
+/// `state.json` with no `schema_version` field at all (every release before
+/// this field existed) is treated as version `0`.
+pub fn migrate_workflow_state(raw: Value) -> Result<WorkflowState> {
+    let mut raw = raw;
+    let mut version = schema_version_of(&raw);
+
+    while version < CURRENT_WORKFLOW_STATE_SCHEMA_VERSION {
+        raw = match version {
+            // Version 0 -> 1: the schema_version field itself didn't exist
+            // yet, so there's nothing to transform beyond stamping the
+            // version; every field added before this migration system
+            // existed already has its own `#[serde(default)]`.
+            0 => set_schema_version(raw, 1),
+            other => bail!("No migration defined for WorkflowState schema version {other}"),
+        };
+        version = schema_version_of(&raw);
+    }
+
+    Ok(serde_json::from_value(raw)?)
+}
+
+/// Same mechanism as `migrate_workflow_state`, for `character_map.json`.
+pub fn migrate_character_map(raw: Value) -> Result<CharacterMap> {
+    let mut raw = raw;
+    let mut version = schema_version_of(&raw);
+
+    while version < CURRENT_CHARACTER_MAP_SCHEMA_VERSION {
+        raw = match version {
+            0 => set_schema_version(raw, 1),
+            other => bail!("No migration defined for CharacterMap schema version {other}"),
+        };
+        version = schema_version_of(&raw);
+    }
+
+    Ok(serde_json::from_value(raw)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_workflow_state_stamps_missing_schema_version() {
+        let old = serde_json::json!({
+            "completed_chapters": ["ch1.txt"],
+            "chapter_hashes": {},
+            "chapter_mob_voices": {}
+        });
+
+        let state = migrate_workflow_state(old).unwrap();
+
+        assert_eq!(state.schema_version, CURRENT_WORKFLOW_STATE_SCHEMA_VERSION);
+        assert_eq!(state.completed_chapters, vec!["ch1.txt".to_string()]);
+    }
+
+    #[test]
+    fn test_migrate_workflow_state_is_noop_when_already_current() {
+        let current = serde_json::json!({
+            "schema_version": CURRENT_WORKFLOW_STATE_SCHEMA_VERSION,
+            "completed_chapters": [],
+            "chapter_hashes": {},
+            "chapter_mob_voices": {}
+        });
+
+        let state = migrate_workflow_state(current).unwrap();
+
+        assert_eq!(state.schema_version, CURRENT_WORKFLOW_STATE_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_workflow_state_passes_future_schema_version_through() {
+        let future = serde_json::json!({
+            "schema_version": CURRENT_WORKFLOW_STATE_SCHEMA_VERSION + 100,
+            "completed_chapters": [],
+        });
+
+        // A version newer than anything this build knows about isn't < the
+        // current constant, so the migration loop never runs and
+        // deserialization proceeds as-is (and succeeds, since the extra
+        // fields this hypothetical future version added aren't present
+        // here). This documents that forward-compatibility is NOT handled
+        // by this mechanism, only backward migrations are.
+        assert!(migrate_workflow_state(future).is_ok());
+    }
+
+    #[test]
+    fn test_migrate_character_map_stamps_missing_schema_version() {
+        let old = serde_json::json!({
+            "characters": {}
+        });
+
+        let map = migrate_character_map(old).unwrap();
+
+        assert_eq!(map.schema_version, CURRENT_CHARACTER_MAP_SCHEMA_VERSION);
+        assert!(map.characters.is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+pub mod migrations;
+
+use crate::services::script::AudioSegment;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Current `WorkflowState::schema_version`. Bump this and add a branch to
+/// `migrations::migrate_workflow_state` whenever a new release needs to
+/// transform an older `state.json` rather than just defaulting a new field.
+pub const CURRENT_WORKFLOW_STATE_SCHEMA_VERSION: u32 = 1;
+
+/// Current `CharacterMap::schema_version`. See
+/// `CURRENT_WORKFLOW_STATE_SCHEMA_VERSION` for the same mechanism applied to
+/// `character_map.json`.
+pub const CURRENT_CHARACTER_MAP_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct WorkflowState {
+    /// Defaults to `0` (not `CURRENT_WORKFLOW_STATE_SCHEMA_VERSION`) so a
+    /// `state.json` saved before this field existed deserializes as version
+    /// 0 and `migrations::migrate_workflow_state` upgrades it; callers that
+    /// construct a *fresh* `WorkflowState` should set this explicitly to
+    /// `CURRENT_WORKFLOW_STATE_SCHEMA_VERSION` instead of relying on
+    /// `Default`.
+    #[serde(default)]
+    pub schema_version: u32,
+
+    pub completed_chapters: Vec<String>,
+
+    /// SHA-256 hash of each completed chapter's source text, keyed by
+    /// filename. `#[serde(default)]` keeps state files saved before this
+    /// field existed loadable.
+    #[serde(default)]
+    pub chapter_hashes: HashMap<String, String>,
+
+    /// Voice IDs assigned to chapter mob characters (e.g. `章節路人(男)`),
+    /// keyed by chapter filename then mob character name. Reusing these on
+    /// rerun keeps a single-chapter reprocess (e.g. after a TTS failure)
+    /// from assigning a different mob voice than the original run.
+    #[serde(default)]
+    pub chapter_mob_voices: HashMap<String, HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CharacterMap {
+    /// See `WorkflowState::schema_version`; same defaulting/migration
+    /// convention applies here.
+    #[serde(default)]
+    pub schema_version: u32,
+
+    pub characters: HashMap<String, CharacterInfo>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct CharacterInfo {
+    pub gender: String, // "Male", "Female"
+    pub voice_id: Option<String>,
+    pub description: Option<String>, // Context for LLM
+    #[serde(default)]
+    pub is_protagonist: bool,
+
+    /// Speaking rate multiplier (`1.0` = normal speed). `None` means
+    /// unmodified synthesis, same as `Some(1.0)`.
+    #[serde(default)]
+    pub speed: Option<f32>,
+
+    /// Pitch shift in semitones (`0.0` = unmodified). SSML-based providers
+    /// (Edge TTS, Azure) apply this via a `<prosody>` wrapper.
+    #[serde(default)]
+    pub pitch_semitones: Option<f32>,
+
+    /// Set by LLM character analysis when the character is a child, whose
+    /// voice is distinctly higher-pitched than an adult's. Read by
+    /// `EdgeTtsClient::resolve_voice`/GPT-SoVITS voice selection to prefer a
+    /// voice tagged with one of `AudioConfig::child_voice_tags`.
+    #[serde(default)]
+    pub is_child: bool,
+}
+
+/// Composition report for a single chapter's segments, written to
+/// `build/{chapter}/stats.json` so a user can see what a chapter is made of
+/// before synthesis runs. Narrator lines are identified the same way the
+/// rest of the workflow treats an unassigned speaker: `None` or the literal
+/// `"旁白"`.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct ChapterStats {
+    pub total_segments: usize,
+    pub dialogue_segments: usize,
+    pub narrator_segments: usize,
+    pub unique_speakers: usize,
+    pub total_characters: usize,
+    pub average_segment_length: f32,
+    pub speaker_segment_counts: HashMap<String, usize>,
+}
+
+impl ChapterStats {
+    pub fn from_segments(segments: &[AudioSegment]) -> Self {
+        let total_segments = segments.len();
+        let mut speaker_segment_counts: HashMap<String, usize> = HashMap::new();
+        let mut narrator_segments = 0;
+        let mut total_characters = 0;
+
+        for segment in segments {
+            let speaker = segment.speaker.clone().unwrap_or_else(|| "旁白".to_string());
+            if speaker == "旁白" {
+                narrator_segments += 1;
+            }
+            total_characters += segment.text.chars().count();
+            *speaker_segment_counts.entry(speaker).or_insert(0) += 1;
+        }
+
+        let average_segment_length = if total_segments > 0 {
+            total_characters as f32 / total_segments as f32
+        } else {
+            0.0
+        };
+
+        Self {
+            total_segments,
+            dialogue_segments: total_segments - narrator_segments,
+            narrator_segments,
+            unique_speakers: speaker_segment_counts.len(),
+            total_characters,
+            average_segment_length,
+            speaker_segment_counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(speaker: Option<&str>, text: &str) -> AudioSegment {
+        AudioSegment {
+            speaker: speaker.map(|s| s.to_string()),
+            text: text.to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn test_chapter_stats_from_segments_is_accurate() {
+        let segments = vec![
+            segment(Some("旁白"), "四字敘述"),
+            segment(Some("Hero"), "Hi"),
+            segment(Some("Hero"), "there"),
+            segment(None, "Unnamed narration"),
+        ];
+
+        let stats = ChapterStats::from_segments(&segments);
+
+        assert_eq!(stats.total_segments, 4);
+        assert_eq!(stats.narrator_segments, 2);
+        assert_eq!(stats.dialogue_segments, 2);
+        assert_eq!(stats.unique_speakers, 2);
+        assert_eq!(stats.total_characters, 4 + 2 + 5 + 17);
+        assert_eq!(stats.speaker_segment_counts.get("Hero"), Some(&2));
+        assert_eq!(stats.speaker_segment_counts.get("旁白"), Some(&2));
+        assert!((stats.average_segment_length - (stats.total_characters as f32 / 4.0)).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_chapter_stats_from_segments_empty() {
+        let stats = ChapterStats::from_segments(&[]);
+
+        assert_eq!(stats.total_segments, 0);
+        assert_eq!(stats.average_segment_length, 0.0);
+        assert!(stats.speaker_segment_counts.is_empty());
+    }
+}
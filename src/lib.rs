@@ -1,3 +1,26 @@
 pub mod core;
 pub mod services;
 pub mod utils;
+
+// There is no Leptos/WASM UI crate in this repository (no `leptos`,
+// `wasm-bindgen`, or `web_sys` dependency, and no `App`/`StorageControl`/
+// `WebStorage` types to hang a `CharacterMapEditor` component off of), so a
+// character-voice-editor UI component can't be added here. The native
+// equivalent lives in `services::setup::run_character_editor`, driven from
+// the CLI instead of a browser.
+//
+// The same gap rules out browser-side `FileUpload`/`FileDownload`
+// components (no `web_sys::FileReader`/`Url::create_object_url_with_blob`
+// bindings, no `WebStorage` to write `input/{filename}` into). Chapter
+// input/output on this tree goes through `input_folder`/`output_folder` on
+// the local filesystem instead (see `WorkflowManager::run`).
+//
+// Same reason a browser `SettingsPanel` (reactive `Config` editing via
+// Leptos `provide_context`) isn't possible here either; `Config` is edited
+// through `config.yml` on disk and `services::setup::run_setup`'s
+// terminal prompts instead.
+//
+// Same reason a `DownloadObserver`-driven progress bar for Qwen3 TTS's
+// on-demand voice file download can't be wired into a Leptos reactive
+// signal here either — `Qwen3TtsClient::synthesize` fetches a missing voice
+// file through `hf_hub` on demand, with no UI to report progress to.
@@ -1,11 +1,99 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use novel2audiobook::core::config::Config;
-use novel2audiobook::services::{llm, setup, tts, workflow::WorkflowManager};
+use novel2audiobook::core::state::{CharacterMap, CURRENT_CHARACTER_MAP_SCHEMA_VERSION};
+use novel2audiobook::services::script::AudioSegment;
+use novel2audiobook::services::{llm, setup, tts, workflow};
+use novel2audiobook::services::workflow::WorkflowManager;
+use std::collections::HashMap;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
 
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--diff-config") {
+        let old_config_path = args
+            .get(pos + 1)
+            .ok_or_else(|| anyhow::anyhow!("--diff-config requires a path to the old config.yml"))?;
+        return print_config_diff(Path::new(old_config_path));
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("reset") {
+        let chapter_pos = args
+            .iter()
+            .position(|a| a == "--chapter")
+            .ok_or_else(|| anyhow::anyhow!("reset requires --chapter <filename>"))?;
+        let chapter = args
+            .get(chapter_pos + 1)
+            .ok_or_else(|| anyhow::anyhow!("--chapter requires a filename"))?;
+        return reset_chapter(chapter).await;
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("estimate") {
+        return estimate_cost().await;
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("init") {
+        let force = args.iter().any(|a| a == "--force");
+        return init_config(force);
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("voices") {
+        return list_voices(&args).await;
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("preview-voice") {
+        let voice_id_pos = args
+            .iter()
+            .position(|a| a == "--voice-id")
+            .ok_or_else(|| anyhow::anyhow!("preview-voice requires --voice-id <id>"))?;
+        let voice_id = args
+            .get(voice_id_pos + 1)
+            .ok_or_else(|| anyhow::anyhow!("--voice-id requires a value"))?
+            .clone();
+        let text = args
+            .iter()
+            .position(|a| a == "--text")
+            .and_then(|pos| args.get(pos + 1))
+            .cloned();
+        let output = args
+            .iter()
+            .position(|a| a == "--output")
+            .and_then(|pos| args.get(pos + 1))
+            .cloned()
+            .unwrap_or_else(|| "preview.mp3".to_string());
+        let style = args
+            .iter()
+            .position(|a| a == "--style")
+            .and_then(|pos| args.get(pos + 1))
+            .cloned();
+        return preview_voice(&voice_id, text, &output, style).await;
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("assign-voices") {
+        let mapping_pos = args
+            .iter()
+            .position(|a| a == "--mapping")
+            .ok_or_else(|| anyhow::anyhow!("assign-voices requires --mapping <path>"))?;
+        let mapping_path = args
+            .get(mapping_pos + 1)
+            .ok_or_else(|| anyhow::anyhow!("--mapping requires a path"))?;
+        let validate = !args.iter().any(|a| a == "--no-validate");
+        return assign_voices(mapping_path, validate).await;
+    }
+
+    if args.get(1).map(|a| a.as_str()) == Some("export") {
+        let output = args
+            .iter()
+            .position(|a| a == "--output")
+            .and_then(|pos| args.get(pos + 1))
+            .cloned()
+            .unwrap_or_else(|| "project.zip".to_string());
+        return export_project(&output).await;
+    }
+
     // 1. Load or Create Config
     let mut config = match Config::load() {
         Ok(cfg) => cfg,
@@ -16,6 +104,10 @@ async fn main() -> Result<()> {
         }
     };
 
+    if args.iter().any(|a| a == "--dry-run") {
+        config.workflow.dry_run = true;
+    }
+
     config.ensure_directories()?;
 
     // 2. Initialize LLM
@@ -24,12 +116,303 @@ async fn main() -> Result<()> {
     // 3. Interactive Setup (Voice Selection)
     setup::run_setup(&mut config, Some(llm.as_ref())).await?;
 
+    #[cfg(not(target_arch = "wasm32"))]
+    if config.workflow.interactive_character_edit {
+        let char_map_path = Path::new(&config.build_folder).join("character_map.json");
+        if char_map_path.exists() {
+            let content = std::fs::read_to_string(&char_map_path)?;
+            let mut char_map: novel2audiobook::core::state::CharacterMap =
+                serde_json::from_str(&content)?;
+            setup::run_character_editor(&config, &mut char_map, Some(llm.as_ref())).await?;
+        } else {
+            println!(
+                "No character map found yet; run once to generate character analysis before editing voices."
+            );
+        }
+    }
+
     // 4. Initialize TTS
     let tts = tts::create_tts_client(&config, Some(llm.as_ref())).await?;
 
     // 5. Initialize and Run Workflow
-    let mut manager = WorkflowManager::new(config.clone(), llm, tts)?;
+    let cancellation = CancellationToken::new();
+    let ctrl_c_token = cancellation.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("\nCancellation requested, finishing in-flight segments...");
+            ctrl_c_token.cancel();
+        }
+    });
+
+    let mut manager = WorkflowManager::new(config.clone(), llm, tts)
+        .await?
+        .with_cancellation(cancellation);
     manager.run().await?;
 
     Ok(())
 }
+
+/// Handles `novel2audiobook reset --chapter <filename>`: loads `config.yml`
+/// and the persisted workflow state, then calls
+/// `WorkflowManager::reset_chapter` so the next normal `run` re-synthesizes
+/// `filename` instead of skipping it as already completed.
+async fn reset_chapter(filename: &str) -> Result<()> {
+    let config = Config::load()?;
+    let llm = llm::create_llm(&config.llm)?;
+    let tts = tts::create_tts_client(&config, Some(llm.as_ref())).await?;
+
+    let mut manager = WorkflowManager::new(config, llm, tts).await?;
+    manager.reset_chapter(filename).await?;
+
+    println!("Chapter '{}' will be reprocessed on the next run.", filename);
+    Ok(())
+}
+
+/// Handles `novel2audiobook export [--output <path>]`: packages `config.yml`
+/// and the build-folder metadata needed to resume this project elsewhere
+/// (character map, workflow state, every chapter's `segments.json`) into a
+/// ZIP archive, deliberately excluding synthesized audio. `--output`
+/// defaults to `project.zip`.
+async fn export_project(output: &str) -> Result<()> {
+    let config = Config::load()?;
+    let llm = llm::create_llm(&config.llm)?;
+    let tts = tts::create_tts_client(&config, Some(llm.as_ref())).await?;
+
+    let manager = WorkflowManager::new(config, llm, tts).await?;
+    manager.export_project(output).await?;
+
+    println!("Exported project to {}", output);
+    Ok(())
+}
+
+/// Handles `novel2audiobook assign-voices --mapping <path> [--no-validate]`:
+/// applies a character-name -> voice-ID mapping from an external JSON file to
+/// the persisted `character_map.json`, for users who already know their voice
+/// assignments (e.g. a book series with recurring characters) and want to
+/// skip re-running LLM character analysis. Characters in the mapping that
+/// aren't already in `character_map.json` are reported as warnings, not
+/// errors, since one typo shouldn't abort the rest of the import. Unless
+/// `--no-validate` is passed, each assigned voice ID is checked against the
+/// configured TTS provider's current voice list and mismatches are warned
+/// about too.
+async fn assign_voices(mapping_path: &str, validate: bool) -> Result<()> {
+    let config = Config::load()?;
+
+    let char_map_path = Path::new(&config.build_folder).join("character_map.json");
+    let mut char_map = workflow::WorkflowManager::load_character_map_from_build_dir(
+        &config.build_folder,
+    )
+    .with_context(|| format!("Failed to load character map from {:?}", char_map_path))?;
+
+    let mapping_content = std::fs::read_to_string(mapping_path)
+        .with_context(|| format!("Failed to read voice mapping file {:?}", mapping_path))?;
+    let assignments: HashMap<String, String> = serde_json::from_str(&mapping_content)
+        .with_context(|| format!("Failed to parse voice mapping JSON in {:?}", mapping_path))?;
+
+    let updated = workflow::merge_character_assignments(&mut char_map, &assignments);
+    for name in assignments.keys() {
+        if !updated.contains(name) {
+            eprintln!(
+                "Warning: '{}' not found in character_map.json; skipped",
+                name
+            );
+        }
+    }
+
+    if validate {
+        let llm = llm::create_llm(&config.llm)?;
+        let tts = tts::create_tts_client(&config, Some(llm.as_ref())).await?;
+        let voices = tts.list_voices().await?;
+        let known_ids: std::collections::HashSet<&str> =
+            voices.iter().map(|v| v.short_name.as_str()).collect();
+
+        for name in &updated {
+            if let Some(voice_id) = char_map.characters[name].voice_id.as_deref() {
+                if !known_ids.contains(voice_id) {
+                    eprintln!(
+                        "Warning: voice '{}' assigned to '{}' was not found in the current TTS provider's voice list",
+                        voice_id, name
+                    );
+                }
+            }
+        }
+    }
+
+    std::fs::write(&char_map_path, serde_json::to_string_pretty(&char_map)?)?;
+    println!(
+        "Updated {} character voice assignment(s) in {:?}",
+        updated.len(),
+        char_map_path
+    );
+    Ok(())
+}
+
+/// Handles `novel2audiobook preview-voice --voice-id <id> [--text "..."] [--output <file>] [--style <style>]`:
+/// synthesizes a single synthetic `AudioSegment` directly through the
+/// configured TTS provider, bypassing `WorkflowManager` entirely, so a user
+/// can hear a voice before assigning it to a character. `--text` defaults to
+/// a locale-appropriate test sentence picked from `config.audio.language`;
+/// `--output` defaults to `preview.mp3`.
+async fn preview_voice(voice_id: &str, text: Option<String>, output: &str, style: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let llm = llm::create_llm(&config.llm)?;
+    let tts = tts::create_tts_client(&config, Some(llm.as_ref())).await?;
+
+    let voices = tts.list_voices().await?;
+    if !voices.iter().any(|v| v.short_name == voice_id) {
+        return Err(anyhow::anyhow!(
+            "Unknown voice ID '{}'; run `novel2audiobook voices` to list available voices",
+            voice_id
+        ));
+    }
+
+    let segment = AudioSegment {
+        text: text.unwrap_or_else(|| default_preview_text(&config.audio.language)),
+        speaker: None,
+        style,
+        voice_id: Some(voice_id.to_string()),
+        detected_language: None,
+        confidence: Some(1.0),
+    };
+    let char_map = CharacterMap {
+        schema_version: CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+        characters: HashMap::new(),
+    };
+
+    let audio = tts
+        .synthesize(&segment, &char_map, &[])
+        .await
+        .with_context(|| format!("Failed to synthesize preview for voice '{}'", voice_id))?;
+
+    std::fs::write(output, &audio)
+        .with_context(|| format!("Failed to write preview audio to {:?}", output))?;
+
+    println!("Wrote {} byte preview to {:?}", audio.len(), output);
+    Ok(())
+}
+
+/// Test sentence for `preview_voice` when `--text` isn't given, picked by
+/// `config.audio.language` prefix the same way other locale-sensitive
+/// behavior in this crate branches on it.
+fn default_preview_text(language: &str) -> String {
+    if language.starts_with("zh") {
+        "這是一段語音預覽，讓您在指派角色前先聽聽這個聲音。".to_string()
+    } else {
+        "This is a voice preview, so you can hear what this voice sounds like before assigning it.".to_string()
+    }
+}
+
+/// Handles `novel2audiobook voices [--gender Male|Female] [--locale <prefix>] [--json]`:
+/// lists the voices available for the configured TTS provider, without
+/// running interactive setup or initializing an LLM/full TTS client - only
+/// `tts::fetch_voice_list`'s lightweight per-provider API call. Filtered the
+/// same way `WorkflowManager::process_chapter` filters its own voice list
+/// (`config.audio.language`/`exclude_locales`), plus `--gender`/`--locale`
+/// for narrowing the printed results. This repo parses subcommands by hand
+/// rather than with `clap` (see the `reset`/`init`/`assign-voices` handlers
+/// above), so `voices` follows the same convention instead of introducing a
+/// new dependency for one subcommand.
+async fn list_voices(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+
+    let gender = args
+        .iter()
+        .position(|a| a == "--gender")
+        .and_then(|pos| args.get(pos + 1))
+        .cloned();
+    if let Some(g) = &gender {
+        if g != "Male" && g != "Female" {
+            return Err(anyhow::anyhow!("--gender must be 'Male' or 'Female'"));
+        }
+    }
+    let locale_prefix = args
+        .iter()
+        .position(|a| a == "--locale")
+        .and_then(|pos| args.get(pos + 1))
+        .cloned();
+    let json_output = args.iter().any(|a| a == "--json");
+
+    let voices = tts::fetch_voice_list(&config, None).await?;
+    let voices = tts::filter_voices(
+        voices,
+        &config.audio.language,
+        &config.audio.exclude_locales,
+        gender.as_deref(),
+        locale_prefix.as_deref(),
+    );
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&voices)?);
+        return Ok(());
+    }
+
+    println!("{:<30} {:<8} {:<8} {}", "ID", "GENDER", "LOCALE", "NAME");
+    for voice in &voices {
+        println!(
+            "{:<30} {:<8} {:<8} {}",
+            voice.short_name,
+            voice.gender,
+            voice.locale,
+            voice.friendly_name.as_deref().unwrap_or("")
+        );
+    }
+
+    Ok(())
+}
+
+/// Handles `novel2audiobook init [--force]`: writes `Config::generate_template`
+/// to `config.yml` in the current directory. Prompts for confirmation before
+/// overwriting an existing `config.yml`, unless `--force` is passed.
+fn init_config(force: bool) -> Result<()> {
+    let path = Path::new("config.yml");
+    if path.exists() && !force {
+        let overwrite = inquire::Confirm::new("config.yml already exists. Overwrite?")
+            .with_default(false)
+            .prompt()?;
+        if !overwrite {
+            println!("Aborted; config.yml left unchanged.");
+            return Ok(());
+        }
+    }
+
+    std::fs::write(path, Config::generate_template())?;
+    println!("Wrote config.yml. Fill in llm/audio settings, then run `novel2audiobook`.");
+    Ok(())
+}
+
+/// Handles `novel2audiobook estimate`: loads `config.yml` and the persisted
+/// workflow state, sums `WorkflowManager::estimate_total_cost` across all
+/// unprocessed chapters, and prints the estimate without synthesizing
+/// anything.
+async fn estimate_cost() -> Result<()> {
+    let config = Config::load()?;
+    let llm = llm::create_llm(&config.llm)?;
+    let tts = tts::create_tts_client(&config, Some(llm.as_ref())).await?;
+
+    let manager = WorkflowManager::new(config, llm, tts).await?;
+    let total = manager.estimate_total_cost().await?;
+
+    println!("Estimated synthesis cost: ${:.2}", total);
+    Ok(())
+}
+
+/// Loads `old_config_path` and the active `config.yml`, diffs them, and
+/// prints a human-readable unified diff of what changed between the two.
+fn print_config_diff(old_config_path: &Path) -> Result<()> {
+    let old_config = Config::load_from_path(old_config_path)?;
+    let new_config = Config::load()?;
+
+    let diffs = old_config.diff(&new_config);
+    if diffs.is_empty() {
+        println!("No differences found.");
+        return Ok(());
+    }
+
+    for diff in diffs {
+        println!("--- {}", diff.path);
+        println!("-{}", diff.old);
+        println!("+{}", diff.new);
+    }
+
+    Ok(())
+}
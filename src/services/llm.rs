@@ -1,7 +1,13 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use futures_util::{stream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+use std::pin::Pin;
 
 // --- Configs ---
 
@@ -12,9 +18,109 @@ pub struct LlmConfig {
     pub retry_count: usize,
     #[serde(default = "default_retry_delay")]
     pub retry_delay_seconds: u64,
+    /// If true, the chapter text embedded in the character-analysis prompt is
+    /// truncated to `max_context_chars` instead of the built-in default.
+    #[serde(default)]
+    pub truncate_analysis_context: bool,
+    #[serde(default = "default_max_context_chars")]
+    pub max_context_chars: usize,
+    /// When a chapter's text exceeds `max_context_chars`, split it into
+    /// overlapping windows (instead of truncating) for both character
+    /// analysis and script generation, so the whole chapter is still seen by
+    /// models with small context windows. Each window after the first
+    /// overlaps the previous one by `window_overlap_chars` characters and
+    /// includes a "previously identified characters" context block.
+    #[serde(default)]
+    pub window_long_chapters: bool,
+    /// Overlap, in characters, between consecutive windows when
+    /// `window_long_chapters` is enabled.
+    #[serde(default = "default_window_overlap_chars")]
+    pub window_overlap_chars: usize,
+    /// Cache `chat` responses on disk under `build_folder/llm_cache`, keyed
+    /// on a hash of the model name and prompt. Lets character analysis
+    /// survive config tweaks (e.g. switching TTS voices) without re-running
+    /// every LLM call from scratch.
+    #[serde(default)]
+    pub use_llm_cache: bool,
+    /// Stream script-generation responses token-by-token and print them as
+    /// they arrive instead of waiting for the full reply. Only `OllamaClient`
+    /// streams for real (see `LlmClient::stream_chat`); other providers fall
+    /// back to their normal `chat` response printed all at once.
+    #[serde(default)]
+    pub stream: bool,
+    pub gemini: Option<GeminiConfig>,
+    pub ollama: Option<OllamaConfig>,
+    pub openai: Option<OpenAIConfig>,
+    pub claude: Option<ClaudeConfig>,
+
+    /// Aborts the workflow once cumulative token usage tracked by
+    /// `WorkflowManager`'s `LlmUsageTracker` exceeds this, so a misbehaving
+    /// chapter (or an unexpectedly expensive model) can't silently rack up
+    /// charges. `None` (the default) never enforces a budget. Only
+    /// `GeminiClient` and `OpenAIClient` currently report usage (see
+    /// `LlmClient::last_usage`); Ollama/Claude responses don't contribute to
+    /// the tracked total.
+    #[serde(default)]
+    pub max_total_tokens: Option<u64>,
+
+    /// Max number of follow-up turns `WorkflowManager` will send via
+    /// `LlmClient::chat_multi_turn` to retry a script response that failed to
+    /// parse as JSON, each appending the previous parse error as the new user
+    /// turn. `0` disables multi-turn retry entirely (the original single
+    /// `chat` response is kept as-is, errors and all).
+    #[serde(default = "default_max_retry_turns")]
+    pub max_retry_turns: usize,
+
+    /// Alternate to the single `gemini`/`ollama`/`openai`/`claude` blocks
+    /// above: a list of independently-configured providers to spread calls
+    /// across via `create_llm_multi`, e.g. several API keys for the same
+    /// provider to dodge per-account rate limits. When non-empty, this takes
+    /// precedence over the single-provider fields - `create_llm` itself
+    /// still only ever builds the single-provider client; callers that want
+    /// multi-provider fallback call `create_llm_multi` instead.
+    #[serde(default)]
+    pub providers: Vec<LlmProviderConfig>,
+
+    /// How `MultiProviderLlmClient` picks a provider for each `chat` call.
+    /// Only meaningful when `providers` is non-empty.
+    #[serde(default)]
+    pub fallback_strategy: FallbackStrategy,
+}
+
+fn default_max_retry_turns() -> usize {
+    2
+}
+
+/// One entry of `LlmConfig::providers`, mirroring the subset of `LlmConfig`
+/// needed to build a single underlying client - a provider name plus its
+/// own retry settings and provider-specific config block.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LlmProviderConfig {
+    pub provider: String,
+    #[serde(default = "default_retry_count")]
+    pub retry_count: usize,
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay_seconds: u64,
     pub gemini: Option<GeminiConfig>,
     pub ollama: Option<OllamaConfig>,
     pub openai: Option<OpenAIConfig>,
+    pub claude: Option<ClaudeConfig>,
+}
+
+/// Strategy `MultiProviderLlmClient` uses to pick a provider for each call.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackStrategy {
+    /// Always starts from the first provider, falling through to the next
+    /// on error. Good when providers aren't equivalent, e.g. a cheap
+    /// provider with an expensive one as backup.
+    #[default]
+    Sequential,
+    /// Starts from the next provider in rotation (wrapping around), falling
+    /// through the rest on error. Spreads load evenly across equivalent
+    /// providers, e.g. several API keys for the same account-rate-limited
+    /// provider.
+    RoundRobin,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,53 +142,339 @@ pub struct OllamaConfig {
     pub model: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClaudeConfig {
+    pub api_key: String,
+    pub model: String,
+}
+
 fn default_retry_count() -> usize {
     3
 }
 fn default_retry_delay() -> u64 {
     10
 }
+fn default_max_context_chars() -> usize {
+    10000
+}
+fn default_window_overlap_chars() -> usize {
+    500
+}
+
+/// Token counts reported by a provider's `usage` field for a single `chat`
+/// call, as tracked cumulatively by `WorkflowManager`'s `LlmUsageTracker`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
 
 #[async_trait]
 pub trait LlmClient: Send + Sync + Debug {
     async fn chat(&self, system: &str, user: &str) -> Result<String>;
+
+    /// Streams the response token-by-token instead of waiting for the full
+    /// reply, so callers (see script generation in `WorkflowManager`) can
+    /// print progress on long generations. The return type is boxed rather
+    /// than `impl Stream` so the trait stays object-safe for the
+    /// `Box<dyn LlmClient>`/`Arc<dyn LlmClient>` this codebase passes
+    /// around. Defaults to yielding `chat`'s full response as a single item;
+    /// `OllamaClient` overrides this with a real streaming request.
+    async fn stream_chat(
+        &self,
+        system: &str,
+        user: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        let full = self.chat(system, user).await?;
+        Ok(Box::pin(stream::once(async move { Ok(full) })))
+    }
+
+    /// Continues a multi-turn conversation, where `history` is the list of
+    /// `(user, assistant)` turns so far. The last entry's assistant side is
+    /// ignored - it's the in-flight turn being answered, not a completed one
+    /// - while earlier entries are replayed as prior conversation context.
+    /// `WorkflowManager` uses this to retry an invalid JSON script response
+    /// with the parse error appended as a follow-up user turn, so the model
+    /// sees what it got wrong instead of just being asked fresh. Defaults to
+    /// dropping all context and calling `chat` with just the last entry's
+    /// user message; `GeminiClient` and `OpenAIClient` override this with a
+    /// real multi-turn request.
+    async fn chat_multi_turn(&self, system: &str, history: &[(String, String)]) -> Result<String> {
+        let last_user = history
+            .last()
+            .map(|(user, _)| user.as_str())
+            .unwrap_or_default();
+        self.chat(system, last_user).await
+    }
+
+    /// Identifies the model backing this client, so callers like
+    /// `CachingLlmClient` can key a cache on (model, prompt) instead of just
+    /// the prompt. Defaults to empty for wrapper clients that don't have a
+    /// single underlying model.
+    fn model_name(&self) -> String {
+        String::new()
+    }
+
+    /// Token usage reported by the most recent `chat`/`chat_once` call, if
+    /// the provider's response included a `usage` field. Defaults to `None`
+    /// for providers that don't report usage (Ollama, Claude) and for
+    /// wrapper clients that don't override it; `WorkflowManager` reads this
+    /// after each LLM call to update its cumulative `LlmUsageTracker`.
+    fn last_usage(&self) -> Option<TokenUsage> {
+        None
+    }
+}
+
+/// Extension trait for JSON-returning helpers built on top of `LlmClient::chat`.
+/// Kept separate from `LlmClient` itself because a generic method would make
+/// the trait object-unsafe, and this codebase passes LLM clients around as
+/// `Box<dyn LlmClient>` / `Arc<dyn LlmClient>`.
+#[async_trait]
+pub trait LlmClientExt {
+    /// Calls `chat` and parses the response as JSON, stripping markdown code
+    /// blocks first since models often wrap JSON in ` ```json ` fences. If
+    /// parsing fails, retries once with an extra instruction appended to the
+    /// prompt asking for plain JSON.
+    async fn chat_json<T: DeserializeOwned>(&self, system: &str, user: &str) -> Result<T>;
+}
+
+#[async_trait]
+impl<C: LlmClient + ?Sized> LlmClientExt for C {
+    async fn chat_json<T: DeserializeOwned>(&self, system: &str, user: &str) -> Result<T> {
+        let response = self.chat(system, user).await?;
+        let clean = crate::services::script::strip_code_blocks(&response);
+        if let Ok(parsed) = serde_json::from_str(&clean) {
+            return Ok(parsed);
+        }
+
+        let retry_user = format!("{}\n\nReturn only valid JSON without comments.", user);
+        let response = self.chat(system, &retry_user).await?;
+        let clean = crate::services::script::strip_code_blocks(&response);
+        serde_json::from_str(&clean).context(format!("Failed to parse JSON response: {}", clean))
+    }
 }
 
 pub fn create_llm(config: &LlmConfig) -> Result<Box<dyn LlmClient>> {
-    let client: Box<dyn LlmClient> = match config.provider.as_str() {
+    build_provider_client(
+        &config.provider,
+        config.gemini.as_ref(),
+        config.ollama.as_ref(),
+        config.openai.as_ref(),
+        config.claude.as_ref(),
+        config.retry_count,
+        config.retry_delay_seconds,
+    )
+}
+
+/// Builds a `MultiProviderLlmClient` that spreads calls across several
+/// independently-configured providers (see `LlmConfig::providers`), falling
+/// back to the next on error per `LlmConfig::fallback_strategy`. Useful for
+/// running several accounts of the same provider to dodge per-account rate
+/// limits, or a cheap primary provider with a pricier fallback.
+///
+/// Returns an error if `config.providers` is empty - callers should check
+/// that and fall back to `create_llm` with the single-provider fields
+/// themselves, the same way `WorkflowManager` picks between them.
+pub fn create_llm_multi(config: &LlmConfig) -> Result<Box<dyn LlmClient>> {
+    if config.providers.is_empty() {
+        return Err(anyhow!(
+            "create_llm_multi requires at least one entry in llm.providers"
+        ));
+    }
+
+    let clients = config
+        .providers
+        .iter()
+        .map(|p| {
+            build_provider_client(
+                &p.provider,
+                p.gemini.as_ref(),
+                p.ollama.as_ref(),
+                p.openai.as_ref(),
+                p.claude.as_ref(),
+                p.retry_count,
+                p.retry_delay_seconds,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Box::new(MultiProviderLlmClient::new(
+        clients,
+        config.fallback_strategy,
+    )))
+}
+
+/// Builds a single provider client from its raw config fields. Shared by
+/// `create_llm` (single `llm` block) and `create_llm_multi` (one call per
+/// entry in `llm.providers`).
+///
+/// Gemini, Ollama and OpenAI retry transient errors (429/500/503)
+/// themselves via `retry_with_backoff`, using the configured retry_count
+/// and retry_delay_seconds directly. Claude doesn't have that built in
+/// yet, so it's still wrapped in the generic `RetryLlmClient` below.
+fn build_provider_client(
+    provider: &str,
+    gemini: Option<&GeminiConfig>,
+    ollama: Option<&OllamaConfig>,
+    openai: Option<&OpenAIConfig>,
+    claude: Option<&ClaudeConfig>,
+    retry_count: usize,
+    retry_delay_seconds: u64,
+) -> Result<Box<dyn LlmClient>> {
+    let retry_count_u32 = retry_count as u32;
+
+    match provider {
         "gemini" => {
-            let cfg = config
-                .gemini
-                .as_ref()
-                .context("Gemini config missing")?;
-            Box::new(GeminiClient::new(&cfg.api_key, &cfg.model))
+            let cfg = gemini.context("Gemini config missing")?;
+            Ok(Box::new(GeminiClient::new(
+                &cfg.api_key,
+                &cfg.model,
+                retry_count_u32,
+                retry_delay_seconds,
+            )))
         }
         "ollama" => {
-            let cfg = config
-                .ollama
-                .as_ref()
-                .context("Ollama config missing")?;
-            Box::new(OllamaClient::new(&cfg.base_url, &cfg.model))
+            let cfg = ollama.context("Ollama config missing")?;
+            Ok(Box::new(OllamaClient::new(
+                &cfg.base_url,
+                &cfg.model,
+                retry_count_u32,
+                retry_delay_seconds,
+            )))
         }
         "openai" => {
-            let cfg = config
-                .openai
-                .as_ref()
-                .context("OpenAI config missing")?;
-            Box::new(OpenAIClient::new(
+            let cfg = openai.context("OpenAI config missing")?;
+            Ok(Box::new(OpenAIClient::new(
                 &cfg.api_key,
                 &cfg.model,
                 cfg.base_url.as_deref(),
-            ))
+                retry_count_u32,
+                retry_delay_seconds,
+            )))
+        }
+        "claude" => {
+            let cfg = claude.context("Claude config missing")?;
+            let client: Box<dyn LlmClient> = Box::new(ClaudeClient::new(&cfg.api_key, &cfg.model));
+            let retry_client = RetryLlmClient::new(client, retry_count);
+            let retry_client = if retry_count > 0 {
+                retry_client.with_delay_seconds(retry_delay_seconds)
+            } else {
+                retry_client
+            };
+            Ok(Box::new(retry_client))
+        }
+        _ => Err(anyhow!("Unknown LLM provider: {}", provider)),
+    }
+}
+
+/// Wraps several providers, routing `chat` to one via `strategy` and falling
+/// through the rest in rotation order on error so a single account's outage
+/// or rate limit doesn't stop the workflow. `model_name`/`last_usage` report
+/// whichever provider most recently handled a call successfully.
+#[derive(Debug)]
+pub struct MultiProviderLlmClient {
+    providers: Vec<Box<dyn LlmClient>>,
+    strategy: FallbackStrategy,
+    next_index: std::sync::atomic::AtomicUsize,
+    last_used: std::sync::atomic::AtomicUsize,
+}
+
+impl MultiProviderLlmClient {
+    pub fn new(providers: Vec<Box<dyn LlmClient>>, strategy: FallbackStrategy) -> Self {
+        Self {
+            providers,
+            strategy,
+            next_index: std::sync::atomic::AtomicUsize::new(0),
+            last_used: std::sync::atomic::AtomicUsize::new(0),
+        }
+    }
+
+    /// Picks the starting provider index for the next call: always 0 for
+    /// `Sequential`, the next slot in rotation for `RoundRobin`.
+    fn start_index(&self) -> usize {
+        match self.strategy {
+            FallbackStrategy::Sequential => 0,
+            FallbackStrategy::RoundRobin => {
+                self.next_index
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                    % self.providers.len()
+            }
+        }
+    }
+
+    /// Records which provider just succeeded, so `model_name`/`last_usage`
+    /// report it, and logs the choice the same way the rest of this module
+    /// logs retries (a plain `println!`, not a tracing framework).
+    fn record_success(&self, index: usize, provider: &dyn LlmClient) {
+        println!("llm: used provider {} ({})", index, provider.model_name());
+        self.last_used
+            .store(index, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn log_failure(index: usize, provider: &dyn LlmClient, err: &anyhow::Error) {
+        println!(
+            "llm: provider {} ({}) failed, trying next: {}",
+            index,
+            provider.model_name(),
+            err
+        );
+    }
+}
+
+#[async_trait]
+impl LlmClient for MultiProviderLlmClient {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let start = self.start_index();
+        let mut last_err = None;
+        for offset in 0..self.providers.len() {
+            let index = (start + offset) % self.providers.len();
+            let provider = self.providers[index].as_ref();
+            match provider.chat(system, user).await {
+                Ok(response) => {
+                    self.record_success(index, provider);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    Self::log_failure(index, provider, &e);
+                    last_err = Some(e);
+                }
+            }
         }
-        _ => return Err(anyhow!("Unknown LLM provider: {}", config.provider)),
-    };
+        Err(last_err.unwrap_or_else(|| anyhow!("MultiProviderLlmClient has no providers")))
+    }
+
+    async fn chat_multi_turn(&self, system: &str, history: &[(String, String)]) -> Result<String> {
+        let start = self.start_index();
+        let mut last_err = None;
+        for offset in 0..self.providers.len() {
+            let index = (start + offset) % self.providers.len();
+            let provider = self.providers[index].as_ref();
+            match provider.chat_multi_turn(system, history).await {
+                Ok(response) => {
+                    self.record_success(index, provider);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    Self::log_failure(index, provider, &e);
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow!("MultiProviderLlmClient has no providers")))
+    }
 
-    Ok(Box::new(RetryLlmClient {
-        inner: client,
-        retry_count: config.retry_count,
-        retry_delay_seconds: config.retry_delay_seconds,
-    }))
+    fn model_name(&self) -> String {
+        let index = self.last_used.load(std::sync::atomic::Ordering::Relaxed);
+        self.providers
+            .get(index)
+            .map(|p| p.model_name())
+            .unwrap_or_default()
+    }
+
+    fn last_usage(&self) -> Option<TokenUsage> {
+        let index = self.last_used.load(std::sync::atomic::Ordering::Relaxed);
+        self.providers.get(index).and_then(|p| p.last_usage())
+    }
 }
 
 #[derive(Debug)]
@@ -92,6 +484,30 @@ struct RetryLlmClient {
     retry_delay_seconds: u64,
 }
 
+impl RetryLlmClient {
+    fn new(inner: Box<dyn LlmClient>, retry_count: usize) -> Self {
+        Self {
+            inner,
+            retry_count,
+            retry_delay_seconds: default_retry_delay(),
+        }
+    }
+
+    fn with_delay_seconds(mut self, secs: u64) -> Self {
+        self.retry_delay_seconds = secs;
+        self
+    }
+
+    /// Jitters the configured delay to `[0.5x, 1.5x]` so that multiple chapters
+    /// retrying against the same LLM client don't all wake up at once.
+    fn jittered_delay(&self) -> u64 {
+        use rand::Rng;
+        let base = self.retry_delay_seconds as f64;
+        let jittered = rand::rng().random_range(base * 0.5..=base * 1.5);
+        jittered.round() as u64
+    }
+}
+
 #[async_trait]
 impl LlmClient for RetryLlmClient {
     async fn chat(&self, system: &str, user: &str) -> Result<String> {
@@ -109,14 +525,171 @@ impl LlmClient for RetryLlmClient {
                         return Err(e);
                     }
 
-                    println!("wait {} sec retry", self.retry_delay_seconds);
-                    tokio::time::sleep(tokio::time::Duration::from_secs(self.retry_delay_seconds))
-                        .await;
+                    let delay = self.jittered_delay();
+                    println!("wait {} sec retry", delay);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
                     attempt += 1;
                 }
             }
         }
     }
+
+    async fn stream_chat(
+        &self,
+        system: &str,
+        user: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        self.inner.stream_chat(system, user).await
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn last_usage(&self) -> Option<TokenUsage> {
+        self.inner.last_usage()
+    }
+}
+
+/// Tags an HTTP error with its status code so `retry_with_backoff` can tell
+/// transient failures apart from ones that should surface immediately.
+#[derive(Debug)]
+struct HttpStatusError {
+    status: reqwest::StatusCode,
+    body: String,
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {} - {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::TOO_MANY_REQUESTS
+            | reqwest::StatusCode::INTERNAL_SERVER_ERROR
+            | reqwest::StatusCode::SERVICE_UNAVAILABLE
+    )
+}
+
+/// Doubles `base_delay_secs` on each retry and applies +/-25% jitter so
+/// concurrent chapters hitting the same quota don't all wake up at once.
+fn backoff_delay_secs(base_delay_secs: u64, attempt: u32) -> u64 {
+    use rand::Rng;
+    let doubled = base_delay_secs.saturating_mul(1u64 << attempt.min(32));
+    let base = doubled as f64;
+    if base <= 0.0 {
+        return 0;
+    }
+    rand::rng().random_range(base * 0.75..=base * 1.25).round() as u64
+}
+
+/// Calls `f` up to `attempts` additional times after the first try, doubling
+/// the delay between attempts (+/-25% jitter, starting from
+/// `base_delay_secs`). Only retries when the error is an `HttpStatusError`
+/// carrying a transient status (429, 500, 503); anything else - including
+/// non-HTTP errors and client errors like 400/401 - surfaces immediately.
+async fn retry_with_backoff<F, Fut, T>(attempts: u32, base_delay_secs: u64, f: F) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let retryable = e
+                    .downcast_ref::<HttpStatusError>()
+                    .map(|http_err| is_retryable_status(http_err.status))
+                    .unwrap_or(false);
+
+                if !retryable || attempt >= attempts {
+                    return Err(e);
+                }
+
+                let delay = backoff_delay_secs(base_delay_secs, attempt);
+                tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Caches `chat` responses on disk, keyed on a SHA-256 hash of the inner
+/// client's model name plus the system/user prompt. A cache hit skips the
+/// inner client entirely, so re-running character analysis after an
+/// unrelated config tweak (e.g. switching TTS voices) doesn't re-spend LLM
+/// quota on prompts it has already answered.
+#[derive(Debug)]
+pub struct CachingLlmClient {
+    inner: Box<dyn LlmClient>,
+    cache_dir: PathBuf,
+}
+
+impl CachingLlmClient {
+    pub fn with_cache(inner: Box<dyn LlmClient>, build_folder: &str) -> Self {
+        Self {
+            inner,
+            cache_dir: PathBuf::from(build_folder).join("llm_cache"),
+        }
+    }
+
+    fn cache_path(&self, system: &str, user: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(self.inner.model_name().as_bytes());
+        hasher.update(b"\0");
+        hasher.update(system.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(user.as_bytes());
+        let hash = hasher.finalize();
+        self.cache_dir.join(format!("{:x}.json", hash))
+    }
+}
+
+#[async_trait]
+impl LlmClient for CachingLlmClient {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let path = self.cache_path(system, user);
+
+        if let Ok(cached) = fs::read_to_string(&path) {
+            if let Ok(response) = serde_json::from_str::<String>(&cached) {
+                return Ok(response);
+            }
+        }
+
+        let response = self.inner.chat(system, user).await?;
+
+        fs::create_dir_all(&self.cache_dir)?;
+        fs::write(&path, serde_json::to_string(&response)?)?;
+
+        Ok(response)
+    }
+
+    /// Not cached: a streamed response is consumed token-by-token, so
+    /// there's no single final string to write to the cache file until the
+    /// whole thing has already been printed. Delegates straight to `inner`.
+    async fn stream_chat(
+        &self,
+        system: &str,
+        user: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        self.inner.stream_chat(system, user).await
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    /// On a cache hit this reflects whatever the inner client's last *actual*
+    /// request reported, not the cached call, since no request was made.
+    fn last_usage(&self) -> Option<TokenUsage> {
+        self.inner.last_usage()
+    }
 }
 
 // --- Gemini ---
@@ -125,14 +698,20 @@ struct GeminiClient {
     api_key: String,
     model: String,
     client: reqwest::Client,
+    retry_count: u32,
+    retry_delay_seconds: u64,
+    last_usage: std::sync::Mutex<Option<TokenUsage>>,
 }
 
 impl GeminiClient {
-    fn new(api_key: &str, model: &str) -> Self {
+    fn new(api_key: &str, model: &str, retry_count: u32, retry_delay_seconds: u64) -> Self {
         Self {
             api_key: api_key.to_string(),
             model: model.to_string(),
             client: reqwest::Client::new(),
+            retry_count,
+            retry_delay_seconds,
+            last_usage: std::sync::Mutex::new(None),
         }
     }
 }
@@ -164,6 +743,16 @@ struct GeminiPart {
 struct GeminiResponse {
     candidates: Option<Vec<GeminiCandidate>>,
     error: Option<GeminiError>,
+    #[serde(rename = "usageMetadata")]
+    usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct GeminiUsageMetadata {
+    #[serde(rename = "promptTokenCount", default)]
+    prompt_token_count: u64,
+    #[serde(rename = "candidatesTokenCount", default)]
+    candidates_token_count: u64,
 }
 
 #[derive(Deserialize)]
@@ -189,21 +778,40 @@ struct GeminiError {
     message: String,
 }
 
-#[async_trait]
-impl LlmClient for GeminiClient {
-    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+impl GeminiClient {
+    /// Builds the `contents` array for a multi-turn request: each history
+    /// entry's user side becomes a `user` turn, and - except for the last
+    /// entry, which is the in-flight turn being answered - its assistant side
+    /// becomes the following `model` turn.
+    fn multi_turn_contents(history: &[(String, String)]) -> Vec<GeminiContent> {
+        let mut contents = Vec::with_capacity(history.len() * 2);
+        for (i, (user, assistant)) in history.iter().enumerate() {
+            contents.push(GeminiContent {
+                role: "user".to_string(),
+                parts: vec![GeminiPart {
+                    text: user.clone(),
+                }],
+            });
+            if i + 1 < history.len() {
+                contents.push(GeminiContent {
+                    role: "model".to_string(),
+                    parts: vec![GeminiPart {
+                        text: assistant.clone(),
+                    }],
+                });
+            }
+        }
+        contents
+    }
+
+    async fn send(&self, system: &str, contents: Vec<GeminiContent>) -> Result<String> {
         let url = format!(
             "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
             self.model, self.api_key
         );
 
         let request_body = GeminiRequest {
-            contents: vec![GeminiContent {
-                role: "user".to_string(),
-                parts: vec![GeminiPart {
-                    text: user.to_string(),
-                }],
-            }],
+            contents,
             system_instruction: Some(GeminiSystemInstruction {
                 parts: vec![GeminiPart {
                     text: system.to_string(),
@@ -216,14 +824,12 @@ impl LlmClient for GeminiClient {
         if !resp.status().is_success() {
             let status = resp.status();
             let error_text = resp.text().await?;
-            if status == reqwest::StatusCode::UNAUTHORIZED
-                || status == reqwest::StatusCode::FORBIDDEN
-            {
-                return Err(anyhow!(
-                    "FATAL: Gemini API error: {} - {}",
+            if is_retryable_status(status) {
+                return Err(HttpStatusError {
                     status,
-                    error_text
-                ));
+                    body: error_text,
+                }
+                .into());
             }
             return Err(anyhow!("Gemini API error: {} - {}", status, error_text));
         }
@@ -245,6 +851,13 @@ impl LlmClient for GeminiClient {
             return Err(anyhow!("Gemini API returned error: {}", err.message));
         }
 
+        if let Some(usage) = &result.usage_metadata {
+            *self.last_usage.lock().unwrap() = Some(TokenUsage {
+                prompt_tokens: usage.prompt_token_count,
+                completion_tokens: usage.candidates_token_count,
+            });
+        }
+
         if let Some(candidates) = result.candidates {
             if let Some(first) = candidates.first() {
                 if let Some(content) = &first.content {
@@ -264,6 +877,48 @@ impl LlmClient for GeminiClient {
             response_text
         ))
     }
+
+    async fn chat_once(&self, system: &str, user: &str) -> Result<String> {
+        self.send(
+            system,
+            vec![GeminiContent {
+                role: "user".to_string(),
+                parts: vec![GeminiPart {
+                    text: user.to_string(),
+                }],
+            }],
+        )
+        .await
+    }
+
+    async fn chat_multi_turn_once(&self, system: &str, history: &[(String, String)]) -> Result<String> {
+        self.send(system, Self::multi_turn_contents(history)).await
+    }
+}
+
+#[async_trait]
+impl LlmClient for GeminiClient {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        retry_with_backoff(self.retry_count, self.retry_delay_seconds, || {
+            self.chat_once(system, user)
+        })
+        .await
+    }
+
+    async fn chat_multi_turn(&self, system: &str, history: &[(String, String)]) -> Result<String> {
+        retry_with_backoff(self.retry_count, self.retry_delay_seconds, || {
+            self.chat_multi_turn_once(system, history)
+        })
+        .await
+    }
+
+    fn last_usage(&self) -> Option<TokenUsage> {
+        *self.last_usage.lock().unwrap()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
 }
 
 // --- Ollama ---
@@ -272,14 +927,18 @@ struct OllamaClient {
     base_url: String,
     model: String,
     client: reqwest::Client,
+    retry_count: u32,
+    retry_delay_seconds: u64,
 }
 
 impl OllamaClient {
-    fn new(base_url: &str, model: &str) -> Self {
+    fn new(base_url: &str, model: &str, retry_count: u32, retry_delay_seconds: u64) -> Self {
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
             model: model.to_string(),
             client: reqwest::Client::new(),
+            retry_count,
+            retry_delay_seconds,
         }
     }
 }
@@ -297,19 +956,18 @@ struct OllamaMessage {
     content: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 struct OllamaResponse {
     message: OllamaMessageResponse,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 struct OllamaMessageResponse {
     content: String,
 }
 
-#[async_trait]
-impl LlmClient for OllamaClient {
-    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+impl OllamaClient {
+    async fn chat_once(&self, system: &str, user: &str) -> Result<String> {
         let url = format!("{}/api/chat", self.base_url);
 
         let request_body = OllamaRequest {
@@ -332,14 +990,12 @@ impl LlmClient for OllamaClient {
         if !resp.status().is_success() {
             let status = resp.status();
             let error_text = resp.text().await?;
-            if status == reqwest::StatusCode::UNAUTHORIZED
-                || status == reqwest::StatusCode::FORBIDDEN
-            {
-                return Err(anyhow!(
-                    "FATAL: Ollama API error: {} - {}",
+            if is_retryable_status(status) {
+                return Err(HttpStatusError {
                     status,
-                    error_text
-                ));
+                    body: error_text,
+                }
+                .into());
             }
             return Err(anyhow!("Ollama API error: {} - {}", status, error_text));
         }
@@ -349,34 +1005,138 @@ impl LlmClient for OllamaClient {
     }
 }
 
-// --- OpenAI ---
-
-#[derive(Debug)]
-struct OpenAIClient {
-    api_key: String,
-    model: String,
-    base_url: String,
-    client: reqwest::Client,
-}
-
-impl OpenAIClient {
-    fn new(api_key: &str, model: &str, base_url: Option<&str>) -> Self {
-        Self {
-            api_key: api_key.to_string(),
-            model: model.to_string(),
-            base_url: base_url
-                .unwrap_or("https://api.openai.com/v1")
-                .trim_end_matches('/')
-                .to_string(),
-            client: reqwest::Client::new(),
-        }
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        retry_with_backoff(self.retry_count, self.retry_delay_seconds, || {
+            self.chat_once(system, user)
+        })
+        .await
     }
-}
 
-#[derive(Serialize)]
-struct OpenAIRequest {
-    model: String,
-    messages: Vec<OpenAIMessage>,
+    async fn stream_chat(
+        &self,
+        system: &str,
+        user: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let request_body = OllamaRequest {
+            model: self.model.clone(),
+            messages: vec![
+                OllamaMessage {
+                    role: "system".to_string(),
+                    content: system.to_string(),
+                },
+                OllamaMessage {
+                    role: "user".to_string(),
+                    content: user.to_string(),
+                },
+            ],
+            stream: true,
+        };
+
+        let resp = self.client.post(&url).json(&request_body).send().await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let error_text = resp.text().await?;
+            return Err(anyhow!("Ollama API error: {} - {}", status, error_text));
+        }
+
+        Ok(Box::pin(ndjson_content_stream(resp.bytes_stream().boxed())))
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+}
+
+/// Turns a streamed HTTP body of newline-delimited `OllamaResponse` JSON
+/// objects into a stream of `message.content` deltas, buffering bytes across
+/// chunk boundaries so a line split across two chunks still parses.
+fn ndjson_content_stream<S, B>(byte_stream: S) -> impl Stream<Item = Result<String>>
+where
+    S: Stream<Item = reqwest::Result<B>> + Unpin + Send + 'static,
+    B: AsRef<[u8]>,
+{
+    stream::unfold(
+        (byte_stream, String::new()),
+        |(mut byte_stream, mut buffer)| async move {
+            loop {
+                if let Some(pos) = buffer.find('\n') {
+                    let line = buffer[..pos].trim().to_string();
+                    buffer.drain(..=pos);
+                    if line.is_empty() {
+                        continue;
+                    }
+                    return Some((parse_ollama_chunk(&line), (byte_stream, buffer)));
+                }
+
+                match byte_stream.next().await {
+                    Some(Ok(bytes)) => buffer.push_str(&String::from_utf8_lossy(bytes.as_ref())),
+                    Some(Err(e)) => return Some((Err(anyhow!(e)), (byte_stream, String::new()))),
+                    None => {
+                        let remaining = buffer.trim().to_string();
+                        if remaining.is_empty() {
+                            return None;
+                        }
+                        return Some((
+                            parse_ollama_chunk(&remaining),
+                            (byte_stream, String::new()),
+                        ));
+                    }
+                }
+            }
+        },
+    )
+}
+
+fn parse_ollama_chunk(line: &str) -> Result<String> {
+    let parsed: OllamaResponse = serde_json::from_str(line)?;
+    Ok(parsed.message.content)
+}
+
+// --- OpenAI ---
+
+#[derive(Debug)]
+struct OpenAIClient {
+    api_key: String,
+    model: String,
+    base_url: String,
+    client: reqwest::Client,
+    retry_count: u32,
+    retry_delay_seconds: u64,
+    last_usage: std::sync::Mutex<Option<TokenUsage>>,
+}
+
+impl OpenAIClient {
+    fn new(
+        api_key: &str,
+        model: &str,
+        base_url: Option<&str>,
+        retry_count: u32,
+        retry_delay_seconds: u64,
+    ) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            base_url: base_url
+                .unwrap_or("https://api.openai.com/v1")
+                .trim_end_matches('/')
+                .to_string(),
+            client: reqwest::Client::new(),
+            retry_count,
+            retry_delay_seconds,
+            last_usage: std::sync::Mutex::new(None),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAIRequest {
+    model: String,
+    messages: Vec<OpenAIMessage>,
 }
 
 #[derive(Serialize)]
@@ -388,6 +1148,7 @@ struct OpenAIMessage {
 #[derive(Deserialize)]
 struct OpenAIResponse {
     choices: Vec<OpenAIChoice>,
+    usage: Option<OpenAIUsage>,
 }
 
 #[derive(Deserialize)]
@@ -400,23 +1161,46 @@ struct OpenAIMessageResponse {
     content: Option<String>,
 }
 
-#[async_trait]
-impl LlmClient for OpenAIClient {
-    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+#[derive(Deserialize)]
+struct OpenAIUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl OpenAIClient {
+    /// Builds the alternating `user`/`assistant` message list for a
+    /// multi-turn request, following the system message. As with
+    /// `GeminiClient::multi_turn_contents`, the last history entry's
+    /// assistant side is the in-flight turn being answered, so it's omitted.
+    fn multi_turn_messages(system: &str, history: &[(String, String)]) -> Vec<OpenAIMessage> {
+        let mut messages = Vec::with_capacity(history.len() * 2 + 1);
+        messages.push(OpenAIMessage {
+            role: "system".to_string(),
+            content: system.to_string(),
+        });
+        for (i, (user, assistant)) in history.iter().enumerate() {
+            messages.push(OpenAIMessage {
+                role: "user".to_string(),
+                content: user.clone(),
+            });
+            if i + 1 < history.len() {
+                messages.push(OpenAIMessage {
+                    role: "assistant".to_string(),
+                    content: assistant.clone(),
+                });
+            }
+        }
+        messages
+    }
+
+    async fn send(&self, messages: Vec<OpenAIMessage>) -> Result<String> {
         let url = format!("{}/chat/completions", self.base_url);
 
         let request_body = OpenAIRequest {
             model: self.model.clone(),
-            messages: vec![
-                OpenAIMessage {
-                    role: "system".to_string(),
-                    content: system.to_string(),
-                },
-                OpenAIMessage {
-                    role: "user".to_string(),
-                    content: user.to_string(),
-                },
-            ],
+            messages,
         };
 
         let resp = self
@@ -430,19 +1214,25 @@ impl LlmClient for OpenAIClient {
         if !resp.status().is_success() {
             let status = resp.status();
             let error_text = resp.text().await?;
-            if status == reqwest::StatusCode::UNAUTHORIZED
-                || status == reqwest::StatusCode::FORBIDDEN
-            {
-                return Err(anyhow!(
-                    "FATAL: OpenAI API error: {} - {}",
+            if is_retryable_status(status) {
+                return Err(HttpStatusError {
                     status,
-                    error_text
-                ));
+                    body: error_text,
+                }
+                .into());
             }
             return Err(anyhow!("OpenAI API error: {} - {}", status, error_text));
         }
 
         let result: OpenAIResponse = resp.json().await?;
+
+        if let Some(usage) = &result.usage {
+            *self.last_usage.lock().unwrap() = Some(TokenUsage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+            });
+        }
+
         if let Some(choice) = result.choices.first() {
             if let Some(content) = &choice.message.content {
                 return Ok(content.clone());
@@ -451,6 +1241,174 @@ impl LlmClient for OpenAIClient {
 
         Err(anyhow!("OpenAI response empty or missing content"))
     }
+
+    async fn chat_once(&self, system: &str, user: &str) -> Result<String> {
+        self.send(vec![
+            OpenAIMessage {
+                role: "system".to_string(),
+                content: system.to_string(),
+            },
+            OpenAIMessage {
+                role: "user".to_string(),
+                content: user.to_string(),
+            },
+        ])
+        .await
+    }
+
+    async fn chat_multi_turn_once(&self, system: &str, history: &[(String, String)]) -> Result<String> {
+        self.send(Self::multi_turn_messages(system, history)).await
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAIClient {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        retry_with_backoff(self.retry_count, self.retry_delay_seconds, || {
+            self.chat_once(system, user)
+        })
+        .await
+    }
+
+    async fn chat_multi_turn(&self, system: &str, history: &[(String, String)]) -> Result<String> {
+        retry_with_backoff(self.retry_count, self.retry_delay_seconds, || {
+            self.chat_multi_turn_once(system, history)
+        })
+        .await
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn last_usage(&self) -> Option<TokenUsage> {
+        *self.last_usage.lock().unwrap()
+    }
+}
+
+// --- Claude ---
+
+#[derive(Debug)]
+struct ClaudeClient {
+    api_key: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl ClaudeClient {
+    fn new(api_key: &str, model: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClaudeRequest {
+    model: String,
+    system: String,
+    max_tokens: u32,
+    messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Serialize)]
+struct ClaudeMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ClaudeResponse {
+    #[serde(default)]
+    content: Vec<ClaudeContentBlock>,
+    error: Option<ClaudeError>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeContentBlock {
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClaudeError {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+}
+
+#[async_trait]
+impl LlmClient for ClaudeClient {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let url = "https://api.anthropic.com/v1/messages";
+
+        let request_body = ClaudeRequest {
+            model: self.model.clone(),
+            system: system.to_string(),
+            max_tokens: 4096,
+            messages: vec![ClaudeMessage {
+                role: "user".to_string(),
+                content: user.to_string(),
+            }],
+        };
+
+        let resp = self
+            .client
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&request_body)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let response_text = resp.text().await?;
+
+        if !status.is_success() {
+            if status == reqwest::StatusCode::UNAUTHORIZED
+                || status == reqwest::StatusCode::FORBIDDEN
+            {
+                return Err(anyhow!(
+                    "FATAL: Claude API error: {} - {}",
+                    status,
+                    response_text
+                ));
+            }
+            return Err(anyhow!("Claude API error: {} - {}", status, response_text));
+        }
+
+        let result: ClaudeResponse = serde_json::from_str(&response_text).map_err(|e| {
+            anyhow!(
+                "Failed to parse Claude response: {}. Body: {}",
+                e,
+                response_text
+            )
+        })?;
+
+        if let Some(err) = result.error {
+            // The Anthropic API returns HTTP 529/overloaded_error inside a 200
+            // body in some proxy setups, so treat it as a transient error
+            // rather than fatal regardless of status code.
+            if err.error_type == "overloaded_error" {
+                return Err(anyhow!("Claude API overloaded: {}", err.message));
+            }
+            return Err(anyhow!("Claude API returned error: {}", err.message));
+        }
+
+        if let Some(block) = result.content.first() {
+            return Ok(block.text.clone());
+        }
+
+        Err(anyhow!(
+            "Claude response empty or missing content. Body: {}",
+            response_text
+        ))
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
 }
 
 #[cfg(test)]
@@ -537,6 +1495,172 @@ mod tests {
         assert_eq!(*failures.lock().unwrap(), 4);
     }
 
+    #[tokio::test]
+    async fn test_retry_with_backoff_retries_transient_status() {
+        let calls = Arc::new(Mutex::new(0u32));
+        let calls_clone = calls.clone();
+        let result = retry_with_backoff(3, 0, move || {
+            let calls = calls_clone.clone();
+            async move {
+                let mut count = calls.lock().unwrap();
+                *count += 1;
+                if *count < 3 {
+                    Err(HttpStatusError {
+                        status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+                        body: "rate limited".to_string(),
+                    }
+                    .into())
+                } else {
+                    Ok(*count)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(*calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_exhausts_attempts() {
+        let calls = Arc::new(Mutex::new(0u32));
+        let calls_clone = calls.clone();
+        let result: Result<u32> = retry_with_backoff(2, 0, move || {
+            let calls = calls_clone.clone();
+            async move {
+                *calls.lock().unwrap() += 1;
+                Err(HttpStatusError {
+                    status: reqwest::StatusCode::SERVICE_UNAVAILABLE,
+                    body: "down".to_string(),
+                }
+                .into())
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        // Initial attempt plus two retries.
+        assert_eq!(*calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_surfaces_bad_request_immediately() {
+        let calls = Arc::new(Mutex::new(0u32));
+        let calls_clone = calls.clone();
+        let result: Result<u32> = retry_with_backoff(5, 0, move || {
+            let calls = calls_clone.clone();
+            async move {
+                *calls.lock().unwrap() += 1;
+                Err(HttpStatusError {
+                    status: reqwest::StatusCode::BAD_REQUEST,
+                    body: "bad request".to_string(),
+                }
+                .into())
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_surfaces_unauthorized_immediately() {
+        let calls = Arc::new(Mutex::new(0u32));
+        let calls_clone = calls.clone();
+        let result: Result<u32> = retry_with_backoff(5, 0, move || {
+            let calls = calls_clone.clone();
+            async move {
+                *calls.lock().unwrap() += 1;
+                Err(HttpStatusError {
+                    status: reqwest::StatusCode::UNAUTHORIZED,
+                    body: "unauthorized".to_string(),
+                }
+                .into())
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[derive(Debug)]
+    struct CountingLlmClient {
+        calls: Arc<Mutex<usize>>,
+    }
+
+    #[async_trait]
+    impl LlmClient for CountingLlmClient {
+        async fn chat(&self, _system: &str, _user: &str) -> Result<String> {
+            let mut count = self.calls.lock().unwrap();
+            *count += 1;
+            Ok(format!("response-{}", count))
+        }
+
+        fn model_name(&self) -> String {
+            "counting-model".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caching_llm_client_cache_miss_calls_inner_and_populates_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let build_folder = temp_dir.path().to_string_lossy().to_string();
+        let calls = Arc::new(Mutex::new(0));
+        let inner = Box::new(CountingLlmClient {
+            calls: calls.clone(),
+        });
+        let client = CachingLlmClient::with_cache(inner, &build_folder);
+
+        let response = client.chat("system prompt", "user prompt").await.unwrap();
+
+        assert_eq!(response, "response-1");
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        let cache_dir = temp_dir.path().join("llm_cache");
+        let entries: Vec<_> = fs::read_dir(&cache_dir).unwrap().collect();
+        assert_eq!(entries.len(), 1, "Cache miss should write exactly one file");
+    }
+
+    #[tokio::test]
+    async fn test_caching_llm_client_cache_hit_skips_inner() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let build_folder = temp_dir.path().to_string_lossy().to_string();
+        let calls = Arc::new(Mutex::new(0));
+        let inner = Box::new(CountingLlmClient {
+            calls: calls.clone(),
+        });
+        let client = CachingLlmClient::with_cache(inner, &build_folder);
+
+        let first = client.chat("system prompt", "user prompt").await.unwrap();
+        let second = client.chat("system prompt", "user prompt").await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            1,
+            "Second call should be served from cache, not hit the inner client"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_caching_llm_client_different_prompts_do_not_collide() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let build_folder = temp_dir.path().to_string_lossy().to_string();
+        let calls = Arc::new(Mutex::new(0));
+        let inner = Box::new(CountingLlmClient {
+            calls: calls.clone(),
+        });
+        let client = CachingLlmClient::with_cache(inner, &build_folder);
+
+        let first = client.chat("system prompt", "prompt A").await.unwrap();
+        let second = client.chat("system prompt", "prompt B").await.unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
     #[test]
     fn test_gemini_response_parsing_safety_block() {
         // Simulating a response where content is blocked (safety)
@@ -633,5 +1757,237 @@ mod tests {
             result.choices[0].message.content.as_deref(),
             Some("Hello there, how may I assist you today?")
         );
+        let usage = result.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 9);
+        assert_eq!(usage.completion_tokens, 12);
+    }
+
+    #[test]
+    fn test_gemini_response_parsing_usage_metadata() {
+        let json = r#"{
+            "candidates": [
+                {
+                    "content": {
+                        "parts": [
+                            { "text": "Hello world" }
+                        ],
+                        "role": "model"
+                    },
+                    "finishReason": "STOP",
+                    "index": 0
+                }
+            ],
+            "usageMetadata": {
+                "promptTokenCount": 15,
+                "candidatesTokenCount": 7,
+                "totalTokenCount": 22
+            }
+        }"#;
+
+        let result: GeminiResponse = serde_json::from_str(json).unwrap();
+        let usage = result.usage_metadata.unwrap();
+        assert_eq!(usage.prompt_token_count, 15);
+        assert_eq!(usage.candidates_token_count, 7);
+    }
+
+    #[test]
+    fn test_claude_response_parsing_success() {
+        let json = r#"{
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-5-sonnet-20241022",
+            "content": [
+                { "type": "text", "text": "Hello world" }
+            ],
+            "stop_reason": "end_turn"
+        }"#;
+
+        let result: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(result.content[0].text, "Hello world");
+        assert!(result.error.is_none());
+    }
+
+    #[test]
+    fn test_claude_response_parsing_overloaded_error() {
+        let json = r#"{
+            "type": "error",
+            "error": {
+                "type": "overloaded_error",
+                "message": "Overloaded"
+            }
+        }"#;
+
+        let result: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert!(result.content.is_empty());
+        let err = result.error.unwrap();
+        assert_eq!(err.error_type, "overloaded_error");
+        assert_eq!(err.message, "Overloaded");
+    }
+
+    #[test]
+    fn test_claude_response_parsing_missing_content() {
+        // Simulates a response where content is present but empty, e.g. a
+        // stop_reason that cuts the message off before any block is emitted.
+        let json = r#"{
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-5-sonnet-20241022",
+            "content": [],
+            "stop_reason": "max_tokens"
+        }"#;
+
+        let result: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert!(result.content.is_empty());
+        assert!(result.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_chat_yields_full_chat_response_as_one_item() {
+        let client = MockFailingClient {
+            failures: Arc::new(Mutex::new(0)),
+            fatal: false,
+        };
+
+        let mut stream = client.stream_chat("sys", "user").await.unwrap();
+        let mut items = Vec::new();
+        while let Some(item) = stream.next().await {
+            items.push(item.unwrap());
+        }
+
+        assert_eq!(items, vec!["Success".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_content_stream_reassembles_lines_split_across_chunks() {
+        let first_line = serde_json::to_string(&OllamaResponse {
+            message: OllamaMessageResponse {
+                content: "Hello".to_string(),
+            },
+        })
+        .unwrap();
+        let second_line = serde_json::to_string(&OllamaResponse {
+            message: OllamaMessageResponse {
+                content: " world".to_string(),
+            },
+        })
+        .unwrap();
+
+        // Simulate a chunked HTTP response: the first line is split across
+        // two chunks, and the second line arrives whole in the same chunk
+        // as the first line's terminating newline.
+        let (first_half, second_half) = first_line.split_at(first_line.len() / 2);
+        let chunks: Vec<reqwest::Result<Vec<u8>>> = vec![
+            Ok(first_half.as_bytes().to_vec()),
+            Ok(format!("{}\n{}\n", second_half, second_line).into_bytes()),
+        ];
+
+        let tokens: Vec<String> = ndjson_content_stream(stream::iter(chunks))
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(tokens.concat(), "Hello world");
+    }
+
+    #[derive(Debug)]
+    struct NamedMockClient {
+        name: String,
+        calls: Arc<Mutex<usize>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl LlmClient for NamedMockClient {
+        async fn chat(&self, _system: &str, _user: &str) -> Result<String> {
+            *self.calls.lock().unwrap() += 1;
+            if self.fail {
+                return Err(anyhow!("HTTP 429 - rate limited"));
+            }
+            Ok(format!("response from {}", self.name))
+        }
+
+        fn model_name(&self) -> String {
+            self.name.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_provider_sequential_falls_back_to_next_on_error() {
+        let first_calls = Arc::new(Mutex::new(0));
+        let second_calls = Arc::new(Mutex::new(0));
+        let client = MultiProviderLlmClient::new(
+            vec![
+                Box::new(NamedMockClient {
+                    name: "first".to_string(),
+                    calls: first_calls.clone(),
+                    fail: true,
+                }),
+                Box::new(NamedMockClient {
+                    name: "second".to_string(),
+                    calls: second_calls.clone(),
+                    fail: false,
+                }),
+            ],
+            FallbackStrategy::Sequential,
+        );
+
+        let response = client.chat("sys", "user").await.unwrap();
+
+        assert_eq!(response, "response from second");
+        assert_eq!(*first_calls.lock().unwrap(), 1);
+        assert_eq!(*second_calls.lock().unwrap(), 1);
+        assert_eq!(client.model_name(), "second");
+    }
+
+    #[tokio::test]
+    async fn test_multi_provider_sequential_errors_when_all_providers_fail() {
+        let client = MultiProviderLlmClient::new(
+            vec![
+                Box::new(NamedMockClient {
+                    name: "first".to_string(),
+                    calls: Arc::new(Mutex::new(0)),
+                    fail: true,
+                }),
+                Box::new(NamedMockClient {
+                    name: "second".to_string(),
+                    calls: Arc::new(Mutex::new(0)),
+                    fail: true,
+                }),
+            ],
+            FallbackStrategy::Sequential,
+        );
+
+        let result = client.chat("sys", "user").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multi_provider_round_robin_distributes_calls_evenly() {
+        let first_calls = Arc::new(Mutex::new(0));
+        let second_calls = Arc::new(Mutex::new(0));
+        let client = MultiProviderLlmClient::new(
+            vec![
+                Box::new(NamedMockClient {
+                    name: "first".to_string(),
+                    calls: first_calls.clone(),
+                    fail: false,
+                }),
+                Box::new(NamedMockClient {
+                    name: "second".to_string(),
+                    calls: second_calls.clone(),
+                    fail: false,
+                }),
+            ],
+            FallbackStrategy::RoundRobin,
+        );
+
+        for _ in 0..4 {
+            client.chat("sys", "user").await.unwrap();
+        }
+
+        assert_eq!(*first_calls.lock().unwrap(), 2);
+        assert_eq!(*second_calls.lock().unwrap(), 2);
     }
 }
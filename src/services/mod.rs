@@ -1,5 +1,9 @@
 pub mod llm;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod notifications;
+pub mod preprocessing;
 pub mod script;
 pub mod setup;
+pub mod stats;
 pub mod tts;
 pub mod workflow;
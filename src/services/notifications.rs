@@ -0,0 +1,75 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Body posted to `config.workflow.webhook_url` by `send_webhook`, so a CI
+/// pipeline watching a headless run can react to chapter/book progress
+/// without polling the build folder.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload {
+    pub event: String,
+    pub chapter: Option<String>,
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub timestamp: u64,
+}
+
+impl WebhookPayload {
+    pub fn new(event: impl Into<String>, chapter: Option<String>, success: bool, error_message: Option<String>) -> Self {
+        Self {
+            event: event.into(),
+            chapter,
+            success,
+            error_message,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Posts `payload` as JSON to `url`. Errors are returned rather than
+/// swallowed so callers can decide whether a failed notification should
+/// interrupt the workflow; `WorkflowManager` currently logs and continues.
+pub async fn send_webhook(url: &str, payload: &WebhookPayload) -> Result<()> {
+    let client = reqwest::Client::new();
+    client.post(url).json(payload).send().await?.error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_webhook_posts_expected_json_body() {
+        let server = wiremock::MockServer::start().await;
+
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .and(wiremock::matchers::path("/hook"))
+            .and(wiremock::matchers::body_json(serde_json::json!({
+                "event": "chapter_complete",
+                "chapter": "chapter1.txt",
+                "success": true,
+                "error_message": null,
+                "timestamp": 42
+            })))
+            .respond_with(wiremock::ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let payload = WebhookPayload {
+            event: "chapter_complete".to_string(),
+            chapter: Some("chapter1.txt".to_string()),
+            success: true,
+            error_message: None,
+            timestamp: 42,
+        };
+
+        send_webhook(&format!("{}/hook", server.uri()), &payload)
+            .await
+            .unwrap();
+    }
+}
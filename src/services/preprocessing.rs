@@ -0,0 +1,285 @@
+/// A single text transformation applied to a segment's text before it's
+/// handed to `TtsClient::synthesize`. Implementations should be cheap and
+/// side-effect free; `TextPreprocessor` may run several of them per segment.
+pub trait TextNormalizer: Send + Sync {
+    /// Stable identifier used in `config.preprocessing.enabled_normalizers`.
+    fn name(&self) -> &'static str;
+    fn normalize(&self, text: &str) -> String;
+}
+
+/// Collapses runs of the ellipsis character (`…`, including the common
+/// "……" double-width form) into a single `…`, so repeated ellipses don't
+/// make the TTS engine stumble over an unusually long pause.
+pub struct EllipsisNormalizer;
+
+impl TextNormalizer for EllipsisNormalizer {
+    fn name(&self) -> &'static str {
+        "ellipsis"
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '…' {
+                while chars.peek() == Some(&'…') {
+                    chars.next();
+                }
+                result.push('…');
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+}
+
+/// Collapses runs of the same exclamation/question mark (e.g. `！！！` or
+/// `???`) down to a single one.
+pub struct RepeatedPunctuationNormalizer;
+
+impl TextNormalizer for RepeatedPunctuationNormalizer {
+    fn name(&self) -> &'static str {
+        "repeated_punctuation"
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            result.push(c);
+            if matches!(c, '!' | '?' | '！' | '？') {
+                while chars.peek() == Some(&c) {
+                    chars.next();
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Converts runs of Arabic numerals embedded in the text into Chinese
+/// numerals (e.g. `"123"` -> `"一百二十三"`), since most TTS voices read
+/// digit strings one digit at a time rather than as a number.
+pub struct ChineseNumberNormalizer;
+
+impl TextNormalizer for ChineseNumberNormalizer {
+    fn name(&self) -> &'static str {
+        "chinese_number"
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut digits = String::new();
+        for c in text.chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+            } else {
+                if !digits.is_empty() {
+                    result.push_str(&digits_to_chinese(&digits));
+                    digits.clear();
+                }
+                result.push(c);
+            }
+        }
+        if !digits.is_empty() {
+            result.push_str(&digits_to_chinese(&digits));
+        }
+        result
+    }
+}
+
+const CHINESE_DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+const CHINESE_UNITS: [&str; 4] = ["", "十", "百", "千"];
+
+/// Converts a run of ASCII digits (no sign, no decimal point) into Chinese
+/// numerals, grouped by `萬`/`億` every 4 digits. Falls back to reading the
+/// digits one at a time for anything that doesn't look like a plain integer
+/// (e.g. a run longer than what `萬`/`億` grouping below covers).
+fn digits_to_chinese(digits: &str) -> String {
+    if digits.len() > 12 || digits.is_empty() {
+        return digits
+            .chars()
+            .map(|c| CHINESE_DIGITS[c.to_digit(10).unwrap() as usize])
+            .collect();
+    }
+
+    if digits == "0" {
+        return "零".to_string();
+    }
+
+    let groups = group_by_four(digits);
+    let big_units = ["", "萬", "億"];
+    let mut parts = Vec::new();
+    for (i, group) in groups.iter().enumerate() {
+        if group == "0000" || group.chars().all(|c| c == '0') {
+            continue;
+        }
+        let unit = big_units[groups.len() - 1 - i];
+        parts.push(format!("{}{}", group_to_chinese(group), unit));
+    }
+    parts.join("")
+}
+
+/// Splits a digit string into 4-digit groups, most-significant first,
+/// left-padding the leading group with zeros so every group is exactly 4
+/// digits wide (simplifies the per-group conversion in `group_to_chinese`).
+fn group_by_four(digits: &str) -> Vec<String> {
+    let padded_len = digits.len().div_ceil(4) * 4;
+    let padded = format!("{:0>width$}", digits, width = padded_len);
+    padded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+        .collect()
+}
+
+/// Converts a single 4-digit group (e.g. `"0205"`) into Chinese numerals
+/// (`"二百零五"`), omitting leading-zero digits but keeping an internal
+/// `零` wherever a digit is skipped (e.g. `"1001"` -> `"一千零一"`).
+fn group_to_chinese(group: &str) -> String {
+    let digits: Vec<u32> = group.chars().map(|c| c.to_digit(10).unwrap()).collect();
+    let mut result = String::new();
+    let mut pending_zero = false;
+    let mut started = false;
+
+    for (i, &d) in digits.iter().enumerate() {
+        let place = digits.len() - 1 - i;
+        if d == 0 {
+            if started {
+                pending_zero = true;
+            }
+            continue;
+        }
+        if pending_zero {
+            result.push('零');
+            pending_zero = false;
+        }
+        // Omit a leading "一十" -> "十" (e.g. "12" reads as "十二", not "一十二").
+        if !(place == 1 && d == 1 && !started) {
+            result.push(CHINESE_DIGITS[d as usize]);
+        }
+        result.push_str(CHINESE_UNITS[place]);
+        started = true;
+    }
+
+    result
+}
+
+/// Collapses runs of whitespace (spaces, tabs, newlines) into a single
+/// space, and trims leading/trailing whitespace — TTS providers otherwise
+/// tend to render extra whitespace as an audible pause.
+pub struct WhitespaceNormalizer;
+
+impl TextNormalizer for WhitespaceNormalizer {
+    fn name(&self) -> &'static str {
+        "whitespace"
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn all_normalizers() -> Vec<Box<dyn TextNormalizer>> {
+    vec![
+        Box::new(EllipsisNormalizer),
+        Box::new(RepeatedPunctuationNormalizer),
+        Box::new(ChineseNumberNormalizer),
+        Box::new(WhitespaceNormalizer),
+    ]
+}
+
+/// Runs a configured chain of `TextNormalizer`s over a segment's text
+/// before synthesis, applied in the order the normalizers are listed.
+pub struct TextPreprocessor {
+    normalizers: Vec<Box<dyn TextNormalizer>>,
+}
+
+impl TextPreprocessor {
+    pub fn new(normalizers: Vec<Box<dyn TextNormalizer>>) -> Self {
+        Self { normalizers }
+    }
+
+    /// Builds a preprocessor from `config.preprocessing.enabled_normalizers`,
+    /// keeping only the built-in normalizers named there. Unknown names are
+    /// silently ignored rather than erroring out, so a typo in config.yml
+    /// doesn't stop the whole workflow.
+    pub fn from_config(enabled_normalizers: &[String]) -> Self {
+        let mut available = all_normalizers();
+        let normalizers = enabled_normalizers
+            .iter()
+            .filter_map(|name| {
+                available
+                    .iter()
+                    .position(|n| n.name() == name.as_str())
+                    .map(|i| available.remove(i))
+            })
+            .collect();
+        Self { normalizers }
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        for normalizer in &self.normalizers {
+            result = normalizer.normalize(&result);
+        }
+        result
+    }
+}
+
+impl Default for TextPreprocessor {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ellipsis_normalizer_collapses_repeated_dots() {
+        let normalizer = EllipsisNormalizer;
+        assert_eq!(normalizer.normalize("等等……………………他來了"), "等等…他來了");
+    }
+
+    #[test]
+    fn test_repeated_punctuation_normalizer_collapses_runs() {
+        let normalizer = RepeatedPunctuationNormalizer;
+        assert_eq!(normalizer.normalize("不要！！！"), "不要！");
+        assert_eq!(normalizer.normalize("真的嗎???"), "真的嗎?");
+    }
+
+    #[test]
+    fn test_chinese_number_normalizer_converts_digits() {
+        let normalizer = ChineseNumberNormalizer;
+        assert_eq!(normalizer.normalize("他有123顆糖果"), "他有一百二十三顆糖果");
+        assert_eq!(normalizer.normalize("第12章"), "第十二章");
+        assert_eq!(normalizer.normalize("門牌1001號"), "門牌一千零一號");
+    }
+
+    #[test]
+    fn test_whitespace_normalizer_collapses_and_trims() {
+        let normalizer = WhitespaceNormalizer;
+        assert_eq!(normalizer.normalize("  他   說\n\n好的  "), "他 說 好的");
+    }
+
+    #[test]
+    fn test_preprocessor_applies_only_enabled_normalizers_in_order() {
+        let preprocessor = TextPreprocessor::from_config(&[
+            "ellipsis".to_string(),
+            "chinese_number".to_string(),
+        ]);
+
+        let result = preprocessor.normalize("他說……………10次  謝謝");
+
+        assert_eq!(result, "他說…十次  謝謝");
+    }
+
+    #[test]
+    fn test_preprocessor_with_no_enabled_normalizers_is_a_no_op() {
+        let preprocessor = TextPreprocessor::from_config(&[]);
+        assert_eq!(preprocessor.normalize("原封不動123"), "原封不動123");
+    }
+}
@@ -4,7 +4,7 @@ use anyhow::{Context, Ok, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct AudioSegment {
     pub text: String,
     pub speaker: Option<String>,
@@ -12,6 +12,24 @@ pub struct AudioSegment {
     pub style: Option<String>,
     #[serde(default)]
     pub voice_id: Option<String>,
+    /// Script language detected from `text` by
+    /// `crate::utils::text::detect_script_language`, when it differs from
+    /// the chapter's primary `AudioConfig::language` and matches one of
+    /// `AudioConfig::additional_languages`. `None` means "use the primary
+    /// language's voice as usual".
+    #[serde(default)]
+    pub detected_language: Option<String>,
+    /// How sure the LLM is of this segment's `speaker` assignment, from 0.0
+    /// (pure guess) to 1.0 (certain). Optional in the script JSON - absent
+    /// means full confidence, matching a script generator that doesn't
+    /// bother estimating it. `WorkflowManager::process_chapter` flags
+    /// segments below `WorkflowConfig::low_confidence_threshold` for review.
+    #[serde(default = "default_confidence")]
+    pub confidence: Option<f32>,
+}
+
+fn default_confidence() -> Option<f32> {
+    Some(1.0)
 }
 
 pub trait ScriptGenerator: Send + Sync {
@@ -27,11 +45,32 @@ pub trait ScriptGenerator: Send + Sync {
     fn support_style(&self) -> Vec<String>;
 }
 
-pub struct JsonScriptGenerator;
+/// Controls which emotion/style tags `JsonScriptGenerator` advertises to the LLM.
+#[derive(Debug, Clone, Default)]
+pub enum StyleMode {
+    /// Use the built-in global style list (the default behaviour).
+    #[default]
+    Global(Vec<String>),
+    /// Rely entirely on per-voice styles supplied via `voice_styles` in `generate_prompt`.
+    PerVoice,
+    /// Don't advertise any styles at all (e.g. the provider doesn't support them).
+    Disabled,
+}
+
+pub struct JsonScriptGenerator {
+    style_mode: StyleMode,
+}
 
 impl JsonScriptGenerator {
     pub fn new() -> Self {
-        Self
+        Self {
+            style_mode: StyleMode::Global(default_global_styles()),
+        }
+    }
+
+    pub fn with_style_mode(mut self, style_mode: StyleMode) -> Self {
+        self.style_mode = style_mode;
+        self
     }
 }
 
@@ -41,6 +80,23 @@ impl Default for JsonScriptGenerator {
     }
 }
 
+fn default_global_styles() -> Vec<String> {
+    [
+        "cheerful",
+        "sad",
+        "angry",
+        "affectionate",
+        "newscast",
+        "assistant",
+        "lyrical",
+        "calm",
+        "fearful",
+        "whispering",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
 impl ScriptGenerator for JsonScriptGenerator {
     fn get_system_prompt(&self) -> String {
         "你是一個有聲書腳本生成器。請將小說文本轉換為結構化的音頻腳本 JSON。".to_string()
@@ -93,7 +149,7 @@ impl ScriptGenerator for JsonScriptGenerator {
             \n\
             輸出格式（JSON 列表）：\n\
             [\n\
-              {{ \"speaker\": \"角色名或'旁白'\", \"text\": \"文本內容\", \"style\": \"情感/語氣(可選)\" }},\n\
+              {{ \"speaker\": \"角色名或'旁白'\", \"text\": \"文本內容\", \"style\": \"情感/語氣(可選)\", \"confidence\": \"說話者判斷信心(可選，0.0-1.0)\" }},\n\
               ...\n\
             ]\n\
             \n\
@@ -105,6 +161,7 @@ impl ScriptGenerator for JsonScriptGenerator {
             4. 保持文本完整，不要遺漏。\n\
             5. 對於不重要的路人角色，請根據性別使用 '路人(男)', '路人(女)' 或 '路人' 作為 speaker。\n\
             6. 若角色有特別指定情緒，請從該列表中選擇最合適的情緒。\n\
+            7. 若說話者身分不明確（如多人對話交錯、缺乏明顯標示），請填寫 confidence 為低於 0.7 的數值；判斷清楚時可省略該欄位。\n\
             \n\n文本：\n{}",
             characters_json,
             style_instruction,
@@ -121,20 +178,10 @@ impl ScriptGenerator for JsonScriptGenerator {
     }
 
     fn support_style(&self) -> Vec<String> {
-        [
-            "cheerful",
-            "sad",
-            "angry",
-            "affectionate",
-            "newscast",
-            "assistant",
-            "lyrical",
-            "calm",
-            "fearful",
-            "whispering",
-        ]
-        .map(String::from)
-        .to_vec()
+        match &self.style_mode {
+            StyleMode::Global(styles) => styles.clone(),
+            StyleMode::PerVoice | StyleMode::Disabled => Vec::new(),
+        }
     }
 }
 
@@ -190,7 +237,7 @@ impl ScriptGenerator for GptSovitsScriptGenerator {
             \n\
             輸出格式（JSON 列表）：\n\
             [\n\
-              {{ \"speaker\": \"角色名\", \"text\": \"文本內容\", \"style\": \"情緒(可選)\", \"voice_id\": \"聲音ID(可選)\" }},\n\
+              {{ \"speaker\": \"角色名\", \"text\": \"文本內容\", \"style\": \"情緒(可選)\", \"voice_id\": \"聲音ID(可選)\", \"confidence\": \"說話者判斷信心(可選，0.0-1.0)\" }},\n\
               ...\n\
             ]\n\
             \n\
@@ -202,6 +249,7 @@ impl ScriptGenerator for GptSovitsScriptGenerator {
             5. 指定 style，必須是該 voice_id 支援的情緒 (emotion)。\n\
             6. 重要：voice_id 和 style 的值必須嚴格對應列表中的 Key，**絕對禁止翻譯或修改**（例如 'happy' 不能寫成 '開心'）。\n\
             7. 保持文本完整，不要遺漏。\n\
+            8. 若說話者身分不明確，請填寫 confidence 為低於 0.7 的數值；判斷清楚時可省略該欄位。\n\
             \n\n文本：\n{}",
             self.narrator_voice_id,
             characters_json,
@@ -275,7 +323,7 @@ impl ScriptGenerator for Qwen3ScriptGenerator {
             \n\
             輸出格式（JSON 列表）：\n\
             [\n\
-              {{ \"speaker\": \"角色名\", \"text\": \"文本內容\", \"style\": \"情緒(可選)\", \"voice_id\": \"聲音ID(可選)\" }},\n\
+              {{ \"speaker\": \"角色名\", \"text\": \"文本內容\", \"style\": \"情緒(可選)\", \"voice_id\": \"聲音ID(可選)\", \"confidence\": \"說話者判斷信心(可選，0.0-1.0)\" }},\n\
               ...\n\
             ]\n\
             \n\
@@ -287,6 +335,7 @@ impl ScriptGenerator for Qwen3ScriptGenerator {
             5. 指定 style，必須是該 voice_id 支援的情緒。\n\
             6. 重要：voice_id 和 style 的值必須嚴格對應列表中的 Key，**絕對禁止翻譯或修改**。\n\
             7. 保持文本完整，不要遺漏。\n\
+            8. 若說話者身分不明確，請填寫 confidence 為低於 0.7 的數值；判斷清楚時可省略該欄位。\n\
             \n\n文本：\n{}",
             self.narrator_voice_id,
             characters_json,
@@ -308,6 +357,66 @@ impl ScriptGenerator for Qwen3ScriptGenerator {
     }
 }
 
+/// Splits `segment` into pieces no longer than `max_chars`, cutting only at
+/// sentence-ending punctuation (`。！？.!?`) so a TTS provider's length limit
+/// doesn't truncate mid-sentence. A boundary found while inside an open
+/// quote (`「`/`」`, `『`/`』`, `"`/`"`/`"`) is skipped so a split never lands
+/// inside quoted dialogue; outside a quote, if no safe boundary exists
+/// before `max_chars` is reached, the text is force-cut there. While a quote
+/// is still open no cut is made, so a part can run past `max_chars` until
+/// the quote closes.
+/// `speaker`/`style`/`voice_id` are copied onto every part.
+pub fn split_long_segment(segment: &AudioSegment, max_chars: usize) -> Vec<AudioSegment> {
+    if max_chars == 0 || segment.text.chars().count() <= max_chars {
+        return vec![segment.clone()];
+    }
+
+    let chars: Vec<char> = segment.text.chars().collect();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quote_depth: i32 = 0;
+    let mut last_boundary: Option<usize> = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '「' | '『' | '\u{201C}' => quote_depth += 1,
+            '」' | '』' | '\u{201D}' => quote_depth = (quote_depth - 1).max(0),
+            // A straight ASCII quote can't tell open from close on its own,
+            // so treat it as a toggle.
+            '"' => quote_depth = if quote_depth == 0 { 1 } else { 0 },
+            _ => {}
+        }
+
+        if matches!(c, '。' | '！' | '？' | '.' | '!' | '?') && quote_depth == 0 {
+            last_boundary = Some(i + 1);
+        }
+
+        // Don't even consider cutting while inside an open quote — exceeding
+        // `max_chars` is preferable to splitting quoted dialogue in half.
+        if quote_depth == 0 && i + 1 - start >= max_chars {
+            let cut = match last_boundary {
+                Some(boundary) if boundary > start => boundary,
+                _ => i + 1,
+            };
+            parts.push(chars[start..cut].iter().collect::<String>());
+            start = cut;
+            last_boundary = None;
+        }
+    }
+
+    if start < chars.len() {
+        parts.push(chars[start..].iter().collect::<String>());
+    }
+
+    parts
+        .into_iter()
+        .map(|text| AudioSegment {
+            text,
+            ..segment.clone()
+        })
+        .collect()
+}
+
 pub fn strip_code_blocks(s: &str) -> String {
     let s = s.trim();
     if s.starts_with("```json") {
@@ -324,3 +433,190 @@ pub fn strip_code_blocks(s: &str) -> String {
         s.to_string()
     }
 }
+
+/// Removes consecutive segments where both `speaker` and `text` are
+/// identical, which LLMs occasionally emit (e.g. repeating a line across two
+/// script entries), wasting synthesis credits and producing a jarring
+/// back-to-back repeat in the audio. Only adjacent duplicates are collapsed;
+/// the same line spoken again later by the same character is left alone.
+pub fn deduplicate_segments(segments: &mut Vec<AudioSegment>) {
+    let before = segments.len();
+    segments.dedup_by(|a, b| a.speaker == b.speaker && a.text == b.text);
+    let removed = before - segments.len();
+    if removed > 0 {
+        log::warn!("Removed {} duplicate consecutive segment(s)", removed);
+    }
+}
+
+/// Removes any segment whose `text` is empty once trimmed, which LLMs
+/// occasionally emit and which would otherwise be sent to a TTS provider for
+/// nothing.
+pub fn filter_empty_segments(segments: &mut Vec<AudioSegment>) {
+    let before = segments.len();
+    segments.retain(|s| !s.text.trim().is_empty());
+    let removed = before - segments.len();
+    if removed > 0 {
+        log::warn!("Removed {} empty segment(s)", removed);
+    }
+}
+
+/// Returns the indices of `segments` whose `confidence` (treating an absent
+/// value as full confidence) falls below `threshold`, for
+/// `WorkflowManager::process_chapter` to flag via
+/// `WorkflowConfig::low_confidence_threshold` before synthesis.
+pub fn low_confidence_segment_indices(segments: &[AudioSegment], threshold: f32) -> Vec<usize> {
+    segments
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.confidence.unwrap_or(1.0) < threshold)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Tags `AudioSegment::detected_language` on each segment whose script
+/// composition (via `crate::utils::text::detect_script_language`) differs
+/// from `primary_language` and is one of `additional_languages` - e.g. an
+/// English line inside an otherwise `"zh"` chapter. Segments whose detected
+/// language isn't configured as an additional language are left untagged
+/// (`None`), so a provider falls back to its primary-language voice for them.
+pub fn tag_detected_languages(
+    segments: &mut [AudioSegment],
+    primary_language: &str,
+    additional_languages: &[String],
+) {
+    if additional_languages.is_empty() {
+        return;
+    }
+    for segment in segments.iter_mut() {
+        let Some(detected) = crate::utils::text::detect_script_language(&segment.text) else {
+            continue;
+        };
+        if detected == primary_language {
+            continue;
+        }
+        if additional_languages.iter().any(|lang| lang == &detected) {
+            segment.detected_language = Some(detected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str) -> AudioSegment {
+        AudioSegment {
+            text: text.to_string(),
+            speaker: Some("旁白".to_string()),
+            style: Some("calm".to_string()),
+            voice_id: Some("voice-1".to_string()),
+            detected_language: None,
+            confidence: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn test_split_long_segment_leaves_short_text_untouched() {
+        let seg = segment("一句短話。");
+        let parts = split_long_segment(&seg, 100);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].text, "一句短話。");
+    }
+
+    #[test]
+    fn test_split_long_segment_splits_at_sentence_boundaries() {
+        let text = "第一句話。第二句話。第三句話。";
+        let seg = segment(text);
+
+        let parts = split_long_segment(&seg, 6);
+
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.text.chars().count() <= 6);
+            assert_eq!(part.speaker, seg.speaker);
+            assert_eq!(part.style, seg.style);
+            assert_eq!(part.voice_id, seg.voice_id);
+        }
+
+        let reconstructed: String = parts.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(reconstructed, text);
+    }
+
+    #[test]
+    fn test_split_long_segment_never_splits_inside_quoted_dialogue() {
+        let text = "他說：「這句話很長很長很長很長很長很長。還沒講完喔。」然後就走了。";
+        let seg = segment(text);
+
+        let parts = split_long_segment(&seg, 8);
+
+        for part in &parts {
+            let opens = part.text.matches('「').count();
+            let closes = part.text.matches('」').count();
+            assert_eq!(
+                opens, closes,
+                "part {:?} should not start or end inside an open quote",
+                part.text
+            );
+        }
+
+        let reconstructed: String = parts.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(reconstructed, text);
+    }
+
+    #[test]
+    fn test_deduplicate_segments_collapses_consecutive_duplicates() {
+        let mut segments = vec![segment("一樣的話。"), segment("一樣的話。"), segment("不一樣。")];
+
+        deduplicate_segments(&mut segments);
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "一樣的話。");
+        assert_eq!(segments[1].text, "不一樣。");
+    }
+
+    #[test]
+    fn test_filter_empty_segments_removes_regardless_of_position() {
+        let mut segments = vec![
+            segment(""),
+            segment("開頭後的第一句。"),
+            segment("   "),
+            segment("中間的句子。"),
+            segment(""),
+        ];
+
+        filter_empty_segments(&mut segments);
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "開頭後的第一句。");
+        assert_eq!(segments[1].text, "中間的句子。");
+    }
+
+    #[test]
+    fn test_tag_detected_languages_marks_ascii_segment_as_english() {
+        let mut segments = vec![segment("This is pure English."), segment("這是中文句子。")];
+
+        tag_detected_languages(&mut segments, "zh", &["en".to_string()]);
+
+        assert_eq!(segments[0].detected_language.as_deref(), Some("en"));
+        assert_eq!(segments[1].detected_language, None);
+    }
+
+    #[test]
+    fn test_tag_detected_languages_leaves_untagged_without_configured_additional_language() {
+        let mut segments = vec![segment("This is pure English.")];
+
+        tag_detected_languages(&mut segments, "zh", &[]);
+
+        assert_eq!(segments[0].detected_language, None);
+    }
+
+    #[test]
+    fn test_low_confidence_segment_indices_flags_below_threshold() {
+        let mut segments = vec![segment("a"), segment("b"), segment("c")];
+        segments[0].confidence = Some(0.9);
+        segments[1].confidence = Some(0.3);
+        segments[2].confidence = None; // absent means full confidence
+
+        assert_eq!(low_confidence_segment_indices(&segments, 0.5), vec![1]);
+    }
+}
@@ -1,12 +1,16 @@
 use crate::core::config::Config;
+use crate::core::state::CharacterMap;
 use crate::services::llm::LlmClient;
 use crate::services::tts::{fetch_voice_list, Voice};
 use anyhow::{anyhow, Result};
-use inquire::Select;
+use inquire::{Confirm, Select, Text};
+use std::fs;
+use std::path::Path;
 
 pub async fn run_setup(config: &mut Config, llm: Option<&dyn LlmClient>) -> Result<()> {
     let mut needs_save = false;
     let provider = config.audio.provider.clone();
+    let headless = config.unattended || config.audio.auto_select_voices;
 
     match provider.as_str() {
         "edge-tts" => {
@@ -38,26 +42,32 @@ pub async fn run_setup(config: &mut Config, llm: Option<&dyn LlmClient>) -> Resu
                 let cfg = config.audio.edge_tts.as_mut().unwrap();
 
                 if cfg.narrator_voice.is_none() {
-                    cfg.narrator_voice = Some(select_voice(
+                    cfg.narrator_voice = Some(resolve_voice(
                         "Select Narrator Voice:",
                         &filtered_voices,
                         |_| true,
+                        VoiceRole::Narrator,
+                        headless,
                     )?);
                     needs_save = true;
                 }
                 if cfg.default_male_voice.is_none() {
-                    cfg.default_male_voice = Some(select_voice(
+                    cfg.default_male_voice = Some(resolve_voice(
                         "Select Default Male Voice:",
                         &filtered_voices,
                         |v| v.gender == "Male",
+                        VoiceRole::DefaultMale,
+                        headless,
                     )?);
                     needs_save = true;
                 }
                 if cfg.default_female_voice.is_none() {
-                    cfg.default_female_voice = Some(select_voice(
+                    cfg.default_female_voice = Some(resolve_voice(
                         "Select Default Female Voice:",
                         &filtered_voices,
                         |v| v.gender == "Female",
+                        VoiceRole::DefaultFemale,
+                        headless,
                     )?);
                     needs_save = true;
                 }
@@ -96,8 +106,13 @@ pub async fn run_setup(config: &mut Config, llm: Option<&dyn LlmClient>) -> Resu
                 let cfg = config.audio.gpt_sovits.as_mut().unwrap();
 
                 if cfg.narrator_voice.is_none() {
-                    cfg.narrator_voice =
-                        Some(select_voice("Select Narrator Voice:", &voices, |_| true)?);
+                    cfg.narrator_voice = Some(resolve_voice(
+                        "Select Narrator Voice:",
+                        &voices,
+                        |_| true,
+                        VoiceRole::Narrator,
+                        headless,
+                    )?);
                     needs_save = true;
                 }
             }
@@ -110,6 +125,8 @@ pub async fn run_setup(config: &mut Config, llm: Option<&dyn LlmClient>) -> Resu
                     narrator_voice: None,
                     concurrency: 1,
                     device: None,
+                    prefetch_voices: true,
+                    ..Default::default()
                 });
             }
 
@@ -134,10 +151,221 @@ pub async fn run_setup(config: &mut Config, llm: Option<&dyn LlmClient>) -> Resu
                 let cfg = config.audio.qwen3_tts.as_mut().unwrap();
 
                 if cfg.narrator_voice.is_none() {
-                    cfg.narrator_voice = Some(select_voice(
+                    cfg.narrator_voice = Some(resolve_voice(
                         "Select Narrator Voice:",
                         &filtered_voices,
                         |_| true,
+                        VoiceRole::Narrator,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+            }
+        }
+        "azure" => {
+            if config.audio.azure.is_none() {
+                config.audio.azure = Some(Default::default());
+            }
+
+            let setup_needed = {
+                let cfg = config.audio.azure.as_ref().unwrap();
+                cfg.subscription_key.is_empty()
+                    || cfg.region.is_empty()
+                    || cfg.narrator_voice.is_none()
+                    || cfg.default_male_voice.is_none()
+                    || cfg.default_female_voice.is_none()
+            };
+
+            if setup_needed {
+                let cfg = config.audio.azure.as_mut().unwrap();
+                if cfg.subscription_key.is_empty() {
+                    cfg.subscription_key = Text::new("Azure subscription key:").prompt()?;
+                    needs_save = true;
+                }
+                if cfg.region.is_empty() {
+                    cfg.region = Text::new("Azure region (e.g. eastus):").prompt()?;
+                    needs_save = true;
+                }
+
+                println!("Fetching Azure TTS voices...");
+                let voices = fetch_voice_list(config, llm).await?;
+                let lang = &config.audio.language;
+                let filtered_voices: Vec<Voice> = voices
+                    .into_iter()
+                    .filter(|v| v.locale.starts_with(lang))
+                    .collect();
+
+                if filtered_voices.is_empty() {
+                    return Err(anyhow!("No voices found for language: {}", lang));
+                }
+
+                let cfg = config.audio.azure.as_mut().unwrap();
+
+                if cfg.narrator_voice.is_none() {
+                    cfg.narrator_voice = Some(resolve_voice(
+                        "Select Narrator Voice:",
+                        &filtered_voices,
+                        |_| true,
+                        VoiceRole::Narrator,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+                if cfg.default_male_voice.is_none() {
+                    cfg.default_male_voice = Some(resolve_voice(
+                        "Select Default Male Voice:",
+                        &filtered_voices,
+                        |v| v.gender == "Male",
+                        VoiceRole::DefaultMale,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+                if cfg.default_female_voice.is_none() {
+                    cfg.default_female_voice = Some(resolve_voice(
+                        "Select Default Female Voice:",
+                        &filtered_voices,
+                        |v| v.gender == "Female",
+                        VoiceRole::DefaultFemale,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        "polly" => {
+            if config.audio.polly.is_none() {
+                config.audio.polly = Some(Default::default());
+            }
+
+            let setup_needed = {
+                let cfg = config.audio.polly.as_ref().unwrap();
+                cfg.access_key_id.is_empty()
+                    || cfg.secret_access_key.is_empty()
+                    || cfg.region.is_empty()
+                    || cfg.narrator_voice.is_none()
+                    || cfg.default_male_voice.is_none()
+                    || cfg.default_female_voice.is_none()
+            };
+
+            if setup_needed {
+                let cfg = config.audio.polly.as_mut().unwrap();
+                if cfg.access_key_id.is_empty() {
+                    cfg.access_key_id = Text::new("AWS access key ID:").prompt()?;
+                    needs_save = true;
+                }
+                if cfg.secret_access_key.is_empty() {
+                    cfg.secret_access_key = Text::new("AWS secret access key:").prompt()?;
+                    needs_save = true;
+                }
+                if cfg.region.is_empty() {
+                    cfg.region = Text::new("AWS region (e.g. us-east-1):").prompt()?;
+                    needs_save = true;
+                }
+
+                println!("Fetching Polly voices...");
+                let voices = fetch_voice_list(config, llm).await?;
+                let lang = &config.audio.language;
+                let filtered_voices: Vec<Voice> = voices
+                    .into_iter()
+                    .filter(|v| v.locale.starts_with(lang))
+                    .collect();
+
+                if filtered_voices.is_empty() {
+                    return Err(anyhow!("No voices found for language: {}", lang));
+                }
+
+                let cfg = config.audio.polly.as_mut().unwrap();
+
+                if cfg.narrator_voice.is_none() {
+                    cfg.narrator_voice = Some(resolve_voice(
+                        "Select Narrator Voice:",
+                        &filtered_voices,
+                        |_| true,
+                        VoiceRole::Narrator,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+                if cfg.default_male_voice.is_none() {
+                    cfg.default_male_voice = Some(resolve_voice(
+                        "Select Default Male Voice:",
+                        &filtered_voices,
+                        |v| v.gender == "Male",
+                        VoiceRole::DefaultMale,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+                if cfg.default_female_voice.is_none() {
+                    cfg.default_female_voice = Some(resolve_voice(
+                        "Select Default Female Voice:",
+                        &filtered_voices,
+                        |v| v.gender == "Female",
+                        VoiceRole::DefaultFemale,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+            }
+        }
+        "elevenlabs" => {
+            if config.audio.elevenlabs.is_none() {
+                config.audio.elevenlabs = Some(Default::default());
+            }
+
+            let setup_needed = {
+                let cfg = config.audio.elevenlabs.as_ref().unwrap();
+                cfg.api_key.is_empty()
+                    || cfg.narrator_voice.is_none()
+                    || cfg.default_male_voice.is_none()
+                    || cfg.default_female_voice.is_none()
+            };
+
+            if setup_needed {
+                let cfg = config.audio.elevenlabs.as_mut().unwrap();
+                if cfg.api_key.is_empty() {
+                    cfg.api_key = Text::new("ElevenLabs API key:").prompt()?;
+                    needs_save = true;
+                }
+
+                println!("Fetching ElevenLabs voices...");
+                let voices = fetch_voice_list(config, llm).await?;
+
+                if voices.is_empty() {
+                    return Err(anyhow!("No ElevenLabs voices found on this account."));
+                }
+
+                let cfg = config.audio.elevenlabs.as_mut().unwrap();
+
+                if cfg.narrator_voice.is_none() {
+                    cfg.narrator_voice = Some(resolve_voice(
+                        "Select Narrator Voice:",
+                        &voices,
+                        |_| true,
+                        VoiceRole::Narrator,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+                if cfg.default_male_voice.is_none() {
+                    cfg.default_male_voice = Some(resolve_voice(
+                        "Select Default Male Voice:",
+                        &voices,
+                        |v| v.gender == "Male",
+                        VoiceRole::DefaultMale,
+                        headless,
+                    )?);
+                    needs_save = true;
+                }
+                if cfg.default_female_voice.is_none() {
+                    cfg.default_female_voice = Some(resolve_voice(
+                        "Select Default Female Voice:",
+                        &voices,
+                        |v| v.gender == "Female",
+                        VoiceRole::DefaultFemale,
+                        headless,
                     )?);
                     needs_save = true;
                 }
@@ -156,6 +384,66 @@ pub async fn run_setup(config: &mut Config, llm: Option<&dyn LlmClient>) -> Resu
     Ok(())
 }
 
+/// Which of the three voice slots a `resolve_voice` call is filling in, so
+/// `HeadlessSetupStrategy` knows which selection criteria to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VoiceRole {
+    Narrator,
+    DefaultMale,
+    DefaultFemale,
+}
+
+/// Picks voices without any stdin interaction, for CI and other unattended
+/// environments where `inquire::Select` would block forever waiting on a
+/// terminal that doesn't exist. Criteria:
+/// - `Narrator`: the first female voice in the list.
+/// - `DefaultMale`: the first male voice in the list.
+/// - `DefaultFemale`: the second female voice in the list, so it doesn't
+///   collide with whichever one `Narrator` already picked.
+///
+/// Each falls back to the first voice in the list if its preferred gender
+/// isn't represented, so setup still completes on a voice list that's e.g.
+/// all-male.
+struct HeadlessSetupStrategy;
+
+impl HeadlessSetupStrategy {
+    fn select(voices: &[Voice], role: VoiceRole) -> Result<String> {
+        let first_of = |gender: &str| voices.iter().find(|v| v.gender == gender);
+
+        let chosen = match role {
+            VoiceRole::Narrator => first_of("Female"),
+            VoiceRole::DefaultMale => first_of("Male"),
+            VoiceRole::DefaultFemale => voices.iter().filter(|v| v.gender == "Female").nth(1),
+        };
+
+        chosen
+            .or_else(|| voices.first())
+            .map(|v| v.short_name.clone())
+            .ok_or_else(|| anyhow!("No voices available for headless setup"))
+    }
+}
+
+/// Selects a voice for `role`, either interactively via `select_voice` or,
+/// when `headless` is set (`Config::unattended` or
+/// `AudioConfig::auto_select_voices`), via `HeadlessSetupStrategy` with no
+/// prompt at all.
+fn resolve_voice<F>(
+    prompt: &str,
+    voices: &[Voice],
+    filter: F,
+    role: VoiceRole,
+    headless: bool,
+) -> Result<String>
+where
+    F: Fn(&Voice) -> bool,
+{
+    if headless {
+        HeadlessSetupStrategy::select(voices, role)
+    } else {
+        select_voice(prompt, voices, filter)
+    }
+}
+
 fn select_voice<F>(prompt: &str, voices: &[Voice], filter: F) -> Result<String>
 where
     F: Fn(&Voice) -> bool,
@@ -181,8 +469,173 @@ where
         })
         .collect();
 
-    let selection = Select::new(prompt, options).prompt()?;
+    let selection = select_voice_searchable(prompt, options)?;
 
     let short_name = selection.split_whitespace().next().unwrap().to_string();
     Ok(short_name)
 }
+
+/// Like `Select::new(...).prompt()`, but with case-insensitive substring
+/// filtering and pagination
+/// enabled so long voice lists (hundreds of entries for Edge TTS) stay
+/// navigable. Typing `"female"` or `"CN"` narrows the list accordingly.
+fn select_voice_searchable(prompt: &str, options: Vec<String>) -> Result<String> {
+    Select::new(prompt, options)
+        .with_filter(&|input, _value, string_value, _index| {
+            string_value.to_lowercase().contains(&input.to_lowercase())
+        })
+        .with_page_size(20)
+        .prompt()
+        .map_err(Into::into)
+}
+
+/// Walks every character in `char_map` (populated by a previous run's
+/// character analysis) and offers to reassign its voice, previewing the
+/// character's current voice and skipping it on request. Saves
+/// `character_map.json` back to `config.build_folder` once done. Gated to
+/// native builds by its caller in `main.rs`, since `inquire` prompts need a
+/// real terminal.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_character_editor(
+    config: &Config,
+    char_map: &mut CharacterMap,
+    llm: Option<&dyn LlmClient>,
+) -> Result<()> {
+    if char_map.characters.is_empty() {
+        println!("No characters to edit yet.");
+        return Ok(());
+    }
+
+    println!("Fetching voices for character editor...");
+    let voices = fetch_voice_list(config, llm).await?;
+    let lang = &config.audio.language;
+    let filtered_voices: Vec<Voice> = voices
+        .iter()
+        .filter(|v| v.locale.starts_with(lang.as_str()))
+        .cloned()
+        .collect();
+    let voices = if filtered_voices.is_empty() {
+        voices
+    } else {
+        filtered_voices
+    };
+
+    let mut names: Vec<String> = char_map.characters.keys().cloned().collect();
+    names.sort();
+
+    for name in names {
+        let current = char_map
+            .characters
+            .get(&name)
+            .and_then(|info| info.voice_id.as_deref())
+            .unwrap_or("(none)")
+            .to_string();
+
+        let edit = Confirm::new(&format!(
+            "{} - current voice: {}. Reassign?",
+            name, current
+        ))
+        .with_default(false)
+        .prompt()?;
+
+        if !edit {
+            continue;
+        }
+
+        let options: Vec<String> = voices
+            .iter()
+            .map(|v| {
+                format!(
+                    "{} ({}/{}) - {}",
+                    v.short_name,
+                    v.gender,
+                    v.locale,
+                    v.friendly_name.as_deref().unwrap_or(&v.name)
+                )
+            })
+            .collect();
+
+        let selection =
+            select_voice_searchable(&format!("Select voice for {}:", name), options)?;
+        let short_name = selection.split_whitespace().next().unwrap().to_string();
+
+        if let Some(info) = char_map.characters.get_mut(&name) {
+            info.voice_id = Some(short_name);
+        }
+    }
+
+    let path = Path::new(&config.build_folder).join("character_map.json");
+    fs::create_dir_all(&config.build_folder)?;
+    fs::write(path, serde_json::to_string_pretty(char_map)?)?;
+    println!("Character map updated.");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(short_name: &str, gender: &str) -> Voice {
+        Voice {
+            name: short_name.to_string(),
+            short_name: short_name.to_string(),
+            gender: gender.to_string(),
+            locale: "zh-CN".to_string(),
+            friendly_name: None,
+        }
+    }
+
+    #[test]
+    fn test_headless_setup_selects_expected_voices_without_stdin() {
+        let voices = vec![
+            voice("male-a", "Male"),
+            voice("female-a", "Female"),
+            voice("male-b", "Male"),
+            voice("female-b", "Female"),
+        ];
+
+        assert_eq!(
+            HeadlessSetupStrategy::select(&voices, VoiceRole::Narrator).unwrap(),
+            "female-a"
+        );
+        assert_eq!(
+            HeadlessSetupStrategy::select(&voices, VoiceRole::DefaultMale).unwrap(),
+            "male-a"
+        );
+        assert_eq!(
+            HeadlessSetupStrategy::select(&voices, VoiceRole::DefaultFemale).unwrap(),
+            "female-b"
+        );
+    }
+
+    #[test]
+    fn test_headless_setup_falls_back_to_first_voice_when_gender_missing() {
+        let voices = vec![voice("male-a", "Male"), voice("male-b", "Male")];
+
+        assert_eq!(
+            HeadlessSetupStrategy::select(&voices, VoiceRole::Narrator).unwrap(),
+            "male-a"
+        );
+        assert_eq!(
+            HeadlessSetupStrategy::select(&voices, VoiceRole::DefaultFemale).unwrap(),
+            "male-a"
+        );
+    }
+
+    #[test]
+    fn test_resolve_voice_does_not_prompt_when_headless() {
+        let voices = vec![voice("male-a", "Male"), voice("female-a", "Female")];
+
+        let selected = resolve_voice(
+            "Select Narrator Voice:",
+            &voices,
+            |_| true,
+            VoiceRole::Narrator,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(selected, "female-a");
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Usage stats for one voice/character pairing, accumulated across the
+/// segments synthesized with it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VoiceStats {
+    pub voice_id: String,
+    pub character_name: String,
+    pub segment_count: u32,
+    pub estimated_chars: u32,
+}
+
+/// Per-voice usage stats collected during synthesis, written to
+/// `build_folder/voice_stats.json` so a user can review which voices ended
+/// up used (and how often) across the whole book, for quality review.
+/// `load`/merging makes this additive across runs: resynthesizing a single
+/// chapter only updates that chapter's entries, it doesn't discard stats
+/// already recorded for the rest of the book.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VoiceStatsReport {
+    pub stats: Vec<VoiceStats>,
+}
+
+impl VoiceStatsReport {
+    /// Loads `path`, falling back to an empty report if it doesn't exist yet
+    /// or fails to parse (e.g. on the very first run).
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Adds one synthesized segment's usage to the matching `(voice_id,
+    /// character_name)` entry, creating it if this is the first time that
+    /// pairing has been seen.
+    pub fn record(&mut self, voice_id: &str, character_name: &str, chars: u32) {
+        match self
+            .stats
+            .iter_mut()
+            .find(|s| s.voice_id == voice_id && s.character_name == character_name)
+        {
+            Some(entry) => {
+                entry.segment_count += 1;
+                entry.estimated_chars += chars;
+            }
+            None => self.stats.push(VoiceStats {
+                voice_id: voice_id.to_string(),
+                character_name: character_name.to_string(),
+                segment_count: 1,
+                estimated_chars: chars,
+            }),
+        }
+    }
+
+    /// Merges `other`'s entries into `self`, summing counts for any
+    /// `(voice_id, character_name)` pairing present in both.
+    pub fn merge(&mut self, other: &VoiceStatsReport) {
+        for entry in &other.stats {
+            match self
+                .stats
+                .iter_mut()
+                .find(|s| s.voice_id == entry.voice_id && s.character_name == entry.character_name)
+            {
+                Some(existing) => {
+                    existing.segment_count += entry.segment_count;
+                    existing.estimated_chars += entry.estimated_chars;
+                }
+                None => self.stats.push(entry.clone()),
+            }
+        }
+    }
+
+    /// Prints a human-readable table of voice usage to stdout.
+    pub fn print_summary(&self) {
+        println!("\n=== Voice Usage ===");
+        println!(
+            "{:<30} {:<20} {:>10} {:>14}",
+            "Voice", "Character", "Segments", "Chars"
+        );
+        for s in &self.stats {
+            println!(
+                "{:<30} {:<20} {:>10} {:>14}",
+                s.voice_id, s.character_name, s.segment_count, s.estimated_chars
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_accumulates_same_voice_across_calls() {
+        let mut report = VoiceStatsReport::default();
+        report.record("voice-a", "Hero", 10);
+        report.record("voice-a", "Hero", 5);
+        report.record("voice-b", "旁白", 20);
+
+        assert_eq!(report.stats.len(), 2);
+        let hero = report
+            .stats
+            .iter()
+            .find(|s| s.character_name == "Hero")
+            .unwrap();
+        assert_eq!(hero.segment_count, 2);
+        assert_eq!(hero.estimated_chars, 15);
+    }
+
+    #[test]
+    fn test_merge_sums_overlapping_entries_and_keeps_unique_ones() {
+        let mut a = VoiceStatsReport::default();
+        a.record("voice-a", "Hero", 10);
+
+        let mut b = VoiceStatsReport::default();
+        b.record("voice-a", "Hero", 3);
+        b.record("voice-c", "Villain", 7);
+
+        a.merge(&b);
+
+        assert_eq!(a.stats.len(), 2);
+        let hero = a.stats.iter().find(|s| s.character_name == "Hero").unwrap();
+        assert_eq!(hero.segment_count, 2);
+        assert_eq!(hero.estimated_chars, 13);
+        let villain = a
+            .stats
+            .iter()
+            .find(|s| s.character_name == "Villain")
+            .unwrap();
+        assert_eq!(villain.segment_count, 1);
+        assert_eq!(villain.estimated_chars, 7);
+    }
+
+    #[test]
+    fn test_load_returns_default_when_file_missing() {
+        let report = VoiceStatsReport::load(Path::new("/nonexistent/voice_stats.json"));
+        assert_eq!(report, VoiceStatsReport::default());
+    }
+}
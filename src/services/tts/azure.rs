@@ -0,0 +1,458 @@
+use crate::core::state::CharacterMap;
+use crate::services::script::{AudioSegment, JsonScriptGenerator, ScriptGenerator};
+use crate::services::tts::{
+    TtsClient, Voice, VOICE_ID_MOB_FEMALE, VOICE_ID_MOB_MALE, VOICE_ID_MOB_NEUTRAL,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+// --- Config ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AzureTtsConfig {
+    pub subscription_key: String,
+    pub region: String,
+    pub narrator_voice: Option<String>,
+    pub default_male_voice: Option<String>,
+    pub default_female_voice: Option<String>,
+}
+
+/// Azure issues access tokens valid for 10 minutes; refresh a minute early
+/// so a synthesis call never races an about-to-expire token.
+const TOKEN_TTL: Duration = Duration::from_secs(9 * 60);
+
+/// Wraps `text` in a `<prosody>` tag when `speed`/`pitch_semitones` deviate
+/// from their defaults (`1.0`/`0.0`), matching `edge::apply_prosody`.
+fn apply_prosody(text: &str, speed: Option<f32>, pitch_semitones: Option<f32>) -> String {
+    let speed = speed.unwrap_or(1.0);
+    let pitch = pitch_semitones.unwrap_or(0.0);
+    if speed == 1.0 && pitch == 0.0 {
+        return text.to_string();
+    }
+    let rate_pct = ((speed - 1.0) * 100.0).round() as i32;
+    format!(
+        "<prosody rate='{:+}%' pitch='{:+}st'>{}</prosody>",
+        rate_pct, pitch, text
+    )
+}
+
+// --- Azure Voice List API ---
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+struct AzureVoiceEntry {
+    #[serde(rename = "ShortName")]
+    short_name: String,
+    #[serde(rename = "Gender")]
+    gender: String,
+    #[serde(rename = "Locale")]
+    locale: String,
+    #[serde(rename = "DisplayName")]
+    display_name: Option<String>,
+    #[serde(rename = "LocalName")]
+    local_name: Option<String>,
+}
+
+impl From<AzureVoiceEntry> for Voice {
+    fn from(entry: AzureVoiceEntry) -> Self {
+        let friendly_name = entry.local_name.or_else(|| entry.display_name.clone());
+        Voice {
+            name: entry
+                .display_name
+                .unwrap_or_else(|| entry.short_name.clone()),
+            short_name: entry.short_name,
+            gender: entry.gender,
+            locale: entry.locale,
+            friendly_name,
+        }
+    }
+}
+
+pub async fn list_voices(config: &AzureTtsConfig) -> Result<Vec<Voice>> {
+    let url = format!(
+        "https://{}.tts.speech.microsoft.com/cognitiveservices/voices/list",
+        config.region
+    );
+    let client = reqwest::Client::new();
+    let resp = client
+        .get(&url)
+        .header("Ocp-Apim-Subscription-Key", &config.subscription_key)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("Failed to list Azure voices: {}", resp.status()));
+    }
+    let entries: Vec<AzureVoiceEntry> = resp.json().await?;
+    Ok(entries.into_iter().map(Voice::from).collect())
+}
+
+async fn fetch_token(config: &AzureTtsConfig) -> Result<String> {
+    let url = format!(
+        "https://{}.api.cognitive.microsoft.com/sts/v1.0/issueToken",
+        config.region
+    );
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(&url)
+        .header("Ocp-Apim-Subscription-Key", &config.subscription_key)
+        .header("Content-Length", "0")
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!(
+            "Failed to issue Azure TTS access token: {}",
+            resp.status()
+        ));
+    }
+    Ok(resp.text().await?)
+}
+
+// --- Azure TTS Client ---
+
+pub struct AzureTtsClient {
+    config: AzureTtsConfig,
+    voices_cache: Vec<Voice>,
+    token: Mutex<Option<(String, Instant)>>,
+    phonetic_corrections: HashMap<String, String>,
+}
+
+impl AzureTtsClient {
+    pub async fn new(
+        config: AzureTtsConfig,
+        phonetic_corrections: HashMap<String, String>,
+    ) -> Result<Self> {
+        let voices_cache = list_voices(&config).await.unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Failed to fetch Azure TTS voices for random selection: {}",
+                e
+            );
+            Vec::new()
+        });
+        Ok(Self {
+            config,
+            voices_cache,
+            token: Mutex::new(None),
+            phonetic_corrections,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_with_voices(config: AzureTtsConfig, voices: Vec<Voice>) -> Self {
+        Self {
+            config,
+            voices_cache: voices,
+            token: Mutex::new(None),
+            phonetic_corrections: HashMap::new(),
+        }
+    }
+
+    /// Returns a cached access token if it's still within `TOKEN_TTL` of
+    /// being issued, otherwise fetches and caches a fresh one.
+    async fn get_token(&self) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some((token, issued_at)) = cached.as_ref() {
+            if issued_at.elapsed() < TOKEN_TTL {
+                return Ok(token.clone());
+            }
+        }
+
+        let token = fetch_token(&self.config).await?;
+        *cached = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+
+    pub fn pick_random_voice(&self, gender: Option<&str>, excluded_voices: &[String]) -> String {
+        let mut rng = rand::rng();
+
+        let candidates: Vec<&Voice> = self
+            .voices_cache
+            .iter()
+            .filter(|v| {
+                if excluded_voices.contains(&v.short_name) {
+                    return false;
+                }
+                if let Some(g) = gender {
+                    if !v.gender.eq_ignore_ascii_case(g) {
+                        return false;
+                    }
+                }
+                true
+            })
+            .collect();
+
+        if let Some(v) = candidates.choose(&mut rng) {
+            v.short_name.clone()
+        } else {
+            // Fallback
+            self.config
+                .narrator_voice
+                .clone()
+                .unwrap_or_else(|| "en-US-JennyNeural".to_string())
+        }
+    }
+
+    fn resolve_voice(
+        &self,
+        speaker: &str,
+        char_map: &CharacterMap,
+        excluded_voices: &[String],
+    ) -> String {
+        let azure_config = &self.config;
+
+        // 1. Check if Narrator
+        if speaker == "旁白" || speaker.eq_ignore_ascii_case("Narrator") {
+            if let Some(v) = &azure_config.narrator_voice {
+                return v.clone();
+            }
+        }
+
+        // 2. Check Character Map
+        if let Some(info) = char_map.characters.get(speaker) {
+            if let Some(voice_id) = &info.voice_id {
+                // Check for Special Mob IDs
+                match voice_id.as_str() {
+                    VOICE_ID_MOB_MALE => {
+                        return self.pick_random_voice(Some("Male"), excluded_voices)
+                    }
+                    VOICE_ID_MOB_FEMALE => {
+                        return self.pick_random_voice(Some("Female"), excluded_voices)
+                    }
+                    VOICE_ID_MOB_NEUTRAL => return self.pick_random_voice(None, excluded_voices),
+                    _ => return voice_id.clone(),
+                }
+            }
+
+            // 3. Fallback to Gender Default
+            match info.gender.to_lowercase().as_str() {
+                "male" => {
+                    if let Some(v) = &azure_config.default_male_voice {
+                        return v.clone();
+                    }
+                }
+                "female" => {
+                    if let Some(v) = &azure_config.default_female_voice {
+                        return v.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 4. Ultimate Fallback (Narrator or hard fallback)
+        if let Some(v) = &azure_config.narrator_voice {
+            return v.clone();
+        }
+
+        "en-US-JennyNeural".to_string() // Hard fallback
+    }
+}
+
+#[async_trait]
+impl TtsClient for AzureTtsClient {
+    async fn list_voices(&self) -> Result<Vec<Voice>> {
+        if !self.voices_cache.is_empty() {
+            Ok(self.voices_cache.clone())
+        } else {
+            list_voices(&self.config).await
+        }
+    }
+
+    async fn synthesize(
+        &self,
+        segment: &AudioSegment,
+        char_map: &CharacterMap,
+        excluded_voices: &[String],
+    ) -> Result<Vec<u8>> {
+        let voice = if let Some(vid) = &segment.voice_id {
+            vid.clone()
+        } else if let Some(speaker) = &segment.speaker {
+            self.resolve_voice(speaker, char_map, excluded_voices)
+        } else {
+            panic!("No speaker or voice_id specified for segment");
+        };
+
+        let char_info = segment.speaker.as_deref().and_then(|s| char_map.characters.get(s));
+        let (speed, pitch_semitones) = char_info
+            .map(|info| (info.speed, info.pitch_semitones))
+            .unwrap_or((None, None));
+        let corrected = crate::utils::text::apply_phonetic_corrections_ssml(
+            &segment.text,
+            &self.phonetic_corrections,
+        );
+        let text = apply_prosody(&corrected, speed, pitch_semitones);
+        let ssml = format!(
+            "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xml:lang='en-US'><voice name='{}'>{}</voice></speak>",
+            voice, text
+        );
+
+        let token = self.get_token().await?;
+        let url = format!(
+            "https://{}.tts.speech.microsoft.com/cognitiveservices/v1",
+            self.config.region
+        );
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/ssml+xml")
+            .header(
+                "X-Microsoft-OutputFormat",
+                "audio-24khz-48kbitrate-mono-mp3",
+            )
+            .body(ssml)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(anyhow!(
+                "Azure TTS synthesis request failed: {}",
+                resp.status()
+            ));
+        }
+
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    async fn get_random_voice(
+        &self,
+        gender: Option<&str>,
+        excluded_voices: &[String],
+    ) -> Result<String> {
+        Ok(self.pick_random_voice(gender, excluded_voices))
+    }
+
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+        self.config
+            .narrator_voice
+            .clone()
+            .unwrap_or_else(|| "en-US-JennyNeural".to_string())
+    }
+
+    fn is_mob_enabled(&self) -> bool {
+        true
+    }
+
+    fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
+        voices
+            .iter()
+            .map(Voice::to_analysis_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+        Box::new(JsonScriptGenerator::new())
+    }
+
+    fn chars_per_second(&self) -> f64 {
+        1000.0
+    }
+
+    fn uses_ssml(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config() -> AzureTtsConfig {
+        AzureTtsConfig {
+            subscription_key: "test-key".to_string(),
+            region: "eastus".to_string(),
+            narrator_voice: Some("en-US-JennyNeural".to_string()),
+            default_male_voice: None,
+            default_female_voice: None,
+        }
+    }
+
+    #[test]
+    fn test_synthesize_text_applies_phonetic_corrections_as_phoneme_tags() {
+        let mut corrections = HashMap::new();
+        corrections.insert("長".to_string(), "zhang3".to_string());
+        let corrected =
+            crate::utils::text::apply_phonetic_corrections_ssml("長公主駕到", &corrections);
+        assert_eq!(
+            corrected,
+            "<phoneme alphabet=\"ipa\" ph=\"zhang3\">長</phoneme>公主駕到"
+        );
+    }
+
+    #[test]
+    fn test_apply_prosody_leaves_default_speed_and_pitch_unwrapped() {
+        assert_eq!(apply_prosody("hello", None, None), "hello");
+        assert_eq!(apply_prosody("hello", Some(1.0), Some(0.0)), "hello");
+    }
+
+    #[test]
+    fn test_apply_prosody_wraps_when_speed_or_pitch_differ_from_default() {
+        assert_eq!(
+            apply_prosody("hello", Some(0.8), None),
+            "<prosody rate='-20%' pitch='+0st'>hello</prosody>"
+        );
+        assert_eq!(
+            apply_prosody("hello", None, Some(3.0)),
+            "<prosody rate='+0%' pitch='+3st'>hello</prosody>"
+        );
+    }
+
+    #[test]
+    fn test_pick_random_voice_filters_by_gender_and_exclusion() {
+        let voices = vec![
+            Voice {
+                short_name: "en-US-Male".to_string(),
+                gender: "Male".to_string(),
+                locale: "en-US".to_string(),
+                name: "".to_string(),
+                friendly_name: None,
+            },
+            Voice {
+                short_name: "en-US-Female".to_string(),
+                gender: "Female".to_string(),
+                locale: "en-US".to_string(),
+                name: "".to_string(),
+                friendly_name: None,
+            },
+        ];
+        let client = AzureTtsClient::new_with_voices(sample_config(), voices);
+
+        let v = client.pick_random_voice(Some("Male"), &[]);
+        assert_eq!(v, "en-US-Male");
+
+        // Only male voice is excluded, so it should fall back to the narrator.
+        let v = client.pick_random_voice(Some("Male"), &["en-US-Male".to_string()]);
+        assert_eq!(v, "en-US-JennyNeural");
+    }
+
+    #[test]
+    fn test_voice_from_azure_entry_prefers_local_name_for_friendly_name() {
+        let entry = AzureVoiceEntry {
+            short_name: "en-US-JennyNeural".to_string(),
+            gender: "Female".to_string(),
+            locale: "en-US".to_string(),
+            display_name: Some("Jenny".to_string()),
+            local_name: Some("Jenny (US English)".to_string()),
+        };
+        let voice: Voice = entry.into();
+        assert_eq!(voice.friendly_name, Some("Jenny (US English)".to_string()));
+    }
+
+    #[test]
+    fn test_voice_from_azure_entry_falls_back_to_display_name() {
+        let entry = AzureVoiceEntry {
+            short_name: "en-US-JennyNeural".to_string(),
+            gender: "Female".to_string(),
+            locale: "en-US".to_string(),
+            display_name: Some("Jenny".to_string()),
+            local_name: None,
+        };
+        let voice: Voice = entry.into();
+        assert_eq!(voice.friendly_name, Some("Jenny".to_string()));
+    }
+}
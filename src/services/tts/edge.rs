@@ -1,5 +1,5 @@
 use crate::core::state::CharacterMap;
-use crate::services::script::{AudioSegment, JsonScriptGenerator, ScriptGenerator};
+use crate::services::script::{AudioSegment, JsonScriptGenerator, ScriptGenerator, StyleMode};
 use crate::services::tts::{
     TtsClient, Voice, VOICE_ID_MOB_FEMALE, VOICE_ID_MOB_MALE, VOICE_ID_MOB_NEUTRAL,
 };
@@ -8,6 +8,7 @@ use async_trait::async_trait;
 use rand::seq::IndexedRandom;
 use reqwest::header::{HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 const TRUSTED_CLIENT_TOKEN: &str = "6A5AA1D4EAFF4E9FB37E23D68491D6F4";
 const CHROMIUM_MAJOR_VERSION: &str = "143";
@@ -39,6 +40,71 @@ pub struct EdgeTtsConfig {
     pub style: bool,
 }
 
+/// Wraps `text` in a `<prosody>` tag when `speed`/`pitch_semitones` deviate
+/// from their defaults (`1.0`/`0.0`), so a character's `CharacterInfo` can
+/// adjust speaking rate/pitch without a different voice. Returns `text`
+/// unmodified when both are at their default (including `None`).
+fn apply_prosody(text: &str, speed: Option<f32>, pitch_semitones: Option<f32>) -> String {
+    let speed = speed.unwrap_or(1.0);
+    let pitch = pitch_semitones.unwrap_or(0.0);
+    if speed == 1.0 && pitch == 0.0 {
+        return text.to_string();
+    }
+    let rate_pct = ((speed - 1.0) * 100.0).round() as i32;
+    format!(
+        "<prosody rate='{:+}%' pitch='{:+}st'>{}</prosody>",
+        rate_pct, pitch, text
+    )
+}
+
+/// Chinese verbs of physical action/movement, used by `auto_detect_style` to
+/// flag narration as a good fit for the `narration-professional` style.
+const ACTION_VERBS: [&str; 8] = ["走", "跑", "跳", "衝", "抓", "打", "轉身", "站起"];
+
+/// Suggests an Edge TTS `<mstts:express-as style="...">` value from simple
+/// text features, for segments the LLM didn't already assign a style to.
+/// Only called from `synthesize` when `segment.style.is_none()` and
+/// `EdgeTtsConfig::style` is enabled — a heuristic fallback, not a
+/// replacement for the LLM's own style choices.
+///
+/// Checked in order, first match wins:
+/// - A parenthesized aside starting with "he thought" → `whispering`.
+/// - An exclamation mark → `excited`.
+/// - A question mark inside quoted dialogue → `customerservice`.
+/// - Narration (`speaker_is_narrator`) containing an action verb →
+///   `narration-professional`.
+pub fn auto_detect_style(text: &str, speaker_is_narrator: bool) -> Option<String> {
+    let trimmed = text.trim();
+
+    let inner = trimmed
+        .strip_prefix('(')
+        .or_else(|| trimmed.strip_prefix('\u{FF08}'))
+        .map(str::trim_start);
+    if let Some(inner) = inner {
+        if inner.to_lowercase().starts_with("he thought") {
+            return Some("whispering".to_string());
+        }
+    }
+
+    if trimmed.contains('!') || trimmed.contains('\u{FF01}') {
+        return Some("excited".to_string());
+    }
+
+    let in_dialogue = trimmed.contains('「')
+        || trimmed.contains('『')
+        || trimmed.contains('"')
+        || trimmed.contains('\u{201C}');
+    if in_dialogue && (trimmed.contains('?') || trimmed.contains('\u{FF1F}')) {
+        return Some("customerservice".to_string());
+    }
+
+    if speaker_is_narrator && ACTION_VERBS.iter().any(|verb| trimmed.contains(verb)) {
+        return Some("narration-professional".to_string());
+    }
+
+    None
+}
+
 // --- Shared Helper for EdgeTTS ---
 
 pub async fn list_voices() -> Result<Vec<Voice>> {
@@ -88,6 +154,8 @@ pub struct EdgeTtsClient {
     exclude_locales: Vec<String>,
     language: String,
     voices_cache: Vec<Voice>,
+    phonetic_corrections: HashMap<String, String>,
+    child_voice_tags: Vec<String>,
 }
 
 impl EdgeTtsClient {
@@ -95,6 +163,8 @@ impl EdgeTtsClient {
         config: EdgeTtsConfig,
         exclude_locales: Vec<String>,
         language: String,
+        phonetic_corrections: HashMap<String, String>,
+        child_voice_tags: Vec<String>,
     ) -> Result<Self> {
         // Pre-fetch voices for caching
         let voices_cache = list_voices().await.unwrap_or_else(|e| {
@@ -109,6 +179,8 @@ impl EdgeTtsClient {
             exclude_locales,
             language,
             voices_cache,
+            phonetic_corrections,
+            child_voice_tags,
         })
     }
 
@@ -124,6 +196,8 @@ impl EdgeTtsClient {
             exclude_locales,
             language,
             voices_cache: voices,
+            phonetic_corrections: HashMap::new(),
+            child_voice_tags: vec!["child".to_string(), "kid".to_string(), "youth".to_string()],
         }
     }
 
@@ -164,6 +238,55 @@ impl EdgeTtsClient {
         }
     }
 
+    /// Prefers a voice whose `friendly_name` contains one of
+    /// `child_voice_tags` (case-insensitive), for `CharacterInfo::is_child`
+    /// characters whose synthesized voice should read distinctly younger
+    /// than the gender-default fallback. Returns `None` (falling through to
+    /// that default) if no tagged voice matches.
+    fn pick_child_voice(&self, gender: Option<&str>, excluded_voices: &[String]) -> Option<String> {
+        let lang_prefix = &self.language;
+        let mut rng = rand::rng();
+
+        let candidates: Vec<&Voice> = self
+            .voices_cache
+            .iter()
+            .filter(|v| v.locale.starts_with(lang_prefix))
+            .filter(|v| !self.exclude_locales.contains(&v.locale))
+            .filter(|v| !excluded_voices.contains(&v.short_name))
+            .filter(|v| gender.is_none() || gender.is_some_and(|g| v.gender.eq_ignore_ascii_case(g)))
+            .filter(|v| {
+                v.friendly_name.as_deref().is_some_and(|name| {
+                    let lower = name.to_lowercase();
+                    self.child_voice_tags
+                        .iter()
+                        .any(|tag| lower.contains(&tag.to_lowercase()))
+                })
+            })
+            .collect();
+
+        candidates.choose(&mut rng).map(|v| v.short_name.clone())
+    }
+
+    /// Overrides `voice` with a cached voice matching `detected_language`'s
+    /// locale prefix, for mixed-language chapters where a segment's script
+    /// (e.g. an English quote in an otherwise `"zh"` chapter) calls for a
+    /// different voice than the speaker's usual one. Falls through to
+    /// `voice` unchanged when `detected_language` is `None`, matches the
+    /// client's own `language`, or no cached voice matches its locale.
+    fn apply_detected_language_override(&self, voice: String, detected_language: Option<&str>) -> String {
+        let Some(lang) = detected_language else {
+            return voice;
+        };
+        if lang == self.language {
+            return voice;
+        }
+        self.voices_cache
+            .iter()
+            .find(|v| v.locale.starts_with(lang))
+            .map(|v| v.short_name.clone())
+            .unwrap_or(voice)
+    }
+
     fn resolve_voice(
         &self,
         speaker: &str,
@@ -195,6 +318,18 @@ impl EdgeTtsClient {
                 }
             }
 
+            // 2.5 Prefer a tagged child voice over the gender default.
+            if info.is_child {
+                let gender_hint = match info.gender.to_lowercase().as_str() {
+                    "male" => Some("Male"),
+                    "female" => Some("Female"),
+                    _ => None,
+                };
+                if let Some(v) = self.pick_child_voice(gender_hint, excluded_voices) {
+                    return v;
+                }
+            }
+
             // 3. Fallback to Gender Default
             match info.gender.to_lowercase().as_str() {
                 "male" => {
@@ -240,20 +375,38 @@ impl TtsClient for EdgeTtsClient {
         let voice = if let Some(vid) = &segment.voice_id {
             vid.clone()
         } else if let Some(speaker) = &segment.speaker {
-            self.resolve_voice(speaker, char_map, excluded_voices)
+            let resolved = self.resolve_voice(speaker, char_map, excluded_voices);
+            self.apply_detected_language_override(resolved, segment.detected_language.as_deref())
         } else {
             panic!("No speaker or voice_id specified for segment");
         };
         let using_style = self.config.style;
-        let ssml = match (using_style, &segment.style) {
+        let char_info = segment.speaker.as_deref().and_then(|s| char_map.characters.get(s));
+        let (speed, pitch_semitones) = char_info
+            .map(|info| (info.speed, info.pitch_semitones))
+            .unwrap_or((None, None));
+        let corrected = crate::utils::text::apply_phonetic_corrections_ssml(
+            &segment.text,
+            &self.phonetic_corrections,
+        );
+        let text = apply_prosody(&corrected, speed, pitch_semitones);
+        let is_narrator = segment
+            .speaker
+            .as_deref()
+            .is_some_and(|s| s == "旁白" || s.eq_ignore_ascii_case("Narrator"));
+        let effective_style = segment
+            .style
+            .clone()
+            .or_else(|| using_style.then(|| auto_detect_style(&segment.text, is_narrator)).flatten());
+        let ssml = match (using_style, &effective_style) {
             (true, Some(style)) =>format!(
                 "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xml:lang='en-US'><voice name='{}'><mstts:express-as style='{}'>{}</mstts:express-as></voice></speak>",
-                voice, style, segment.text
+                voice, style, text
             ),
             _ =>
             format!(
                 "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xml:lang='en-US'><voice name='{}'>{}</voice></speak>",
-                voice, segment.text
+                voice, text
             )
         };
 
@@ -272,7 +425,7 @@ impl TtsClient for EdgeTtsClient {
         Ok(self.pick_random_voice(gender, excluded_voices))
     }
 
-    fn get_narrator_voice_id(&self) -> String {
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
         self.config
             .narrator_voice
             .clone()
@@ -286,18 +439,26 @@ impl TtsClient for EdgeTtsClient {
     fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
         voices
             .iter()
-            .map(|v| {
-                format!(
-                    "{{ \"id\": \"{}\", \"gender\": \"{}\", \"locale\": \"{}\" }}",
-                    v.short_name, v.gender, v.locale
-                )
-            })
+            .map(Voice::to_analysis_string)
             .collect::<Vec<_>>()
             .join("\n")
     }
 
     fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
-        Box::new(JsonScriptGenerator::new())
+        let style_mode = if self.config.style {
+            StyleMode::default()
+        } else {
+            StyleMode::Disabled
+        };
+        Box::new(JsonScriptGenerator::new().with_style_mode(style_mode))
+    }
+
+    fn chars_per_second(&self) -> f64 {
+        1500.0
+    }
+
+    fn uses_ssml(&self) -> bool {
+        true
     }
 }
 
@@ -374,4 +535,162 @@ mod tests {
         // Should fallback to narrator because the only male voice is excluded
         assert_eq!(v_excluded, "Narrator");
     }
+
+    #[test]
+    fn test_resolve_voice_prefers_tagged_child_voice_for_is_child_character() {
+        use crate::core::state::CharacterInfo;
+
+        let edge_config = EdgeTtsConfig {
+            narrator_voice: Some("Narrator".to_string()),
+            default_male_voice: Some("zh-CN-Male".to_string()),
+            ..Default::default()
+        };
+
+        let voices = vec![
+            Voice {
+                short_name: "zh-CN-Male".to_string(),
+                gender: "Male".to_string(),
+                locale: "zh-CN".to_string(),
+                name: "".to_string(),
+                friendly_name: None,
+            },
+            Voice {
+                short_name: "zh-CN-ChildMale".to_string(),
+                gender: "Male".to_string(),
+                locale: "zh-CN".to_string(),
+                name: "".to_string(),
+                friendly_name: Some("Child Male Voice".to_string()),
+            },
+        ];
+
+        let client = EdgeTtsClient::new_with_voices(edge_config, vec![], "zh".to_string(), voices);
+
+        let char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: HashMap::from([(
+                "小明".to_string(),
+                CharacterInfo {
+                    gender: "Male".to_string(),
+                    is_child: true,
+                    ..Default::default()
+                },
+            )]),
+        };
+
+        let voice_id = client.resolve_voice("小明", &char_map, &[]);
+        assert_eq!(voice_id, "zh-CN-ChildMale");
+    }
+
+    #[test]
+    fn test_apply_detected_language_override_picks_matching_locale_voice() {
+        let edge_config = EdgeTtsConfig::default();
+        let voices = vec![
+            Voice {
+                short_name: "zh-CN-Male".to_string(),
+                gender: "Male".to_string(),
+                locale: "zh-CN".to_string(),
+                name: "".to_string(),
+                friendly_name: None,
+            },
+            Voice {
+                short_name: "en-US-Male".to_string(),
+                gender: "Male".to_string(),
+                locale: "en-US".to_string(),
+                name: "".to_string(),
+                friendly_name: None,
+            },
+        ];
+        let client = EdgeTtsClient::new_with_voices(edge_config, vec![], "zh".to_string(), voices);
+
+        // Pure-ASCII text detected as "en" inside an otherwise "zh" chapter
+        // overrides the speaker's resolved voice with an en-US one.
+        let voice = client.apply_detected_language_override("zh-CN-Male".to_string(), Some("en"));
+        assert_eq!(voice, "en-US-Male");
+
+        // Same language as the client's own: no override.
+        let voice = client.apply_detected_language_override("zh-CN-Male".to_string(), Some("zh"));
+        assert_eq!(voice, "zh-CN-Male");
+
+        // No detection: no override.
+        let voice = client.apply_detected_language_override("zh-CN-Male".to_string(), None);
+        assert_eq!(voice, "zh-CN-Male");
+    }
+
+    #[test]
+    fn test_synthesize_text_applies_phonetic_corrections_as_phoneme_tags() {
+        let mut corrections = HashMap::new();
+        corrections.insert("長".to_string(), "zhang3".to_string());
+        let corrected =
+            crate::utils::text::apply_phonetic_corrections_ssml("長公主駕到", &corrections);
+        assert_eq!(
+            corrected,
+            "<phoneme alphabet=\"ipa\" ph=\"zhang3\">長</phoneme>公主駕到"
+        );
+    }
+
+    #[test]
+    fn test_apply_prosody_leaves_default_speed_and_pitch_unwrapped() {
+        assert_eq!(apply_prosody("hello", None, None), "hello");
+        assert_eq!(apply_prosody("hello", Some(1.0), Some(0.0)), "hello");
+    }
+
+    #[test]
+    fn test_apply_prosody_wraps_when_speed_differs_from_default() {
+        assert_eq!(
+            apply_prosody("hello", Some(1.5), None),
+            "<prosody rate='+50%' pitch='+0st'>hello</prosody>"
+        );
+    }
+
+    #[test]
+    fn test_apply_prosody_wraps_when_pitch_differs_from_default() {
+        assert_eq!(
+            apply_prosody("hello", None, Some(-2.0)),
+            "<prosody rate='+0%' pitch='-2st'>hello</prosody>"
+        );
+    }
+
+    #[test]
+    fn test_auto_detect_style_parenthetical_thought_is_whispering() {
+        assert_eq!(
+            auto_detect_style("(he thought, this can't be right)", false),
+            Some("whispering".to_string())
+        );
+        assert_eq!(
+            auto_detect_style("（he thought 這不可能）", false),
+            Some("whispering".to_string())
+        );
+    }
+
+    #[test]
+    fn test_auto_detect_style_exclamation_is_excited() {
+        assert_eq!(auto_detect_style("小心！", false), Some("excited".to_string()));
+        assert_eq!(
+            auto_detect_style("快走\u{FF01}", false),
+            Some("excited".to_string())
+        );
+    }
+
+    #[test]
+    fn test_auto_detect_style_quoted_question_is_customerservice() {
+        assert_eq!(
+            auto_detect_style("「你今天吃飯了嗎？」", false),
+            Some("customerservice".to_string())
+        );
+    }
+
+    #[test]
+    fn test_auto_detect_style_narrator_action_verb_is_narration_professional() {
+        assert_eq!(
+            auto_detect_style("他轉身走向門口", true),
+            Some("narration-professional".to_string())
+        );
+        // Same text from a non-narrator speaker shouldn't get the narration style.
+        assert_eq!(auto_detect_style("他轉身走向門口", false), None);
+    }
+
+    #[test]
+    fn test_auto_detect_style_plain_text_is_none() {
+        assert_eq!(auto_detect_style("今天天氣很好", false), None);
+    }
 }
@@ -0,0 +1,448 @@
+use crate::core::state::CharacterMap;
+use crate::services::script::{AudioSegment, JsonScriptGenerator, ScriptGenerator};
+use crate::services::tts::{
+    TtsClient, Voice, VOICE_ID_MOB_FEMALE, VOICE_ID_MOB_MALE, VOICE_ID_MOB_NEUTRAL,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+
+const VOICES_URL: &str = "https://api.elevenlabs.io/v1/voices";
+
+// --- Config ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ElevenLabsConfig {
+    pub api_key: String,
+    #[serde(default = "default_model_id")]
+    pub model_id: String,
+    pub narrator_voice: Option<String>,
+    pub default_male_voice: Option<String>,
+    pub default_female_voice: Option<String>,
+    #[serde(default = "default_stability")]
+    pub stability: f32,
+    #[serde(default = "default_similarity_boost")]
+    pub similarity_boost: f32,
+    #[serde(default = "default_retry_count")]
+    pub retry_count: usize,
+    #[serde(default = "default_retry_delay_seconds")]
+    pub retry_delay_seconds: u64,
+    /// USD cost per character, used by `estimate_cost` to give a rough
+    /// pre-synthesis estimate. Defaults to ElevenLabs' published rate for
+    /// the Creator plan (~$0.00018/credit, 1 credit per character for
+    /// `eleven_multilingual_v2`).
+    #[serde(default = "default_cost_per_char")]
+    pub cost_per_char: f64,
+}
+
+fn default_model_id() -> String {
+    "eleven_multilingual_v2".to_string()
+}
+fn default_stability() -> f32 {
+    0.5
+}
+fn default_similarity_boost() -> f32 {
+    0.75
+}
+fn default_retry_count() -> usize {
+    3
+}
+fn default_retry_delay_seconds() -> u64 {
+    10
+}
+fn default_cost_per_char() -> f64 {
+    0.00018
+}
+
+// --- ElevenLabs Voices API ---
+
+#[derive(Debug, Deserialize)]
+struct ElevenLabsVoicesResponse {
+    voices: Vec<ElevenLabsVoiceEntry>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct ElevenLabsVoiceEntry {
+    voice_id: String,
+    name: String,
+    #[serde(default)]
+    labels: std::collections::HashMap<String, String>,
+}
+
+impl From<ElevenLabsVoiceEntry> for Voice {
+    fn from(entry: ElevenLabsVoiceEntry) -> Self {
+        let gender = entry
+            .labels
+            .get("gender")
+            .map(|g| {
+                if g.eq_ignore_ascii_case("male") {
+                    "Male".to_string()
+                } else if g.eq_ignore_ascii_case("female") {
+                    "Female".to_string()
+                } else {
+                    g.clone()
+                }
+            })
+            .unwrap_or_default();
+        let locale = entry
+            .labels
+            .get("language")
+            .or_else(|| entry.labels.get("accent"))
+            .cloned()
+            .unwrap_or_default();
+        Voice {
+            name: entry.name.clone(),
+            short_name: entry.voice_id,
+            gender,
+            locale,
+            friendly_name: Some(entry.name),
+        }
+    }
+}
+
+pub async fn list_voices(config: &ElevenLabsConfig) -> Result<Vec<Voice>> {
+    let client = reqwest::Client::new();
+    let resp = client
+        .get(VOICES_URL)
+        .header("xi-api-key", &config.api_key)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("Failed to list ElevenLabs voices: {}", resp.status()));
+    }
+    let data: ElevenLabsVoicesResponse = resp.json().await?;
+    Ok(data.voices.into_iter().map(Voice::from).collect())
+}
+
+// --- ElevenLabs TTS Client ---
+
+pub struct ElevenLabsClient {
+    config: ElevenLabsConfig,
+    voices_cache: Vec<Voice>,
+}
+
+impl ElevenLabsClient {
+    pub async fn new(config: ElevenLabsConfig) -> Result<Self> {
+        let voices_cache = list_voices(&config).await.unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Failed to fetch ElevenLabs voices for random selection: {}",
+                e
+            );
+            Vec::new()
+        });
+        Ok(Self {
+            config,
+            voices_cache,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_with_voices(config: ElevenLabsConfig, voices: Vec<Voice>) -> Self {
+        Self {
+            config,
+            voices_cache: voices,
+        }
+    }
+
+    pub fn pick_random_voice(&self, gender: Option<&str>, excluded_voices: &[String]) -> String {
+        let mut rng = rand::rng();
+
+        let candidates: Vec<&Voice> = self
+            .voices_cache
+            .iter()
+            .filter(|v| {
+                if excluded_voices.contains(&v.short_name) {
+                    return false;
+                }
+                if let Some(g) = gender {
+                    if !v.gender.eq_ignore_ascii_case(g) {
+                        return false;
+                    }
+                }
+                true
+            })
+            .collect();
+
+        if let Some(v) = candidates.choose(&mut rng) {
+            v.short_name.clone()
+        } else {
+            // Fallback
+            self.config
+                .narrator_voice
+                .clone()
+                .unwrap_or_else(|| "21m00Tcm4TlvDq8ikWAM".to_string())
+        }
+    }
+
+    fn resolve_voice(
+        &self,
+        speaker: &str,
+        char_map: &CharacterMap,
+        excluded_voices: &[String],
+    ) -> String {
+        let elevenlabs_config = &self.config;
+
+        // 1. Check if Narrator
+        if speaker == "旁白" || speaker.eq_ignore_ascii_case("Narrator") {
+            if let Some(v) = &elevenlabs_config.narrator_voice {
+                return v.clone();
+            }
+        }
+
+        // 2. Check Character Map
+        if let Some(info) = char_map.characters.get(speaker) {
+            if let Some(voice_id) = &info.voice_id {
+                // Check for Special Mob IDs
+                match voice_id.as_str() {
+                    VOICE_ID_MOB_MALE => {
+                        return self.pick_random_voice(Some("Male"), excluded_voices)
+                    }
+                    VOICE_ID_MOB_FEMALE => {
+                        return self.pick_random_voice(Some("Female"), excluded_voices)
+                    }
+                    VOICE_ID_MOB_NEUTRAL => return self.pick_random_voice(None, excluded_voices),
+                    _ => return voice_id.clone(),
+                }
+            }
+
+            // 3. Fallback to Gender Default
+            match info.gender.to_lowercase().as_str() {
+                "male" => {
+                    if let Some(v) = &elevenlabs_config.default_male_voice {
+                        return v.clone();
+                    }
+                }
+                "female" => {
+                    if let Some(v) = &elevenlabs_config.default_female_voice {
+                        return v.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 4. Ultimate Fallback (Narrator or hard fallback)
+        if let Some(v) = &elevenlabs_config.narrator_voice {
+            return v.clone();
+        }
+
+        "21m00Tcm4TlvDq8ikWAM".to_string() // Hard fallback ("Rachel")
+    }
+
+    /// Jitters the configured delay to `[0.5x, 1.5x]` so that concurrent
+    /// segments hitting quota exhaustion at the same time don't all retry
+    /// in lockstep, matching the jitter approach used by `RetryLlmClient`.
+    fn jittered_delay(&self) -> u64 {
+        use rand::Rng;
+        let base = self.config.retry_delay_seconds as f64;
+        let jittered = rand::rng().random_range(base * 0.5..=base * 1.5);
+        jittered.round() as u64
+    }
+}
+
+#[async_trait]
+impl TtsClient for ElevenLabsClient {
+    async fn list_voices(&self) -> Result<Vec<Voice>> {
+        if !self.voices_cache.is_empty() {
+            Ok(self.voices_cache.clone())
+        } else {
+            list_voices(&self.config).await
+        }
+    }
+
+    async fn synthesize(
+        &self,
+        segment: &AudioSegment,
+        char_map: &CharacterMap,
+        excluded_voices: &[String],
+    ) -> Result<Vec<u8>> {
+        let voice = if let Some(vid) = &segment.voice_id {
+            vid.clone()
+        } else if let Some(speaker) = &segment.speaker {
+            self.resolve_voice(speaker, char_map, excluded_voices)
+        } else {
+            panic!("No speaker or voice_id specified for segment");
+        };
+
+        let url = format!("https://api.elevenlabs.io/v1/text-to-speech/{}", voice);
+        let body = serde_json::json!({
+            "text": segment.text,
+            "model_id": self.config.model_id,
+            "voice_settings": {
+                "stability": self.config.stability,
+                "similarity_boost": self.config.similarity_boost,
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let mut attempt = 0;
+        loop {
+            let resp = client
+                .post(&url)
+                .header("xi-api-key", &self.config.api_key)
+                .header("Content-Type", "application/json")
+                .json(&body)
+                .send()
+                .await?;
+
+            if resp.status().as_u16() == 429 {
+                if attempt >= self.config.retry_count {
+                    return Err(anyhow!("ElevenLabs quota exhausted (HTTP 429)"));
+                }
+                let delay = self.jittered_delay();
+                tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+                attempt += 1;
+                continue;
+            }
+
+            if !resp.status().is_success() {
+                return Err(anyhow!("ElevenLabs synthesis failed: {}", resp.status()));
+            }
+
+            return Ok(resp.bytes().await?.to_vec());
+        }
+    }
+
+    async fn get_random_voice(
+        &self,
+        gender: Option<&str>,
+        excluded_voices: &[String],
+    ) -> Result<String> {
+        Ok(self.pick_random_voice(gender, excluded_voices))
+    }
+
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+        self.config
+            .narrator_voice
+            .clone()
+            .unwrap_or_else(|| "21m00Tcm4TlvDq8ikWAM".to_string())
+    }
+
+    fn is_mob_enabled(&self) -> bool {
+        true
+    }
+
+    fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
+        voices
+            .iter()
+            .map(Voice::to_analysis_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+        Box::new(JsonScriptGenerator::new())
+    }
+
+    fn chars_per_second(&self) -> f64 {
+        1000.0
+    }
+
+    async fn estimate_cost(&self, segments: &[AudioSegment]) -> Result<f64> {
+        let total_chars: usize = segments.iter().map(|s| s.text.chars().count()).sum();
+        Ok(total_chars as f64 * self.config.cost_per_char)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config() -> ElevenLabsConfig {
+        ElevenLabsConfig {
+            api_key: "test-key".to_string(),
+            model_id: "eleven_multilingual_v2".to_string(),
+            narrator_voice: Some("21m00Tcm4TlvDq8ikWAM".to_string()),
+            default_male_voice: None,
+            default_female_voice: None,
+            stability: 0.5,
+            similarity_boost: 0.75,
+            retry_count: 3,
+            retry_delay_seconds: 10,
+            cost_per_char: 0.00018,
+        }
+    }
+
+    #[test]
+    fn test_pick_random_voice_filters_by_gender_and_exclusion() {
+        let voices = vec![
+            Voice {
+                short_name: "male-voice-id".to_string(),
+                gender: "Male".to_string(),
+                locale: "en".to_string(),
+                name: "Adam".to_string(),
+                friendly_name: Some("Adam".to_string()),
+            },
+            Voice {
+                short_name: "female-voice-id".to_string(),
+                gender: "Female".to_string(),
+                locale: "en".to_string(),
+                name: "Rachel".to_string(),
+                friendly_name: Some("Rachel".to_string()),
+            },
+        ];
+        let client = ElevenLabsClient::new_with_voices(sample_config(), voices);
+
+        let v = client.pick_random_voice(Some("Male"), &[]);
+        assert_eq!(v, "male-voice-id");
+
+        // Only male voice is excluded, so it should fall back to the narrator.
+        let v = client.pick_random_voice(Some("Male"), &["male-voice-id".to_string()]);
+        assert_eq!(v, "21m00Tcm4TlvDq8ikWAM");
+    }
+
+    #[test]
+    fn test_voice_from_entry_infers_gender_from_labels() {
+        let mut labels = std::collections::HashMap::new();
+        labels.insert("gender".to_string(), "male".to_string());
+        labels.insert("language".to_string(), "en".to_string());
+        let entry = ElevenLabsVoiceEntry {
+            voice_id: "abc123".to_string(),
+            name: "Adam".to_string(),
+            labels,
+        };
+        let voice: Voice = entry.into();
+        assert_eq!(voice.short_name, "abc123");
+        assert_eq!(voice.gender, "Male");
+        assert_eq!(voice.locale, "en");
+        assert_eq!(voice.friendly_name, Some("Adam".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_cost_multiplies_total_chars_by_rate() {
+        let client = ElevenLabsClient::new_with_voices(sample_config(), Vec::new());
+        let segments = vec![
+            AudioSegment {
+                text: "Hello".to_string(),
+                speaker: None,
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                text: "World!".to_string(),
+                speaker: None,
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+
+        let cost = client.estimate_cost(&segments).await.unwrap();
+        assert!((cost - 11.0 * 0.00018).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_voice_from_entry_without_gender_label() {
+        let entry = ElevenLabsVoiceEntry {
+            voice_id: "abc123".to_string(),
+            name: "Adam".to_string(),
+            labels: std::collections::HashMap::new(),
+        };
+        let voice: Voice = entry.into();
+        assert_eq!(voice.gender, "");
+    }
+}
@@ -0,0 +1,224 @@
+use crate::core::state::CharacterMap;
+use crate::services::script::{AudioSegment, JsonScriptGenerator, ScriptGenerator};
+use crate::services::tts::{TtsClient, Voice};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Config for a user-supplied CLI-based TTS provider, for expert users whose
+/// TTS system isn't one of the built-in providers. `command` is spawned once
+/// per segment with `args`; the segment is passed as a JSON object on stdin
+/// (`{"text": "...", "voice_id": "...", "style": "..."}`), and the process
+/// must write raw audio bytes to stdout and exit 0 on success.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExternalTtsConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub narrator_voice: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExternalSynthesizeRequest<'a> {
+    text: &'a str,
+    voice_id: &'a str,
+    style: Option<&'a str>,
+}
+
+pub struct ExternalTtsClient {
+    config: ExternalTtsConfig,
+}
+
+impl ExternalTtsClient {
+    pub fn new(config: ExternalTtsConfig) -> Self {
+        Self { config }
+    }
+
+    fn resolve_voice(&self, segment: &AudioSegment, char_map: &CharacterMap) -> String {
+        if let Some(vid) = &segment.voice_id {
+            return vid.clone();
+        }
+        if let Some(speaker) = &segment.speaker {
+            if let Some(info) = char_map.characters.get(speaker) {
+                if let Some(voice_id) = &info.voice_id {
+                    return voice_id.clone();
+                }
+            }
+        }
+        self.get_narrator_voice_id(segment.detected_language.as_deref())
+    }
+}
+
+pub async fn list_voices(config: &ExternalTtsConfig) -> Result<Vec<Voice>> {
+    let output = Command::new(&config.command)
+        .args(&config.args)
+        .arg("--list-voices")
+        .output()
+        .await
+        .with_context(|| format!("Failed to run external TTS command {:?} --list-voices", config.command))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "external TTS command {:?} --list-voices exited with status {}: {}",
+            config.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let voices: Vec<Voice> = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Failed to parse voice list JSON from {:?}", config.command))?;
+    Ok(voices)
+}
+
+#[async_trait]
+impl TtsClient for ExternalTtsClient {
+    async fn list_voices(&self) -> Result<Vec<Voice>> {
+        list_voices(&self.config).await
+    }
+
+    async fn synthesize(
+        &self,
+        segment: &AudioSegment,
+        char_map: &CharacterMap,
+        _excluded_voices: &[String],
+    ) -> Result<Vec<u8>> {
+        let voice_id = self.resolve_voice(segment, char_map);
+        let request = ExternalSynthesizeRequest {
+            text: &segment.text,
+            voice_id: &voice_id,
+            style: segment.style.as_deref(),
+        };
+        let payload = serde_json::to_vec(&request)?;
+
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to spawn external TTS command {:?}", self.config.command))?;
+
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdin for external TTS command"))?
+            .write_all(&payload)
+            .await
+            .context("Failed to write segment JSON to external TTS command stdin")?;
+
+        let output = child
+            .wait_with_output()
+            .await
+            .context("Failed to read external TTS command output")?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "external TTS command {:?} exited with status {}: {}",
+                self.config.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(output.stdout)
+    }
+
+    async fn get_random_voice(
+        &self,
+        _gender: Option<&str>,
+        _excluded_voices: &[String],
+    ) -> Result<String> {
+        Ok(self.get_narrator_voice_id(None))
+    }
+
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+        self.config
+            .narrator_voice
+            .clone()
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    fn is_mob_enabled(&self) -> bool {
+        false
+    }
+
+    fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
+        voices
+            .iter()
+            .map(Voice::to_analysis_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+        Box::new(JsonScriptGenerator::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_config() -> ExternalTtsConfig {
+        // `cat` echoes the stdin JSON payload back out on stdout, which is
+        // enough to exercise the stdin-write/stdout-read plumbing without
+        // depending on a real TTS binary being installed.
+        ExternalTtsConfig {
+            command: "cat".to_string(),
+            args: Vec::new(),
+            narrator_voice: Some("default".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_writes_segment_json_to_stdin_and_reads_stdout() {
+        let client = ExternalTtsClient::new(echo_config());
+        let segment = AudioSegment {
+            speaker: Some("旁白".to_string()),
+            text: "Hello world".to_string(),
+            voice_id: Some("voice-a".to_string()),
+            style: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        };
+        let char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: std::collections::HashMap::new(),
+        };
+
+        let bytes = client
+            .synthesize(&segment, &char_map, &[])
+            .await
+            .expect("cat should echo stdin back on stdout");
+
+        let echoed: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(echoed["text"], "Hello world");
+        assert_eq!(echoed["voice_id"], "voice-a");
+    }
+
+    #[test]
+    fn test_resolve_voice_falls_back_to_narrator() {
+        let client = ExternalTtsClient::new(ExternalTtsConfig {
+            command: "cat".to_string(),
+            args: Vec::new(),
+            narrator_voice: Some("narrator-voice".to_string()),
+        });
+        let segment = AudioSegment {
+            speaker: None,
+            text: "hi".to_string(),
+            voice_id: None,
+            style: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        };
+        let char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: std::collections::HashMap::new(),
+        };
+        assert_eq!(client.resolve_voice(&segment, &char_map), "narrator-voice");
+    }
+}
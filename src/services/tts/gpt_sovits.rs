@@ -31,12 +31,20 @@ pub async fn list_voices(
 fn metadata_to_voices(metadata: &GptSovitsVoiceMap) -> Vec<Voice> {
     metadata
         .iter()
-        .map(|(name, meta)| Voice {
-            name: name.clone(),
-            short_name: name.clone(),
-            gender: meta.gender.clone(),
-            locale: "zh".to_string(),
-            friendly_name: Some(format!("{} {:?}", name, meta.tags)),
+        .map(|(name, meta)| {
+            let gender = if meta.gender.is_empty() || meta.gender.eq_ignore_ascii_case("unknown") {
+                crate::services::tts::infer_gender_from_name(name)
+                    .unwrap_or_else(|| meta.gender.clone())
+            } else {
+                meta.gender.clone()
+            };
+            Voice {
+                name: name.clone(),
+                short_name: name.clone(),
+                gender,
+                locale: "zh".to_string(),
+                friendly_name: Some(format!("{} {:?}", name, meta.tags)),
+            }
         })
         .collect()
 }
@@ -44,6 +52,8 @@ fn metadata_to_voices(metadata: &GptSovitsVoiceMap) -> Vec<Voice> {
 pub struct GptSovitsClient {
     config: GptSovitsConfig,
     metadata: GptSovitsVoiceMap,
+    client: reqwest::Client,
+    child_voice_tags: Vec<String>,
 }
 
 impl GptSovitsClient {
@@ -51,10 +61,24 @@ impl GptSovitsClient {
         config: GptSovitsConfig,
         language: &str,
         llm: Option<&dyn LlmClient>,
+        child_voice_tags: Vec<String>,
     ) -> Result<Self> {
         let metadata = load_or_refresh_metadata(&config, language, llm).await?;
-
-        Ok(Self { config, metadata })
+        // One pooled client reused across every `synthesize` call instead of
+        // `reqwest::Client::new()` per segment, so high `concurrency`
+        // settings don't open a fresh connection pool (and exhaust OS socket
+        // limits) for every synthesized line.
+        let client = reqwest::Client::builder()
+            .pool_max_idle_per_host(config.concurrency * 2)
+            .timeout(std::time::Duration::from_secs(config.timeout_secs.unwrap_or(120)))
+            .build()?;
+
+        Ok(Self {
+            config,
+            metadata,
+            client,
+            child_voice_tags,
+        })
     }
 
     fn pick_random_voice(
@@ -101,6 +125,40 @@ impl GptSovitsClient {
         }
     }
 
+    /// Prefers a voice whose metadata `tags` contains one of
+    /// `child_voice_tags` (case-insensitive), for `CharacterInfo::is_child`
+    /// characters. Returns `None` if no tagged voice matches, so the caller
+    /// can fall back to the plain gender-based `pick_random_voice`.
+    fn pick_child_voice(&self, gender: Option<&str>, excluded_voices: &[String]) -> Option<String> {
+        let mut rng = rand::rng();
+        let candidates: Vec<&String> = self
+            .metadata
+            .iter()
+            .filter_map(|(name, meta)| {
+                if excluded_voices.contains(name) {
+                    return None;
+                }
+                if let Some(g) = gender {
+                    if !meta.gender.eq_ignore_ascii_case(g) {
+                        return None;
+                    }
+                }
+                let is_tagged = meta.tags.iter().any(|tag| {
+                    self.child_voice_tags
+                        .iter()
+                        .any(|child_tag| tag.eq_ignore_ascii_case(child_tag))
+                });
+                if is_tagged {
+                    Some(name)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        candidates.choose(&mut rng).map(|v| v.to_string())
+    }
+
     async fn resolve_voice(
         &self,
         speaker: &str,
@@ -136,6 +194,13 @@ impl GptSovitsClient {
                 }
             }
 
+            // 2.5 Prefer a tagged child voice over the plain gender pick.
+            if info.is_child {
+                if let Some(v) = self.pick_child_voice(Some(&info.gender), excluded_voices) {
+                    return Ok(v);
+                }
+            }
+
             // 3. Gender default - REMOVED
             // Random based on gender
             return self.pick_random_voice(Some(&info.gender), excluded_voices);
@@ -326,21 +391,28 @@ impl TtsClient for GptSovitsClient {
             panic!("No speaker or voice_id specified for segment");
         };
         let gpt_sovits_config = &self.config;
+        let speed_factor = segment
+            .speaker
+            .as_deref()
+            .and_then(|s| char_map.characters.get(s))
+            .and_then(|info| info.speed)
+            .map(|speed| speed.round() as u8)
+            .unwrap_or(gpt_sovits_config.speed_factor);
 
         let payload = json!({
-          "batch_size": 10,
-          "batch_threshold": 0.75,
+          "batch_size": gpt_sovits_config.batch_size,
+          "batch_threshold": gpt_sovits_config.batch_threshold,
           "emotion": segment.style.clone().unwrap_or_default(),
-          "fragment_interval": 0.3,
+          "fragment_interval": gpt_sovits_config.fragment_interval,
           "if_sr": false,
           "media_type": "mp3",
           "model_name": voice_id,
           "parallel_infer": true,
           "prompt_text_lang": "中文",
           "repetition_penalty": gpt_sovits_config.repetition_penalty,
-          "sample_steps": 16,
+          "sample_steps": gpt_sovits_config.sample_steps,
           "seed": format!("{}", rand::random::<u32>()),
-          "speed_facter": gpt_sovits_config.speed_factor,
+          "speed_facter": speed_factor,
           "split_bucket": true,
           "version": "v4",
           "text": segment.text,
@@ -352,7 +424,7 @@ impl TtsClient for GptSovitsClient {
           //"text_split_method": "凑四句一切",
         });
 
-        let client = reqwest::Client::new();
+        let client = &self.client;
 
         let mut retry = gpt_sovits_config.retry;
         let mut download_url = String::new();
@@ -400,15 +472,44 @@ impl TtsClient for GptSovitsClient {
             }
         }
 
-        let base_url = gpt_sovits_config.base_url.clone();
         let mut durl = url::Url::parse(&download_url)?;
-        let burl = url::Url::parse(&base_url)?;
-        durl.set_host(burl.host_str())?;
-        
-        let _ = durl.set_port(burl.port());
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        if gpt_sovits_config.rewrite_download_host {
+            let base_url = gpt_sovits_config.base_url.clone();
+            let burl = url::Url::parse(&base_url)?;
+            durl.set_host(burl.host_str())?;
+            let _ = durl.set_port(burl.port());
+        }
+
         // Download WAV
-        let wav_resp = client.get(durl.as_str()).send().await?;
+        let download_client = reqwest::ClientBuilder::new()
+            .timeout(std::time::Duration::from_secs(
+                gpt_sovits_config.download_timeout_secs,
+            ))
+            .build()?;
+
+        // The `infer_single` response above only carries `audio_url`, not a
+        // task id we could poll a dedicated status endpoint with, so instead
+        // we poll the download URL itself with HEAD requests (exponential
+        // backoff) until the server reports it's ready, rather than hoping a
+        // fixed sleep was long enough.
+        let poll_timeout = tokio::time::Duration::from_secs(gpt_sovits_config.poll_timeout_secs);
+        let mut poll_delay = tokio::time::Duration::from_millis(gpt_sovits_config.poll_interval_ms);
+        let poll_start = tokio::time::Instant::now();
+        loop {
+            if matches!(
+                download_client.head(durl.as_str()).send().await,
+                Ok(resp) if resp.status().is_success()
+            ) {
+                break;
+            }
+            if poll_start.elapsed() >= poll_timeout {
+                break;
+            }
+            tokio::time::sleep(poll_delay).await;
+            poll_delay = (poll_delay * 2).min(poll_timeout);
+        }
+
+        let wav_resp = download_client.get(durl.as_str()).send().await?;
         let wav_bytes = wav_resp.bytes().await?;
 
         Ok(wav_bytes.into())
@@ -422,7 +523,7 @@ impl TtsClient for GptSovitsClient {
         self.pick_random_voice(gender, excluded_voices)
     }
 
-    fn get_narrator_voice_id(&self) -> String {
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
         self.config
             .narrator_voice
             .clone()
@@ -436,20 +537,13 @@ impl TtsClient for GptSovitsClient {
     fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
         voices
             .iter()
-            .map(|v| {
-                format!(
-                    "{{ \"id\": \"{}\", \"gender\": \"{}\", \"info\": \"{}\" }}",
-                    v.short_name,
-                    v.gender,
-                    v.friendly_name.as_deref().unwrap_or("")
-                )
-            })
+            .map(Voice::to_analysis_string)
             .collect::<Vec<_>>()
             .join("\n")
     }
 
     fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
-        Box::new(GptSovitsScriptGenerator::new(self.get_narrator_voice_id()))
+        Box::new(GptSovitsScriptGenerator::new(self.get_narrator_voice_id(None)))
     }
 
     fn merge_audio_files(
@@ -459,4 +553,91 @@ impl TtsClient for GptSovitsClient {
     ) -> Result<()> {
         crate::utils::audio::merge_wav_files(inputs, output)
     }
+
+    fn is_mp3_output(&self) -> bool {
+        false
+    }
+
+    fn chars_per_second(&self) -> f64 {
+        200.0
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.config.concurrency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(base_url: String) -> GptSovitsConfig {
+        GptSovitsConfig {
+            base_url,
+            retry: 1,
+            rewrite_download_host: false,
+            poll_timeout_secs: 0,
+            download_timeout_secs: 5,
+            concurrency: 3,
+            timeout_secs: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_reuses_pooled_client_across_segments() -> Result<()> {
+        let server = wiremock::MockServer::start().await;
+
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .and(wiremock::matchers::path("/infer_single"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string(format!(
+                "{{\"msg\":\"合成成功\",\"audio_url\":\"{}/download\"}}",
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::method("HEAD"))
+            .and(wiremock::matchers::path("/download"))
+            .respond_with(wiremock::ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .and(wiremock::matchers::path("/download"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_bytes(b"fake wav bytes".to_vec()))
+            .mount(&server)
+            .await;
+
+        let client = GptSovitsClient {
+            config: test_config(server.uri()),
+            metadata: GptSovitsVoiceMap::new(),
+            client: reqwest::Client::builder()
+                .pool_max_idle_per_host(6)
+                .build()?,
+            child_voice_tags: Vec::new(),
+        };
+        let char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: std::collections::HashMap::new(),
+        };
+
+        // Two segments synthesized sequentially through the same client
+        // instance (rather than a fresh `reqwest::Client::new()` per call)
+        // should both succeed without the connection pool being recreated.
+        for text in ["第一段", "第二段"] {
+            let segment = AudioSegment {
+                text: text.to_string(),
+                speaker: None,
+                style: None,
+                voice_id: Some("test-voice".to_string()),
+                detected_language: None,
+                confidence: Some(1.0),
+            };
+            let audio = client.synthesize(&segment, &char_map, &[]).await?;
+            assert_eq!(audio, b"fake wav bytes".to_vec());
+        }
+
+        Ok(())
+    }
 }
@@ -32,6 +32,66 @@ pub struct GptSovitsConfig {
 
     #[serde(default)]
     pub autofix: bool,
+
+    /// The Acgnai API returns `audio_url` pointing at an internal hostname
+    /// that's unreachable from outside, so by default we rewrite its host to
+    /// match `base_url`. Set this to false if your `base_url` points at a
+    /// server that returns a directly reachable download URL.
+    #[serde(default = "default_gpt_sovits_rewrite_download_host")]
+    pub rewrite_download_host: bool,
+
+    /// Timeout in seconds for the WAV download request, so a slow CDN
+    /// doesn't hang synthesis indefinitely.
+    #[serde(default = "default_gpt_sovits_download_timeout_secs")]
+    pub download_timeout_secs: u64,
+
+    /// Number of sentences the server batches together per inference pass.
+    /// Higher values improve throughput but increase latency before the
+    /// first chunk of audio comes back.
+    #[serde(default = "default_gpt_sovits_batch_size")]
+    pub batch_size: u32,
+
+    /// Minimum fraction of a batch that must be ready before the server
+    /// starts returning audio. Lower values reduce latency at the cost of
+    /// less consistent batching (and therefore slightly worse throughput).
+    #[serde(default = "default_gpt_sovits_batch_threshold")]
+    pub batch_threshold: f32,
+
+    /// Minimum silence, in seconds, inserted between synthesized fragments.
+    /// Larger values sound more natural for dramatic pauses but make the
+    /// narration slower overall.
+    #[serde(default = "default_gpt_sovits_fragment_interval")]
+    pub fragment_interval: f32,
+
+    /// Number of diffusion steps used by the GPT-SoVITS sampler. More steps
+    /// generally produce cleaner audio but take proportionally longer to
+    /// synthesize.
+    #[serde(default = "default_gpt_sovits_sample_steps")]
+    pub sample_steps: u32,
+
+    /// Initial delay between HEAD-request polls of the download URL while
+    /// waiting for the server to finish writing the synthesized file,
+    /// doubling after each miss up to `poll_timeout_secs`.
+    #[serde(default = "default_gpt_sovits_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// Maximum time to keep polling the download URL before giving up and
+    /// attempting the download anyway.
+    #[serde(default = "default_gpt_sovits_poll_timeout_secs")]
+    pub poll_timeout_secs: u64,
+
+    /// Per-request timeout for the pooled `reqwest::Client` `GptSovitsClient`
+    /// builds in `new()`. `None` falls back to 120 seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// Max number of segments synthesized concurrently (see
+    /// `TtsClient::max_concurrency`), and the basis for the pooled client's
+    /// `pool_max_idle_per_host` (`concurrency * 2`, so a burst of requests
+    /// doesn't immediately evict idle connections the next burst could have
+    /// reused).
+    #[serde(default = "default_gpt_sovits_concurrency")]
+    pub concurrency: usize,
 }
 
 fn default_gpt_sovits_base_url() -> String {
@@ -53,6 +113,33 @@ fn default_gpt_sovits_speed_factor() -> u8 {
 fn default_gpt_sovits_repetition_penalty() -> f64 {
     1.35
 }
+fn default_gpt_sovits_rewrite_download_host() -> bool {
+    true
+}
+fn default_gpt_sovits_download_timeout_secs() -> u64 {
+    60
+}
+fn default_gpt_sovits_batch_size() -> u32 {
+    10
+}
+fn default_gpt_sovits_batch_threshold() -> f32 {
+    0.75
+}
+fn default_gpt_sovits_fragment_interval() -> f32 {
+    0.3
+}
+fn default_gpt_sovits_sample_steps() -> u32 {
+    16
+}
+fn default_gpt_sovits_poll_interval_ms() -> u64 {
+    500
+}
+fn default_gpt_sovits_poll_timeout_secs() -> u64 {
+    60
+}
+fn default_gpt_sovits_concurrency() -> usize {
+    5
+}
 
 // --- Metadata ---
 
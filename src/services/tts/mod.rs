@@ -5,7 +5,8 @@ use crate::services::llm::LlmClient;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::info;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 // --- Constants ---
 
@@ -15,7 +16,7 @@ pub const VOICE_ID_MOB_NEUTRAL: &str = "placeholder_mob_neutral";
 pub const VOICE_ID_CHAPTER_MOB_MALE: &str = "placeholder_chapter_mob_male";
 pub const VOICE_ID_CHAPTER_MOB_FEMALE: &str = "placeholder_chapter_mob_female";
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct Voice {
     pub name: String,
@@ -25,6 +26,27 @@ pub struct Voice {
     pub friendly_name: Option<String>,
 }
 
+impl std::fmt::Display for Voice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}/{})", self.short_name, self.gender, self.locale)
+    }
+}
+
+impl Voice {
+    /// Renders this voice as the JSON-object format used across
+    /// `format_voice_list_for_analysis` implementations when listing
+    /// candidate voices in LLM analysis prompts.
+    pub fn to_analysis_string(&self) -> String {
+        format!(
+            "{{ \"id\": \"{}\", \"gender\": \"{}\", \"locale\": \"{}\", \"info\": \"{}\" }}",
+            self.short_name,
+            self.gender,
+            self.locale,
+            self.friendly_name.as_deref().unwrap_or("")
+        )
+    }
+}
+
 #[async_trait]
 pub trait TtsClient: Send + Sync {
     async fn list_voices(&self) -> Result<Vec<Voice>>;
@@ -54,7 +76,27 @@ pub trait TtsClient: Send + Sync {
         Ok(())
     }
 
-    fn get_narrator_voice_id(&self) -> String;
+    /// Verifies that at least one voice is reachable before synthesis starts.
+    /// Providers that don't need a network round-trip (e.g. ones relying purely
+    /// on a local metadata cache) should override this to avoid the extra call.
+    async fn check_voice_availability(&self) -> Result<()> {
+        let voices = self.list_voices().await?;
+        if voices.is_empty() {
+            return Err(anyhow!(
+                "No voices available. Check your network connection or TTS provider configuration."
+            ));
+        }
+        Ok(())
+    }
+
+    /// `language_hint` (an ISO-ish code from `AudioSegment::detected_language`,
+    /// e.g. `"en"`) lets a provider pick a different narrator voice for a
+    /// detected-foreign-language passage. Providers without a per-language
+    /// narrator mapping of their own can ignore the hint; the cross-provider
+    /// `AudioConfig::narrator_voices` override in `services::workflow` is
+    /// applied independently by setting `AudioSegment::voice_id` directly, so
+    /// implementations are not required to consult it here.
+    fn get_narrator_voice_id(&self, language_hint: Option<&str>) -> String;
     fn is_mob_enabled(&self) -> bool;
     fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String;
     fn get_script_generator(&self) -> Box<dyn ScriptGenerator>;
@@ -70,6 +112,73 @@ pub trait TtsClient: Send + Sync {
     fn max_concurrency(&self) -> usize {
         5
     }
+
+    /// Whether this provider's merged chapter output is an MP3 file (as
+    /// opposed to e.g. the WAV output `merge_wav_files` produces).
+    /// `WorkflowManager` uses this to decide whether ID3 tag embedding
+    /// applies to a chapter's merged output.
+    fn is_mp3_output(&self) -> bool {
+        true
+    }
+
+    /// Rough ETA for synthesizing `segments`, based on total character count
+    /// and a provider-specific throughput estimate. Providers with a very
+    /// different cost model (e.g. self-hosted GPU vs. fast cloud API) should
+    /// override `chars_per_second` rather than this method.
+    async fn estimate_synthesis_time(&self, segments: &[AudioSegment]) -> Duration {
+        let total_chars: usize = segments.iter().map(|s| s.text.chars().count()).sum();
+        Duration::from_secs_f64(total_chars as f64 / self.chars_per_second())
+    }
+
+    fn chars_per_second(&self) -> f64 {
+        100.0
+    }
+
+    /// Whether this provider synthesizes from SSML markup rather than plain
+    /// text. `WorkflowManager` uses this to decide which of
+    /// `utils::text::apply_phonetic_corrections`/`apply_phonetic_corrections_ssml`
+    /// to apply to a segment before synthesis: SSML providers build their
+    /// own `<phoneme>` tags internally (see `tts::edge`, `tts::azure`), so
+    /// they don't want the plain-text substitution applied ahead of them.
+    fn uses_ssml(&self) -> bool {
+        false
+    }
+
+    /// Rough USD cost estimate for synthesizing `segments`, so a user can
+    /// check the bill before spending API credits. Defaults to `0.0` for
+    /// providers with no per-character cost (e.g. `EdgeTtsClient`, which
+    /// talks to the free unofficial API, and self-hosted providers like
+    /// `gpt_sovits`). Providers billed per character (e.g. `ElevenLabsClient`)
+    /// should override this.
+    async fn estimate_cost(&self, _segments: &[AudioSegment]) -> Result<f64> {
+        Ok(0.0)
+    }
+}
+
+/// Best-effort gender guess from a voice name, for providers that don't
+/// return structured gender metadata. Checks explicit "Male"/"Female"
+/// substrings first, then a short list of well-known Edge-TTS given names.
+pub fn infer_gender_from_name(voice_name: &str) -> Option<String> {
+    let lower = voice_name.to_lowercase();
+
+    if lower.contains("female") {
+        return Some("Female".to_string());
+    }
+    if lower.contains("male") {
+        return Some("Male".to_string());
+    }
+
+    const FEMALE_NAMES: &[&str] = &["hsiaochen", "hsiaoyu", "xiaoxiao", "xiaoyi", "hiumaan"];
+    const MALE_NAMES: &[&str] = &["yunjian", "yunyang", "yunxi", "yunjhe", "wanlung"];
+
+    if FEMALE_NAMES.iter().any(|n| lower.contains(n)) {
+        return Some("Female".to_string());
+    }
+    if MALE_NAMES.iter().any(|n| lower.contains(n)) {
+        return Some("Male".to_string());
+    }
+
+    None
 }
 
 pub async fn fetch_voice_list(
@@ -94,9 +203,43 @@ pub async fn fetch_voice_list(
                 .clone()
                 .ok_or_else(|| anyhow!("Qwen3 TTS config missing"))?;
             let language = config.audio.language.clone();
-            let client = qwen3_tts::Qwen3TtsClient::new(qwen_config, language).await?;
+            let zh_variant = config.audio.resolved_zh_variant();
+            let client = qwen3_tts::Qwen3TtsClient::new(qwen_config, language, zh_variant).await?;
             client.list_voices().await
         }
+        "azure" => {
+            let azure_config = config
+                .audio
+                .azure
+                .as_ref()
+                .ok_or_else(|| anyhow!("Azure TTS config missing"))?;
+            azure::list_voices(azure_config).await
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        "polly" => {
+            let polly_config = config
+                .audio
+                .polly
+                .as_ref()
+                .ok_or_else(|| anyhow!("Polly config missing"))?;
+            polly::list_voices(polly_config).await
+        }
+        "elevenlabs" => {
+            let elevenlabs_config = config
+                .audio
+                .elevenlabs
+                .as_ref()
+                .ok_or_else(|| anyhow!("ElevenLabs config missing"))?;
+            elevenlabs::list_voices(elevenlabs_config).await
+        }
+        "external" => {
+            let external_config = config
+                .audio
+                .external
+                .as_ref()
+                .ok_or_else(|| anyhow!("External TTS config missing"))?;
+            external::list_voices(external_config).await
+        }
         _ => Err(anyhow::anyhow!(
             "Unknown TTS provider: {}",
             config.audio.provider
@@ -104,6 +247,27 @@ pub async fn fetch_voice_list(
     }
 }
 
+/// Narrows `voices` down to `config.audio.language`/`exclude_locales` (the
+/// same filter `WorkflowManager::process_chapter` applies before character
+/// analysis), plus the `voices` CLI subcommand's own `--gender`/`--locale`
+/// flags. Kept separate from `fetch_voice_list` so the CLI's filtering logic
+/// is unit-testable without a real provider call.
+pub fn filter_voices(
+    voices: Vec<Voice>,
+    language: &str,
+    exclude_locales: &[String],
+    gender: Option<&str>,
+    locale_prefix: Option<&str>,
+) -> Vec<Voice> {
+    voices
+        .into_iter()
+        .filter(|v| v.locale.starts_with(language))
+        .filter(|v| !exclude_locales.contains(&v.locale))
+        .filter(|v| gender.is_none() || gender.is_some_and(|g| v.gender == g))
+        .filter(|v| locale_prefix.is_none() || locale_prefix.is_some_and(|prefix| v.locale.starts_with(prefix)))
+        .collect()
+}
+
 pub async fn create_tts_client(
     config: &Config,
     llm: Option<&dyn LlmClient>,
@@ -114,8 +278,17 @@ pub async fn create_tts_client(
             let edge_config = config.audio.edge_tts.clone().unwrap_or_default();
             let exclude_locales = config.audio.exclude_locales.clone();
             let language = config.audio.language.clone();
+            let phonetic_corrections = config.audio.phonetic_corrections.clone();
+            let child_voice_tags = config.audio.child_voice_tags.clone();
             Ok(Box::new(
-                edge::EdgeTtsClient::new(edge_config, exclude_locales, language).await?,
+                edge::EdgeTtsClient::new(
+                    edge_config,
+                    exclude_locales,
+                    language,
+                    phonetic_corrections,
+                    child_voice_tags,
+                )
+                .await?,
             ))
         }
         "gpt_sovits" => {
@@ -125,8 +298,10 @@ pub async fn create_tts_client(
                 .clone()
                 .ok_or_else(|| anyhow!("GPT-Sovits config missing"))?;
             let language = config.audio.language.clone();
+            let child_voice_tags = config.audio.child_voice_tags.clone();
             Ok(Box::new(
-                gpt_sovits::GptSovitsClient::new(gpt_config, &language, llm).await?,
+                gpt_sovits::GptSovitsClient::new(gpt_config, &language, llm, child_voice_tags)
+                    .await?,
             ))
         }
         "qwen3_tts" => {
@@ -136,25 +311,94 @@ pub async fn create_tts_client(
                 .clone()
                 .ok_or_else(|| anyhow!("Qwen3 TTS config missing"))?;
             let language = config.audio.language.clone();
+            let zh_variant = config.audio.resolved_zh_variant();
+            Ok(Box::new(
+                qwen3_tts::Qwen3TtsClient::new(qwen_config, language, zh_variant).await?,
+            ))
+        }
+        "azure" => {
+            let azure_config = config
+                .audio
+                .azure
+                .clone()
+                .ok_or_else(|| anyhow!("Azure TTS config missing"))?;
+            let phonetic_corrections = config.audio.phonetic_corrections.clone();
+            Ok(Box::new(
+                azure::AzureTtsClient::new(azure_config, phonetic_corrections).await?,
+            ))
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        "polly" => {
+            let polly_config = config
+                .audio
+                .polly
+                .clone()
+                .ok_or_else(|| anyhow!("Polly config missing"))?;
+            Ok(Box::new(polly::PollyTtsClient::new(polly_config).await?))
+        }
+        "elevenlabs" => {
+            let elevenlabs_config = config
+                .audio
+                .elevenlabs
+                .clone()
+                .ok_or_else(|| anyhow!("ElevenLabs config missing"))?;
             Ok(Box::new(
-                qwen3_tts::Qwen3TtsClient::new(qwen_config, language).await?,
+                elevenlabs::ElevenLabsClient::new(elevenlabs_config).await?,
             ))
         }
+        "external" => {
+            let external_config = config
+                .audio
+                .external
+                .clone()
+                .ok_or_else(|| anyhow!("External TTS config missing"))?;
+            Ok(Box::new(external::ExternalTtsClient::new(external_config)))
+        }
         _ => Err(anyhow!("Unknown TTS provider: {}", config.audio.provider)),
     }
 }
 
+pub mod azure;
 pub mod edge;
+pub mod elevenlabs;
+pub mod external;
 pub mod gpt_sovits;
 pub mod qwen3_tts;
 pub mod gpt_sovits_config;
 pub mod qwen3_api;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod polly;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::services::tts::edge::EdgeTtsConfig;
 
+    #[test]
+    fn test_infer_gender_from_name() {
+        assert_eq!(
+            infer_gender_from_name("zh-CN-XiaoxiaoNeural"),
+            Some("Female".to_string())
+        );
+        assert_eq!(
+            infer_gender_from_name("zh-CN-YunxiNeural"),
+            Some("Male".to_string())
+        );
+        assert_eq!(
+            infer_gender_from_name("zh-TW-HsiaoChenNeural"),
+            Some("Female".to_string())
+        );
+        assert_eq!(
+            infer_gender_from_name("zh-TW-YunJheNeural"),
+            Some("Male".to_string())
+        );
+        assert_eq!(
+            infer_gender_from_name("en-US-SomeMaleVoice"),
+            Some("Male".to_string())
+        );
+        assert_eq!(infer_gender_from_name("Totally Unknown Voice"), None);
+    }
+
     #[test]
     fn test_pick_random_voice() {
         let edge_config = EdgeTtsConfig {
@@ -199,4 +443,97 @@ mod tests {
         let v = client.pick_random_voice(Some("Male"), &[]);
         assert_eq!(v, "zh-CN-Male"); // Only one zh Male
     }
+
+    #[test]
+    fn test_voice_display() {
+        let voice = Voice {
+            name: "".to_string(),
+            short_name: "zh-CN-XiaoxiaoNeural".to_string(),
+            gender: "Female".to_string(),
+            locale: "zh-CN".to_string(),
+            friendly_name: None,
+        };
+        assert_eq!(voice.to_string(), "zh-CN-XiaoxiaoNeural (Female/zh-CN)");
+    }
+
+    #[test]
+    fn test_voice_to_analysis_string() {
+        let voice = Voice {
+            name: "".to_string(),
+            short_name: "zh-CN-XiaoxiaoNeural".to_string(),
+            gender: "Female".to_string(),
+            locale: "zh-CN".to_string(),
+            friendly_name: Some("Xiaoxiao (Chatty)".to_string()),
+        };
+        assert_eq!(
+            voice.to_analysis_string(),
+            "{ \"id\": \"zh-CN-XiaoxiaoNeural\", \"gender\": \"Female\", \"locale\": \"zh-CN\", \"info\": \"Xiaoxiao (Chatty)\" }"
+        );
+    }
+
+    #[test]
+    fn test_voice_to_analysis_string_missing_friendly_name() {
+        let voice = Voice {
+            name: "".to_string(),
+            short_name: "zh-CN-XiaoxiaoNeural".to_string(),
+            gender: "Female".to_string(),
+            locale: "zh-CN".to_string(),
+            friendly_name: None,
+        };
+        assert_eq!(
+            voice.to_analysis_string(),
+            "{ \"id\": \"zh-CN-XiaoxiaoNeural\", \"gender\": \"Female\", \"locale\": \"zh-CN\", \"info\": \"\" }"
+        );
+    }
+
+    fn mock_voices() -> Vec<Voice> {
+        vec![
+            Voice {
+                name: "".to_string(),
+                short_name: "zh-CN-Male".to_string(),
+                gender: "Male".to_string(),
+                locale: "zh-CN".to_string(),
+                friendly_name: Some("Mandarin male".to_string()),
+            },
+            Voice {
+                name: "".to_string(),
+                short_name: "zh-HK-Female".to_string(),
+                gender: "Female".to_string(),
+                locale: "zh-HK".to_string(),
+                friendly_name: None,
+            },
+            Voice {
+                name: "".to_string(),
+                short_name: "en-US-Male".to_string(),
+                gender: "Male".to_string(),
+                locale: "en-US".to_string(),
+                friendly_name: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_filter_voices_applies_language_and_exclude_locales() {
+        let exclude_locales = vec!["zh-HK".to_string()];
+        let filtered = filter_voices(mock_voices(), "zh", &exclude_locales, None, None);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].short_name, "zh-CN-Male");
+    }
+
+    #[test]
+    fn test_filter_voices_applies_gender_and_locale_flags() {
+        let filtered = filter_voices(mock_voices(), "", &[], Some("Male"), Some("en"));
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].short_name, "en-US-Male");
+    }
+
+    #[test]
+    fn test_filtered_voices_serialize_to_json_with_expected_fields() {
+        let filtered = filter_voices(mock_voices(), "zh", &[], None, None);
+        let json = serde_json::to_value(&filtered).unwrap();
+        let entry = &json.as_array().unwrap()[0];
+        assert_eq!(entry["ShortName"], "zh-CN-Male");
+        assert_eq!(entry["Gender"], "Male");
+        assert_eq!(entry["Locale"], "zh-CN");
+    }
 }
@@ -0,0 +1,364 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use crate::core::state::CharacterMap;
+use crate::services::script::{AudioSegment, JsonScriptGenerator, ScriptGenerator};
+use crate::services::tts::{
+    TtsClient, Voice, VOICE_ID_MOB_FEMALE, VOICE_ID_MOB_MALE, VOICE_ID_MOB_NEUTRAL,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use aws_sdk_polly::config::{BehaviorVersion, Credentials, Region};
+use aws_sdk_polly::types::{Engine, OutputFormat, VoiceId};
+use aws_sdk_polly::Client;
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+
+// --- Config ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PollyConfig {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub region: String,
+    #[serde(default = "default_engine")]
+    pub engine: String,
+    pub narrator_voice: Option<String>,
+    pub default_male_voice: Option<String>,
+    pub default_female_voice: Option<String>,
+    #[serde(default = "default_retry_count")]
+    pub retry_count: usize,
+}
+
+fn default_engine() -> String {
+    "neural".to_string()
+}
+fn default_retry_count() -> usize {
+    3
+}
+
+fn build_client(config: &PollyConfig) -> Client {
+    let credentials = Credentials::new(
+        &config.access_key_id,
+        &config.secret_access_key,
+        None,
+        None,
+        "novel2audiobook",
+    );
+    let conf = aws_sdk_polly::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new(config.region.clone()))
+        .credentials_provider(credentials)
+        .build();
+    Client::from_conf(conf)
+}
+
+pub async fn list_voices(config: &PollyConfig) -> Result<Vec<Voice>> {
+    let client = build_client(config);
+    let resp = client
+        .describe_voices()
+        .send()
+        .await
+        .map_err(|e| anyhow!("Failed to list Polly voices: {}", e))?;
+
+    Ok(resp
+        .voices()
+        .iter()
+        .map(|v| {
+            let id = v.id().map(|id| id.as_str().to_string()).unwrap_or_default();
+            Voice {
+                name: v.name().unwrap_or(&id).to_string(),
+                short_name: id,
+                gender: v.gender().map(|g| g.as_str().to_string()).unwrap_or_default(),
+                locale: v
+                    .language_code()
+                    .map(|l| l.as_str().to_string())
+                    .unwrap_or_default(),
+                friendly_name: v.name().map(|n| n.to_string()),
+            }
+        })
+        .collect())
+}
+
+// --- Polly TTS Client ---
+
+pub struct PollyTtsClient {
+    config: PollyConfig,
+    client: Client,
+    voices_cache: Vec<Voice>,
+}
+
+impl PollyTtsClient {
+    pub async fn new(config: PollyConfig) -> Result<Self> {
+        let client = build_client(&config);
+        let voices_cache = list_voices(&config).await.unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Failed to fetch Polly voices for random selection: {}",
+                e
+            );
+            Vec::new()
+        });
+        Ok(Self {
+            config,
+            client,
+            voices_cache,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_with_voices(config: PollyConfig, voices: Vec<Voice>) -> Self {
+        let client = build_client(&config);
+        Self {
+            config,
+            client,
+            voices_cache: voices,
+        }
+    }
+
+    pub fn pick_random_voice(&self, gender: Option<&str>, excluded_voices: &[String]) -> String {
+        let mut rng = rand::rng();
+
+        let candidates: Vec<&Voice> = self
+            .voices_cache
+            .iter()
+            .filter(|v| {
+                if excluded_voices.contains(&v.short_name) {
+                    return false;
+                }
+                if let Some(g) = gender {
+                    if !v.gender.eq_ignore_ascii_case(g) {
+                        return false;
+                    }
+                }
+                true
+            })
+            .collect();
+
+        if let Some(v) = candidates.choose(&mut rng) {
+            v.short_name.clone()
+        } else {
+            // Fallback
+            self.config
+                .narrator_voice
+                .clone()
+                .unwrap_or_else(|| "Joanna".to_string())
+        }
+    }
+
+    fn resolve_voice(
+        &self,
+        speaker: &str,
+        char_map: &CharacterMap,
+        excluded_voices: &[String],
+    ) -> String {
+        let polly_config = &self.config;
+
+        // 1. Check if Narrator
+        if speaker == "旁白" || speaker.eq_ignore_ascii_case("Narrator") {
+            if let Some(v) = &polly_config.narrator_voice {
+                return v.clone();
+            }
+        }
+
+        // 2. Check Character Map
+        if let Some(info) = char_map.characters.get(speaker) {
+            if let Some(voice_id) = &info.voice_id {
+                // Check for Special Mob IDs
+                match voice_id.as_str() {
+                    VOICE_ID_MOB_MALE => {
+                        return self.pick_random_voice(Some("Male"), excluded_voices)
+                    }
+                    VOICE_ID_MOB_FEMALE => {
+                        return self.pick_random_voice(Some("Female"), excluded_voices)
+                    }
+                    VOICE_ID_MOB_NEUTRAL => return self.pick_random_voice(None, excluded_voices),
+                    _ => return voice_id.clone(),
+                }
+            }
+
+            // 3. Fallback to Gender Default
+            match info.gender.to_lowercase().as_str() {
+                "male" => {
+                    if let Some(v) = &polly_config.default_male_voice {
+                        return v.clone();
+                    }
+                }
+                "female" => {
+                    if let Some(v) = &polly_config.default_female_voice {
+                        return v.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 4. Ultimate Fallback (Narrator or hard fallback)
+        if let Some(v) = &polly_config.narrator_voice {
+            return v.clone();
+        }
+
+        "Joanna".to_string() // Hard fallback
+    }
+
+    /// Jitters a base delay to `[0.5x, 1.5x]` so concurrent segments retrying
+    /// against a throttled Polly endpoint don't all wake up at once, matching
+    /// the jitter approach used by `RetryLlmClient`.
+    fn jittered_delay_ms(attempt: usize) -> u64 {
+        use rand::Rng;
+        let base = 500.0 * (attempt as f64 + 1.0);
+        rand::rng().random_range(base * 0.5..=base * 1.5).round() as u64
+    }
+}
+
+#[async_trait]
+impl TtsClient for PollyTtsClient {
+    async fn list_voices(&self) -> Result<Vec<Voice>> {
+        if !self.voices_cache.is_empty() {
+            Ok(self.voices_cache.clone())
+        } else {
+            list_voices(&self.config).await
+        }
+    }
+
+    async fn synthesize(
+        &self,
+        segment: &AudioSegment,
+        char_map: &CharacterMap,
+        excluded_voices: &[String],
+    ) -> Result<Vec<u8>> {
+        let voice = if let Some(vid) = &segment.voice_id {
+            vid.clone()
+        } else if let Some(speaker) = &segment.speaker {
+            self.resolve_voice(speaker, char_map, excluded_voices)
+        } else {
+            panic!("No speaker or voice_id specified for segment");
+        };
+
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .client
+                .synthesize_speech()
+                .text(&segment.text)
+                .voice_id(VoiceId::from(voice.as_str()))
+                .engine(Engine::from(self.config.engine.as_str()))
+                .output_format(OutputFormat::Mp3)
+                .send()
+                .await;
+
+            match result {
+                Ok(output) => {
+                    let bytes = output
+                        .audio_stream
+                        .collect()
+                        .await
+                        .map_err(|e| anyhow!("Failed to read Polly audio stream: {}", e))?
+                        .into_bytes();
+                    return Ok(bytes.to_vec());
+                }
+                Err(e) => {
+                    let is_throttling = e.to_string().contains("Throttling");
+                    if !is_throttling || attempt >= self.config.retry_count {
+                        return Err(anyhow!("Polly synthesis failed: {}", e));
+                    }
+                    let delay = Self::jittered_delay_ms(attempt);
+                    tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn get_random_voice(
+        &self,
+        gender: Option<&str>,
+        excluded_voices: &[String],
+    ) -> Result<String> {
+        Ok(self.pick_random_voice(gender, excluded_voices))
+    }
+
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+        self.config
+            .narrator_voice
+            .clone()
+            .unwrap_or_else(|| "Joanna".to_string())
+    }
+
+    fn is_mob_enabled(&self) -> bool {
+        true
+    }
+
+    fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
+        voices
+            .iter()
+            .map(Voice::to_analysis_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+        Box::new(JsonScriptGenerator::new())
+    }
+
+    fn chars_per_second(&self) -> f64 {
+        1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config() -> PollyConfig {
+        PollyConfig {
+            access_key_id: "test-key".to_string(),
+            secret_access_key: "test-secret".to_string(),
+            region: "us-east-1".to_string(),
+            engine: "neural".to_string(),
+            narrator_voice: Some("Joanna".to_string()),
+            default_male_voice: None,
+            default_female_voice: None,
+            retry_count: 3,
+        }
+    }
+
+    #[test]
+    fn test_pick_random_voice_filters_by_gender_and_exclusion() {
+        let voices = vec![
+            Voice {
+                short_name: "Matthew".to_string(),
+                gender: "Male".to_string(),
+                locale: "en-US".to_string(),
+                name: "Matthew".to_string(),
+                friendly_name: None,
+            },
+            Voice {
+                short_name: "Joanna".to_string(),
+                gender: "Female".to_string(),
+                locale: "en-US".to_string(),
+                name: "Joanna".to_string(),
+                friendly_name: None,
+            },
+        ];
+        let client = PollyTtsClient::new_with_voices(sample_config(), voices);
+
+        let v = client.pick_random_voice(Some("Male"), &[]);
+        assert_eq!(v, "Matthew");
+
+        // Only male voice is excluded, so it should fall back to the narrator.
+        let v = client.pick_random_voice(Some("Male"), &["Matthew".to_string()]);
+        assert_eq!(v, "Joanna");
+    }
+
+    #[test]
+    fn test_resolve_voice_uses_narrator_for_narrator_speaker() {
+        let client = PollyTtsClient::new_with_voices(sample_config(), Vec::new());
+        let char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: std::collections::HashMap::new(),
+        };
+        assert_eq!(
+            client.resolve_voice("旁白", &char_map, &[]),
+            "Joanna".to_string()
+        );
+    }
+}
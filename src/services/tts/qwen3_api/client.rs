@@ -144,5 +144,50 @@ async fn try_generate_and_download(
     let file_bytes = client.get(download_url).send().await.context("Failed to download result")?.bytes().await.context("Failed to get bytes")?;
 
     debug!("下載成功，大小: {} bytes", file_bytes.len());
-    Ok(file_bytes.to_vec())
+    check_audio_response(file_bytes.to_vec())
+}
+
+#[derive(serde::Deserialize)]
+struct Qwen3ErrorResponse {
+    error: String,
+}
+
+/// The Python server returns raw audio bytes on success, but falls back to a
+/// JSON error object (`{ "error": "..." }`) for invalid requests. Sniff the
+/// first byte so a JSON error doesn't get passed downstream as corrupt audio.
+fn check_audio_response(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    if bytes.first() == Some(&b'{') {
+        if let Ok(err) = serde_json::from_slice::<Qwen3ErrorResponse>(&bytes) {
+            return Err(anyhow!("Qwen3 TTS server returned an error: {}", err.error));
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_audio_response_passes_through_audio_bytes() {
+        let audio = vec![0x52, 0x49, 0x46, 0x46, 0u8, 0u8, 0u8, 0u8];
+        let result = check_audio_response(audio.clone()).unwrap();
+        assert_eq!(result, audio);
+    }
+
+    #[test]
+    fn test_check_audio_response_rejects_error_json() {
+        let body = br#"{"error": "voice file not found"}"#.to_vec();
+        let err = check_audio_response(body).unwrap_err();
+        assert!(err.to_string().contains("voice file not found"));
+    }
+
+    #[test]
+    fn test_check_audio_response_passes_through_json_like_audio() {
+        // Audio bytes that happen to start with `{` but aren't a valid error
+        // object should still be treated as audio rather than rejected.
+        let weird_audio = vec![b'{', 0x01, 0x02, 0x03];
+        let result = check_audio_response(weird_audio.clone()).unwrap();
+        assert_eq!(result, weird_audio);
+    }
 }
@@ -11,7 +11,6 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 use tokio::fs;
-use zhconv::{zhconv, Variant};
 
 // --- Config ---
 
@@ -25,6 +24,20 @@ pub struct Qwen3TtsConfig {
     #[serde(default = "default_concurrency")]
     pub concurrency: usize,
     pub device: Option<String>,
+    /// Download every known voice `.pt` file on startup instead of lazily on
+    /// first use. Avoids a stall on the first synthesis of each voice/style.
+    #[serde(default = "default_prefetch_voices")]
+    pub prefetch_voices: bool,
+
+    /// Output format for each chapter's merged audio: `"wav"` (the default)
+    /// or `"mp3"`, which transcodes the merged WAV via
+    /// `utils::audio::encode_to_mp3` to keep the final files small.
+    #[serde(default = "default_output_format")]
+    pub output_format: String,
+
+    /// Constant bitrate, in kbps, used when `output_format` is `"mp3"`.
+    #[serde(default = "default_mp3_bitrate_kbps")]
+    pub mp3_bitrate_kbps: u32,
 }
 
 impl Default for Qwen3TtsConfig {
@@ -35,6 +48,9 @@ impl Default for Qwen3TtsConfig {
             narrator_voice: None,
             concurrency: default_concurrency(),
             device: None,
+            prefetch_voices: default_prefetch_voices(),
+            output_format: default_output_format(),
+            mp3_bitrate_kbps: default_mp3_bitrate_kbps(),
         }
     }
 }
@@ -47,6 +63,18 @@ fn default_concurrency() -> usize {
     1
 }
 
+fn default_prefetch_voices() -> bool {
+    true
+}
+
+fn default_output_format() -> String {
+    "wav".to_string()
+}
+
+fn default_mp3_bitrate_kbps() -> u32 {
+    128
+}
+
 // --- Metadata ---
 
 #[derive(Debug, Deserialize, Clone)]
@@ -64,6 +92,7 @@ type Metadata = HashMap<String, HashMap<String, VoiceMetadata>>;
 pub struct Qwen3TtsClient {
     config: Qwen3TtsConfig,
     language: String,
+    zh_variant: crate::utils::text::ZhConversionMode,
     #[allow(dead_code)]
     server: Option<Qwen3Server>,
     metadata: Metadata,
@@ -71,7 +100,11 @@ pub struct Qwen3TtsClient {
 }
 
 impl Qwen3TtsClient {
-    pub async fn new(config: Qwen3TtsConfig, language: String) -> Result<Self> {
+    pub async fn new(
+        config: Qwen3TtsConfig,
+        language: String,
+        zh_variant: crate::utils::text::ZhConversionMode,
+    ) -> Result<Self> {
         info!("Initializing Qwen3 TTS Client...");
         
         // 1. Start Server if self_host
@@ -89,8 +122,8 @@ impl Qwen3TtsClient {
             fs::create_dir_all(voices_dir).await?;
         }
 
-        // 3. Check/Download voices
-        download_voices_if_needed(voices_dir).await?;
+        // 3. Ensure metadata.json is present (always needed to build the voice list)
+        download_voice_file_if_needed(voices_dir, "metadata.json").await?;
 
         // 4. Load metadata
         let metadata_path = voices_dir.join("metadata.json");
@@ -116,38 +149,78 @@ impl Qwen3TtsClient {
             }
         }
 
-        Ok(Self {
+        let client = Self {
             config,
             language,
+            zh_variant,
             server,
             metadata,
             voice_list,
-        })
+        };
+
+        // 6. Prefetch all voice `.pt` files if configured, to avoid stalling
+        // the first synthesis of each voice/style.
+        if client.config.prefetch_voices {
+            client.prefetch_all_voices().await?;
+        }
+
+        Ok(client)
+    }
+
+    /// Downloads every `.pt` voice file for the configured language that
+    /// isn't already cached locally, showing progress via `indicatif`.
+    pub async fn prefetch_all_voices(&self) -> Result<()> {
+        let voices_dir = Path::new("qwen3_tts_voices");
+        let api = Api::new()?;
+        let repo = api.model("kautism/qwen3_tts_voices".to_string());
+        let info = repo.info().await?;
+
+        let missing: Vec<String> = info
+            .siblings
+            .into_iter()
+            .map(|f| f.rfilename)
+            .filter(|name| name.starts_with(&format!("{}-", self.language)))
+            .filter(|name| !voices_dir.join(name).exists())
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let pb = indicatif::ProgressBar::new(missing.len() as u64);
+        pb.set_style(
+            indicatif::ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} voices")?
+                .progress_chars("#>-"),
+        );
+
+        for filename in missing {
+            download_voice_file_if_needed(voices_dir, &filename).await?;
+            pb.inc(1);
+        }
+        pb.finish_with_message("Voice prefetch complete");
+
+        Ok(())
     }
 }
 
-async fn download_voices_if_needed(target_dir: &Path) -> Result<()> {
-    info!("Checking voice files from HuggingFace via hf-hub...");
+async fn download_voice_file_if_needed(target_dir: &Path, filename: &str) -> Result<()> {
+    let target_path = target_dir.join(filename);
+    if target_path.exists() {
+        return Ok(());
+    }
+
+    info!("Downloading {}...", filename);
     let api = Api::new()?;
     let repo = api.model("kautism/qwen3_tts_voices".to_string());
-    let info = repo.info().await?;
-
-    for file in info.siblings {
-        let filename = file.rfilename;
-        let target_path = target_dir.join(&filename);
-
-        if !target_path.exists() {
-            info!("Downloading {}...", filename);
-            let path = repo.get(&filename).await?;
-            
-            if let Some(parent) = target_path.parent() {
-                if !parent.exists() {
-                    fs::create_dir_all(parent).await?;
-                }
-            }
-            fs::copy(path, target_path).await?;
+    let path = repo.get(filename).await?;
+
+    if let Some(parent) = target_path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).await?;
         }
     }
+    fs::copy(path, target_path).await?;
 
     Ok(())
 }
@@ -158,6 +231,16 @@ impl TtsClient for Qwen3TtsClient {
         Ok(self.voice_list.clone())
     }
 
+    async fn check_voice_availability(&self) -> Result<()> {
+        if self.voice_list.is_empty() {
+            return Err(anyhow!(
+                "No Qwen3 TTS voices found for language '{}' in metadata.json",
+                self.language
+            ));
+        }
+        Ok(())
+    }
+
     async fn synthesize(
         &self,
         segment: &AudioSegment,
@@ -173,13 +256,13 @@ impl TtsClient for Qwen3TtsClient {
                 char_info
                     .voice_id
                     .clone()
-                    .unwrap_or_else(|| self.get_narrator_voice_id())
+                    .unwrap_or_else(|| self.get_narrator_voice_id(segment.detected_language.as_deref()))
             } else {
                 warn!("Speaker {} not found in map", speaker);
-                self.get_narrator_voice_id()
+                self.get_narrator_voice_id(segment.detected_language.as_deref())
             }
         } else {
-            self.get_narrator_voice_id()
+            self.get_narrator_voice_id(segment.detected_language.as_deref())
         };
 
         let style = segment.style.as_deref().unwrap_or("中立");
@@ -207,7 +290,10 @@ impl TtsClient for Qwen3TtsClient {
         let file_path = Path::new("qwen3_tts_voices").join(&filename);
 
         if !file_path.exists() {
-            return Err(anyhow!("Voice file not found: {:?}", file_path));
+            // Not prefetched: download on demand rather than failing outright.
+            download_voice_file_if_needed(Path::new("qwen3_tts_voices"), &filename)
+                .await
+                .map_err(|e| anyhow!("Voice file not found and could not be downloaded: {:?}: {}", file_path, e))?;
         }
 
         let infer_lang = match lang.as_str() {
@@ -216,11 +302,19 @@ impl TtsClient for Qwen3TtsClient {
             _ => "Chinese", 
         };
 
-        let text = if infer_lang == "Chinese" {
-            &zhconv(&segment.text, Variant::ZhCN)
+        // Apply the configured Simplified/Traditional/None conversion
+        // (`AudioConfig::zh_variant`/`resolved_zh_variant`) rather than
+        // always forcing Simplified, so Traditional Chinese novels aren't
+        // silently rewritten.
+        let converted_text = if infer_lang == "Chinese" {
+            Some(crate::utils::text::convert_zh_variant(
+                &segment.text,
+                self.zh_variant,
+            ))
         } else {
-            &segment.text
+            None
         };
+        let text = converted_text.as_deref().unwrap_or(&segment.text);
 
         qwen3_tts_infer(
             base_url,
@@ -270,7 +364,7 @@ impl TtsClient for Qwen3TtsClient {
         Ok(vec![])
     }
 
-    fn get_narrator_voice_id(&self) -> String {
+    fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
         self.config
             .narrator_voice
             .clone()
@@ -282,21 +376,16 @@ impl TtsClient for Qwen3TtsClient {
     }
 
     fn format_voice_list_for_analysis(&self, voices: &[Voice]) -> String {
-        let mut s = String::new();
-        for v in voices {
-            s.push_str(&format!(
-                "- ID: {}, Gender: {}, Info: {}\n",
-                v.short_name,
-                v.gender,
-                v.friendly_name.as_deref().unwrap_or("")
-            ));
-        }
-        s
+        voices
+            .iter()
+            .map(Voice::to_analysis_string)
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
     fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
         Box::new(crate::services::script::Qwen3ScriptGenerator::new(
-            self.get_narrator_voice_id(),
+            self.get_narrator_voice_id(None),
         ))
     }
 
@@ -305,10 +394,26 @@ impl TtsClient for Qwen3TtsClient {
         inputs: &[std::path::PathBuf],
         output: &std::path::Path,
     ) -> Result<()> {
-        crate::utils::audio::merge_wav_files(inputs, output)
+        crate::utils::audio::merge_wav_files(inputs, output)?;
+
+        if self.config.output_format == "mp3" {
+            let wav = std::fs::read(output)?;
+            let mp3 = crate::utils::audio::encode_to_mp3(&wav, self.config.mp3_bitrate_kbps)?;
+            std::fs::write(output, mp3)?;
+        }
+
+        Ok(())
+    }
+
+    fn is_mp3_output(&self) -> bool {
+        self.config.output_format == "mp3"
     }
 
     fn max_concurrency(&self) -> usize {
         self.config.concurrency
     }
+
+    fn chars_per_second(&self) -> f64 {
+        self.config.concurrency as f64 * 300.0
+    }
 }
@@ -1,33 +1,379 @@
-use crate::core::config::Config;
-use crate::core::state::{CharacterInfo, CharacterMap, WorkflowState};
-use crate::services::llm::LlmClient;
-use crate::services::script::{strip_code_blocks, AudioSegment, ScriptGenerator};
+use crate::core::config::{AudioConfig, AudioOutputFormat, ChapterSort, Config, OutputConfig};
+use crate::core::state::{ChapterStats, CharacterInfo, CharacterMap, WorkflowState};
+use crate::services::llm::{FallbackStrategy, LlmClient, LlmClientExt, TokenUsage};
+use crate::services::script::{
+    deduplicate_segments, filter_empty_segments, strip_code_blocks, tag_detected_languages,
+    AudioSegment, ScriptGenerator,
+};
+use crate::services::stats::VoiceStatsReport;
+use crate::utils::storage::NativeStorage;
 use crate::services::tts::{
     TtsClient, VOICE_ID_CHAPTER_MOB_FEMALE, VOICE_ID_CHAPTER_MOB_MALE, VOICE_ID_MOB_FEMALE,
     VOICE_ID_MOB_MALE, VOICE_ID_MOB_NEUTRAL,
 };
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
+use log::warn;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs as tokio_fs;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use sha2::{Digest, Sha256};
+
+/// Sibling file to `segments.json`, recording the hash of the chapter text
+/// it was generated from so a later run can tell whether the source file
+/// changed since segments were cached.
+#[derive(Debug, Serialize, Deserialize)]
+struct SegmentsMeta {
+    chapter_hash: String,
+}
+
+/// One entry of a chapter's `timings.json`, giving the millisecond offsets
+/// of a synthesized segment within its chapter's final merged audio. Written
+/// alongside `segments.json` after synthesis so a later `generate_srt`-style
+/// call can build subtitles without re-probing every chunk file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SegmentTiming {
+    pub index: usize,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub speaker: String,
+    pub text: String,
+}
+
+/// Computes `SegmentTiming`s by measuring each synthesized chunk's duration
+/// and accumulating start/end offsets in order. Segments with no audio
+/// (skipped due to `WorkflowConfig::continue_on_error`) are left out, same
+/// as `indexed_audio_files` itself; a chunk that is silently inserted by
+/// `intersperse_silence` during merge is not reflected here, since that only
+/// runs for non-MP3-native providers and its fixed-length gaps can be
+/// reconstructed from `config.audio.silence` if exact sync is ever needed.
+fn build_segment_timings(
+    segments: &[AudioSegment],
+    indexed_audio_files: &[(usize, PathBuf)],
+    is_mp3_output: bool,
+) -> Result<Vec<SegmentTiming>> {
+    let mut timings = Vec::with_capacity(indexed_audio_files.len());
+    let mut cursor_ms: u64 = 0;
+
+    for (index, path) in indexed_audio_files {
+        let duration_ms = if is_mp3_output {
+            let data = fs::read(path)
+                .with_context(|| format!("Failed to read synthesized chunk {:?}", path))?;
+            crate::utils::audio::mp3_duration_ms(&data)
+                .with_context(|| format!("Failed to parse MP3 frames in {:?}", path))?
+        } else {
+            (crate::utils::audio::audio_duration_secs(path)? * 1000.0).round() as u64
+        };
+
+        let segment = &segments[*index];
+        timings.push(SegmentTiming {
+            index: *index,
+            start_ms: cursor_ms,
+            end_ms: cursor_ms + duration_ms,
+            speaker: segment.speaker.clone().unwrap_or_else(|| "旁白".to_string()),
+            text: segment.text.clone(),
+        });
+        cursor_ms += duration_ms;
+    }
+
+    Ok(timings)
+}
+
+#[derive(Debug, Serialize)]
+struct ChapterSummary {
+    filename: String,
+    segments: usize,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkflowSummary {
+    total_chapters: usize,
+    total_segments: usize,
+    total_size_bytes: u64,
+    chapters: Vec<ChapterSummary>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VoiceConflict {
+    pub voice_id: String,
+    pub characters: Vec<String>,
+}
+
+/// Cumulative token usage reported by `LlmClient::last_usage` across a run,
+/// persisted to `build_folder/llm_usage.json` after each chapter so a
+/// headless run leaves a record of its spend even if interrupted.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LlmUsageTracker {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl LlmUsageTracker {
+    fn add(&mut self, usage: TokenUsage) {
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Tracks the temporary directories created while extracting `.epub`
+/// chapters so they can be cleaned up once `WorkflowManager::run` returns,
+/// even on an early `?` exit. `utils::storage::NativeStorage` only writes
+/// inside the build folder it is rooted at, so this writes directly under
+/// the OS temp directory via `std::fs` instead.
+#[derive(Debug, Default)]
+struct EpubTempGuard(Vec<PathBuf>);
+
+impl EpubTempGuard {
+    /// Creates a fresh, uniquely-named directory under the OS temp
+    /// directory for the chapters extracted from `epub_path`, and records
+    /// it for cleanup.
+    fn new_chapter_dir(&mut self, epub_path: &Path) -> Result<PathBuf> {
+        let stem = epub_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "book".to_string());
+        let dir = std::env::temp_dir().join(format!(
+            "novel2audiobook_epub_{}_{:x}",
+            stem,
+            rand::random::<u64>()
+        ));
+        fs::create_dir_all(&dir)?;
+        self.0.push(dir.clone());
+        Ok(dir)
+    }
+}
+
+impl Drop for EpubTempGuard {
+    fn drop(&mut self) {
+        for dir in &self.0 {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Observes `WorkflowManager` progress without depending on `println!` or
+/// `indicatif` directly, so non-terminal front ends (e.g. a Leptos WASM UI)
+/// can drive their own reactive progress display. The native CLI path uses
+/// `NativeProgressObserver`; callers can swap in their own implementation
+/// via `WorkflowManager::with_observer`.
+pub trait WorkflowObserver: Send + Sync {
+    fn on_chapter_start(&self, name: &str, index: usize, total: usize);
+    fn on_segment_synthesized(&self, index: usize, total: usize);
+    fn on_chapter_complete(&self, name: &str);
+    fn on_error(&self, name: &str, error: &str);
+}
+
+/// Default `WorkflowObserver` for the native CLI: prints chapter
+/// start/complete/error lines and drives the `indicatif` segment-synthesis
+/// progress bar that used to live directly in `process_chapter`.
+pub struct NativeProgressObserver {
+    segment_bar: std::sync::Mutex<Option<ProgressBar>>,
+}
+
+impl NativeProgressObserver {
+    pub fn new() -> Self {
+        Self {
+            segment_bar: std::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl Default for NativeProgressObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkflowObserver for NativeProgressObserver {
+    fn on_chapter_start(&self, name: &str, index: usize, total: usize) {
+        println!("Processing chapter ({}/{}): {}", index + 1, total, name);
+    }
+
+    fn on_segment_synthesized(&self, index: usize, total: usize) {
+        let mut guard = self.segment_bar.lock().unwrap();
+        let bar = guard.get_or_insert_with(|| {
+            let pb = ProgressBar::new(total as u64);
+            if let Ok(style) = ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+            {
+                pb.set_style(style.progress_chars("#>-"));
+            }
+            pb
+        });
+        bar.set_position(index as u64);
+        if index >= total {
+            bar.finish_with_message("Synthesis complete");
+            *guard = None;
+        }
+    }
+
+    fn on_chapter_complete(&self, name: &str) {
+        println!("Chapter complete: {}", name);
+    }
+
+    fn on_error(&self, name: &str, error: &str) {
+        eprintln!("Chapter {} failed: {}", name, error);
+    }
+}
+
+/// Appends one JSON object per line to `build_folder/workflow.log` for every
+/// chapter start/end, LLM call completion (with token counts, when the
+/// provider reports them), segment synthesis success/failure, and file
+/// merge - a persistent, `grep`/`jq`-able trail for debugging synthesis
+/// failures that the transient `WorkflowObserver` terminal output doesn't
+/// leave behind. `utils::storage::NativeStorage` has no append operation and
+/// there's no `WebStorage` backend in this crate (see the top-of-crate
+/// comment in `lib.rs`), so writes go straight through `std::fs` in append
+/// mode on native builds and are a no-op under wasm32.
+pub struct FileLogger {
+    path: PathBuf,
+}
+
+impl FileLogger {
+    pub fn new(build_folder: &str) -> Self {
+        Self {
+            path: Path::new(build_folder).join("workflow.log"),
+        }
+    }
+
+    /// Appends `{"timestamp": <unix_secs>, "event": event, ...fields}` as one
+    /// JSON line. Write failures are logged to stderr and swallowed, since a
+    /// full disk shouldn't abort synthesis over a debugging aid.
+    fn log(&self, event: &str, mut fields: serde_json::Map<String, serde_json::Value>) {
+        fields.insert("event".to_string(), serde_json::Value::String(event.to_string()));
+        fields.insert(
+            "timestamp".to_string(),
+            serde_json::Value::Number(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0)
+                    .into(),
+            ),
+        );
+        let line = serde_json::to_string(&serde_json::Value::Object(fields)).unwrap_or_default();
+        self.append_line(&line);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn append_line(&self, line: &str) {
+        use std::io::Write;
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::OpenOptions::new().create(true).append(true).open(&self.path) {
+            Ok(mut file) => {
+                if let Err(e) = writeln!(file, "{}", line) {
+                    eprintln!("Failed to write to workflow.log: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to open workflow.log: {}", e),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn append_line(&self, _line: &str) {}
+
+    pub fn chapter_start(&self, filename: &str, index: usize, total: usize) {
+        self.log(
+            "chapter_start",
+            serde_json::Map::from_iter([
+                ("chapter".to_string(), serde_json::Value::String(filename.to_string())),
+                ("index".to_string(), serde_json::json!(index)),
+                ("total".to_string(), serde_json::json!(total)),
+            ]),
+        );
+    }
+
+    pub fn chapter_end(&self, filename: &str, success: bool, error: Option<&str>) {
+        self.log(
+            "chapter_end",
+            serde_json::Map::from_iter([
+                ("chapter".to_string(), serde_json::Value::String(filename.to_string())),
+                ("success".to_string(), serde_json::json!(success)),
+                ("error".to_string(), serde_json::json!(error)),
+            ]),
+        );
+    }
+
+    pub fn llm_call_end(&self, filename: &str, purpose: &str, prompt_tokens: u64, completion_tokens: u64) {
+        self.log(
+            "llm_call_end",
+            serde_json::Map::from_iter([
+                ("chapter".to_string(), serde_json::Value::String(filename.to_string())),
+                ("purpose".to_string(), serde_json::Value::String(purpose.to_string())),
+                ("prompt_tokens".to_string(), serde_json::json!(prompt_tokens)),
+                ("completion_tokens".to_string(), serde_json::json!(completion_tokens)),
+            ]),
+        );
+    }
+
+    pub fn segment_result(&self, filename: &str, index: usize, success: bool, error: Option<&str>) {
+        self.log(
+            "segment_result",
+            serde_json::Map::from_iter([
+                ("chapter".to_string(), serde_json::Value::String(filename.to_string())),
+                ("index".to_string(), serde_json::json!(index)),
+                ("success".to_string(), serde_json::json!(success)),
+                ("error".to_string(), serde_json::json!(error)),
+            ]),
+        );
+    }
+
+    pub fn merge_complete(&self, filename: &str, output_path: &str) {
+        self.log(
+            "merge_complete",
+            serde_json::Map::from_iter([
+                ("chapter".to_string(), serde_json::Value::String(filename.to_string())),
+                ("output_path".to_string(), serde_json::Value::String(output_path.to_string())),
+            ]),
+        );
+    }
+}
 
 pub struct WorkflowManager {
-    config: Config,
-    llm: Box<dyn LlmClient>,
+    config: Arc<Config>,
+    llm: Arc<dyn LlmClient>,
     state: WorkflowState,
-    character_map: CharacterMap,
-    tts: Box<dyn TtsClient>,
-    script_generator: Box<dyn ScriptGenerator>,
+    character_map: Arc<RwLock<CharacterMap>>,
+    tts: Arc<dyn TtsClient>,
+    script_generator: Arc<dyn ScriptGenerator>,
+    observer: Arc<dyn WorkflowObserver>,
+    cancellation: CancellationToken,
+    usage_tracker: Arc<std::sync::Mutex<LlmUsageTracker>>,
+    voice_stats: Arc<std::sync::Mutex<VoiceStatsReport>>,
+    file_logger: Arc<FileLogger>,
+    storage: NativeStorage,
 }
 
 impl WorkflowManager {
-    pub fn new(config: Config, llm: Box<dyn LlmClient>, tts: Box<dyn TtsClient>) -> Result<Self> {
-        let state = Self::load_state(&config.build_folder)?;
-        let mut character_map = Self::load_character_map(&config.build_folder)?;
+    pub async fn new(config: Config, llm: Box<dyn LlmClient>, tts: Box<dyn TtsClient>) -> Result<Self> {
+        let llm: Box<dyn LlmClient> = if config.llm.use_llm_cache {
+            Box::new(crate::services::llm::CachingLlmClient::with_cache(
+                llm,
+                &config.build_folder,
+            ))
+        } else {
+            llm
+        };
+
+        Self::cleanup_temp_files(Path::new(&config.build_folder))?;
+        Self::cleanup_temp_files(Path::new(&config.output_folder))?;
+
+        let storage = NativeStorage::new(Path::new(&config.build_folder));
+        let state = Self::load_state(&storage)?;
+        let mut character_map = Self::load_character_map(&storage)?;
 
         let enable_mobs = tts.is_mob_enabled();
 
@@ -98,554 +444,6164 @@ impl WorkflowManager {
             }
 
             if map_updated {
-                let path = Path::new(&config.build_folder).join("character_map.json");
-                // Ensure build dir exists (it might not if it's the first run)
-                fs::create_dir_all(&config.build_folder)?;
                 let content = serde_json::to_string_pretty(&character_map)?;
-                fs::write(path, content)?;
+                storage.write(Path::new("character_map.json"), &content)?;
             }
         }
 
+        tts.check_voice_availability()
+            .await
+            .context("No TTS voices available; cannot start workflow")?;
+
         let script_generator = tts.get_script_generator();
+        let file_logger = Arc::new(FileLogger::new(&config.build_folder));
 
         Ok(Self {
-            config,
-            llm,
+            config: Arc::new(config),
+            llm: Arc::from(llm),
             state,
-            character_map,
-            tts,
-            script_generator,
+            character_map: Arc::new(RwLock::new(character_map)),
+            tts: Arc::from(tts),
+            script_generator: Arc::from(script_generator),
+            observer: Arc::new(NativeProgressObserver::new()),
+            cancellation: CancellationToken::new(),
+            usage_tracker: Arc::new(std::sync::Mutex::new(LlmUsageTracker::default())),
+            voice_stats: Arc::new(std::sync::Mutex::new(VoiceStatsReport::default())),
+            file_logger,
+            storage,
         })
     }
 
-    fn load_state(build_dir: &str) -> Result<WorkflowState> {
-        let path = Path::new(build_dir).join("state.json");
-        if path.exists() {
-            let content = fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&content)?)
+    /// Replaces the default `NativeProgressObserver` with a custom
+    /// `WorkflowObserver`, e.g. one that drives a UI's reactive state
+    /// instead of printing to the terminal.
+    pub fn with_observer(mut self, observer: Arc<dyn WorkflowObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Wires in a `CancellationToken` that `run`/`process_chapter` check
+    /// between segments, so a caller can interrupt a long-running
+    /// synthesis without killing the process. Cancelling mid-chapter
+    /// leaves already-synthesized segment chunks on disk, so a later run
+    /// resumes from where it stopped rather than re-synthesizing them.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Forces `filename` to be reprocessed on the next `run`, e.g. after
+    /// updating its voice assignments. Removes it from
+    /// `state.completed_chapters`/`state.chapter_hashes` and deletes its
+    /// cached `segments.json` and synthesized `chunk_*.mp3` files so
+    /// `process_chapter` regenerates them from scratch. Leaves the global
+    /// character map and `state.chapter_mob_voices` untouched, so mob voice
+    /// assignments from the original run are reused rather than re-rolled.
+    pub async fn reset_chapter(&mut self, filename: &str) -> Result<()> {
+        self.state.completed_chapters.retain(|c| c != filename);
+        self.state.chapter_hashes.remove(filename);
+        self.save_state()?;
+
+        let chapter_build_dir = Path::new(&self.config.build_folder).join(filename.replace(".", "_"));
+        let segments_path = chapter_build_dir.join("segments.json");
+        if segments_path.exists() {
+            fs::remove_file(&segments_path)?;
+        }
+
+        if chapter_build_dir.exists() {
+            for entry in fs::read_dir(&chapter_build_dir)? {
+                let path = entry?.path();
+                if path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("chunk_") && n.ends_with(".mp3"))
+                {
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Packages `config.yml` (read from the current directory, same as
+    /// `Config::load`) and the build-folder metadata needed to resume a
+    /// project elsewhere - character map, workflow state, and every
+    /// chapter's `segments.json` - into a ZIP archive at `output_path`.
+    /// Deliberately excludes synthesized audio, which dominates
+    /// `build_folder`'s size and can be regenerated from the exported
+    /// metadata. See `import_project` for the inverse operation.
+    pub async fn export_project(&self, output_path: &str) -> Result<()> {
+        write_project_archive(Path::new("."), &self.config.build_folder, Path::new(output_path))
+    }
+
+    /// Sums `TtsClient::estimate_cost` across every `.txt` chapter in
+    /// `input_folder` that hasn't already completed, so a user can check the
+    /// likely bill before running synthesis. Each chapter's raw text is
+    /// treated as a single pseudo-segment, since generating the real
+    /// per-speaker script would itself require LLM calls; this is a rough
+    /// estimate, not what actually gets synthesized.
+    pub async fn estimate_total_cost(&self) -> Result<f64> {
+        let input_path = Path::new(&self.config.input_folder);
+        let mut dir = tokio_fs::read_dir(input_path).await?;
+        let mut total = 0.0;
+
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if !path.extension().is_some_and(|ext| ext == "txt") {
+                continue;
+            }
+            let filename = path.file_name().unwrap().to_string_lossy().to_string();
+            if self.state.completed_chapters.contains(&filename) {
+                continue;
+            }
+
+            let text = fs::read_to_string(&path)?;
+            let segment = AudioSegment {
+                text,
+                speaker: None,
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            };
+            total += self.tts.estimate_cost(&[segment]).await?;
+        }
+
+        Ok(total)
+    }
+
+    /// POSTs a `WebhookPayload` to `config.workflow.webhook_url` if one is
+    /// configured. Failures are logged and swallowed rather than propagated,
+    /// since a CI pipeline's webhook receiver being briefly unreachable
+    /// shouldn't abort an otherwise-successful book.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn notify_webhook(
+        config: &Config,
+        event: &str,
+        chapter: Option<String>,
+        success: bool,
+        error_message: Option<String>,
+    ) {
+        let Some(url) = &config.workflow.webhook_url else {
+            return;
+        };
+        let payload = crate::services::notifications::WebhookPayload::new(
+            event,
+            chapter,
+            success,
+            error_message,
+        );
+        if let Err(e) = crate::services::notifications::send_webhook(url, &payload).await {
+            eprintln!("Failed to send webhook notification: {}", e);
+        }
+    }
+
+    /// Deletes leftover `*.tmp` files under `dir`, recursing into
+    /// subdirectories since chunk temp files live under per-chapter
+    /// subdirectories of `build_folder`. These are only ever left behind if
+    /// a previous run was killed between the temp-file write and rename in
+    /// `process_chapter`'s chunk writes, so it's always safe to remove them
+    /// on startup.
+    fn cleanup_temp_files(dir: &Path) -> Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                Self::cleanup_temp_files(&path)?;
+            } else if path.extension().is_some_and(|ext| ext == "tmp") {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_state(storage: &NativeStorage) -> Result<WorkflowState> {
+        let path = Path::new("state.json");
+        if storage.exists(path) {
+            let content = storage.read(path)?;
+            crate::core::state::migrations::migrate_workflow_state(serde_json::from_str(&content)?)
         } else {
-            Ok(WorkflowState::default())
+            Ok(WorkflowState {
+                schema_version: crate::core::state::CURRENT_WORKFLOW_STATE_SCHEMA_VERSION,
+                ..Default::default()
+            })
         }
     }
 
     fn save_state(&self) -> Result<()> {
-        let path = Path::new(&self.config.build_folder).join("state.json");
         let content = serde_json::to_string_pretty(&self.state)?;
-        fs::write(path, content)?;
+        self.storage.write(Path::new("state.json"), &content)?;
         Ok(())
     }
 
-    fn load_character_map(build_dir: &str) -> Result<CharacterMap> {
-        let path = Path::new(build_dir).join("character_map.json");
-        if path.exists() {
-            let content = fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&content)?)
+    fn load_character_map(storage: &NativeStorage) -> Result<CharacterMap> {
+        let path = Path::new("character_map.json");
+        if storage.exists(path) {
+            let content = storage.read(path)?;
+            crate::core::state::migrations::migrate_character_map(serde_json::from_str(&content)?)
         } else {
             Ok(CharacterMap {
+                schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
                 characters: HashMap::new(),
             })
         }
     }
 
-    fn save_character_map(&self) -> Result<()> {
-        let path = Path::new(&self.config.build_folder).join("character_map.json");
-        let content = serde_json::to_string_pretty(&self.character_map)?;
-        fs::write(path, content)?;
-        Ok(())
+    /// Finds voices shared by more than one character in the global
+    /// character map, which usually means the LLM assigned the same voice
+    /// to two characters across different chapters.
+    pub async fn check_global_voice_conflicts(&self) -> Vec<VoiceConflict> {
+        find_voice_conflicts(&self.character_map.read().await)
+    }
+
+    /// Loads `character_map.json` from `build_dir` the same way
+    /// `load_character_map` does, for external callers like the
+    /// `assign-voices` CLI subcommand that need the same schema-migration
+    /// handling without a full `WorkflowManager` instance.
+    pub fn load_character_map_from_build_dir(build_dir: &str) -> Result<CharacterMap> {
+        Self::load_character_map(&NativeStorage::new(Path::new(build_dir)))
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        // List input files
+        if self.config.workflow.combine_only {
+            return self.combine_chapters().await;
+        }
+
+        // List input files. Both plain `.txt` chapters and `.epub` books are
+        // recognized. EPUB chapters are extracted in memory (see
+        // `utils::epub::extract_chapters`) and written out as temporary
+        // `.txt` files under the OS temp directory so they can flow through
+        // the same pipeline as everything else; no permanent `.txt` files
+        // are ever written into the input folder. The temp files are
+        // removed again once `run` returns, via `epub_guard`'s `Drop` impl.
         let input_path = Path::new(&self.config.input_folder);
         let mut entries = Vec::new();
+        let mut epub_paths = Vec::new();
         let mut dir = tokio_fs::read_dir(input_path).await?;
         while let Some(entry) = dir.next_entry().await? {
             let path = entry.path();
             if path.extension().is_some_and(|ext| ext == "txt") {
                 entries.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "epub") {
+                epub_paths.push(path);
             }
         }
 
-        entries.sort();
-        let total_chapters = entries.len();
+        let mut names: Vec<String> = entries
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        sort_chapters(&mut names, &self.config.workflow.chapter_sort);
+        let by_name: HashMap<String, PathBuf> = entries
+            .drain(..)
+            .map(|p| (p.file_name().unwrap().to_string_lossy().to_string(), p))
+            .collect();
+        entries = names
+            .into_iter()
+            .map(|name| by_name[&name].clone())
+            .collect();
+        epub_paths.sort();
+
+        let mut epub_guard = EpubTempGuard::default();
+        for epub_path in &epub_paths {
+            let bytes = tokio_fs::read(epub_path).await?;
+            let chapters = crate::utils::epub::extract_chapters(&bytes)
+                .with_context(|| format!("Failed to extract chapters from {:?}", epub_path))?;
+            let chapter_dir = epub_guard.new_chapter_dir(epub_path)?;
+            for (filename, text) in chapters {
+                let chapter_path = chapter_dir.join(filename);
+                fs::write(&chapter_path, text)?;
+                entries.push(chapter_path);
+            }
+        }
 
-        for (i, path) in entries.iter().enumerate() {
-            let filename = path.file_name().unwrap().to_string_lossy().to_string();
+        let total_chapters = entries.len();
 
-            if self.state.completed_chapters.contains(&filename) {
-                println!("Skipping completed chapter: {}", filename);
-                continue;
-            }
+        let parallel_chapters = self.config.workflow.parallel_chapters.max(1);
 
-            println!("Processing chapter: {}", filename);
-            self.process_chapter(path, &filename).await?;
+        if parallel_chapters <= 1 {
+            for (i, path) in entries.iter().enumerate() {
+                let filename = path.file_name().unwrap().to_string_lossy().to_string();
 
-            self.state.completed_chapters.push(filename);
-            self.save_state()?;
+                if self.state.completed_chapters.contains(&filename) {
+                    println!("Skipping completed chapter: {}", filename);
+                    continue;
+                }
 
-            if !self.config.unattended && i < total_chapters - 1 {
-                let ans = inquire::Confirm::new("Continue to next chapter?")
-                    .with_default(true)
-                    .prompt();
+                if self.cancellation.is_cancelled() {
+                    return Err(anyhow::anyhow!("Cancelled"));
+                }
 
-                match ans {
-                    Ok(true) => {}
-                    Ok(false) => {
-                        println!("Stopping as requested.");
-                        break;
-                    }
-                    Err(_) => {
-                        println!("Error reading input, stopping.");
-                        break;
+                self.observer.on_chapter_start(&filename, i, total_chapters);
+                if let Err(e) = self.process_chapter(path, &filename, i + 1, total_chapters).await {
+                    self.observer.on_error(&filename, &e.to_string());
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Self::notify_webhook(
+                        &self.config,
+                        "chapter_failed",
+                        Some(filename.clone()),
+                        false,
+                        Some(e.to_string()),
+                    )
+                    .await;
+                    return Err(e);
+                }
+                self.observer.on_chapter_complete(&filename);
+                #[cfg(not(target_arch = "wasm32"))]
+                Self::notify_webhook(
+                    &self.config,
+                    "chapter_complete",
+                    Some(filename.clone()),
+                    true,
+                    None,
+                )
+                .await;
+
+                if let Ok(text) = fs::read_to_string(path) {
+                    self.state
+                        .chapter_hashes
+                        .insert(filename.clone(), format!("{:x}", Sha256::digest(text.as_bytes())));
+                }
+                self.state.completed_chapters.push(filename);
+                self.save_state()?;
+
+                if !self.config.unattended && i < total_chapters - 1 {
+                    let ans = inquire::Confirm::new("Continue to next chapter?")
+                        .with_default(true)
+                        .prompt();
+
+                    match ans {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            println!("Stopping as requested.");
+                            break;
+                        }
+                        Err(_) => {
+                            println!("Error reading input, stopping.");
+                            break;
+                        }
                     }
                 }
             }
+        } else {
+            self.run_parallel(&entries, parallel_chapters).await?;
         }
 
         println!("All chapters processed!");
-        Ok(())
-    }
+        self.print_summary()?;
+        self.write_voice_stats()?;
 
-    async fn process_chapter(&mut self, path: &Path, filename: &str) -> Result<()> {
-        let text = fs::read_to_string(path)?;
+        if self.config.output.format == crate::core::config::OutputFormat::M4bSingleFile {
+            self.combine_to_m4b().await?;
+        } else if self.config.output.combine {
+            self.combine_chapters().await?;
+        }
 
-        let chapter_build_dir =
-            Path::new(&self.config.build_folder).join(filename.replace(".", "_"));
-        fs::create_dir_all(&chapter_build_dir)?;
-        let segments_path = chapter_build_dir.join("segments.json");
+        #[cfg(not(target_arch = "wasm32"))]
+        Self::notify_webhook(&self.config, "workflow_complete", None, true, None).await;
 
-        // Prepare voices for Analysis & Script Generation
-        let mut voices = self.tts.list_voices().await?;
-        voices.retain(|v| {
-            v.locale.starts_with(&self.config.audio.language)
-                && !self.config.audio.exclude_locales.contains(&v.locale)
-        });
+        Ok(())
+    }
 
-        let mut segments: Vec<AudioSegment> = if segments_path.exists() {
-            println!("Loading cached segments from {:?}", segments_path);
-            let content = fs::read_to_string(&segments_path)?;
-            serde_json::from_str(&content)?
+    /// File extension `process_chapter` wrote each completed chapter with,
+    /// given this run's TTS provider and `AudioConfig::output_format`.
+    /// Providers whose merged output is already MP3 (see
+    /// `TtsClient::is_mp3_output`) always stay MP3 regardless of
+    /// `output_format`, since there's no decoder to transcode out of it.
+    fn output_extension(&self) -> &'static str {
+        if self.tts.is_mp3_output() {
+            AudioOutputFormat::Mp3.extension()
         } else {
-            // 1. Analyze Characters
-            println!("Analyzing characters...");
-
-            let existing_chars_str = self
-                .character_map
-                .characters
-                .keys()
-                .map(|k| k.as_str())
-                .collect::<Vec<_>>()
-                .join(", ");
-
-            let voice_list_str = self.tts.format_voice_list_for_analysis(&voices);
-            let narrator_voice_id = self.tts.get_narrator_voice_id();
-            let enable_mobs = self.tts.is_mob_enabled();
-
-            let mob_instruction = if enable_mobs {
-                "- 系統已內建路人、路人(男)、路人(女)、章節路人(男)、章節路人(女)等角色，請勿重複創建。\n\
-                 - 章節內話多但後續不出現的角色，請使用「章節路人(男)」或「章節路人(女)」。\n\
-                 - 不重要的丟棄式角色請直接使用路人、路人(男)或路人(女)。"
-            } else {
-                "- 對於不重要的路人或龍套角色，無須分配，直接略過即可。"
-            };
-
-            let analysis_prompt = format!(
-                "請分析以下文本。識別所有說話的角色。\
-                \n\n上下文資訊 (Context):\
-                \n1. 目前已存在的角色 (Existing Characters): [{}]\
-                \n2. 旁白聲音 ID (Narrator Voice ID): \"{}\"\
-                \n3. 可用聲音列表 (Available Voices):\n[{}]\
-                \n\n指令 (Instructions):\
-                \n- 識別文本中的說話角色，確定性別（Male/Female）及是否為主要角色。\
-                \n- 若角色為「主角」(Protagonist)，請將 \"is_protagonist\" 欄位設為 true。\
-                \n- 若角色已存在於「目前已存在的角色」中，請使用相同的名稱。\
-                \n- 若文本為第一人稱（如「我」），請識別主角，將其 voice_id 設定為旁白聲音 ID，並設定 \"is_protagonist\": true。\
-                \n- 主要角色，尤其主角，請避免重複使用該聲音。旁白亦同。\
-                \n- 對於新角色，你可以從「可用聲音列表」中選擇合適的 voice_id (選填)，否則留空。\
-                \n{}\n\
-                \n- 創建的JSON對象由於是key必須使用繁體中文。使用簡體將導致程式出錯。\
-                \n\n請僅返回一個 JSON 對象(不可翻譯json key)：\
-                {{ \"characters\": [ {{ \"name\": \"...\", \"gender\": \"Male/Female\", \"is_protagonist\": true/false, \"important\": true/false, \"description\": \"...\", \"voice_id\": \"...\" }} ] }} \
-                \n\n文本：\n{}", 
-                existing_chars_str,
-                narrator_voice_id,
-                voice_list_str,
-                mob_instruction,
-                text.chars().take(10000).collect::<String>(),
-            );
-
-            let mut analysis_json = self
-                .llm
-                .chat("你是一位文學助手。請僅返回有效的 JSON。", &analysis_prompt)
-                .await?;
+            self.config.audio.output_format.extension()
+        }
+    }
 
-            analysis_json = analysis_json.replace("\n", ""); // Clean newlines
+    /// Concatenates every completed chapter's MP3 into a single
+    /// `output/{book_title}.mp3` (or `output/combined.mp3` if `BookMetadata`
+    /// has no title) via `tts.merge_audio_files`, then embeds ID3 tags with
+    /// the combined chapter count. Chapters that never finished processing
+    /// (and so aren't in `completed_chapters`), or whose MP3 went missing,
+    /// are skipped rather than failing the whole combination.
+    pub async fn combine_chapters(&self) -> Result<()> {
+        let extension = self.output_extension();
+        if extension != "mp3" {
+            return Err(anyhow::anyhow!(
+                "Combining chapters into a single file isn't supported for output_format \"{}\" yet; only mp3 is",
+                extension
+            ));
+        }
 
-            // Parse JSON
-            #[derive(Deserialize)]
-            struct AnalysisResult {
-                characters: Vec<AnalysisChar>,
-            }
-            #[derive(Deserialize)]
-            struct AnalysisChar {
-                name: String,
-                gender: String,
-                #[serde(default)]
-                important: bool, // Renamed from _important to allow usage
-                #[serde(default)]
-                description: Option<String>,
-                #[serde(default)]
-                voice_id: Option<String>,
-                #[serde(default)]
-                is_protagonist: bool,
-            }
+        let output_dir = Path::new(&self.config.output_folder);
+        let mp3_paths: Vec<PathBuf> = self
+            .state
+            .completed_chapters
+            .iter()
+            .map(|filename| output_dir.join(Path::new(filename).with_extension(extension)))
+            .filter(|path| path.exists())
+            .collect();
+
+        if mp3_paths.is_empty() {
+            return Err(anyhow::anyhow!("No completed chapters to combine"));
+        }
 
-            // Clean markdown code blocks if present
-            let clean_json = strip_code_blocks(&analysis_json);
-            let analysis: AnalysisResult = serde_json::from_str(&clean_json)
-                .context(format!("Failed to parse analysis JSON: {}", clean_json))?;
+        let combined_filename = if self.config.book_metadata.title.is_empty() {
+            "combined.mp3".to_string()
+        } else {
+            format!("{}.mp3", self.config.book_metadata.title)
+        };
+        let combined_path = output_dir.join(combined_filename);
+
+        let merge_inputs = insert_chapter_gaps(output_dir, &mp3_paths, &self.config.output)?;
+        self.tts.merge_audio_files(&merge_inputs, &combined_path)?;
+
+        let mut mp3 = fs::read(&combined_path)?;
+        let cover_jpeg = self
+            .config
+            .book_metadata
+            .cover_image_path
+            .as_ref()
+            .map(fs::read)
+            .transpose()?;
+        crate::utils::audio::embed_id3_tags(
+            &mut mp3,
+            &self.config.book_metadata.title,
+            &self.config.book_metadata.author,
+            &self.config.book_metadata.title,
+            mp3_paths.len() as u32,
+            cover_jpeg.as_deref(),
+        )?;
+        fs::write(&combined_path, mp3)?;
+
+        println!(
+            "Combined {} chapters into {:?}",
+            mp3_paths.len(),
+            combined_path
+        );
+        Ok(())
+    }
 
-            // Update Character Map
-            let mut chapter_local_chars = HashMap::new();
-            let mut updated_global_map = false;
+    /// Concatenates every completed chapter's MP3 into a single M4B
+    /// audiobook with chapter markers, via `ffmpeg`. Chapter start/end
+    /// timestamps are derived from each chapter's exact MP3 duration (see
+    /// `utils::audio::mp3_duration_secs`), not an estimate, so markers line
+    /// up with the actual audio rather than drifting over a long book.
+    pub async fn combine_to_m4b(&self) -> Result<()> {
+        let extension = self.output_extension();
+        if extension != "mp3" {
+            return Err(anyhow::anyhow!(
+                "M4B combination isn't supported for output_format \"{}\" yet; only mp3 is",
+                extension
+            ));
+        }
 
-            for char in analysis.characters {
-                // Logic:
-                // If mobs enabled: all processed as usual (persisted).
-                // If mobs disabled:
-                //    - Named/Important/Protagonist -> Global Map
-                //    - Unimportant/Mob-like -> Local Map (do not save to global json)
-
-                let should_persist = if enable_mobs {
-                    true
-                } else {
-                    char.important || char.is_protagonist || char.voice_id.is_some()
-                };
+        let output_dir = Path::new(&self.config.output_folder);
+        let mp3_paths: Vec<PathBuf> = self
+            .state
+            .completed_chapters
+            .iter()
+            .map(|filename| output_dir.join(Path::new(filename).with_extension(extension)))
+            .collect();
+
+        if mp3_paths.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No completed chapters to combine into an M4B"
+            ));
+        }
 
-                // Override: placeholders are never "persisted" in the sense of adding new keys, but updating existing keys.
-                // But if user disables mobs, we don't want to create "路人A" in global map.
-
-                if should_persist {
-                    let entry = self.character_map.characters.entry(char.name.clone());
-                    match entry {
-                        std::collections::hash_map::Entry::Vacant(e) => {
-                            e.insert(CharacterInfo {
-                                gender: char.gender,
-                                voice_id: char.voice_id,
-                                description: char.description,
-                                is_protagonist: char.is_protagonist,
-                            });
-                            updated_global_map = true;
-                        }
-                        std::collections::hash_map::Entry::Occupied(mut e) => {
-                            if e.get().voice_id.is_none() && char.voice_id.is_some() {
-                                e.get_mut().voice_id = char.voice_id;
-                                updated_global_map = true;
-                            }
-                        }
-                    }
-                } else {
-                    // Local map
-                    chapter_local_chars.insert(
-                        char.name.clone(),
-                        CharacterInfo {
-                            gender: char.gender,
-                            voice_id: char.voice_id,
-                            description: char.description,
-                            is_protagonist: char.is_protagonist,
-                        },
-                    );
-                }
-            }
-            if updated_global_map {
-                self.save_character_map()?;
-            }
+        let mut chapters = Vec::new();
+        let mut cursor_ms: u64 = 0;
+        for (filename, path) in self.state.completed_chapters.iter().zip(&mp3_paths) {
+            let duration_secs = crate::utils::audio::mp3_duration_secs(path)
+                .with_context(|| format!("Failed to read MP3 duration of {:?}", path))?;
+            let duration_ms = (duration_secs * 1000.0).round() as u64;
+            let title = chapter_title(filename);
+            chapters.push((title, cursor_ms, cursor_ms + duration_ms));
+            cursor_ms += duration_ms;
+        }
 
-            // Create combined map for this chapter
-            let mut combined_map = self.character_map.clone();
-            for (k, v) in chapter_local_chars {
-                combined_map.characters.insert(k, v);
-            }
+        let concat_list_path = output_dir.join("m4b_concat_list.txt");
+        let concat_list = mp3_paths
+            .iter()
+            .map(|p| format!("file '{}'", p.to_string_lossy().replace('\'', "'\\''")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        tokio_fs::write(&concat_list_path, concat_list).await?;
+
+        let metadata_path = output_dir.join("m4b_chapters.txt");
+        let mut metadata = String::from(";FFMETADATA1\n");
+        if !self.config.book_metadata.title.is_empty() {
+            metadata.push_str(&format!("title={}\n", self.config.book_metadata.title));
+        }
+        if !self.config.book_metadata.author.is_empty() {
+            metadata.push_str(&format!("artist={}\n", self.config.book_metadata.author));
+        }
+        for (title, start_ms, end_ms) in &chapters {
+            metadata.push_str("[CHAPTER]\n");
+            metadata.push_str("TIMEBASE=1/1000\n");
+            metadata.push_str(&format!("START={}\n", start_ms));
+            metadata.push_str(&format!("END={}\n", end_ms));
+            metadata.push_str(&format!("title={}\n", title));
+        }
+        tokio_fs::write(&metadata_path, metadata).await?;
+
+        let m4b_path = output_dir.join("audiobook.m4b");
+        let status = tokio::process::Command::new("ffmpeg")
+            .args(["-y", "-f", "concat", "-safe", "0", "-i"])
+            .arg(&concat_list_path)
+            .arg("-i")
+            .arg(&metadata_path)
+            .args(["-map_metadata", "1", "-map", "0:a", "-c", "copy"])
+            .arg(&m4b_path)
+            .status()
+            .await
+            .context("Failed to run ffmpeg; is it installed and on PATH?")?;
+
+        let _ = fs::remove_file(&concat_list_path);
+        let _ = fs::remove_file(&metadata_path);
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("ffmpeg exited with status {}", status));
+        }
 
-            // 2. Script Generation
-            println!("Generating Script...");
+        println!("Combined {} chapters into {:?}", chapters.len(), m4b_path);
+        Ok(())
+    }
 
-            // Gather voice styles
-            let mut voice_styles = HashMap::new();
-            for info in combined_map.characters.values() {
-                if let Some(vid) = &info.voice_id {
-                    if let Ok(styles) = self.tts.get_voice_styles(vid).await {
-                        voice_styles.insert(vid.clone(), styles);
-                    }
+    /// Processes up to `parallel_chapters` chapters concurrently. Unlike the
+    /// sequential path, this does not prompt between chapters since there's
+    /// no single "next" chapter to confirm.
+    async fn run_parallel(&mut self, entries: &[PathBuf], parallel_chapters: usize) -> Result<()> {
+        let pending: Vec<PathBuf> = entries
+            .iter()
+            .filter(|path| {
+                let filename = path.file_name().unwrap().to_string_lossy().to_string();
+                let is_completed = self.state.completed_chapters.contains(&filename);
+                if is_completed {
+                    println!("Skipping completed chapter: {}", filename);
                 }
-            }
-            // For GPT-SoVITS, populate styles for ALL available voices (candidates) so ScriptGenerator can use them
-            if self.config.audio.provider == "gpt_sovits" {
-                for v in &voices {
-                    if !voice_styles.contains_key(&v.short_name) {
-                        if let Ok(styles) = self.tts.get_voice_styles(&v.short_name).await {
-                            voice_styles.insert(v.short_name.clone(), styles);
-                        }
-                    }
+                !is_completed
+            })
+            .cloned()
+            .collect();
+
+        let config = self.config.clone();
+        let llm = self.llm.clone();
+        let tts = self.tts.clone();
+        let script_generator = self.script_generator.clone();
+        let character_map = self.character_map.clone();
+        let observer = self.observer.clone();
+        let cancellation = self.cancellation.clone();
+        let total = pending.len();
+        let chapter_mob_voices = self.state.chapter_mob_voices.clone();
+        let usage_tracker = self.usage_tracker.clone();
+        let voice_stats = self.voice_stats.clone();
+        let file_logger = self.file_logger.clone();
+
+        let mut stream = futures_util::stream::iter(pending.into_iter().enumerate().map(|(i, path)| {
+            let config = config.clone();
+            let llm = llm.clone();
+            let tts = tts.clone();
+            let script_generator = script_generator.clone();
+            let character_map = character_map.clone();
+            let observer = observer.clone();
+            let cancellation = cancellation.clone();
+            let chapter_mob_voices = chapter_mob_voices.clone();
+            let usage_tracker = usage_tracker.clone();
+            let voice_stats = voice_stats.clone();
+            let file_logger = file_logger.clone();
+            async move {
+                let filename = path.file_name().unwrap().to_string_lossy().to_string();
+                observer.on_chapter_start(&filename, i, total);
+                let mob_voices = chapter_mob_voices.get(&filename).cloned().unwrap_or_default();
+                #[cfg(not(target_arch = "wasm32"))]
+                let webhook_config = config.clone();
+                let result = process_chapter(
+                    config,
+                    llm,
+                    tts,
+                    script_generator,
+                    character_map,
+                    path.clone(),
+                    filename.clone(),
+                    observer.clone(),
+                    cancellation,
+                    mob_voices,
+                    i + 1,
+                    total,
+                    usage_tracker,
+                    voice_stats,
+                    file_logger,
+                )
+                .await;
+                match &result {
+                    Ok(_) => observer.on_chapter_complete(&filename),
+                    Err(e) => observer.on_error(&filename, &e.to_string()),
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                WorkflowManager::notify_webhook(
+                    &webhook_config,
+                    if result.is_ok() { "chapter_complete" } else { "chapter_failed" },
+                    Some(filename.clone()),
+                    result.is_ok(),
+                    result.as_ref().err().map(|e| e.to_string()),
+                )
+                .await;
+                (filename, path, result)
             }
+        }))
+        .buffer_unordered(parallel_chapters);
+
+        while let Some((filename, path, result)) = stream.next().await {
+            let (resolved_mob_voices, chapter_usage) = result?;
+            if let Ok(text) = fs::read_to_string(&path) {
+                self.state
+                    .chapter_hashes
+                    .insert(filename.clone(), format!("{:x}", Sha256::digest(text.as_bytes())));
+            }
+            self.state
+                .chapter_mob_voices
+                .insert(filename.clone(), resolved_mob_voices);
+            self.state.completed_chapters.push(filename);
+            self.save_state()?;
+            self.log_and_persist_llm_usage(&filename, &chapter_usage)?;
+        }
 
-            let prompt = self.script_generator.generate_prompt(
-                &text,
-                &combined_map,
-                &voice_styles,
-                &voices,
-            )?;
-            let system_instruction = self.script_generator.get_system_prompt();
-
-            let script_json = self.llm.chat(&system_instruction, &prompt).await?;
-            let segments = self.script_generator.parse_response(&script_json)?;
+        Ok(())
+    }
 
-            // Save Script to cache
-            fs::write(&segments_path, serde_json::to_string_pretty(&segments)?)?;
+    /// Prints a summary table of all processed chapters and writes the same
+    /// data to `{output_folder}/summary.json` for programmatic consumption.
+    pub fn print_summary(&self) -> Result<()> {
+        let output_dir = Path::new(&self.config.output_folder);
+        let mut chapters = Vec::new();
+        let mut total_size_bytes: u64 = 0;
+        let mut total_segments: usize = 0;
+
+        let extension = self.output_extension();
+        for filename in &self.state.completed_chapters {
+            let output_name = Path::new(filename).with_extension(extension);
+            let output_path = output_dir.join(&output_name);
+            let size_bytes = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+
+            let chapter_build_dir =
+                Path::new(&self.config.build_folder).join(filename.replace(".", "_"));
+            let segments_path = chapter_build_dir.join("segments.json");
+            let segment_count = fs::read_to_string(&segments_path)
+                .ok()
+                .and_then(|c| serde_json::from_str::<Vec<AudioSegment>>(&c).ok())
+                .map(|s| s.len())
+                .unwrap_or(0);
+
+            total_size_bytes += size_bytes;
+            total_segments += segment_count;
+
+            chapters.push(ChapterSummary {
+                filename: filename.clone(),
+                segments: segment_count,
+                size_bytes,
+            });
+        }
 
-            segments
+        let summary = WorkflowSummary {
+            total_chapters: chapters.len(),
+            total_segments,
+            total_size_bytes,
+            chapters,
         };
 
-        // Re-construct combined map in case we loaded from cache (segments exist)
-        // But wait, if segments exist, we didn't populate local map from LLM.
-        // We might be missing local characters info if we resume!
-        // This is a known issue with this architecture if local state isn't persisted.
-        // However, if segments exist, we iterate segments.
-        // If segments have `voice_id` (new feature), we are good.
-        // If segments rely on speaker name map... we might fail if local char isn't in map.
-        // For now, let's assume if cache exists, we rely on segment.voice_id or global map.
-        // If local chars were used and not saved... reconstruction is hard without saving local map.
-        // But user said "disposable mobs". Maybe it's fine.
-        // Or we should save `chapter_character_map.json` in build dir?
-        // Let's rely on `voice_id` being in segment for those mobs.
-
-        // 3. Synthesize
-        println!("Synthesizing audio ({} segments)...", segments.len());
-
-        // Build Excluded Voices (Narrator + Protagonists)
-        let mut excluded_voices = Vec::new();
-        let narrator_voice_id = self.tts.get_narrator_voice_id();
-
-        excluded_voices.push(narrator_voice_id);
-
-        for char_info in self.character_map.characters.values() {
-            if char_info.is_protagonist {
-                if let Some(vid) = &char_info.voice_id {
-                    if !excluded_voices.contains(vid) {
-                        excluded_voices.push(vid.clone());
-                    }
-                }
-            }
+        println!("\n=== Summary ===");
+        println!(
+            "{:<30} {:>10} {:>14}",
+            "Chapter", "Segments", "Size (bytes)"
+        );
+        for c in &summary.chapters {
+            println!("{:<30} {:>10} {:>14}", c.filename, c.segments, c.size_bytes);
         }
+        println!(
+            "Total: {} chapters, {} segments, {} bytes",
+            summary.total_chapters, summary.total_segments, summary.total_size_bytes
+        );
 
-        // We need a map for resolving speakers.
-        // Since we didn't save local map, if we just loaded segments, we only have global map.
-        // If segment has voice_id, we use it.
-        // If segment uses a local mob name but no voice_id... we have a problem if we didn't regenerate.
-        // But `GptSovitsScriptGenerator` is instructed to output `voice_id`.
+        let summary_path = output_dir.join("summary.json");
+        fs::write(summary_path, serde_json::to_string_pretty(&summary)?)?;
 
-        // Let's create a working map, defaulting to global.
-        // Note: Chapter Mobs (placeholders) are in global map if enable_mobs=true.
-        let mut working_map = self.character_map.clone();
+        Ok(())
+    }
 
-        let enable_mobs = self.tts.is_mob_enabled();
+    /// Merges this run's accumulated voice usage stats with whatever is
+    /// already on disk at `build_folder/voice_stats.json`, writes the result
+    /// back, and prints a pretty summary. Merging against the existing file
+    /// (rather than overwriting it) is what makes stats additive across
+    /// runs: resynthesizing a single chapter via `reset_chapter` only
+    /// contributes that chapter's entries, it doesn't discard stats already
+    /// recorded for the rest of the book.
+    pub fn write_voice_stats(&self) -> Result<()> {
+        let path = Path::new(&self.config.build_folder).join("voice_stats.json");
+        let mut report = VoiceStatsReport::load(&path);
+        report.merge(&self.voice_stats.lock().unwrap());
+
+        report.print_summary();
+        fs::write(path, serde_json::to_string_pretty(&report)?)?;
 
-        // Resolve Standard Chapter Mobs (if enabled)
-        if enable_mobs {
-            if let Ok(vid) = self
-                .tts
-                .get_random_voice(Some("Male"), &excluded_voices)
-                .await
-            {
-                if let Some(info) = working_map.characters.get_mut("章節路人(男)") {
-                    info.voice_id = Some(vid);
+        Ok(())
+    }
+
+    async fn process_chapter(
+        &mut self,
+        path: &Path,
+        filename: &str,
+        chapter_index: usize,
+        total_chapters: usize,
+    ) -> Result<()> {
+        let mob_voices = self
+            .state
+            .chapter_mob_voices
+            .get(filename)
+            .cloned()
+            .unwrap_or_default();
+        let (resolved_mob_voices, chapter_usage) = process_chapter(
+            self.config.clone(),
+            self.llm.clone(),
+            self.tts.clone(),
+            self.script_generator.clone(),
+            self.character_map.clone(),
+            path.to_path_buf(),
+            filename.to_string(),
+            self.observer.clone(),
+            self.cancellation.clone(),
+            mob_voices,
+            chapter_index,
+            total_chapters,
+            self.usage_tracker.clone(),
+            self.voice_stats.clone(),
+            self.file_logger.clone(),
+        )
+        .await?;
+        self.state
+            .chapter_mob_voices
+            .insert(filename.to_string(), resolved_mob_voices);
+        self.log_and_persist_llm_usage(filename, &chapter_usage)?;
+
+        if !self.config.unattended {
+            let stats_path = Path::new(&filename.replace(".", "_")).join("stats.json");
+            if let Ok(content) = self.storage.read(&stats_path) {
+                if let Ok(stats) = serde_json::from_str::<ChapterStats>(&content) {
+                    self.print_stats_summary(filename, &stats);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the per-chapter composition breakdown written to `stats.json`
+    /// by `process_chapter`. Called only in non-unattended mode, where
+    /// there's a terminal for someone to actually read it.
+    pub fn print_stats_summary(&self, filename: &str, stats: &ChapterStats) {
+        println!("\n=== Chapter Stats: {} ===", filename);
+        println!(
+            "Segments: {} ({} dialogue, {} narrator)",
+            stats.total_segments, stats.dialogue_segments, stats.narrator_segments
+        );
+        println!("Unique speakers: {}", stats.unique_speakers);
+        println!("Total characters: {}", stats.total_characters);
+        println!(
+            "Average segment length: {:.1} chars",
+            stats.average_segment_length
+        );
+
+        let mut speakers: Vec<(&String, &usize)> = stats.speaker_segment_counts.iter().collect();
+        speakers.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (speaker, count) in speakers {
+            println!("  {:<20} {:>5}", speaker, count);
+        }
+    }
+
+    /// Logs `chapter_usage`'s totals alongside the run's cumulative usage and
+    /// writes the cumulative tracker to `build_folder/llm_usage.json`.
+    fn log_and_persist_llm_usage(&self, filename: &str, chapter_usage: &LlmUsageTracker) -> Result<()> {
+        let cumulative = self.usage_tracker.lock().unwrap().clone();
+        println!(
+            "LLM usage for {}: {} tokens (prompt {}, completion {}); cumulative: {} tokens",
+            filename,
+            chapter_usage.total_tokens(),
+            chapter_usage.prompt_tokens,
+            chapter_usage.completion_tokens,
+            cumulative.total_tokens(),
+        );
+
+        let path = Path::new(&self.config.build_folder).join("llm_usage.json");
+        fs::write(path, serde_json::to_string_pretty(&cumulative)?)?;
+        Ok(())
+    }
+}
+
+/// Applies a character-name -> voice-ID mapping to `char_map`, for the
+/// `assign-voices` CLI subcommand's batch voice import. Only characters
+/// already present in `char_map.characters` are updated - unknown names are
+/// left for the caller to report as warnings, since a typo in the mapping
+/// file shouldn't abort the rest of the import. Returns the names of the
+/// characters that were actually updated.
+pub fn merge_character_assignments(
+    char_map: &mut CharacterMap,
+    assignments: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut updated = Vec::new();
+    for (name, voice_id) in assignments {
+        if let Some(info) = char_map.characters.get_mut(name) {
+            info.voice_id = Some(voice_id.clone());
+            updated.push(name.clone());
+        }
+    }
+    updated
+}
+
+/// Backstop for LLM character analysis missing (or duplicating) the
+/// `is_protagonist` tag: counts, across every already-processed chapter's
+/// cached `segments.json` under `build_folder`, how many chapters each
+/// speaker appears in at all, and marks whichever known character clears
+/// 60% of them as the protagonist - but only if analysis hasn't already
+/// flagged one, since this is meant to catch a miss, not second-guess a
+/// call the LLM already made. Returns the inferred name, if any.
+///
+/// `utils::storage::NativeStorage` has no directory listing, so chapter
+/// directories are discovered with a plain `fs::read_dir` over
+/// `build_folder`, the same way `WorkflowManager::cleanup_temp_files` does.
+pub fn infer_protagonist(char_map: &mut CharacterMap, build_folder: &str) -> Result<Option<String>> {
+    if char_map.characters.values().any(|info| info.is_protagonist) {
+        return Ok(None);
+    }
+
+    let mut appearances: HashMap<String, usize> = HashMap::new();
+    let mut total_chapters = 0usize;
+
+    for entry in fs::read_dir(build_folder)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let segments_path = path.join("segments.json");
+        if !segments_path.exists() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&segments_path)?;
+        let segments: Vec<AudioSegment> = serde_json::from_str(&content)?;
+        let speakers: std::collections::HashSet<String> =
+            segments.into_iter().filter_map(|s| s.speaker).collect();
+
+        total_chapters += 1;
+        for speaker in speakers {
+            *appearances.entry(speaker).or_insert(0) += 1;
+        }
+    }
+
+    if total_chapters == 0 {
+        return Ok(None);
+    }
+
+    let threshold = total_chapters as f32 * 0.6;
+    let candidate = appearances
+        .into_iter()
+        .filter(|(name, count)| char_map.characters.contains_key(name) && *count as f32 > threshold)
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name);
+
+    if let Some(name) = &candidate {
+        if let Some(info) = char_map.characters.get_mut(name) {
+            info.is_protagonist = true;
+        }
+    }
+
+    Ok(candidate)
+}
+
+/// Resolves the narrator voice to use, preferring `audio.narrator_voices`'s
+/// entry for `language_hint` (e.g. an English passage in an otherwise
+/// Chinese novel) and falling back to the active provider's own
+/// `TtsClient::get_narrator_voice_id` when there's no hint or no matching
+/// override configured.
+fn resolve_narrator_voice_id(tts: &dyn TtsClient, audio: &AudioConfig, language_hint: Option<&str>) -> String {
+    if let Some(lang) = language_hint {
+        if let Some(voice_id) = audio.narrator_voices.get(lang) {
+            return voice_id.clone();
+        }
+    }
+    tts.get_narrator_voice_id(language_hint)
+}
+
+/// Writes `project_root`'s `config.yml` and `build_folder`'s
+/// `character_map.json`/`state.json`/`*/segments.json` into a ZIP archive at
+/// `output_path`. Backs `WorkflowManager::export_project`; split out as a
+/// free function (taking an explicit `project_root` instead of assuming the
+/// current directory) so it's testable without mutating the process's
+/// working directory.
+fn write_project_archive(project_root: &Path, build_folder: &str, output_path: &Path) -> Result<()> {
+    use std::io::Write as _;
+
+    let config_path = project_root.join("config.yml");
+    let config_content = fs::read(&config_path)
+        .with_context(|| format!("Failed to read {:?}", config_path))?;
+
+    let file = fs::File::create(output_path)
+        .with_context(|| format!("Failed to create {:?}", output_path))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file("config.yml", options)?;
+    zip.write_all(&config_content)?;
+
+    for name in ["character_map.json", "state.json"] {
+        let path = Path::new(build_folder).join(name);
+        if path.exists() {
+            zip.start_file(format!("build/{}", name), options)?;
+            zip.write_all(&fs::read(&path)?)?;
+        }
+    }
+
+    for entry in fs::read_dir(build_folder)? {
+        let chapter_dir = entry?.path();
+        if !chapter_dir.is_dir() {
+            continue;
+        }
+        let segments_path = chapter_dir.join("segments.json");
+        if !segments_path.exists() {
+            continue;
+        }
+        let chapter_name = chapter_dir
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Chapter directory {:?} has no name", chapter_dir))?
+            .to_string_lossy();
+        zip.start_file(format!("build/{}/segments.json", chapter_name), options)?;
+        zip.write_all(&fs::read(&segments_path)?)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Inverse of `WorkflowManager::export_project`: extracts a project archive's
+/// `character_map.json`/`state.json`/`*/segments.json` into `build_folder`
+/// (creating it if needed) and returns the parsed `Config` from the
+/// archive's `config.yml`, leaving the caller to decide whether/where to
+/// write it (e.g. to `./config.yml`, mirroring `Config::load`'s assumption
+/// that lives in the current directory). Errors if any of the required
+/// entries - `config.yml`, `build/character_map.json`, `build/state.json`,
+/// at least one `build/*/segments.json` - are missing, rather than silently
+/// importing a partial project. Takes `build_folder: &str`, since this crate
+/// has no `Storage` trait, and writes the extracted entries through a
+/// `utils::storage::NativeStorage` rooted there.
+pub fn import_project(zip_path: &str, build_folder: &str) -> Result<Config> {
+    let file = fs::File::open(zip_path).with_context(|| format!("Failed to open {:?}", zip_path))?;
+    let mut archive =
+        zip::ZipArchive::new(file).with_context(|| format!("{:?} is not a valid ZIP archive", zip_path))?;
+    let storage = NativeStorage::new(Path::new(build_folder));
+
+    let mut config_content: Option<String> = None;
+    let mut has_character_map = false;
+    let mut has_state = false;
+    let mut has_segments = false;
+
+    for i in 0..archive.len() {
+        use std::io::Read as _;
+
+        let mut entry = archive.by_index(i)?;
+        let name = entry.name().to_string();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        if name == "config.yml" {
+            config_content = Some(String::from_utf8(content)?);
+            continue;
+        }
+
+        let Some(relative) = name.strip_prefix("build/") else {
+            continue;
+        };
+        if relative == "character_map.json" {
+            has_character_map = true;
+        } else if relative == "state.json" {
+            has_state = true;
+        } else if relative.ends_with("/segments.json") {
+            has_segments = true;
+        } else {
+            continue;
+        }
+
+        let content = String::from_utf8(content)
+            .with_context(|| format!("Archive entry {:?} is not valid UTF-8", name))?;
+        storage.write(Path::new(relative), &content)?;
+    }
+
+    let config_content = config_content.ok_or_else(|| anyhow::anyhow!("Archive is missing config.yml"))?;
+    if !has_character_map {
+        return Err(anyhow::anyhow!("Archive is missing build/character_map.json"));
+    }
+    if !has_state {
+        return Err(anyhow::anyhow!("Archive is missing build/state.json"));
+    }
+    if !has_segments {
+        return Err(anyhow::anyhow!("Archive contains no build/*/segments.json files"));
+    }
+
+    serde_yaml_ng::from_str(&config_content).context("Failed to parse config.yml from archive")
+}
+
+/// Orders `entries` (chapter filenames) in place according to `strategy`,
+/// before `WorkflowManager::run` starts processing them.
+pub fn sort_chapters(entries: &mut Vec<String>, strategy: &ChapterSort) {
+    match strategy {
+        ChapterSort::Lexicographic => entries.sort(),
+        ChapterSort::NaturalNumeric => entries.sort_by(|a, b| natural_cmp(a, b)),
+        ChapterSort::Manual(order) => {
+            let listed_count = entries.iter().filter(|e| order.contains(e)).count();
+            entries.sort_by_key(|e| order.iter().position(|o| o == e).unwrap_or(usize::MAX));
+            // Everything not in `order` landed at the end via `usize::MAX`;
+            // `sort_by_key` is stable among those, so sort just that tail
+            // lexicographically instead of leaving it at the mercy of
+            // directory-listing order.
+            entries[listed_count..].sort();
+        }
+    }
+}
+
+/// Compares two strings treating runs of ASCII digits as numbers, so
+/// `"Chapter2.txt"` sorts before `"Chapter10.txt"` instead of after it.
+/// Non-digit runs are compared as plain text.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(ac), Some(bc)) if ac.is_ascii_digit() && bc.is_ascii_digit() => {
+                let a_num: String = std::iter::from_fn(|| {
+                    a_chars.peek().filter(|c| c.is_ascii_digit()).copied().map(|c| {
+                        a_chars.next();
+                        c
+                    })
+                })
+                .collect();
+                let b_num: String = std::iter::from_fn(|| {
+                    b_chars.peek().filter(|c| c.is_ascii_digit()).copied().map(|c| {
+                        b_chars.next();
+                        c
+                    })
+                })
+                .collect();
+
+                let a_val: u64 = a_num.parse().unwrap_or(0);
+                let b_val: u64 = b_num.parse().unwrap_or(0);
+                match a_val.cmp(&b_val) {
+                    std::cmp::Ordering::Equal => continue,
+                    other => return other,
+                }
+            }
+            (Some(ac), Some(bc)) => match ac.cmp(bc) {
+                std::cmp::Ordering::Equal => {
+                    a_chars.next();
+                    b_chars.next();
+                    continue;
                 }
+                other => return other,
+            },
+        }
+    }
+}
+
+/// Finds voices shared by more than one character in the given map, which
+/// usually means the LLM assigned the same voice to two characters across
+/// different chapters.
+fn find_voice_conflicts(character_map: &CharacterMap) -> Vec<VoiceConflict> {
+    let mut by_voice: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, info) in &character_map.characters {
+        if let Some(voice_id) = &info.voice_id {
+            by_voice
+                .entry(voice_id.clone())
+                .or_default()
+                .push(name.clone());
+        }
+    }
+
+    let mut conflicts: Vec<VoiceConflict> = by_voice
+        .into_iter()
+        .filter(|(_, characters)| characters.len() > 1)
+        .map(|(voice_id, mut characters)| {
+            characters.sort();
+            VoiceConflict {
+                voice_id,
+                characters,
+            }
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.voice_id.cmp(&b.voice_id));
+    conflicts
+}
+
+/// Adds `llm`'s usage for the call that just completed to both the
+/// per-chapter `chapter_usage` accumulator and the shared `usage_tracker`,
+/// then errors out if `max_total_tokens` is now exceeded. A no-op when the
+/// provider didn't report usage (see `LlmClient::last_usage`).
+fn record_llm_usage(
+    llm: &dyn LlmClient,
+    chapter_usage: &mut LlmUsageTracker,
+    usage_tracker: &std::sync::Mutex<LlmUsageTracker>,
+    max_total_tokens: Option<u64>,
+    file_logger: &FileLogger,
+    filename: &str,
+    purpose: &str,
+) -> Result<()> {
+    let Some(usage) = llm.last_usage() else {
+        return Ok(());
+    };
+
+    chapter_usage.add(usage);
+    let cumulative_total = {
+        let mut tracker = usage_tracker.lock().unwrap();
+        tracker.add(usage);
+        tracker.total_tokens()
+    };
+    file_logger.llm_call_end(filename, purpose, usage.prompt_tokens, usage.completion_tokens);
+
+    if let Some(max) = max_total_tokens {
+        if cumulative_total > max {
+            anyhow::bail!(
+                "LLM token budget exceeded: {} tokens used (limit {})",
+                cumulative_total,
+                max
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn save_character_map(config: &Config, character_map: &RwLock<CharacterMap>) -> Result<()> {
+    let storage = NativeStorage::new(Path::new(&config.build_folder));
+    let snapshot = character_map.read().await;
+    let content = serde_json::to_string_pretty(&*snapshot)?;
+    drop(snapshot);
+    storage.write(Path::new("character_map.json"), &content)?;
+    Ok(())
+}
+
+/// Reassigns the secondary character(s) in each conflict to a different
+/// random voice, excluding the conflicting voice_id.
+async fn resolve_voice_conflicts(
+    config: &Config,
+    tts: &dyn TtsClient,
+    character_map: &RwLock<CharacterMap>,
+    conflicts: &[VoiceConflict],
+) -> Result<()> {
+    let mut changed = false;
+    for conflict in conflicts {
+        for name in conflict.characters.iter().skip(1) {
+            let gender = {
+                let map = character_map.read().await;
+                map.characters.get(name).map(|c| c.gender.clone())
+            };
+            let new_voice = tts
+                .get_random_voice(gender.as_deref(), &[conflict.voice_id.clone()])
+                .await?;
+            let mut map = character_map.write().await;
+            if let Some(info) = map.characters.get_mut(name) {
+                info.voice_id = Some(new_voice);
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        save_character_map(config, character_map).await?;
+    }
+    Ok(())
+}
+
+/// Splits `text` into overlapping windows of at most `max_chars` characters
+/// each, where every window after the first repeats the previous window's
+/// last `overlap_chars` characters for continuity. Returns a single window
+/// containing the whole text when it already fits within `max_chars`.
+fn chunk_chapter_text(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars || max_chars == 0 {
+        return vec![text.to_string()];
+    }
+
+    let step = max_chars.saturating_sub(overlap_chars).max(1);
+    let mut windows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + max_chars).min(chars.len());
+        windows.push(chars[start..end].iter().collect::<String>());
+        if end == chars.len() {
+            break;
+        }
+        start += step;
+    }
+    windows
+}
+
+/// Bitrate used when transcoding a WAV-based provider's merged chapter audio
+/// to MP3 for `AudioConfig::output_format`. Matches `Qwen3TtsConfig`'s own
+/// MP3 default; there's no equivalent provider-agnostic config field yet.
+const TRANSCODE_MP3_BITRATE_KBPS: u32 = 128;
+
+/// Consumes `llm.stream_chat`, printing each token as it arrives on native
+/// builds (so a long script generation gives visible progress) and
+/// concatenating them into the full response. Printing is skipped under
+/// wasm32, which has no stdout to write to.
+async fn stream_chat_to_string(llm: &dyn LlmClient, system: &str, user: &str) -> Result<String> {
+    let mut stream = llm.stream_chat(system, user).await?;
+    let mut full = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use std::io::Write;
+            print!("{}", chunk);
+            std::io::stdout().flush().ok();
+        }
+        full.push_str(&chunk);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    println!();
+    Ok(full)
+}
+
+/// Sends `prompt` and parses the response as a script via
+/// `script_generator.parse_response`. If parsing fails, retries up to
+/// `max_retry_turns` times via `LlmClient::chat_multi_turn`, appending the
+/// parse error as a follow-up user turn each time so the model can see what
+/// it got wrong, rather than re-asking the same question from scratch.
+async fn generate_script_segments(
+    llm: &dyn LlmClient,
+    script_generator: &dyn ScriptGenerator,
+    system_instruction: &str,
+    prompt: &str,
+    stream: bool,
+    max_retry_turns: usize,
+) -> Result<(String, Vec<AudioSegment>)> {
+    let response = if stream {
+        stream_chat_to_string(llm, system_instruction, prompt).await?
+    } else {
+        llm.chat(system_instruction, prompt).await?
+    };
+
+    let mut last_response = response;
+    let mut last_err = match script_generator.parse_response(&last_response) {
+        Ok(segments) => return Ok((last_response, segments)),
+        Err(e) => e,
+    };
+
+    let mut history = vec![(prompt.to_string(), last_response.clone())];
+    for _ in 0..max_retry_turns {
+        let retry_user = format!(
+            "The previous JSON was invalid: {}\n\nPlease reply with corrected, valid JSON only.",
+            last_err
+        );
+        history.push((retry_user, String::new()));
+
+        let response = llm.chat_multi_turn(system_instruction, &history).await?;
+        match script_generator.parse_response(&response) {
+            Ok(segments) => return Ok((response, segments)),
+            Err(e) => {
+                history.last_mut().unwrap().1 = response.clone();
+                last_response = response;
+                last_err = e;
+            }
+        }
+    }
+
+    Err(last_err).with_context(|| format!("Script response still invalid after retries: {}", last_response))
+}
+
+/// Processes a single chapter end-to-end: character analysis, script
+/// generation, synthesis, and merging. Stateless aside from the shared
+/// `character_map`, so callers can run several of these concurrently.
+async fn process_chapter(
+    config: Arc<Config>,
+    llm: Arc<dyn LlmClient>,
+    tts: Arc<dyn TtsClient>,
+    script_generator: Arc<dyn ScriptGenerator>,
+    character_map: Arc<RwLock<CharacterMap>>,
+    path: PathBuf,
+    filename: String,
+    observer: Arc<dyn WorkflowObserver>,
+    cancellation: CancellationToken,
+    mob_voices: HashMap<String, String>,
+    chapter_index: usize,
+    total_chapters: usize,
+    usage_tracker: Arc<std::sync::Mutex<LlmUsageTracker>>,
+    voice_stats: Arc<std::sync::Mutex<VoiceStatsReport>>,
+    file_logger: Arc<FileLogger>,
+) -> Result<(HashMap<String, String>, LlmUsageTracker)> {
+    file_logger.chapter_start(&filename, chapter_index, total_chapters);
+    {
+        let mut map = character_map.write().await;
+        if let Some(name) = infer_protagonist(&mut map, &config.build_folder)? {
+            println!(
+                "Inferred '{}' as protagonist from inter-chapter frequency analysis",
+                name
+            );
+        }
+    }
+    let mut chapter_usage = LlmUsageTracker::default();
+    let text = crate::utils::text::decode_bytes_with_encoding(
+        &fs::read(&path)?,
+        config.workflow.input_encoding.as_deref(),
+    )?;
+
+    let chapter_build_dir = Path::new(&config.build_folder).join(filename.replace(".", "_"));
+    fs::create_dir_all(&chapter_build_dir)?;
+    let segments_path = chapter_build_dir.join("segments.json");
+    let segments_meta_path = chapter_build_dir.join("segments_meta.json");
+    let chapter_hash = format!("{:x}", Sha256::digest(text.as_bytes()));
+
+    if config.workflow.cache_validation && segments_path.exists() {
+        let is_stale = fs::read_to_string(&segments_meta_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SegmentsMeta>(&content).ok())
+            .is_some_and(|meta| meta.chapter_hash != chapter_hash);
+
+        if is_stale {
+            println!(
+                "Chapter text for {} changed since segments were cached; regenerating.",
+                filename
+            );
+            fs::remove_file(&segments_path)?;
+        }
+    }
+
+    // Prepare voices for Analysis & Script Generation
+    let mut voices = tts.list_voices().await?;
+    voices.retain(|v| {
+        v.locale.starts_with(&config.audio.language)
+            && !config.audio.exclude_locales.contains(&v.locale)
+    });
+
+    let mut segments: Vec<AudioSegment> = if segments_path.exists() {
+        println!("Loading cached segments from {:?}", segments_path);
+        let content = fs::read_to_string(&segments_path)?;
+        serde_json::from_str(&content)?
+    } else {
+        // 1. Analyze Characters
+        println!("Analyzing characters...");
+
+        let existing_chars_str = if config.workflow.include_existing_chars_in_analysis {
+            let map = character_map.read().await;
+            map.characters.keys().cloned().collect::<Vec<_>>().join(", ")
+        } else {
+            String::new()
+        };
+
+        let existing_char_instruction = if config.workflow.include_existing_chars_in_analysis {
+            "\n- 若角色已存在於「目前已存在的角色」或「先前已識別角色」中，請使用相同的名稱。"
+        } else {
+            ""
+        };
+
+        let voice_list_str = tts.format_voice_list_for_analysis(&voices);
+        let narrator_voice_id = resolve_narrator_voice_id(tts, &config.audio, None);
+        let enable_mobs = tts.is_mob_enabled();
+
+    let mob_instruction = if enable_mobs {
+        "- 系統已內建路人、路人(男)、路人(女)、章節路人(男)、章節路人(女)等角色，請勿重複創建。\n\
+         - 章節內話多但後續不出現的角色，請使用「章節路人(男)」或「章節路人(女)」。\n\
+         - 不重要的丟棄式角色請直接使用路人、路人(男)或路人(女)。"
+    } else {
+        "- 對於不重要的路人或龍套角色，無須分配，直接略過即可。"
+    };
+
+        let context_char_limit = if config.llm.truncate_analysis_context || config.llm.window_long_chapters {
+            config.llm.max_context_chars
+        } else {
+            10000
+        };
+        let total_chapter_chars = text.chars().count();
+
+        let analysis_windows = if config.llm.window_long_chapters && total_chapter_chars > context_char_limit {
+            chunk_chapter_text(&text, context_char_limit, config.llm.window_overlap_chars)
+        } else {
+            vec![text.chars().take(context_char_limit).collect()]
+        };
+
+        // Parse JSON
+        #[derive(Deserialize, Clone)]
+        struct AnalysisResult {
+            characters: Vec<AnalysisChar>,
+        }
+        #[derive(Deserialize, Clone)]
+        struct AnalysisChar {
+            name: String,
+            gender: String,
+            #[serde(default)]
+            important: bool, // Renamed from _important to allow usage
+            #[serde(default)]
+            description: Option<String>,
+            #[serde(default)]
+            voice_id: Option<String>,
+            #[serde(default)]
+            is_protagonist: bool,
+            #[serde(default)]
+            is_child: bool,
+        }
+
+        // When windowed, each window's prompt also lists the characters
+        // identified in earlier windows of this same chapter, so the model
+        // can keep referring to them by the same name instead of
+        // re-inventing them.
+        let mut all_characters: Vec<AnalysisChar> = Vec::new();
+        let mut seen_names: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+        for (window_index, window_text) in analysis_windows.iter().enumerate() {
+            let previously_identified_block = if window_index == 0 {
+                String::new()
+            } else {
+                format!(
+                    "\n4. 先前已識別角色 (Previously identified characters in this chapter): [{}]",
+                    all_characters
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+
+            let analysis_prompt = format!(
+                "請分析以下文本。識別所有說話的角色。\
+                \n\n上下文資訊 (Context):\
+                \n1. 目前已存在的角色 (Existing Characters): [{}]\
+                \n2. 旁白聲音 ID (Narrator Voice ID): \"{}\"\
+                \n3. 可用聲音列表 (Available Voices):\n[{}]{}\
+                \n\n指令 (Instructions):\
+                \n- 識別文本中的說話角色，確定性別（Male/Female）及是否為主要角色。\
+                \n- 若角色為「主角」(Protagonist)，請將 \"is_protagonist\" 欄位設為 true。{}\
+                \n- 若文本為第一人稱（如「我」），請識別主角，將其 voice_id 設定為旁白聲音 ID，並設定 \"is_protagonist\": true。\
+                \n- 若角色為兒童，請將 \"is_child\" 設為 true。\
+                \n- 主要角色，尤其主角，請避免重複使用該聲音。旁白亦同。\
+                \n- 對於新角色，你可以從「可用聲音列表」中選擇合適的 voice_id (選填)，否則留空。\
+                \n{}\n\
+                \n- 創建的JSON對象由於是key必須使用繁體中文。使用簡體將導致程式出錯。\
+                \n\n請僅返回一個 JSON 對象(不可翻譯json key)：\
+                {{ \"characters\": [ {{ \"name\": \"...\", \"gender\": \"Male/Female\", \"is_protagonist\": true/false, \"is_child\": true/false, \"important\": true/false, \"description\": \"...\", \"voice_id\": \"...\" }} ] }} \
+                \n\n文本：\n{}",
+                existing_chars_str,
+                narrator_voice_id,
+                voice_list_str,
+                previously_identified_block,
+                existing_char_instruction,
+                mob_instruction,
+                window_text,
+            );
+
+            if analysis_prompt.len() > 40_000 {
+                warn!(
+                    "Analysis prompt window {} for {} is {} bytes; window covers {} of {} chapter characters.",
+                    window_index,
+                    filename,
+                    analysis_prompt.len(),
+                    window_text.chars().count(),
+                    total_chapter_chars,
+                );
             }
 
-            if let Ok(vid) = self
-                .tts
-                .get_random_voice(Some("Female"), &excluded_voices)
+            let analysis: AnalysisResult = llm
+                .chat_json("你是一位文學助手。請僅返回有效的 JSON。", &analysis_prompt)
                 .await
-            {
-                if let Some(info) = working_map.characters.get_mut("章節路人(女)") {
-                    info.voice_id = Some(vid);
+                .context("Failed to parse character analysis JSON")?;
+            record_llm_usage(
+                llm.as_ref(),
+                &mut chapter_usage,
+                &usage_tracker,
+                config.llm.max_total_tokens,
+                &file_logger,
+                &filename,
+                "character_analysis",
+            )?;
+
+            for char in analysis.characters {
+                if seen_names.insert(char.name.clone()) {
+                    all_characters.push(char);
+                }
+            }
+        }
+
+        // Update Character Map
+        let mut chapter_local_chars = HashMap::new();
+        let mut updated_global_map = false;
+
+        for mut char in all_characters {
+            if config.workflow.include_existing_chars_in_analysis {
+                let existing_names: Vec<String> =
+                    character_map.read().await.characters.keys().cloned().collect();
+                if let Some(matched) =
+                    crate::utils::text::find_fuzzy_character_match(&char.name, &existing_names)
+                {
+                    char.name = matched.to_string();
+                }
+            }
+
+            // Logic:
+            // If mobs enabled: all processed as usual (persisted).
+            // If mobs disabled:
+            //    - Named/Important/Protagonist -> Global Map
+            //    - Unimportant/Mob-like -> Local Map (do not save to global json)
+
+            let should_persist = if enable_mobs {
+                true
+            } else {
+                char.important || char.is_protagonist || char.voice_id.is_some()
+            };
+
+            // Override: placeholders are never "persisted" in the sense of adding new keys, but updating existing keys.
+            // But if user disables mobs, we don't want to create "路人A" in global map.
+
+            if should_persist {
+                let mut map = character_map.write().await;
+                let entry = map.characters.entry(char.name.clone());
+                match entry {
+                    std::collections::hash_map::Entry::Vacant(e) => {
+                        e.insert(CharacterInfo {
+                            gender: char.gender,
+                            voice_id: char.voice_id,
+                            description: char.description,
+                            is_protagonist: char.is_protagonist,
+                            is_child: char.is_child,
+                            ..Default::default()
+                        });
+                        updated_global_map = true;
+                    }
+                    std::collections::hash_map::Entry::Occupied(mut e) => {
+                        if e.get().voice_id.is_none() && char.voice_id.is_some() {
+                            e.get_mut().voice_id = char.voice_id;
+                            updated_global_map = true;
+                        }
+                    }
+                }
+            } else {
+                // Local map
+                chapter_local_chars.insert(
+                    char.name.clone(),
+                    CharacterInfo {
+                        gender: char.gender,
+                        voice_id: char.voice_id,
+                        description: char.description,
+                        is_protagonist: char.is_protagonist,
+                        is_child: char.is_child,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        if updated_global_map {
+            save_character_map(&config, &character_map).await?;
+
+            let conflicts = find_voice_conflicts(&character_map.read().await);
+            for conflict in &conflicts {
+                warn!(
+                    "Voice {} is assigned to multiple characters: {:?}",
+                    conflict.voice_id, conflict.characters
+                );
+            }
+            if config.workflow.auto_resolve_voice_conflicts && !conflicts.is_empty() {
+                resolve_voice_conflicts(&config, tts.as_ref(), &character_map, &conflicts)
+                    .await?;
+            }
+        }
+
+        // Create combined map for this chapter
+        let mut combined_map = character_map.read().await.clone();
+        for (k, v) in chapter_local_chars {
+            combined_map.characters.insert(k, v);
+        }
+
+        // 2. Script Generation
+        println!("Generating Script...");
+
+        // Gather voice styles
+        let mut voice_styles = HashMap::new();
+        for info in combined_map.characters.values() {
+            if let Some(vid) = &info.voice_id {
+                if let Ok(styles) = tts.get_voice_styles(vid).await {
+                    voice_styles.insert(vid.clone(), styles);
+                }
+            }
+        }
+        // For GPT-SoVITS, populate styles for ALL available voices (candidates) so ScriptGenerator can use them
+        if config.audio.provider == "gpt_sovits" {
+            for v in &voices {
+                if !voice_styles.contains_key(&v.short_name) {
+                    if let Ok(styles) = tts.get_voice_styles(&v.short_name).await {
+                        voice_styles.insert(v.short_name.clone(), styles);
+                    }
                 }
             }
         }
 
-        // Validate and Fix Segments (Autofix) before synthesis
-        // We pass a mutable reference to segments. If it changes, we should save it.
-        let mut segments_mut = segments.clone();
-        self.tts
-            .check_and_fix_segments(
-                &mut segments_mut,
-                &working_map,
-                &excluded_voices,
-                self.llm.as_ref(),
+        let script_windows = if config.llm.window_long_chapters && total_chapter_chars > context_char_limit {
+            chunk_chapter_text(&text, context_char_limit, config.llm.window_overlap_chars)
+        } else {
+            vec![text.clone()]
+        };
+
+        let system_instruction = script_generator.get_system_prompt();
+        let mut segments: Vec<AudioSegment> = Vec::new();
+        for window_text in &script_windows {
+            let prompt = script_generator.generate_prompt(window_text, &combined_map, &voice_styles, &voices)?;
+            let (_script_json, window_segments) = generate_script_segments(
+                llm.as_ref(),
+                script_generator.as_ref(),
+                &system_instruction,
+                &prompt,
+                config.llm.stream,
+                config.llm.max_retry_turns,
             )
             .await?;
+            record_llm_usage(
+                llm.as_ref(),
+                &mut chapter_usage,
+                &usage_tracker,
+                config.llm.max_total_tokens,
+                &file_logger,
+                &filename,
+                "script_generation",
+            )?;
+            segments.extend(window_segments);
+        }
 
-        // If changed, save back to disk
-        // Note: check_and_fix_segments might populate voice_id for mobs, which is good to persist.
-        // It might also fix emotions.
-        // We do a simple check if any changed, or just overwrite.
-        // Since clone is cheap for this size, let's just overwrite if check_and_fix passes.
-        // (If it fails, it panics/errors out, so we don't save broken stuff, though typically it panics on validation failure)
-        // Wait, if check_and_fix_segments modifies segments_mut (e.g. populating voice_ids), we want to use that for synthesis.
-        segments = segments_mut;
+        // Save Script to cache
         fs::write(&segments_path, serde_json::to_string_pretty(&segments)?)?;
 
-        let pb = ProgressBar::new(segments.len() as u64);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")?
-            .progress_chars("#>-"));
-
-        let tts = &self.tts;
-        let working_map_ref = &working_map;
-        let excluded_voices_ref = &excluded_voices;
-
-        let max_concurrency = tts.max_concurrency();
-        let results: Vec<Result<(usize, PathBuf)>> = futures_util::stream::iter(segments.iter().enumerate())
-            .map(|(i, segment)| {
-                let chunk_path = chapter_build_dir.join(format!("chunk_{:04}.mp3", i));
-                let pb = pb.clone();
-                async move {
-                    if !chunk_path.exists() {
-                        let audio_data = tts.synthesize(segment, working_map_ref, excluded_voices_ref).await?;
-                        tokio_fs::write(&chunk_path, audio_data).await?;
+        segments
+        };
+
+    deduplicate_segments(&mut segments);
+    filter_empty_segments(&mut segments);
+    tag_detected_languages(&mut segments, &config.audio.language, &config.audio.additional_languages);
+
+    // Apply per-language narrator voice overrides now that segments carry
+    // `detected_language`. Only narrator-like segments without an explicit
+    // `voice_id` already set are touched; `TtsClient::synthesize` already
+    // prefers `voice_id` over speaker-based resolution (see `preview_voice`
+    // in `main.rs` for the other place that relies on this precedence), so
+    // this is enough to make the override take effect at synthesis time
+    // without threading `narrator_voices` into every provider.
+    if !config.audio.narrator_voices.is_empty() {
+        for segment in segments.iter_mut() {
+            if segment.voice_id.is_some() {
+                continue;
+            }
+            let is_narrator = segment.speaker.as_deref().map(|s| s == "旁白").unwrap_or(true);
+            if !is_narrator {
+                continue;
+            }
+            if let Some(lang) = segment.detected_language.as_deref() {
+                if let Some(voice_id) = config.audio.narrator_voices.get(lang) {
+                    segment.voice_id = Some(voice_id.clone());
+                }
+            }
+        }
+    }
+
+    // Re-construct combined map in case we loaded from cache (segments exist)
+    // But wait, if segments exist, we didn't populate local map from LLM.
+    // We might be missing local characters info if we resume!
+    // This is a known issue with this architecture if local state isn't persisted.
+    // However, if segments exist, we iterate segments.
+    // If segments have `voice_id` (new feature), we are good.
+    // If segments rely on speaker name map... we might fail if local char isn't in map.
+    // For now, let's assume if cache exists, we rely on segment.voice_id or global map.
+    // If local chars were used and not saved... reconstruction is hard without saving local map.
+    // But user said "disposable mobs". Maybe it's fine.
+    // Or we should save `chapter_character_map.json` in build dir?
+    // Let's rely on `voice_id` being in segment for those mobs.
+
+    // 3. Synthesize
+    println!("Synthesizing audio ({} segments)...", segments.len());
+
+    // Build Excluded Voices (Narrator + Protagonists)
+    let mut excluded_voices = Vec::new();
+    let narrator_voice_id = resolve_narrator_voice_id(tts, &config.audio, None);
+
+    excluded_voices.push(narrator_voice_id);
+    for voice_id in config.audio.narrator_voices.values() {
+        if !excluded_voices.contains(voice_id) {
+            excluded_voices.push(voice_id.clone());
+        }
+    }
+
+    {
+        let map = character_map.read().await;
+        for char_info in map.characters.values() {
+            if char_info.is_protagonist {
+                if let Some(vid) = &char_info.voice_id {
+                    if !excluded_voices.contains(vid) {
+                        excluded_voices.push(vid.clone());
                     }
-                    pb.inc(1);
-                    Ok((i, chunk_path))
                 }
-            })
-            .buffer_unordered(max_concurrency)
-            .collect()
-            .await;
+            }
+        }
+    }
+
+    // We need a map for resolving speakers.
+    // Since we didn't save local map, if we just loaded segments, we only have global map.
+    // If segment has voice_id, we use it.
+    // If segment uses a local mob name but no voice_id... we have a problem if we didn't regenerate.
+    // But `GptSovitsScriptGenerator` is instructed to output `voice_id`.
+
+    // Let's create a working map, defaulting to global.
+    // Note: Chapter Mobs (placeholders) are in global map if enable_mobs=true.
+    let mut working_map = character_map.read().await.clone();
+
+    let enable_mobs = tts.is_mob_enabled();
+
+    // Resolve Standard Chapter Mobs (if enabled), reusing a voice assigned on
+    // a previous run of this chapter so a single-chapter rerun doesn't
+    // reshuffle mob voices.
+    let mut resolved_mob_voices = mob_voices.clone();
+    if enable_mobs {
+        let pool_size = config.audio.chapter_mob_pool_size.max(1);
+        for (name, gender) in [("章節路人(男)", "Male"), ("章節路人(女)", "Female")] {
+            if pool_size <= 1 {
+                let vid = match mob_voices.get(name) {
+                    Some(vid) => Some(vid.clone()),
+                    None => tts.get_random_voice(Some(gender), &excluded_voices).await.ok(),
+                };
+                if let Some(vid) = vid {
+                    if let Some(info) = working_map.characters.get_mut(name) {
+                        info.voice_id = Some(vid.clone());
+                    }
+                    resolved_mob_voices.insert(name.to_string(), vid);
+                }
+                continue;
+            }
+
+            // Pool mode: create `name_1`..`name_N`, each a distinct voice
+            // excluded from the rest of the pool and any later random
+            // selection, then round-robin distribute segments addressed to
+            // the plain `name` across the pool.
+            let mut pool_names = Vec::new();
+            for i in 1..=pool_size {
+                let pool_name = format!("{}_{}", name, i);
+                let vid = match mob_voices.get(&pool_name) {
+                    Some(vid) => Some(vid.clone()),
+                    None => tts.get_random_voice(Some(gender), &excluded_voices).await.ok(),
+                };
+                if let Some(vid) = vid {
+                    let gender_label = if gender == "Male" { "男性" } else { "女性" };
+                    working_map.characters.insert(
+                        pool_name.clone(),
+                        CharacterInfo {
+                            gender: gender.to_string(),
+                            voice_id: Some(vid.clone()),
+                            description: Some(format!(
+                                "本章節內的{}路人第 {} 位，聲音在該章節內固定",
+                                gender_label, i
+                            )),
+                            ..Default::default()
+                        },
+                    );
+                    resolved_mob_voices.insert(pool_name.clone(), vid.clone());
+                    excluded_voices.push(vid);
+                    pool_names.push(pool_name);
+                }
+            }
+
+            if !pool_names.is_empty() {
+                let mut next = 0usize;
+                for seg in segments.iter_mut() {
+                    if seg.speaker.as_deref() == Some(name) {
+                        seg.speaker = Some(pool_names[next % pool_names.len()].clone());
+                        next += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // Validate and Fix Segments (Autofix) before synthesis
+    // We pass a mutable reference to segments. If it changes, we should save it.
+    let mut segments_mut = segments.clone();
+    tts.check_and_fix_segments(&mut segments_mut, &working_map, &excluded_voices, llm.as_ref())
+        .await?;
+
+    // If changed, save back to disk
+    // Note: check_and_fix_segments might populate voice_id for mobs, which is good to persist.
+    // It might also fix emotions.
+    // We do a simple check if any changed, or just overwrite.
+    // Since clone is cheap for this size, let's just overwrite if check_and_fix passes.
+    // (If it fails, it panics/errors out, so we don't save broken stuff, though typically it panics on validation failure)
+    // Wait, if check_and_fix_segments modifies segments_mut (e.g. populating voice_ids), we want to use that for synthesis.
+    segments = segments_mut;
+    fs::write(&segments_path, serde_json::to_string_pretty(&segments)?)?;
+    fs::write(
+        &segments_meta_path,
+        serde_json::to_string_pretty(&SegmentsMeta {
+            chapter_hash: chapter_hash.clone(),
+        })?,
+    )?;
+
+    // Written here, before the cached-vs-fresh branches rejoin and any
+    // further mutation (max_segment_chars splitting, low-confidence
+    // review), so the report reflects the chapter as the script generator
+    // (or cache) actually produced it, regardless of which path was taken.
+    let stats = ChapterStats::from_segments(&segments);
+    NativeStorage::new(Path::new(&config.build_folder)).write(
+        &Path::new(&filename.replace(".", "_")).join("stats.json"),
+        &serde_json::to_string_pretty(&stats)?,
+    )?;
+
+    if let Some(max_chars) = config.audio.max_segment_chars {
+        if max_chars > 0 {
+            segments = segments
+                .iter()
+                .flat_map(|s| crate::services::script::split_long_segment(s, max_chars))
+                .collect();
+        }
+    }
+
+    if config.workflow.dry_run {
+        let report = build_dry_run_report(filename, &segments);
+        println!("{}", report);
+        fs::write(chapter_build_dir.join("dry_run_report.txt"), report)?;
+        file_logger.chapter_end(&filename, true, None);
+        return Ok((resolved_mob_voices, chapter_usage));
+    }
+
+    let low_confidence_indices = crate::services::script::low_confidence_segment_indices(
+        &segments,
+        config.workflow.low_confidence_threshold,
+    );
+    if !low_confidence_indices.is_empty() {
+        let flagged: Vec<&AudioSegment> = low_confidence_indices.iter().map(|&i| &segments[i]).collect();
+        let review_path = chapter_build_dir.join("review.json");
+        fs::write(&review_path, serde_json::to_string_pretty(&flagged)?)?;
+        println!(
+            "{} segment(s) flagged for low-confidence speaker review; see {:?}",
+            low_confidence_indices.len(),
+            review_path
+        );
+
+        if config.unattended {
+            println!(
+                "Skipping synthesis for {} low-confidence segment(s) in unattended mode",
+                low_confidence_indices.len()
+            );
+            for &i in low_confidence_indices.iter().rev() {
+                segments.remove(i);
+            }
+        } else {
+            for &i in &low_confidence_indices {
+                let excerpt: String = segments[i].text.chars().take(80).collect();
+                let keep = inquire::Confirm::new(&format!(
+                    "Segment {} speaker \"{}\" (confidence {:.2}): \"{}\" - keep this speaker assignment?",
+                    i,
+                    segments[i].speaker.as_deref().unwrap_or("旁白"),
+                    segments[i].confidence.unwrap_or(1.0),
+                    excerpt
+                ))
+                .with_default(true)
+                .prompt()
+                .unwrap_or(true);
+                if !keep {
+                    segments[i].speaker = Some("旁白".to_string());
+                }
+            }
+        }
+    }
+
+    let estimated_time = tts.estimate_synthesis_time(&segments).await;
+    println!(
+        "Estimated synthesis time: ~{}s",
+        estimated_time.as_secs()
+    );
+
+    let total_segments = segments.len();
+    let completed_segments = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+    let working_map_ref = &working_map;
+    let excluded_voices_ref = &excluded_voices;
+    let post_synthesis_split = config.audio.post_synthesis_split;
+    let preprocessor = crate::services::preprocessing::TextPreprocessor::from_config(
+        &config.preprocessing.enabled_normalizers,
+    );
+    let preprocessor_ref = &preprocessor;
+    let phonetic_corrections_ref = &config.audio.phonetic_corrections;
+
+    let max_concurrency = tts.max_concurrency();
+    let max_segment_retries = config.audio.max_segment_retries;
+    let segment_retry_delay_secs = config.audio.segment_retry_delay_secs;
+    let fade_in_ms = config.audio.fade_in_ms;
+    let fade_out_ms = config.audio.fade_out_ms;
+    let last_segment_index = total_segments.saturating_sub(1);
+    let tts = &tts;
+    let voice_stats_ref = &voice_stats;
+    let results: Vec<Result<(usize, PathBuf)>> = futures_util::stream::iter(segments.iter().enumerate())
+        .map(|(i, segment)| {
+            let chunk_path = chapter_build_dir.join(format!("chunk_{:04}.mp3", i));
+            let completed_segments = completed_segments.clone();
+            let observer = observer.clone();
+            let cancellation = cancellation.clone();
+            let file_logger = file_logger.clone();
+            let filename = filename.clone();
+            async move {
+                if cancellation.is_cancelled() {
+                    return Err(anyhow::anyhow!("Cancelled"));
+                }
+                if !chunk_path.exists() {
+                    let normalized_text = preprocessor_ref.normalize(&segment.text);
+                    let corrected_text = if tts.uses_ssml() {
+                        // SSML providers build their own `<phoneme>` tags from
+                        // `config.audio.phonetic_corrections` inside `synthesize`
+                        // (see `tts::edge`/`tts::azure`), so the raw text is
+                        // passed through unmodified here.
+                        normalized_text
+                    } else {
+                        crate::utils::text::apply_phonetic_corrections(
+                            &normalized_text,
+                            phonetic_corrections_ref,
+                        )
+                    };
+                    let synth_segment = AudioSegment {
+                        text: corrected_text,
+                        ..segment.clone()
+                    };
+                    let mut attempt = 0;
+                    let audio_data = loop {
+                        match tts.synthesize(&synth_segment, working_map_ref, excluded_voices_ref).await {
+                            Ok(data) => break data,
+                            Err(e) => {
+                                if attempt >= max_segment_retries {
+                                    let excerpt: String = segment.text.chars().take(80).collect();
+                                    let error = format!(
+                                        "Segment {} (speaker: {:?}) failed synthesis after {} attempts: {} | text: \"{}\"",
+                                        i,
+                                        segment.speaker,
+                                        attempt + 1,
+                                        e,
+                                        excerpt
+                                    );
+                                    file_logger.segment_result(&filename, i, false, Some(&error));
+                                    return Err(anyhow::anyhow!(error));
+                                }
+                                warn!(
+                                    "Segment {} synthesis failed (attempt {}/{}): {:?}",
+                                    i,
+                                    attempt + 1,
+                                    max_segment_retries + 1,
+                                    e
+                                );
+                                tokio::time::sleep(tokio::time::Duration::from_secs(
+                                    segment_retry_delay_secs,
+                                ))
+                                .await;
+                                attempt += 1;
+                            }
+                        }
+                    };
+                    // The merged chapter's leading/trailing fade only makes sense
+                    // at the very first/last segment; WAV-based providers only,
+                    // since `apply_fade_in`/`apply_fade_out` parse a PCM `fmt`
+                    // chunk an MP3-native provider's output wouldn't have.
+                    let audio_data = if audio_data.starts_with(b"RIFF") && i == 0 && fade_in_ms > 0 {
+                        crate::utils::audio::apply_fade_in(&audio_data, fade_in_ms)?
+                    } else {
+                        audio_data
+                    };
+                    let audio_data = if audio_data.starts_with(b"RIFF")
+                        && i == last_segment_index
+                        && fade_out_ms > 0
+                    {
+                        crate::utils::audio::apply_fade_out(&audio_data, fade_out_ms)?
+                    } else {
+                        audio_data
+                    };
+                    if post_synthesis_split && audio_data.starts_with(b"RIFF") {
+                        if let Ok(regions) = crate::utils::audio::split_wav_at_silence(&audio_data, 300, -40.0) {
+                            if regions.len() > 1 {
+                                warn!(
+                                    "Segment {} contains {} natural pauses; consider splitting it before synthesis",
+                                    i,
+                                    regions.len()
+                                );
+                            }
+                        }
+                    }
+                    // Write through a sibling temp file and rename into place
+                    // (atomic on the same filesystem) so a crash mid-write
+                    // can't leave a `chunk_path` that `!chunk_path.exists()`
+                    // above would treat as already-synthesized but is
+                    // actually truncated/corrupted.
+                    let mut tmp_path = chunk_path.clone().into_os_string();
+                    tmp_path.push(".tmp");
+                    let tmp_path = PathBuf::from(tmp_path);
+                    tokio_fs::write(&tmp_path, audio_data).await?;
+                    tokio_fs::rename(&tmp_path, &chunk_path).await?;
+                }
+
+                let character_name = segment.speaker.clone().unwrap_or_else(|| "旁白".to_string());
+                let voice_id = segment
+                    .voice_id
+                    .clone()
+                    .or_else(|| {
+                        working_map_ref
+                            .characters
+                            .get(&character_name)
+                            .and_then(|info| info.voice_id.clone())
+                    })
+                    .unwrap_or_else(|| "unknown".to_string());
+                voice_stats_ref.lock().unwrap().record(
+                    &voice_id,
+                    &character_name,
+                    segment.text.chars().count() as u32,
+                );
+
+                let done = completed_segments.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                observer.on_segment_synthesized(done, total_segments);
+                file_logger.segment_result(&filename, i, true, None);
+                Ok((i, chunk_path))
+            }
+        })
+        .buffer_unordered(max_concurrency)
+        .collect()
+        .await;
+
+    if cancellation.is_cancelled() {
+        return Err(anyhow::anyhow!("Cancelled"));
+    }
+
+    let mut raw_audio_files = vec![PathBuf::new(); segments.len()];
+    if config.workflow.continue_on_error {
+        for res in results {
+            match res {
+                Ok((i, path)) => raw_audio_files[i] = path,
+                Err(e) => warn!("Skipping segment that failed synthesis: {:?}", e),
+            }
+        }
+    } else {
+        for res in results {
+            let (i, path) = res?;
+            raw_audio_files[i] = path;
+        }
+    }
+
+    let indexed_audio_files: Vec<(usize, PathBuf)> = raw_audio_files
+        .into_iter()
+        .enumerate()
+        .filter(|(_, p)| !p.as_os_str().is_empty())
+        .collect();
+    let audio_files: Vec<PathBuf> = indexed_audio_files.iter().map(|(_, p)| p.clone()).collect();
+
+    let timings = build_segment_timings(&segments, &indexed_audio_files, tts.is_mp3_output())?;
+    let timings_path = chapter_build_dir.join("timings.json");
+    fs::write(&timings_path, serde_json::to_string_pretty(&timings)?)?;
+
+    // 4. Merge
+    println!("Merging audio...");
+    // Providers whose merged output is already MP3 (see `TtsClient::is_mp3_output`)
+    // hand back an encoded file we have no decoder for, so `output_format` only
+    // applies to WAV-based providers; MP3-native providers always stay MP3.
+    let output_format = if tts.is_mp3_output() {
+        AudioOutputFormat::Mp3
+    } else {
+        config.audio.output_format
+    };
+    let stem = Path::new(&filename)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| filename.clone());
+    let ext = output_format.extension().to_string();
+    let index_str = chapter_index.to_string();
+    let total_str = total_chapters.to_string();
+    let template_vars = HashMap::from([
+        ("stem", stem.as_str()),
+        ("ext", ext.as_str()),
+        ("index", index_str.as_str()),
+        ("total", total_str.as_str()),
+        ("title", config.book_metadata.title.as_str()),
+    ]);
+    let output_filename = crate::utils::template::sanitize_filename(
+        &crate::utils::template::render_template(&config.output.filename_template, &template_vars),
+    );
+    let final_audio_path = Path::new(&config.output_folder).join(output_filename);
+
+    let mut merge_inputs = if tts.is_mp3_output() {
+        audio_files.clone()
+    } else {
+        intersperse_silence(
+            &chapter_build_dir,
+            &segments,
+            &indexed_audio_files,
+            &config.audio.silence,
+        )?
+    };
+
+    if let Some(intro_clip) = &config.audio.intro_clip {
+        let prepared = prepare_clip(&config.build_folder, intro_clip, tts.as_ref())?;
+        merge_inputs.insert(0, prepared);
+    }
+    if let Some(outro_clip) = &config.audio.outro_clip {
+        let prepared = prepare_clip(&config.build_folder, outro_clip, tts.as_ref())?;
+        merge_inputs.push(prepared);
+    }
+
+    tts.merge_audio_files(&merge_inputs, &final_audio_path)?;
+    file_logger.merge_complete(&filename, &final_audio_path.to_string_lossy());
+
+    if !tts.is_mp3_output() {
+        if config.audio.normalize {
+            let wav = fs::read(&final_audio_path)?;
+            let normalized =
+                crate::utils::audio::normalize_wav_peak(&wav, config.audio.normalize_target_db)?;
+            fs::write(&final_audio_path, normalized)?;
+        }
+
+        match output_format {
+            AudioOutputFormat::Wav => {}
+            AudioOutputFormat::Mp3 => {
+                let wav = fs::read(&final_audio_path)?;
+                let mp3 = crate::utils::audio::encode_to_mp3(&wav, TRANSCODE_MP3_BITRATE_KBPS)?;
+                fs::write(&final_audio_path, mp3)?;
+            }
+            AudioOutputFormat::Ogg => {
+                let wav = fs::read(&final_audio_path)?;
+                let ogg = crate::utils::audio::encode_to_ogg(&wav, config.audio.ogg_quality)?;
+                fs::write(&final_audio_path, ogg)?;
+            }
+        }
+    }
+
+    if output_format == AudioOutputFormat::Mp3 {
+        let mut mp3 = fs::read(&final_audio_path)?;
+        let cover_jpeg = config
+            .book_metadata
+            .cover_image_path
+            .as_ref()
+            .map(fs::read)
+            .transpose()?;
+        crate::utils::audio::embed_id3_tags(
+            &mut mp3,
+            &chapter_title(filename),
+            &config.book_metadata.author,
+            &config.book_metadata.title,
+            infer_track_number(filename),
+            cover_jpeg.as_deref(),
+        )?;
+        fs::write(&final_audio_path, mp3)?;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(command_template) = &config.workflow.post_process_command {
+        let merged_path =
+            final_audio_path.with_extension(format!("merged.{}", output_format.extension()));
+        tokio_fs::rename(&final_audio_path, &merged_path).await?;
+
+        let input_str = merged_path.to_string_lossy().to_string();
+        let output_str = final_audio_path.to_string_lossy().to_string();
+        let vars = HashMap::from([
+            ("input", input_str.as_str()),
+            ("output", output_str.as_str()),
+        ]);
+        let rendered_command = crate::utils::template::render_template(command_template, &vars);
+
+        let status = if cfg!(windows) {
+            tokio::process::Command::new("cmd")
+                .args(["/C", &rendered_command])
+                .status()
+                .await
+        } else {
+            tokio::process::Command::new("sh")
+                .args(["-c", &rendered_command])
+                .status()
+                .await
+        }
+        .context("Failed to run post_process_command")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!(
+                "post_process_command exited with status {}; merged audio kept at {:?}",
+                status,
+                merged_path
+            ));
+        }
+        if !final_audio_path.exists() {
+            return Err(anyhow::anyhow!(
+                "post_process_command did not produce {:?}; merged audio kept at {:?}",
+                final_audio_path,
+                merged_path
+            ));
+        }
+
+        let _ = fs::remove_file(&merged_path);
+    }
+
+    if config.output.generate_subtitles {
+        let timings = crate::utils::subtitle::estimate_segment_timings(&indexed_audio_files)?;
+        let srt = crate::utils::subtitle::generate_srt(&segments, &timings);
+        let srt_path = Path::new(&config.output_folder)
+            .join(Path::new(&filename).with_extension("srt"));
+        fs::write(&srt_path, srt)?;
+    }
+
+    if let Err(e) = validate_output_audio(&final_audio_path, audio_files.len()) {
+        let _ = fs::remove_file(&final_audio_path);
+        return Err(e);
+    }
+
+    println!("Chapter complete: {:?}", final_audio_path);
+    file_logger.chapter_end(&filename, true, None);
+    Ok((resolved_mob_voices, chapter_usage))
+}
+
+/// Derives a human-readable chapter title from an input filename for the
+/// ID3 `TIT2` frame, stripping the extension and any leading numeric/`_`
+/// ordering prefix (e.g. `"0003_chapter_seven.txt"` -> `"chapter seven"`).
+fn chapter_title(filename: &str) -> String {
+    let stem = Path::new(filename)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| filename.to_string());
+    let trimmed = stem.trim_start_matches(|c: char| c.is_ascii_digit() || c == '_');
+    let title = if trimmed.is_empty() { &stem } else { trimmed };
+    title.replace('_', " ")
+}
+
+/// Extracts the first run of digits in `filename` as a track number, for
+/// the ID3 `TRCK` frame. Falls back to `1` when the filename has none.
+fn infer_track_number(filename: &str) -> u32 {
+    let digits: String = filename
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().unwrap_or(1)
+}
+
+/// Builds the human-readable summary written to `dry_run_report.txt`:
+/// total segment/character counts and a per-speaker breakdown, so a user
+/// can review the script before spending TTS quota on it.
+fn build_dry_run_report(filename: &str, segments: &[AudioSegment]) -> String {
+    let total_chars: usize = segments.iter().map(|s| s.text.chars().count()).sum();
+
+    let mut by_speaker: HashMap<String, usize> = HashMap::new();
+    for segment in segments {
+        let speaker = segment.speaker.clone().unwrap_or_else(|| "旁白".to_string());
+        *by_speaker.entry(speaker).or_default() += 1;
+    }
+    let mut speakers: Vec<(String, usize)> = by_speaker.into_iter().collect();
+    speakers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut report = format!(
+        "Dry run report for {}\n\
+         Total segments: {}\n\
+         Total characters: {}\n\
+         \n\
+         Speaker distribution:\n",
+        filename,
+        segments.len(),
+        total_chars,
+    );
+    for (speaker, count) in speakers {
+        report.push_str(&format!("  {}: {} segment(s)\n", speaker, count));
+    }
+    report
+}
+
+/// Copies `clip_path` (from `AudioConfig::intro_clip`/`outro_clip`) into
+/// `build_folder/clips`, converting WAV to MP3 via `utils::audio::encode_to_mp3`
+/// when the chapter's provider outputs MP3 (see `TtsClient::is_mp3_output`)
+/// but the clip itself is WAV. The cached file is keyed on the clip's file
+/// stem plus the target extension, so it's only copied/converted once per
+/// run and every chapter after the first reuses it. There's no MP3 decoder
+/// in this crate, so an MP3 clip paired with a WAV-based provider is an
+/// honest error rather than a silent pass-through.
+fn prepare_clip(build_folder: &str, clip_path: &str, tts: &dyn TtsClient) -> Result<PathBuf> {
+    let clips_dir = Path::new(build_folder).join("clips");
+    fs::create_dir_all(&clips_dir)?;
+
+    let stem = Path::new(clip_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "clip".to_string());
+    let ext = if tts.is_mp3_output() { "mp3" } else { "wav" };
+    let cached_path = clips_dir.join(format!("{}.{}", stem, ext));
+
+    if cached_path.exists() {
+        return Ok(cached_path);
+    }
+
+    let data =
+        fs::read(clip_path).with_context(|| format!("Failed to read clip {:?}", clip_path))?;
+    let is_wav = data.starts_with(b"RIFF");
+
+    let converted = match (is_wav, tts.is_mp3_output()) {
+        (true, true) => crate::utils::audio::encode_to_mp3(&data, TRANSCODE_MP3_BITRATE_KBPS)?,
+        (false, false) => {
+            return Err(anyhow::anyhow!(
+                "Clip {:?} is MP3 but the configured provider outputs WAV; \
+                 this crate has no MP3 decoder to convert it",
+                clip_path
+            ));
+        }
+        _ => data,
+    };
+
+    fs::write(&cached_path, converted)?;
+    Ok(cached_path)
+}
+
+/// Inserts generated silence clips between `indexed_audio_files` before
+/// merging: `between_speakers_ms` when consecutive segments have different
+/// speakers, `between_segments_ms` otherwise. The silence clip's format is
+/// matched to the first real chunk's WAV header, so it stays compatible
+/// with `merge_wav_files`'s format check. Silence files are written to
+/// `chapter_build_dir` and, like normal chunks, are left on disk afterward.
+fn intersperse_silence(
+    chapter_build_dir: &Path,
+    segments: &[AudioSegment],
+    indexed_audio_files: &[(usize, PathBuf)],
+    silence: &crate::core::config::SilenceConfig,
+) -> Result<Vec<PathBuf>> {
+    if indexed_audio_files.len() < 2
+        || (silence.between_segments_ms == 0 && silence.between_speakers_ms == 0)
+    {
+        return Ok(indexed_audio_files.iter().map(|(_, p)| p.clone()).collect());
+    }
+
+    let (sample_rate, channels) = crate::utils::audio::wav_format(&indexed_audio_files[0].1)?;
+
+    let mut result = Vec::with_capacity(indexed_audio_files.len() * 2 - 1);
+    let mut prev_speaker: Option<&Option<String>> = None;
+
+    for (gap_index, (i, path)) in indexed_audio_files.iter().enumerate() {
+        if let Some(prev) = prev_speaker {
+            let gap_ms = if prev == &segments[*i].speaker {
+                silence.between_segments_ms
+            } else {
+                silence.between_speakers_ms
+            };
+            if gap_ms > 0 {
+                let silence_path = chapter_build_dir.join(format!("silence_{:04}.wav", gap_index));
+                fs::write(
+                    &silence_path,
+                    crate::utils::audio::generate_silence_wav(gap_ms, sample_rate, channels),
+                )?;
+                result.push(silence_path);
+            }
+        }
+        result.push(path.clone());
+        prev_speaker = Some(&segments[*i].speaker);
+    }
+
+    Ok(result)
+}
+
+/// Inserts silence MP3 clips between `mp3_paths` (and, if
+/// `output.before_first_chapter_ms` is set, before the first one) for
+/// `combine_chapters`. Each clip's sample rate matches the chapter
+/// immediately before it, read from its MP3 frame header via
+/// `utils::audio::mp3_sample_rate`; channels are assumed stereo, since an
+/// MP3 frame header doesn't expose channel count the way a WAV `fmt` chunk
+/// does. Gap clips are written to `output_dir` and, like the per-segment
+/// silence clips in `intersperse_silence`, left on disk afterward.
+fn insert_chapter_gaps(
+    output_dir: &Path,
+    mp3_paths: &[PathBuf],
+    output: &OutputConfig,
+) -> Result<Vec<PathBuf>> {
+    const GAP_CHANNELS: u16 = 2;
+
+    if mp3_paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut gap_index = 0;
+    let mut write_gap = |duration_ms: u32, sample_rate: u32| -> Result<PathBuf> {
+        let gap_path = output_dir.join(format!("combine_gap_{:04}.mp3", gap_index));
+        gap_index += 1;
+        let wav = crate::utils::audio::generate_silence_wav(duration_ms, sample_rate, GAP_CHANNELS);
+        let mp3 = crate::utils::audio::encode_to_mp3(&wav, TRANSCODE_MP3_BITRATE_KBPS)?;
+        fs::write(&gap_path, mp3)?;
+        Ok(gap_path)
+    };
+
+    let mut result = Vec::with_capacity(mp3_paths.len() * 2);
+    for (i, path) in mp3_paths.iter().enumerate() {
+        let sample_rate = crate::utils::audio::mp3_sample_rate(&fs::read(path)?)
+            .with_context(|| format!("Failed to read MP3 sample rate of {:?}", path))?;
+
+        if i == 0 && output.before_first_chapter_ms > 0 {
+            result.push(write_gap(output.before_first_chapter_ms, sample_rate)?);
+        }
+
+        result.push(path.clone());
+
+        if i + 1 < mp3_paths.len() && output.chapter_gap_ms > 0 {
+            result.push(write_gap(output.chapter_gap_ms, sample_rate)?);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Sanity-checks a merged chapter output before marking the chapter
+/// complete. A corrupt or truncated merge (e.g. from a crash mid-write)
+/// tends to produce an output far shorter than `expected_segments` would
+/// justify at a minimum of 100ms per segment.
+fn validate_output_audio(path: &Path, expected_segments: usize) -> Result<()> {
+    let duration = crate::utils::audio::audio_duration_secs(path)
+        .with_context(|| format!("Failed to read duration of {:?}", path))?;
+    let min_expected = expected_segments as f64 * 0.1;
+
+    if duration < min_expected {
+        return Err(anyhow::anyhow!(
+            "Output audio {:?} is only {:.2}s long, expected at least {:.2}s for {} segments",
+            path,
+            duration,
+            min_expected,
+            expected_segments
+        ));
+    }
+
+    if duration < min_expected * 2.0 {
+        warn!(
+            "Output audio {:?} duration ({:.2}s) seems short for {} segments",
+            path, duration, expected_segments
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::script::JsonScriptGenerator;
+    use async_trait::async_trait;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_strip_code_blocks() {
+        assert_eq!(strip_code_blocks("json"), "json");
+        assert_eq!(strip_code_blocks("```json\n{}\n```"), "{}");
+        assert_eq!(strip_code_blocks("```\n{}\n```"), "{}");
+        assert_eq!(strip_code_blocks("  ```json  \n  {}  \n  ```  "), "{}");
+    }
+
+    #[test]
+    fn test_sort_chapters_lexicographic_misorders_unpadded_numbers() {
+        let mut entries = vec![
+            "Chapter1.txt".to_string(),
+            "Chapter10.txt".to_string(),
+            "Chapter2.txt".to_string(),
+        ];
+        sort_chapters(&mut entries, &crate::core::config::ChapterSort::Lexicographic);
+        assert_eq!(entries, vec!["Chapter1.txt", "Chapter10.txt", "Chapter2.txt"]);
+    }
+
+    #[test]
+    fn test_sort_chapters_natural_numeric_orders_unpadded_numbers_correctly() {
+        let mut entries = vec![
+            "Chapter10.txt".to_string(),
+            "Chapter1.txt".to_string(),
+            "Chapter2.txt".to_string(),
+        ];
+        sort_chapters(&mut entries, &crate::core::config::ChapterSort::NaturalNumeric);
+        assert_eq!(entries, vec!["Chapter1.txt", "Chapter2.txt", "Chapter10.txt"]);
+    }
+
+    #[test]
+    fn test_sort_chapters_manual_uses_explicit_order_then_appends_rest() {
+        let mut entries = vec![
+            "b.txt".to_string(),
+            "a.txt".to_string(),
+            "extra.txt".to_string(),
+        ];
+        sort_chapters(
+            &mut entries,
+            &crate::core::config::ChapterSort::Manual(vec!["a.txt".to_string(), "b.txt".to_string()]),
+        );
+        assert_eq!(entries, vec!["a.txt", "b.txt", "extra.txt"]);
+    }
+
+    #[test]
+    fn test_merge_character_assignments_updates_known_characters_only() {
+        use crate::core::state::CharacterInfo;
+
+        let mut char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: HashMap::from([
+                (
+                    "Hero".to_string(),
+                    CharacterInfo {
+                        voice_id: None,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "Villain".to_string(),
+                    CharacterInfo {
+                        voice_id: Some("old-voice".to_string()),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        };
+
+        let assignments = HashMap::from([
+            ("Hero".to_string(), "voice-a".to_string()),
+            ("Villain".to_string(), "voice-b".to_string()),
+            ("Unknown Character".to_string(), "voice-c".to_string()),
+        ]);
+
+        let mut updated = merge_character_assignments(&mut char_map, &assignments);
+        updated.sort();
+
+        assert_eq!(updated, vec!["Hero".to_string(), "Villain".to_string()]);
+        assert_eq!(
+            char_map.characters["Hero"].voice_id,
+            Some("voice-a".to_string())
+        );
+        assert_eq!(
+            char_map.characters["Villain"].voice_id,
+            Some("voice-b".to_string())
+        );
+        assert!(!char_map.characters.contains_key("Unknown Character"));
+    }
+
+    fn write_segments_json(chapter_dir: &Path, speakers: &[&str]) -> Result<()> {
+        fs::create_dir_all(chapter_dir)?;
+        let segments: Vec<AudioSegment> = speakers
+            .iter()
+            .map(|speaker| AudioSegment {
+                speaker: Some(speaker.to_string()),
+                text: "Line".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            })
+            .collect();
+        fs::write(chapter_dir.join("segments.json"), serde_json::to_string(&segments)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_protagonist_flags_character_above_60_percent_threshold() -> Result<()> {
+        use crate::core::state::CharacterInfo;
+
+        let build_dir = tempfile::tempdir()?;
+
+        write_segments_json(&build_dir.path().join("chapter_1_txt"), &["Hero", "旁白"])?;
+        write_segments_json(&build_dir.path().join("chapter_2_txt"), &["Hero", "Villain"])?;
+        write_segments_json(&build_dir.path().join("chapter_3_txt"), &["Hero"])?;
+        write_segments_json(&build_dir.path().join("chapter_4_txt"), &["Villain"])?;
+
+        let mut char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: HashMap::from([
+                ("Hero".to_string(), CharacterInfo::default()),
+                ("Villain".to_string(), CharacterInfo::default()),
+            ]),
+        };
+
+        // Hero appears in 3/4 chapters (75%, above the 60% threshold);
+        // Villain appears in 2/4 (50%, below it).
+        let inferred = infer_protagonist(&mut char_map, &build_dir.path().to_string_lossy())?;
+
+        assert_eq!(inferred, Some("Hero".to_string()));
+        assert!(char_map.characters["Hero"].is_protagonist);
+        assert!(!char_map.characters["Villain"].is_protagonist);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_protagonist_noop_when_already_flagged() -> Result<()> {
+        use crate::core::state::CharacterInfo;
+
+        let build_dir = tempfile::tempdir()?;
+        write_segments_json(&build_dir.path().join("chapter_1_txt"), &["Hero"])?;
+
+        let mut char_map = CharacterMap {
+            schema_version: crate::core::state::CURRENT_CHARACTER_MAP_SCHEMA_VERSION,
+            characters: HashMap::from([(
+                "Narrator".to_string(),
+                CharacterInfo {
+                    is_protagonist: true,
+                    ..Default::default()
+                },
+            )]),
+        };
+
+        let inferred = infer_protagonist(&mut char_map, &build_dir.path().to_string_lossy())?;
+
+        assert_eq!(inferred, None);
+        assert!(!char_map.characters.contains_key("Hero"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_narrator_voice_id_picks_per_language_override() {
+        let tts = MockTtsClient { should_fail: false };
+        let mut audio = AudioConfig::default();
+        audio.narrator_voices.insert("en".to_string(), "en-US-JennyNeural".to_string());
+        audio.narrator_voices.insert("zh".to_string(), "zh-TW-HsiaoChenNeural".to_string());
+
+        assert_eq!(
+            resolve_narrator_voice_id(&tts, &audio, Some("en")),
+            "en-US-JennyNeural"
+        );
+        assert_eq!(
+            resolve_narrator_voice_id(&tts, &audio, Some("zh")),
+            "zh-TW-HsiaoChenNeural"
+        );
+    }
+
+    #[test]
+    fn test_resolve_narrator_voice_id_falls_back_without_matching_override() {
+        let tts = MockTtsClient { should_fail: false };
+        let mut audio = AudioConfig::default();
+        audio.narrator_voices.insert("en".to_string(), "en-US-JennyNeural".to_string());
+
+        assert_eq!(resolve_narrator_voice_id(&tts, &audio, Some("ja")), "mock_narrator");
+        assert_eq!(resolve_narrator_voice_id(&tts, &audio, None), "mock_narrator");
+    }
+
+    #[test]
+    fn test_export_then_import_project_restores_identical_content() -> Result<()> {
+        let project_root = tempfile::tempdir()?;
+        let export_build_dir = tempfile::tempdir()?;
+        let archive_dir = tempfile::tempdir()?;
+        let archive_path = archive_dir.path().join("project.zip");
+
+        let config_yml = "llm:\n  provider: mock\naudio:\n  provider: mock\n";
+        fs::write(project_root.path().join("config.yml"), config_yml)?;
+        fs::write(
+            export_build_dir.path().join("character_map.json"),
+            r#"{"schema_version":1,"characters":{}}"#,
+        )?;
+        fs::write(export_build_dir.path().join("state.json"), r#"{"completed_chapters":[]}"#)?;
+        write_segments_json(&export_build_dir.path().join("chapter_1_txt"), &["Hero", "旁白"])?;
+
+        write_project_archive(
+            project_root.path(),
+            &export_build_dir.path().to_string_lossy(),
+            &archive_path,
+        )?;
+
+        let import_build_dir = tempfile::tempdir()?;
+        let config = import_project(
+            &archive_path.to_string_lossy(),
+            &import_build_dir.path().to_string_lossy(),
+        )?;
+
+        assert_eq!(config.llm.provider, "mock");
+        assert_eq!(config.audio.provider, "mock");
+        assert_eq!(
+            fs::read_to_string(import_build_dir.path().join("character_map.json"))?,
+            fs::read_to_string(export_build_dir.path().join("character_map.json"))?,
+        );
+        assert_eq!(
+            fs::read_to_string(import_build_dir.path().join("state.json"))?,
+            fs::read_to_string(export_build_dir.path().join("state.json"))?,
+        );
+        assert_eq!(
+            fs::read_to_string(import_build_dir.path().join("chapter_1_txt").join("segments.json"))?,
+            fs::read_to_string(export_build_dir.path().join("chapter_1_txt").join("segments.json"))?,
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_project_rejects_archive_missing_required_entries() -> Result<()> {
+        use std::io::Write as _;
+
+        let archive_dir = tempfile::tempdir()?;
+        let archive_path = archive_dir.path().join("incomplete.zip");
+        let file = fs::File::create(&archive_path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("config.yml", options)?;
+        zip.write_all(b"llm:\n  provider: mock\n")?;
+        zip.finish()?;
+
+        let build_dir = tempfile::tempdir()?;
+        let result = import_project(&archive_path.to_string_lossy(), &build_dir.path().to_string_lossy());
+
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_project_rejects_path_traversal_entries() -> Result<()> {
+        use std::io::Write as _;
+
+        let archive_dir = tempfile::tempdir()?;
+        let escape_target = archive_dir.path().join("escaped.json");
+        let archive_path = archive_dir.path().join("malicious.zip");
+        let file = fs::File::create(&archive_path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("config.yml", options)?;
+        zip.write_all(b"llm:\n  provider: mock\n")?;
+        zip.start_file("build/character_map.json", options)?;
+        zip.write_all(br#"{"schema_version":1,"characters":{}}"#)?;
+        zip.start_file("build/state.json", options)?;
+        zip.write_all(br#"{"completed_chapters":[]}"#)?;
+        zip.start_file("build/../../escaped.json/segments.json", options)?;
+        zip.write_all(b"[]")?;
+        zip.finish()?;
+
+        let build_dir = tempfile::tempdir()?;
+        let result = import_project(&archive_path.to_string_lossy(), &build_dir.path().to_string_lossy());
+
+        assert!(result.is_err());
+        assert!(!escape_target.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_segment_timings_accumulates_offsets_from_mp3_durations() -> Result<()> {
+        // 4 back-to-back MPEG1 Layer III frames at 44100Hz/128kbps = 4 * 1152
+        // samples ≈ 104ms.
+        const FRAME_SIZE: usize = 417; // 144 * 128000 / 44100
+        let make_mp3 = |frame_count: usize| -> Vec<u8> {
+            let mut buf = Vec::new();
+            for _ in 0..frame_count {
+                buf.extend_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+                buf.resize(buf.len() + FRAME_SIZE - 4, 0);
+            }
+            buf
+        };
+
+        let temp_dir = tempfile::tempdir()?;
+        let path0 = temp_dir.path().join("chunk_0000.mp3");
+        let path1 = temp_dir.path().join("chunk_0001.mp3");
+        fs::write(&path0, make_mp3(4))?;
+        fs::write(&path1, make_mp3(8))?;
+
+        let segments = vec![
+            AudioSegment {
+                text: "Hello".to_string(),
+                speaker: Some("Alice".to_string()),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                text: "World".to_string(),
+                speaker: Some("Bob".to_string()),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+        let indexed_audio_files = vec![(0, path0), (1, path1)];
+
+        let timings = build_segment_timings(&segments, &indexed_audio_files, true)?;
+
+        let expected_0_ms = (4.0 * 1152.0 / 44100.0 * 1000.0).round() as u64;
+        let expected_1_ms = (8.0 * 1152.0 / 44100.0 * 1000.0).round() as u64;
+
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].index, 0);
+        assert_eq!(timings[0].start_ms, 0);
+        assert_eq!(timings[0].end_ms, expected_0_ms);
+        assert_eq!(timings[0].speaker, "Alice");
+        assert_eq!(timings[1].index, 1);
+        assert_eq!(timings[1].start_ms, expected_0_ms);
+        assert_eq!(timings[1].end_ms, expected_0_ms + expected_1_ms);
+        assert_eq!(timings[1].speaker, "Bob");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup_temp_files_removes_leftover_tmp_recursively() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let dir = temp_dir.path();
+        let chapter_dir = dir.join("chapter_1_txt");
+        fs::create_dir_all(&chapter_dir)?;
+
+        let leftover_tmp = chapter_dir.join("chunk_0000.mp3.tmp");
+        fs::write(&leftover_tmp, b"partial")?;
+        let finished_chunk = chapter_dir.join("chunk_0001.mp3");
+        fs::write(&finished_chunk, b"done")?;
+
+        WorkflowManager::cleanup_temp_files(dir)?;
+
+        assert!(!leftover_tmp.exists(), "leftover .tmp file should be removed");
+        assert!(finished_chunk.exists(), "completed chunk should be left alone");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_llm_usage_tracker_accumulates_across_calls() {
+        let mut tracker = LlmUsageTracker::default();
+        tracker.add(TokenUsage {
+            prompt_tokens: 100,
+            completion_tokens: 50,
+        });
+        tracker.add(TokenUsage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+        });
+        assert_eq!(tracker.prompt_tokens, 110);
+        assert_eq!(tracker.completion_tokens, 55);
+        assert_eq!(tracker.total_tokens(), 165);
+    }
+
+    #[derive(Debug)]
+    struct FixedUsageLlmClient {
+        usage: TokenUsage,
+    }
+
+    #[async_trait]
+    impl LlmClient for FixedUsageLlmClient {
+        async fn chat(&self, _system: &str, _user: &str) -> Result<String> {
+            Ok("{}".to_string())
+        }
+
+        fn last_usage(&self) -> Option<TokenUsage> {
+            Some(self.usage)
+        }
+    }
+
+    #[test]
+    fn test_record_llm_usage_accumulates_and_enforces_budget() {
+        let llm = FixedUsageLlmClient {
+            usage: TokenUsage {
+                prompt_tokens: 60,
+                completion_tokens: 40,
+            },
+        };
+        let mut chapter_usage = LlmUsageTracker::default();
+        let usage_tracker = std::sync::Mutex::new(LlmUsageTracker::default());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_logger = FileLogger::new(temp_dir.path().to_str().unwrap());
+
+        record_llm_usage(
+            &llm,
+            &mut chapter_usage,
+            &usage_tracker,
+            Some(150),
+            &file_logger,
+            "chapter1.txt",
+            "character_analysis",
+        )
+        .unwrap();
+        assert_eq!(chapter_usage.total_tokens(), 100);
+        assert_eq!(usage_tracker.lock().unwrap().total_tokens(), 100);
+
+        let result = record_llm_usage(
+            &llm,
+            &mut chapter_usage,
+            &usage_tracker,
+            Some(150),
+            &file_logger,
+            "chapter1.txt",
+            "character_analysis",
+        );
+        assert!(result.is_err(), "budget should be exceeded after the second call");
+        assert_eq!(chapter_usage.total_tokens(), 200);
+    }
+
+    // Mock LLM Client
+    #[derive(Debug)]
+    struct MockLlmClient {
+        call_count: Arc<Mutex<usize>>,
+    }
+
+    impl MockLlmClient {
+        fn new() -> Self {
+            Self {
+                call_count: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LlmClient for MockLlmClient {
+        async fn chat(&self, _system: &str, user: &str) -> Result<String> {
+            let mut count = self.call_count.lock().unwrap();
+            *count += 1;
+
+            if user.contains("請分析以下文本") {
+                return Ok(r#"{"characters": [{"name": "Hero", "gender": "Male"}]}"#.to_string());
+            } else if user.contains("請將以下小說文本分解為對話和旁白段落") {
+                return Ok(r#"[{"speaker": "旁白", "text": "Test audio"}]"#.to_string());
+            }
+
+            Ok("{}".to_string())
+        }
+    }
+
+    struct MockTtsClient {
+        should_fail: bool,
+    }
+
+    #[async_trait]
+    impl TtsClient for MockTtsClient {
+        async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+            Ok(vec![])
+        }
+        async fn check_voice_availability(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn synthesize(
+            &self,
+            _segment: &AudioSegment,
+            _map: &CharacterMap,
+            _excluded_voices: &[String],
+        ) -> Result<Vec<u8>> {
+            if self.should_fail {
+                Err(anyhow::anyhow!("Mock TTS error"))
+            } else {
+                Ok(vec![0u8; 4096])
+            }
+        }
+        async fn get_random_voice(
+            &self,
+            _gender: Option<&str>,
+            _excluded_voices: &[String],
+        ) -> Result<String> {
+            Ok("mock_voice_id".to_string())
+        }
+        fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+            "mock_narrator".to_string()
+        }
+        fn is_mob_enabled(&self) -> bool {
+            true
+        }
+        fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+            "mock voice list".to_string()
+        }
+        fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+            Box::new(JsonScriptGenerator::new())
+        }
+    }
+
+    /// Fails `fail_times` synthesis calls before succeeding, for exercising
+    /// the per-segment retry loop in `process_chapter`.
+    struct FlakyTtsClient {
+        fail_times: Arc<Mutex<usize>>,
+        calls: Arc<Mutex<usize>>,
+    }
+
+    impl FlakyTtsClient {
+        fn new(fail_times: usize) -> Self {
+            Self {
+                fail_times: Arc::new(Mutex::new(fail_times)),
+                calls: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TtsClient for FlakyTtsClient {
+        async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+            Ok(vec![])
+        }
+        async fn check_voice_availability(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn synthesize(
+            &self,
+            _segment: &AudioSegment,
+            _map: &CharacterMap,
+            _excluded_voices: &[String],
+        ) -> Result<Vec<u8>> {
+            *self.calls.lock().unwrap() += 1;
+            let mut remaining = self.fail_times.lock().unwrap();
+            if *remaining > 0 {
+                *remaining -= 1;
+                Err(anyhow::anyhow!("Mock transient TTS error"))
+            } else {
+                Ok(vec![0u8; 4096])
+            }
+        }
+        async fn get_random_voice(
+            &self,
+            _gender: Option<&str>,
+            _excluded_voices: &[String],
+        ) -> Result<String> {
+            Ok("mock_voice_id".to_string())
+        }
+        fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+            "mock_narrator".to_string()
+        }
+        fn is_mob_enabled(&self) -> bool {
+            true
+        }
+        fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+            "mock voice list".to_string()
+        }
+        fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+            Box::new(JsonScriptGenerator::new())
+        }
+    }
+
+    /// Delays its very first `synthesize` call by `first_call_delay_ms`, so
+    /// whichever chapter starts first is guaranteed to finish last. Used to
+    /// exercise `run_parallel`'s completion-order independence.
+    struct OrderVaryingTtsClient {
+        first_call_delay_ms: u64,
+        first_call_taken: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl OrderVaryingTtsClient {
+        fn new(first_call_delay_ms: u64) -> Self {
+            Self {
+                first_call_delay_ms,
+                first_call_taken: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TtsClient for OrderVaryingTtsClient {
+        async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+            Ok(vec![])
+        }
+        async fn check_voice_availability(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn synthesize(
+            &self,
+            _segment: &AudioSegment,
+            _map: &CharacterMap,
+            _excluded_voices: &[String],
+        ) -> Result<Vec<u8>> {
+            let is_first = self
+                .first_call_taken
+                .compare_exchange(
+                    false,
+                    true,
+                    std::sync::atomic::Ordering::SeqCst,
+                    std::sync::atomic::Ordering::SeqCst,
+                )
+                .is_ok();
+            if is_first {
+                tokio::time::sleep(tokio::time::Duration::from_millis(self.first_call_delay_ms))
+                    .await;
+            }
+            Ok(vec![0u8; 4096])
+        }
+        async fn get_random_voice(
+            &self,
+            _gender: Option<&str>,
+            _excluded_voices: &[String],
+        ) -> Result<String> {
+            Ok("mock_voice_id".to_string())
+        }
+        fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+            "mock_narrator".to_string()
+        }
+        fn is_mob_enabled(&self) -> bool {
+            true
+        }
+        fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+            "mock voice list".to_string()
+        }
+        fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+            Box::new(JsonScriptGenerator::new())
+        }
+    }
+
+    /// A WAV-based mock, standing in for providers like GPT-SoVITS/Qwen3
+    /// that return raw PCM and merge via `merge_wav_files`.
+    struct WavTtsClient;
+
+    fn dummy_wav_segment(ms: u32, sample_rate: u32) -> Vec<u8> {
+        crate::utils::audio::generate_silence_wav(ms, sample_rate, 1)
+    }
+
+    #[async_trait]
+    impl TtsClient for WavTtsClient {
+        async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+            Ok(vec![])
+        }
+        async fn check_voice_availability(&self) -> Result<()> {
+            Ok(())
+        }
+        async fn synthesize(
+            &self,
+            _segment: &AudioSegment,
+            _map: &CharacterMap,
+            _excluded_voices: &[String],
+        ) -> Result<Vec<u8>> {
+            Ok(dummy_wav_segment(100, 16000))
+        }
+        async fn get_random_voice(
+            &self,
+            _gender: Option<&str>,
+            _excluded_voices: &[String],
+        ) -> Result<String> {
+            Ok("mock_voice_id".to_string())
+        }
+        fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+            "mock_narrator".to_string()
+        }
+        fn is_mob_enabled(&self) -> bool {
+            true
+        }
+        fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+            "mock voice list".to_string()
+        }
+        fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+            Box::new(JsonScriptGenerator::new())
+        }
+        fn is_mp3_output(&self) -> bool {
+            false
+        }
+        fn merge_audio_files(
+            &self,
+            inputs: &[std::path::PathBuf],
+            output: &std::path::Path,
+        ) -> Result<()> {
+            crate::utils::audio::merge_wav_files(inputs, output)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_silence_inserted_between_segments_for_wav_providers() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "gpt_sovits".to_string(),
+                output_format: crate::core::config::AudioOutputFormat::Wav,
+                silence: crate::core::config::SilenceConfig {
+                    between_segments_ms: 200,
+                    between_speakers_ms: 500,
+                    paragraph_ms: 0,
+                },
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_silence.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        // Two cached segments with the same speaker ("旁白"), so the gap
+        // should use `between_segments_ms`.
+        let chapter_build_dir = build_dir.join("chapter_silence_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let segments = vec![
+            AudioSegment {
+                speaker: Some("旁白".to_string()),
+                text: "First line".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                speaker: Some("旁白".to_string()),
+                text: "Second line".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+        fs::write(&segments_path, serde_json::to_string(&segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(WavTtsClient);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let output_path = output_dir.join("chapter_silence.wav");
+        assert!(output_path.exists());
+
+        // 2 segments @ 100ms + 1 gap @ 200ms (same speaker) = 400ms merged.
+        let duration = crate::utils::audio::audio_duration_secs(&output_path)?;
+        assert!(
+            (duration - 0.4).abs() < 0.01,
+            "merged duration was {}",
+            duration
+        );
+
+        assert!(
+            chapter_build_dir.join("silence_0001.wav").exists(),
+            "Silence clip should be written to the chapter build dir"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_intro_clip_prepended_to_merged_chapter_audio() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        // A 20ms full-amplitude clip, distinguishable from the silent
+        // segments `WavTtsClient` returns, so its samples can be located in
+        // the merged output.
+        let mut intro_clip = crate::utils::audio::generate_silence_wav(20, 16000, 1);
+        let intro_len = intro_clip.len();
+        intro_clip[44..intro_len].fill(0x7f);
+        let intro_path = test_root.join("intro.wav");
+        fs::write(&intro_path, &intro_clip)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "gpt_sovits".to_string(),
+                output_format: crate::core::config::AudioOutputFormat::Wav,
+                intro_clip: Some(intro_path.to_string_lossy().to_string()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_intro.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_intro_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let segments = vec![AudioSegment {
+            speaker: Some("旁白".to_string()),
+            text: "Only line".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(WavTtsClient);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow
+            .process_chapter(&chapter_path, filename, 1, 1)
+            .await?;
+
+        let output_path = output_dir.join("chapter_intro.wav");
+        let merged = fs::read(&output_path)?;
+
+        // The merged file's audio data should begin with the intro clip's
+        // exact samples, confirming it was prepended rather than merely
+        // present somewhere in the output.
+        assert_eq!(&merged[44..intro_len], &intro_clip[44..intro_len]);
+
+        // Cached under build_folder/clips so a later chapter can reuse it
+        // without re-reading/re-converting the source file.
+        assert!(build_dir.join("clips").join("intro.wav").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_voice_stats_accumulate_across_chapters_and_persist() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "gpt_sovits".to_string(),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        // Chapter 1: "Hero" (voice-a) speaks twice, narrator (voice-n) once.
+        let filename1 = "chapter_one.txt";
+        let chapter_path1 = input_dir.join(filename1);
+        fs::write(&chapter_path1, "Some story text.")?;
+        let chapter_build_dir1 = build_dir.join("chapter_one_txt");
+        fs::create_dir_all(&chapter_build_dir1)?;
+        fs::write(
+            chapter_build_dir1.join("segments.json"),
+            serde_json::to_string(&vec![
+                AudioSegment {
+                    speaker: Some("Hero".to_string()),
+                    text: "Hi".to_string(),
+                    style: None,
+                    voice_id: Some("voice-a".to_string()),
+                    detected_language: None,
+                    confidence: Some(1.0),
+                },
+                AudioSegment {
+                    speaker: Some("Hero".to_string()),
+                    text: "There".to_string(),
+                    style: None,
+                    voice_id: Some("voice-a".to_string()),
+                    detected_language: None,
+                    confidence: Some(1.0),
+                },
+                AudioSegment {
+                    speaker: Some("旁白".to_string()),
+                    text: "Narration".to_string(),
+                    style: None,
+                    voice_id: Some("voice-n".to_string()),
+                    detected_language: None,
+                    confidence: Some(1.0),
+                },
+            ])?,
+        )?;
+
+        // Chapter 2: "Hero" (voice-a) speaks once more, plus a new
+        // character "Villain" (voice-b).
+        let filename2 = "chapter_two.txt";
+        let chapter_path2 = input_dir.join(filename2);
+        fs::write(&chapter_path2, "Some more story text.")?;
+        let chapter_build_dir2 = build_dir.join("chapter_two_txt");
+        fs::create_dir_all(&chapter_build_dir2)?;
+        fs::write(
+            chapter_build_dir2.join("segments.json"),
+            serde_json::to_string(&vec![
+                AudioSegment {
+                    speaker: Some("Hero".to_string()),
+                    text: "Again".to_string(),
+                    style: None,
+                    voice_id: Some("voice-a".to_string()),
+                    detected_language: None,
+                    confidence: Some(1.0),
+                },
+                AudioSegment {
+                    speaker: Some("Villain".to_string()),
+                    text: "Never!".to_string(),
+                    style: None,
+                    voice_id: Some("voice-b".to_string()),
+                    detected_language: None,
+                    confidence: Some(1.0),
+                },
+            ])?,
+        )?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(WavTtsClient);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow
+            .process_chapter(&chapter_path1, filename1, 1, 2)
+            .await?;
+        workflow
+            .process_chapter(&chapter_path2, filename2, 2, 2)
+            .await?;
+        workflow.write_voice_stats()?;
+
+        let stats_path = build_dir.join("voice_stats.json");
+        let report: VoiceStatsReport =
+            serde_json::from_str(&fs::read_to_string(&stats_path)?)?;
+
+        let hero = report
+            .stats
+            .iter()
+            .find(|s| s.voice_id == "voice-a" && s.character_name == "Hero")
+            .expect("Hero's stats should be recorded");
+        assert_eq!(hero.segment_count, 3);
+        assert_eq!(hero.estimated_chars, "Hi".chars().count() as u32
+            + "There".chars().count() as u32
+            + "Again".chars().count() as u32);
+
+        let narrator = report
+            .stats
+            .iter()
+            .find(|s| s.voice_id == "voice-n" && s.character_name == "旁白")
+            .expect("Narrator's stats should be recorded");
+        assert_eq!(narrator.segment_count, 1);
+
+        let villain = report
+            .stats
+            .iter()
+            .find(|s| s.voice_id == "voice-b" && s.character_name == "Villain")
+            .expect("Villain's stats should be recorded");
+        assert_eq!(villain.segment_count, 1);
+        assert_eq!(villain.estimated_chars, "Never!".chars().count() as u32);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_parallel_records_all_chapters_despite_out_of_order_completion() -> Result<()>
+    {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: true,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                parallel_chapters: 3,
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        // Chapter 0 is processed first but its synthesis is deliberately
+        // slow, so chapters 1 and 2 should finish and be recorded as
+        // completed before it does.
+        for i in 0..3 {
+            fs::write(
+                input_dir.join(format!("chapter_{}.txt", i)),
+                "Some story text.",
+            )?;
+        }
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(OrderVaryingTtsClient::new(200));
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.run().await?;
+
+        let mut completed = workflow.state.completed_chapters.clone();
+        completed.sort();
+        assert_eq!(
+            completed,
+            vec![
+                "chapter_0.txt".to_string(),
+                "chapter_1.txt".to_string(),
+                "chapter_2.txt".to_string(),
+            ],
+            "All chapters must be recorded as completed regardless of finish order"
+        );
+
+        let state_path = build_dir.join("state.json");
+        let persisted: crate::core::state::WorkflowState =
+            serde_json::from_str(&fs::read_to_string(state_path)?)?;
+        let mut persisted_chapters = persisted.completed_chapters;
+        persisted_chapters.sort();
+        assert_eq!(
+            persisted_chapters, completed,
+            "The persisted state file must match in-memory state"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_miss_generates_segments_file() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_1.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let call_count = mock_llm.call_count.clone();
+
+        let mock_tts = Box::new(MockTtsClient { should_fail: true });
+
+        let mut workflow = WorkflowManager::new(config.clone(), mock_llm, mock_tts).await?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(
+            result.is_err(),
+            "Expected synthesis failure due to mock error"
+        );
+
+        assert_eq!(
+            *call_count.lock().unwrap(),
+            2,
+            "Should call LLM twice (Analysis + Script)"
+        );
+
+        let segments_path = build_dir.join("chapter_1_txt").join("segments.json");
+        assert!(segments_path.exists(), "segments.json should be created");
+
+        let content = fs::read_to_string(segments_path)?;
+        assert!(content.contains("Test audio"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_generates_script_without_synthesizing_audio() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                dry_run: true,
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_1b.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let call_count = mock_llm.call_count.clone();
+
+        // If dry-run skipped the "no synthesize" branch, every call would
+        // fail and `process_chapter` would return an error instead of Ok.
+        let mock_tts = Box::new(MockTtsClient { should_fail: true });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(
+            result.is_ok(),
+            "Dry run should succeed without reaching synthesis: {:?}",
+            result.err()
+        );
+        assert_eq!(
+            *call_count.lock().unwrap(),
+            2,
+            "Should still run character analysis and script generation"
+        );
+
+        let chapter_build_dir = build_dir.join("chapter_1b_txt");
+        assert!(chapter_build_dir.join("segments.json").exists());
+
+        let report = fs::read_to_string(chapter_build_dir.join("dry_run_report.txt"))?;
+        assert!(report.contains("Total segments: 1"));
+        assert!(report.contains("Speaker distribution"));
+
+        assert!(
+            !chapter_build_dir.join("chunk_0000.mp3").exists(),
+            "Dry run must not synthesize any audio chunks"
+        );
+        assert!(
+            !output_dir.join("chapter_1b.mp3").exists(),
+            "Dry run must not produce a merged chapter output"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flattened_output_structure() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_flat.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        // Pre-populate segments to skip LLM
+        let chapter_build_dir = build_dir.join("chapter_flat_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Audio".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        // Check output
+        let output_file = output_dir.join("chapter_flat.mp3");
+        assert!(
+            output_file.exists(),
+            "Output file should exist at root of output folder"
+        );
+
+        let sub_dir = output_dir.join("chapter_flat_txt");
+        assert!(
+            !sub_dir.exists(),
+            "Subdirectory should NOT exist in output folder"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filename_template_renders_index_total_and_title() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: crate::core::config::BookMetadata {
+                title: "My/Book".to_string(),
+                ..Default::default()
+            },
+            output: crate::core::config::OutputConfig {
+                filename_template: "{title} - {index} of {total} - {stem}.{ext}".to_string(),
+                ..Default::default()
+            },
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_template.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        let chapter_build_dir = build_dir.join("chapter_template_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Audio".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow
+            .process_chapter(&chapter_path, filename, 3, 12)
+            .await?;
+
+        let output_file = output_dir.join("My-Book - 3 of 12 - chapter_template.mp3");
+        assert!(
+            output_file.exists(),
+            "Output file should be named from the rendered, sanitized template"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_process_command_replaces_merged_output() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                post_process_command: Some("cp {input} {output}".to_string()),
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_post.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        let chapter_build_dir = build_dir.join("chapter_post_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Audio".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let output_file = output_dir.join("chapter_post.mp3");
+        assert!(
+            output_file.exists(),
+            "post-process command should have produced the final output file"
+        );
+        assert!(
+            !fs::read(&output_file)?.is_empty(),
+            "post-processed output should carry through the merged audio content"
+        );
+
+        let merged_file = output_dir.join("chapter_post.merged.mp3");
+        assert!(
+            !merged_file.exists(),
+            "merged backup should be removed once the post-processed file is confirmed"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_subtitles_writes_srt_with_monotonic_timestamps() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: crate::core::config::OutputConfig {
+                generate_subtitles: true,
+                ..Default::default()
+            },
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_subs.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        // Pre-populate segments to skip LLM
+        let chapter_build_dir = build_dir.join("chapter_subs_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let cached_segments = vec![
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Hello there.".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "General Kenobi.".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let srt_path = output_dir.join("chapter_subs.srt");
+        let srt = fs::read_to_string(&srt_path).context("chapter_subs.srt should be written")?;
+
+        assert!(srt.contains("Hello there."));
+        assert!(srt.contains("General Kenobi."));
+
+        let timestamp_re = regex_free_parse_timestamps(&srt);
+        let mut last = 0.0;
+        for t in timestamp_re {
+            assert!(t >= last, "timestamps should be monotonically increasing");
+            last = t;
+        }
+
+        Ok(())
+    }
+
+    /// Pulls every `HH:MM:SS,mmm` timestamp out of an SRT string, in
+    /// order, as seconds. A tiny hand-rolled parser since this crate
+    /// doesn't otherwise depend on a regex engine.
+    fn regex_free_parse_timestamps(srt: &str) -> Vec<f64> {
+        let mut out = Vec::new();
+        for line in srt.lines() {
+            let Some((start, end)) = line.split_once(" --> ") else {
+                continue;
+            };
+            for part in [start, end] {
+                if let Some(secs) = parse_srt_timestamp(part) {
+                    out.push(secs);
+                }
+            }
+        }
+        out
+    }
+
+    fn parse_srt_timestamp(s: &str) -> Option<f64> {
+        let (hms, ms) = s.split_once(',')?;
+        let mut parts = hms.split(':');
+        let h: f64 = parts.next()?.parse().ok()?;
+        let m: f64 = parts.next()?.parse().ok()?;
+        let sec: f64 = parts.next()?.parse().ok()?;
+        let ms: f64 = ms.parse().ok()?;
+        Some(h * 3600.0 + m * 60.0 + sec + ms / 1000.0)
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        chapter_starts: Mutex<Vec<String>>,
+        segment_calls: Mutex<Vec<(usize, usize)>>,
+        chapter_completes: Mutex<Vec<String>>,
+        errors: Mutex<Vec<String>>,
+    }
+
+    impl WorkflowObserver for RecordingObserver {
+        fn on_chapter_start(&self, name: &str, _index: usize, _total: usize) {
+            self.chapter_starts.lock().unwrap().push(name.to_string());
+        }
+        fn on_segment_synthesized(&self, index: usize, total: usize) {
+            self.segment_calls.lock().unwrap().push((index, total));
+        }
+        fn on_chapter_complete(&self, name: &str) {
+            self.chapter_completes.lock().unwrap().push(name.to_string());
+        }
+        fn on_error(&self, name: &str, error: &str) {
+            self.errors.lock().unwrap().push(format!("{}: {}", name, error));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_observer_receives_chapter_and_segment_events() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: true,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_obs.txt";
+        fs::write(input_dir.join(filename), "Some story text.")?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+        let observer = Arc::new(RecordingObserver::default());
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts)
+            .await?
+            .with_observer(observer.clone());
+        workflow.run().await?;
+
+        assert_eq!(*observer.chapter_starts.lock().unwrap(), vec![filename]);
+        assert_eq!(*observer.chapter_completes.lock().unwrap(), vec![filename]);
+        assert!(observer.errors.lock().unwrap().is_empty());
+        assert!(
+            !observer.segment_calls.lock().unwrap().is_empty(),
+            "on_segment_synthesized should fire for the chapter's segment(s)"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_llm() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_2.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_2_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Cached audio".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let chunk_path = chapter_build_dir.join("chunk_0000.mp3");
+        fs::write(&chunk_path, vec![0u8; 4096])?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let call_count = mock_llm.call_count.clone();
+
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config.clone(), mock_llm, mock_tts).await?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(result.is_ok(), "Should complete successfully");
+
+        assert_eq!(
+            *call_count.lock().unwrap(),
+            0,
+            "Should use cache and NOT call LLM"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reset_chapter_forces_reprocessing_while_others_stay_skipped() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: true,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        for filename in ["chapter_reset_a.txt", "chapter_reset_b.txt"] {
+            fs::write(input_dir.join(filename), "Some story text.")?;
+
+            let chapter_build_dir = build_dir.join(filename.replace(".", "_"));
+            fs::create_dir_all(&chapter_build_dir)?;
+            let cached_segments = vec![AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Cached audio".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            }];
+            fs::write(
+                chapter_build_dir.join("segments.json"),
+                serde_json::to_string(&cached_segments)?,
+            )?;
+            fs::write(
+                chapter_build_dir.join("chunk_0000.mp3"),
+                vec![0u8; 4096],
+            )?;
+        }
+
+        fs::write(
+            build_dir.join("state.json"),
+            serde_json::to_string(&WorkflowState {
+                completed_chapters: vec![
+                    "chapter_reset_a.txt".to_string(),
+                    "chapter_reset_b.txt".to_string(),
+                ],
+                ..Default::default()
+            })?,
+        )?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let call_count = mock_llm.call_count.clone();
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.reset_chapter("chapter_reset_a.txt").await?;
+
+        let reset_build_dir = build_dir.join("chapter_reset_a_txt");
+        assert!(!reset_build_dir.join("segments.json").exists());
+        assert!(!reset_build_dir.join("chunk_0000.mp3").exists());
+        assert!(
+            !workflow
+                .state
+                .completed_chapters
+                .contains(&"chapter_reset_a.txt".to_string()),
+            "reset chapter must be cleared from completed_chapters"
+        );
+
+        workflow.run().await?;
+
+        assert_eq!(
+            *call_count.lock().unwrap(),
+            2,
+            "Only the reset chapter should re-invoke the LLM (Analysis + Script)"
+        );
+
+        let mut completed = workflow.state.completed_chapters.clone();
+        completed.sort();
+        assert_eq!(
+            completed,
+            vec![
+                "chapter_reset_a.txt".to_string(),
+                "chapter_reset_b.txt".to_string(),
+            ],
+            "both chapters should be completed again after the reset chapter reruns"
+        );
+
+        let other_segments = fs::read_to_string(
+            build_dir.join("chapter_reset_b_txt").join("segments.json"),
+        )?;
+        assert!(
+            other_segments.contains("Cached audio"),
+            "the chapter that wasn't reset must keep its cached segments untouched"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_modified_chapter_invalidates_cached_segments() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                cache_validation: true,
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_2b.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Original story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_2b_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let segments_meta_path = chapter_build_dir.join("segments_meta.json");
+
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Stale cached audio".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+        fs::write(
+            &segments_meta_path,
+            serde_json::to_string(&SegmentsMeta {
+                chapter_hash: "stale-hash-from-before-the-edit".to_string(),
+            })?,
+        )?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let call_count = mock_llm.call_count.clone();
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config.clone(), mock_llm, mock_tts).await?;
+
+        // Edit the chapter's source text after the segments were cached.
+        fs::write(&chapter_path, "Edited story text that differs from the cache.")?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(result.is_ok(), "Should complete successfully: {:?}", result.err());
+        assert_eq!(
+            *call_count.lock().unwrap(),
+            2,
+            "Stale segments should be regenerated via the LLM (Analysis + Script)"
+        );
+
+        let regenerated: Vec<AudioSegment> =
+            serde_json::from_str(&fs::read_to_string(&segments_path)?)?;
+        assert_ne!(
+            regenerated, cached_segments,
+            "Stale cached segments should have been discarded and regenerated"
+        );
+
+        let meta: SegmentsMeta = serde_json::from_str(&fs::read_to_string(&segments_meta_path)?)?;
+        let expected_hash = format!(
+            "{:x}",
+            Sha256::digest(b"Edited story text that differs from the cache.")
+        );
+        assert_eq!(meta.chapter_hash, expected_hash);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_validation_disabled_keeps_stale_segments() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                cache_validation: false,
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_2c.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Original story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_2c_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let segments_meta_path = chapter_build_dir.join("segments_meta.json");
+
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Stale cached audio".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+        fs::write(
+            &segments_meta_path,
+            serde_json::to_string(&SegmentsMeta {
+                chapter_hash: "stale-hash-from-before-the-edit".to_string(),
+            })?,
+        )?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let call_count = mock_llm.call_count.clone();
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+
+        fs::write(&chapter_path, "Edited story text that differs from the cache.")?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(result.is_ok(), "Should complete successfully");
+        assert_eq!(
+            *call_count.lock().unwrap(),
+            0,
+            "Should still use the (stale) cache and NOT call the LLM"
+        );
+
+        let segments_after: Vec<AudioSegment> =
+            serde_json::from_str(&fs::read_to_string(&segments_path)?)?;
+        assert_eq!(
+            segments_after, cached_segments,
+            "With cache_validation disabled, stale segments must be kept"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_continue_on_error_skips_failed_segments() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                continue_on_error: true,
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_3.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_3_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+
+        // Two segments: the first already has a cached chunk, the second does
+        // not and will fail synthesis.
+        let cached_segments = vec![
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Already done".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Will fail".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let chunk_path = chapter_build_dir.join("chunk_0000.mp3");
+        fs::write(&chunk_path, vec![0u8; 4096])?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: true });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(
+            result.is_ok(),
+            "continue_on_error should let the chapter complete despite a failed segment: {:?}",
+            result.err()
+        );
+
+        // The cached chunk must not have been touched/re-synthesized.
+        assert_eq!(fs::read(&chunk_path)?, vec![0u8; 4096]);
+
+        let output_path = output_dir.join("chapter_3.mp3");
+        assert!(output_path.exists(), "Output should still be merged");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_stops_chapter_and_keeps_existing_chunks() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_4.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_4_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+
+        // The first segment is already synthesized; the second is not.
+        let cached_segments = vec![
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Already done".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Never reached".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let chunk_path = chapter_build_dir.join("chunk_0000.mp3");
+        fs::write(&chunk_path, vec![0u8; 4096])?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts)
+            .await?
+            .with_cancellation(cancellation);
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(
+            result.is_err(),
+            "A cancelled token should abort the chapter"
+        );
+        assert!(result.unwrap_err().to_string().contains("Cancelled"));
+
+        // The already-synthesized chunk is left untouched for a later retry.
+        assert_eq!(fs::read(&chunk_path)?, vec![0u8; 4096]);
+
+        let output_path = output_dir.join("chapter_4.mp3");
+        assert!(
+            !output_path.exists(),
+            "Cancelled chapter should not produce a merged output"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_segment_retry_succeeds_after_transient_failures() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                max_segment_retries: 3,
+                segment_retry_delay_secs: 0,
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_flaky.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_flaky_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Narrator".to_string()),
+            text: "Will fail twice then succeed".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let flaky_tts = FlakyTtsClient::new(2);
+        let calls = flaky_tts.calls.clone();
+        let mock_tts = Box::new(flaky_tts);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(
+            result.is_ok(),
+            "Should succeed once retries exhaust the transient failures: {:?}",
+            result.err()
+        );
+        assert_eq!(
+            *calls.lock().unwrap(),
+            3,
+            "Should call synthesize twice (failed) then once more (succeeded)"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_segment_retry_exhausts_and_fails_with_context() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                max_segment_retries: 1,
+                segment_retry_delay_secs: 0,
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_broken.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Some story text.")?;
+
+        let chapter_build_dir = build_dir.join("chapter_broken_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+
+        let cached_segments = vec![AudioSegment {
+            speaker: Some("Alice".to_string()),
+            text: "This segment will never synthesize successfully no matter what.".to_string(),
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let flaky_tts = FlakyTtsClient::new(usize::MAX);
+        let calls = flaky_tts.calls.clone();
+        let mock_tts = Box::new(flaky_tts);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+
+        let result = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        assert!(result.is_err(), "Should fail once retries are exhausted");
+        let err_msg = result.unwrap_err().to_string();
+        assert!(err_msg.contains("Segment 0"), "{}", err_msg);
+        assert!(err_msg.contains("Alice"), "{}", err_msg);
+        assert!(err_msg.contains("This segment will never"), "{}", err_msg);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            2,
+            "Should attempt the initial call plus 1 retry"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_global_voice_conflicts_finds_shared_voice() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+
+        {
+            let mut map = workflow.character_map.write().await;
+            map.characters.insert(
+                "Alice".to_string(),
+                CharacterInfo {
+                    gender: "Female".to_string(),
+                    voice_id: Some("Voice_A".to_string()),
+                    description: None,
+                    is_protagonist: false,
+                    ..Default::default()
+                },
+            );
+            map.characters.insert(
+                "Bob".to_string(),
+                CharacterInfo {
+                    gender: "Male".to_string(),
+                    voice_id: Some("Voice_A".to_string()),
+                    description: None,
+                    is_protagonist: false,
+                    ..Default::default()
+                },
+            );
+            map.characters.insert(
+                "Carol".to_string(),
+                CharacterInfo {
+                    gender: "Female".to_string(),
+                    voice_id: Some("Voice_B".to_string()),
+                    description: None,
+                    is_protagonist: false,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let conflicts = workflow.check_global_voice_conflicts().await;
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].voice_id, "Voice_A");
+        assert_eq!(conflicts[0].characters, vec!["Alice", "Bob"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_voice_filtering_in_analysis_prompt() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                language: "zh".to_string(),
+                exclude_locales: vec!["zh-HK".to_string()],
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_filter.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        // Setup Mock LLM to capture prompt
+        #[derive(Debug)]
+        struct CapturingLlmClient {
+            prompts: Arc<Mutex<Vec<String>>>,
+        }
+        #[async_trait]
+        impl LlmClient for CapturingLlmClient {
+            async fn chat(&self, _system: &str, user: &str) -> Result<String> {
+                self.prompts.lock().unwrap().push(user.to_string());
+                // Return valid JSON to proceed
+                Ok(r#"{"characters": []}"#.to_string())
+            }
+        }
+        let prompts_store = Arc::new(Mutex::new(Vec::new()));
+        let mock_llm = Box::new(CapturingLlmClient {
+            prompts: prompts_store.clone(),
+        });
+
+        // Setup Mock TTS with voices
+        struct MockTts {
+            voices: Vec<crate::services::tts::Voice>,
+        }
+        #[async_trait]
+        impl TtsClient for MockTts {
+            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+                Ok(self.voices.clone())
+            }
+            async fn synthesize(
+                &self,
+                _: &AudioSegment,
+                _: &CharacterMap,
+                _: &[String],
+            ) -> Result<Vec<u8>> {
+                Ok(vec![0u8; 4096])
+            }
+            async fn get_random_voice(&self, _: Option<&str>, _: &[String]) -> Result<String> {
+                Ok("mock".to_string())
+            }
+            fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+                "mock_narrator".to_string()
+            }
+            fn is_mob_enabled(&self) -> bool {
+                true
+            }
+            fn format_voice_list_for_analysis(&self, voices: &[crate::services::tts::Voice]) -> String {
+                // Return specific format to verify test expectations if needed, or just a mock
+                // The test checks if specific voice names are in the prompt.
+                // The `format_voice_list_for_analysis` should return string containing voice names.
+                voices
+                    .iter()
+                    .map(|v| v.short_name.clone())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+                Box::new(JsonScriptGenerator::new())
+            }
+        }
+
+        let voices = vec![
+            crate::services::tts::Voice {
+                short_name: "zh-TW-A".to_string(),
+                gender: "Male".to_string(),
+                locale: "zh-TW".to_string(),
+                name: "A".to_string(),
+                friendly_name: None,
+            },
+            crate::services::tts::Voice {
+                short_name: "zh-HK-B".to_string(),
+                gender: "Female".to_string(),
+                locale: "zh-HK".to_string(),
+                name: "B".to_string(),
+                friendly_name: None,
+            },
+            crate::services::tts::Voice {
+                short_name: "zh-CN-C".to_string(),
+                gender: "Male".to_string(),
+                locale: "zh-CN".to_string(),
+                name: "C".to_string(),
+                friendly_name: None,
+            },
+        ];
+        let mock_tts = Box::new(MockTts { voices });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        let _ = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        let prompts = prompts_store.lock().unwrap();
+        let analysis_prompt = &prompts[0];
+
+        // Assertions
+        assert!(analysis_prompt.contains("zh-TW-A"));
+        assert!(analysis_prompt.contains("zh-CN-C"));
+        assert!(
+            !analysis_prompt.contains("zh-HK-B"),
+            "Excluded locale voice should not be in prompt"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analysis_context_truncation() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: true,
+                max_context_chars: 1000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_long.txt";
+        let chapter_path = input_dir.join(filename);
+        let chapter_text: String = "字".repeat(50_000);
+        fs::write(&chapter_path, &chapter_text)?;
+
+        #[derive(Debug)]
+        struct CapturingLlmClient {
+            prompts: Arc<Mutex<Vec<String>>>,
+        }
+        #[async_trait]
+        impl LlmClient for CapturingLlmClient {
+            async fn chat(&self, _system: &str, user: &str) -> Result<String> {
+                self.prompts.lock().unwrap().push(user.to_string());
+                Ok(r#"{"characters": []}"#.to_string())
+            }
+        }
+        let prompts_store = Arc::new(Mutex::new(Vec::new()));
+        let mock_llm = Box::new(CapturingLlmClient {
+            prompts: prompts_store.clone(),
+        });
+
+        struct MockTts;
+        #[async_trait]
+        impl TtsClient for MockTts {
+            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+                Ok(Vec::new())
+            }
+            async fn synthesize(
+                &self,
+                _: &AudioSegment,
+                _: &CharacterMap,
+                _: &[String],
+            ) -> Result<Vec<u8>> {
+                Ok(vec![0u8; 4096])
+            }
+            async fn get_random_voice(&self, _: Option<&str>, _: &[String]) -> Result<String> {
+                Ok("mock".to_string())
+            }
+            fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+                "mock_narrator".to_string()
+            }
+            fn is_mob_enabled(&self) -> bool {
+                true
+            }
+            fn format_voice_list_for_analysis(&self, _: &[crate::services::tts::Voice]) -> String {
+                String::new()
+            }
+            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+                Box::new(JsonScriptGenerator::new())
+            }
+        }
+        let mock_tts = Box::new(MockTts);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        let _ = workflow.process_chapter(&chapter_path, filename, 1, 1).await;
+
+        let prompts = prompts_store.lock().unwrap();
+        let analysis_prompt = &prompts[0];
+        let included_chars = analysis_prompt.matches('字').count();
+
+        assert_eq!(
+            included_chars, 1000,
+            "chapter text should be truncated to max_context_chars"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_window_long_chapters_splits_analysis_and_script_prompts() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 1000,
+                window_long_chapters: true,
+                window_overlap_chars: 200,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_windowed.txt";
+        let chapter_path = input_dir.join(filename);
+        // 3000 characters, an 800-char step (1000 - 200 overlap) means 4
+        // windows: [0,1000) [800,1800) [1600,2600) [2400,3000).
+        let chapter_text: String = "字".repeat(3000);
+        fs::write(&chapter_path, &chapter_text)?;
+
+        #[derive(Debug)]
+        struct CapturingLlmClient {
+            analysis_prompts: Arc<Mutex<Vec<String>>>,
+            script_prompts: Arc<Mutex<Vec<String>>>,
+        }
+        #[async_trait]
+        impl LlmClient for CapturingLlmClient {
+            async fn chat(&self, _system: &str, user: &str) -> Result<String> {
+                if user.contains("請分析以下文本") {
+                    self.analysis_prompts.lock().unwrap().push(user.to_string());
+                    Ok(r#"{"characters": []}"#.to_string())
+                } else {
+                    self.script_prompts.lock().unwrap().push(user.to_string());
+                    Ok("[]".to_string())
+                }
+            }
+        }
+        let analysis_prompts = Arc::new(Mutex::new(Vec::new()));
+        let script_prompts = Arc::new(Mutex::new(Vec::new()));
+        let mock_llm = Box::new(CapturingLlmClient {
+            analysis_prompts: analysis_prompts.clone(),
+            script_prompts: script_prompts.clone(),
+        });
+
+        struct MockTts;
+        #[async_trait]
+        impl TtsClient for MockTts {
+            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+                Ok(Vec::new())
+            }
+            async fn synthesize(
+                &self,
+                _: &AudioSegment,
+                _: &CharacterMap,
+                _: &[String],
+            ) -> Result<Vec<u8>> {
+                Ok(vec![0u8; 4096])
+            }
+            async fn get_random_voice(&self, _: Option<&str>, _: &[String]) -> Result<String> {
+                Ok("mock".to_string())
+            }
+            fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+                "mock_narrator".to_string()
+            }
+            fn is_mob_enabled(&self) -> bool {
+                true
+            }
+            fn format_voice_list_for_analysis(&self, _: &[crate::services::tts::Voice]) -> String {
+                String::new()
+            }
+            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+                Box::new(JsonScriptGenerator::new())
+            }
+        }
+        let mock_tts = Box::new(MockTts);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let analysis_prompts = analysis_prompts.lock().unwrap();
+        let script_prompts = script_prompts.lock().unwrap();
+
+        assert_eq!(analysis_prompts.len(), 4, "analysis should run once per window");
+        assert_eq!(script_prompts.len(), 4, "script generation should run once per window");
+
+        assert!(
+            !analysis_prompts[0].contains("先前已識別角色"),
+            "first window shouldn't reference prior windows"
+        );
+        assert!(
+            analysis_prompts[1].contains("先前已識別角色"),
+            "later windows should include the previously-identified-characters block"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protagonist_exclusion_and_chapter_mob() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(crate::services::tts::edge::EdgeTtsConfig {
+                    narrator_voice: Some("Voice_Narrator".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_test.txt";
+        fs::write(input_dir.join(filename), "Text")?;
+
+        // Mock LLM: Returns Protag
+        #[derive(Debug)]
+        struct ProtagLlm;
+        #[async_trait]
+        impl LlmClient for ProtagLlm {
+            async fn chat(&self, _: &str, user: &str) -> Result<String> {
+                if user.contains("請分析以下文本") {
+                    return Ok(r#"{
+                        "characters": [
+                            { "name": "Hero", "gender": "Male", "is_protagonist": true, "voice_id": "Voice_Hero" },
+                            { "name": "章節路人(男)", "gender": "Male", "voice_id": "placeholder_chapter_mob_male" }
+                        ]
+                    }"#.to_string());
+                }
+                // Script gen
+                Ok(r#"[
+                    {"speaker": "Hero", "text": "I am hero.", "voice_id": null},
+                    {"speaker": "章節路人(男)", "text": "I am mob.", "voice_id": null}
+                ]"#
+                .to_string())
+            }
+        }
+
+        // Mock TTS: Captures exclusions
+        struct VerifyingTts {
+            exclusions: Arc<Mutex<Vec<String>>>,
+        }
+        #[async_trait]
+        impl TtsClient for VerifyingTts {
+            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+                Ok(vec![])
+            }
+            async fn check_voice_availability(&self) -> Result<()> {
+                Ok(())
+            }
+            async fn synthesize(
+                &self,
+                segment: &AudioSegment,
+                map: &CharacterMap,
+                excluded: &[String],
+            ) -> Result<Vec<u8>> {
+                let mut ex = self.exclusions.lock().unwrap();
+                *ex = excluded.to_vec();
+
+                // Verify Chapter Mob resolution
+                if matches!(segment.speaker.as_deref(), Some("章節路人(男)")) {
+                    let info = map.characters.get("章節路人(男)").unwrap();
+                    assert_eq!(info.voice_id.as_deref(), Some("Voice_Mob_Male_Fixed"));
+                }
+
+                Ok(vec![0u8; 4096])
+            }
+            async fn get_random_voice(
+                &self,
+                gender: Option<&str>,
+                excluded: &[String],
+            ) -> Result<String> {
+                // Verify exclusion list is passed here too
+                assert!(excluded.contains(&"Voice_Narrator".to_string()));
+                assert!(excluded.contains(&"Voice_Hero".to_string()));
+
+                if gender == Some("Male") {
+                    Ok("Voice_Mob_Male_Fixed".to_string())
+                } else {
+                    Ok("Voice_Mob_Female_Fixed".to_string())
+                }
+            }
+            fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+                "Voice_Narrator".to_string()
+            }
+            fn is_mob_enabled(&self) -> bool {
+                true
+            }
+            fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+                "".to_string()
+            }
+            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+                Box::new(JsonScriptGenerator::new())
+            }
+        }
+
+        let exclusions = Arc::new(Mutex::new(Vec::new()));
+        let mock_tts = Box::new(VerifyingTts {
+            exclusions: exclusions.clone(),
+        });
+        let mock_llm = Box::new(ProtagLlm);
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow
+            .process_chapter(&input_dir.join(filename), filename, 1, 1)
+            .await?;
+
+        let ex = exclusions.lock().unwrap();
+        assert!(ex.contains(&"Voice_Narrator".to_string()));
+        assert!(ex.contains(&"Voice_Hero".to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chapter_mob_voice_reused_on_rerun() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(crate::services::tts::edge::EdgeTtsConfig {
+                    narrator_voice: Some("Voice_Narrator".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_mob_rerun.txt";
+        fs::write(input_dir.join(filename), "Text")?;
+
+        #[derive(Debug)]
+        struct MobLlm;
+        #[async_trait]
+        impl LlmClient for MobLlm {
+            async fn chat(&self, _: &str, user: &str) -> Result<String> {
+                if user.contains("請分析以下文本") {
+                    return Ok(r#"{
+                        "characters": [
+                            { "name": "章節路人(男)", "gender": "Male", "voice_id": "placeholder_chapter_mob_male" }
+                        ]
+                    }"#.to_string());
+                }
+                Ok(r#"[{"speaker": "章節路人(男)", "text": "I am mob.", "voice_id": null}]"#.to_string())
+            }
+        }
+
+        // Mock TTS whose `get_random_voice` returns a fresh voice ID every
+        // call, so reusing a previously-assigned voice (rather than calling
+        // `get_random_voice` again) is the only way the second run's
+        // assigned voice can match the first's.
+        struct CountingMobTts {
+            counter: Arc<Mutex<u32>>,
+            seen_voices: Arc<Mutex<Vec<String>>>,
+        }
+        #[async_trait]
+        impl TtsClient for CountingMobTts {
+            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+                Ok(vec![])
+            }
+            async fn check_voice_availability(&self) -> Result<()> {
+                Ok(())
+            }
+            async fn synthesize(
+                &self,
+                segment: &AudioSegment,
+                map: &CharacterMap,
+                _excluded: &[String],
+            ) -> Result<Vec<u8>> {
+                if matches!(segment.speaker.as_deref(), Some("章節路人(男)")) {
+                    let info = map.characters.get("章節路人(男)").unwrap();
+                    self.seen_voices
+                        .lock()
+                        .unwrap()
+                        .push(info.voice_id.clone().unwrap());
+                }
+                Ok(vec![0u8; 4096])
+            }
+            async fn get_random_voice(&self, _: Option<&str>, _: &[String]) -> Result<String> {
+                let mut counter = self.counter.lock().unwrap();
+                *counter += 1;
+                Ok(format!("Voice_Mob_Male_{}", counter))
+            }
+            fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+                "Voice_Narrator".to_string()
+            }
+            fn is_mob_enabled(&self) -> bool {
+                true
+            }
+            fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+                "".to_string()
+            }
+            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+                Box::new(JsonScriptGenerator::new())
+            }
+        }
+
+        let seen_voices = Arc::new(Mutex::new(Vec::new()));
+        let mock_tts = Box::new(CountingMobTts {
+            counter: Arc::new(Mutex::new(0)),
+            seen_voices: seen_voices.clone(),
+        });
+
+        let mut workflow = WorkflowManager::new(config, Box::new(MobLlm), mock_tts).await?;
+        let chapter_path = input_dir.join(filename);
+
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let voices = seen_voices.lock().unwrap();
+        assert_eq!(voices.len(), 2);
+        assert_eq!(voices[0], voices[1]);
+
+        assert_eq!(
+            workflow
+                .state
+                .chapter_mob_voices
+                .get(filename)
+                .and_then(|m| m.get("章節路人(男)")),
+            Some(&voices[0])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_workflow_log_records_events_in_chronological_order() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: false,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_log.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let log_path = build_dir.join("workflow.log");
+        let content = fs::read_to_string(&log_path)?;
+        let events: Vec<String> = content
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["event"].as_str().unwrap().to_string()
+            })
+            .collect();
+
+        assert_eq!(events.first(), Some(&"chapter_start".to_string()));
+        assert_eq!(events.last(), Some(&"chapter_end".to_string()));
+        assert!(events.contains(&"llm_call_end".to_string()));
+        assert!(events.contains(&"segment_result".to_string()));
+        assert!(events.contains(&"merge_complete".to_string()));
+
+        let merge_index = events.iter().position(|e| e == "merge_complete").unwrap();
+        let chapter_end_index = events.iter().position(|e| e == "chapter_end").unwrap();
+        assert!(merge_index < chapter_end_index, "merge must complete before chapter_end is logged");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_low_confidence_segments_flagged_and_skipped_in_unattended_mode() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
+
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
+
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: true,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: crate::core::config::WorkflowConfig {
+                low_confidence_threshold: 0.5,
+                ..Default::default()
+            },
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
+
+        let filename = "chapter_review.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
+
+        let chapter_build_dir = build_dir.join("chapter_review_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let cached_segments = vec![
+            AudioSegment {
+                speaker: Some("Narrator".to_string()),
+                text: "Confident line".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(0.9),
+            },
+            AudioSegment {
+                speaker: Some("Hero".to_string()),
+                text: "Ambiguous line".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(0.2),
+            },
+        ];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
 
-        pb.finish_with_message("Synthesis complete");
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
 
-        let mut audio_files = vec![PathBuf::new(); segments.len()];
-        for res in results {
-            let (i, path) = res?;
-            audio_files[i] = path;
-        }
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
 
-        // 4. Merge
-        println!("Merging audio...");
-        let output_filename = Path::new(filename)
-            .with_extension("mp3")
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
-        let final_audio_path = Path::new(&self.config.output_folder).join(output_filename);
+        let review_path = chapter_build_dir.join("review.json");
+        let review_content = fs::read_to_string(&review_path)?;
+        let flagged: Vec<AudioSegment> = serde_json::from_str(&review_content)?;
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].text, "Ambiguous line");
 
-        self.tts
-            .merge_audio_files(&audio_files, &final_audio_path)?;
+        // Only the confident segment should have been synthesized.
+        assert!(chapter_build_dir.join("chunk_0000.mp3").exists());
+        assert!(!chapter_build_dir.join("chunk_0001.mp3").exists());
 
-        println!("Chapter complete: {:?}", final_audio_path);
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::services::script::JsonScriptGenerator;
-    use async_trait::async_trait;
-    use std::fs;
-    use std::sync::{Arc, Mutex};
+    #[tokio::test]
+    async fn test_chapter_stats_written_for_cached_segments() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_root = temp_dir.path();
 
-    #[test]
-    fn test_strip_code_blocks() {
-        assert_eq!(strip_code_blocks("json"), "json");
-        assert_eq!(strip_code_blocks("```json\n{}\n```"), "{}");
-        assert_eq!(strip_code_blocks("```\n{}\n```"), "{}");
-        assert_eq!(strip_code_blocks("  ```json  \n  {}  \n  ```  "), "{}");
-    }
+        let build_dir = test_root.join("build");
+        let input_dir = test_root.join("input");
+        let output_dir = test_root.join("output");
+        fs::create_dir_all(&build_dir)?;
+        fs::create_dir_all(&input_dir)?;
+        fs::create_dir_all(&output_dir)?;
 
-    // Mock LLM Client
-    #[derive(Debug)]
-    struct MockLlmClient {
-        call_count: Arc<Mutex<usize>>,
-    }
+        let config = Config {
+            input_folder: input_dir.to_string_lossy().to_string(),
+            output_folder: output_dir.to_string_lossy().to_string(),
+            build_folder: build_dir.to_string_lossy().to_string(),
+            unattended: true,
+            llm: crate::services::llm::LlmConfig {
+                provider: "mock".to_string(),
+                retry_count: 0,
+                retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
+                gemini: None,
+                ollama: None,
+                openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
+            },
+            audio: crate::core::config::AudioConfig {
+                provider: "edge-tts".to_string(),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
+        };
 
-    impl MockLlmClient {
-        fn new() -> Self {
-            Self {
-                call_count: Arc::new(Mutex::new(0)),
-            }
-        }
-    }
+        let filename = "chapter_stats.txt";
+        let chapter_path = input_dir.join(filename);
+        fs::write(&chapter_path, "Text")?;
 
-    #[async_trait]
-    impl LlmClient for MockLlmClient {
-        async fn chat(&self, _system: &str, user: &str) -> Result<String> {
-            let mut count = self.call_count.lock().unwrap();
-            *count += 1;
+        let chapter_build_dir = build_dir.join("chapter_stats_txt");
+        fs::create_dir_all(&chapter_build_dir)?;
+        let segments_path = chapter_build_dir.join("segments.json");
+        let cached_segments = vec![
+            AudioSegment {
+                speaker: Some("旁白".to_string()),
+                text: "Once upon a time".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+            AudioSegment {
+                speaker: Some("Hero".to_string()),
+                text: "Hello".to_string(),
+                style: None,
+                voice_id: None,
+                detected_language: None,
+                confidence: Some(1.0),
+            },
+        ];
+        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
 
-            if user.contains("請分析以下文本") {
-                return Ok(r#"{"characters": [{"name": "Hero", "gender": "Male"}]}"#.to_string());
-            } else if user.contains("請將以下小說文本分解為對話和旁白段落") {
-                return Ok(r#"[{"speaker": "旁白", "text": "Test audio"}]"#.to_string());
-            }
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
 
-            Ok("{}".to_string())
-        }
-    }
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
 
-    struct MockTtsClient {
-        should_fail: bool,
-    }
+        let stats_content = fs::read_to_string(chapter_build_dir.join("stats.json"))?;
+        let stats: ChapterStats = serde_json::from_str(&stats_content)?;
 
-    #[async_trait]
-    impl TtsClient for MockTtsClient {
-        async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
-            Ok(vec![])
-        }
-        async fn synthesize(
-            &self,
-            _segment: &AudioSegment,
-            _map: &CharacterMap,
-            _excluded_voices: &[String],
-        ) -> Result<Vec<u8>> {
-            if self.should_fail {
-                Err(anyhow::anyhow!("Mock TTS error"))
-            } else {
-                Ok(vec![0u8; 10])
-            }
-        }
-        async fn get_random_voice(
-            &self,
-            _gender: Option<&str>,
-            _excluded_voices: &[String],
-        ) -> Result<String> {
-            Ok("mock_voice_id".to_string())
-        }
-        fn get_narrator_voice_id(&self) -> String {
-            "mock_narrator".to_string()
-        }
-        fn is_mob_enabled(&self) -> bool {
-            true
-        }
-        fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
-            "mock voice list".to_string()
-        }
-        fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
-            Box::new(JsonScriptGenerator::new())
-        }
+        assert_eq!(stats.total_segments, 2);
+        assert_eq!(stats.narrator_segments, 1);
+        assert_eq!(stats.dialogue_segments, 1);
+        assert_eq!(stats.unique_speakers, 2);
+        assert_eq!(stats.total_characters, "Once upon a time".chars().count() + "Hello".chars().count());
+
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_cache_miss_generates_segments_file() -> Result<()> {
+    async fn test_chapter_mob_pool_assigns_distinct_voices_round_robin() -> Result<()> {
         let temp_dir = tempfile::tempdir()?;
         let test_root = temp_dir.path();
 
         let build_dir = test_root.join("build");
         let input_dir = test_root.join("input");
         let output_dir = test_root.join("output");
-
         fs::create_dir_all(&build_dir)?;
         fs::create_dir_all(&input_dir)?;
         fs::create_dir_all(&output_dir)?;
@@ -659,59 +6615,297 @@ mod tests {
                 provider: "mock".to_string(),
                 retry_count: 0,
                 retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
                 gemini: None,
                 ollama: None,
                 openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
             },
             audio: crate::core::config::AudioConfig {
                 provider: "edge-tts".to_string(),
-                edge_tts: Some(Default::default()),
-                ..crate::core::config::AudioConfig::default()
+                chapter_mob_pool_size: 3,
+                edge_tts: Some(crate::services::tts::edge::EdgeTtsConfig {
+                    narrator_voice: Some("Voice_Narrator".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
             },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
         };
 
-        let filename = "chapter_1.txt";
-        let chapter_path = input_dir.join(filename);
-        fs::write(&chapter_path, "Some story text.")?;
+        let filename = "chapter_mob_pool.txt";
+        fs::write(input_dir.join(filename), "Text")?;
 
-        let mock_llm = Box::new(MockLlmClient::new());
-        let call_count = mock_llm.call_count.clone();
+        #[derive(Debug)]
+        struct MobLlm;
+        #[async_trait]
+        impl LlmClient for MobLlm {
+            async fn chat(&self, _: &str, user: &str) -> Result<String> {
+                if user.contains("請分析以下文本") {
+                    return Ok(r#"{
+                        "characters": [
+                            { "name": "章節路人(男)", "gender": "Male", "voice_id": "placeholder_chapter_mob_male" }
+                        ]
+                    }"#.to_string());
+                }
+                Ok(r#"[
+                    {"speaker": "章節路人(男)", "text": "Line one.", "voice_id": null},
+                    {"speaker": "章節路人(男)", "text": "Line two.", "voice_id": null},
+                    {"speaker": "章節路人(男)", "text": "Line three.", "voice_id": null},
+                    {"speaker": "章節路人(男)", "text": "Line four.", "voice_id": null}
+                ]"#
+                .to_string())
+            }
+        }
 
-        let mock_tts = Box::new(MockTtsClient { should_fail: true });
+        // Mock TTS whose `get_random_voice` returns a fresh voice ID every
+        // call, and captures both the voice ID and accumulated exclusions
+        // used to synthesize each segment.
+        struct CountingMobTts {
+            counter: Arc<Mutex<u32>>,
+            seen: Arc<Mutex<Vec<(String, Vec<String>)>>>,
+        }
+        #[async_trait]
+        impl TtsClient for CountingMobTts {
+            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
+                Ok(vec![])
+            }
+            async fn check_voice_availability(&self) -> Result<()> {
+                Ok(())
+            }
+            async fn synthesize(
+                &self,
+                segment: &AudioSegment,
+                map: &CharacterMap,
+                excluded: &[String],
+            ) -> Result<Vec<u8>> {
+                if let Some(speaker) = segment.speaker.as_deref() {
+                    if speaker.starts_with("章節路人(男)_") {
+                        let info = map.characters.get(speaker).unwrap();
+                        self.seen.lock().unwrap().push((
+                            info.voice_id.clone().unwrap(),
+                            excluded.to_vec(),
+                        ));
+                    }
+                }
+                Ok(vec![0u8; 4096])
+            }
+            async fn get_random_voice(&self, _: Option<&str>, _: &[String]) -> Result<String> {
+                let mut counter = self.counter.lock().unwrap();
+                *counter += 1;
+                Ok(format!("Voice_Mob_Male_{}", counter))
+            }
+            fn get_narrator_voice_id(&self, _language_hint: Option<&str>) -> String {
+                "Voice_Narrator".to_string()
+            }
+            fn is_mob_enabled(&self) -> bool {
+                true
+            }
+            fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
+                "".to_string()
+            }
+            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
+                Box::new(JsonScriptGenerator::new())
+            }
+        }
 
-        let mut workflow = WorkflowManager::new(config.clone(), mock_llm, mock_tts)?;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mock_tts = Box::new(CountingMobTts {
+            counter: Arc::new(Mutex::new(0)),
+            seen: seen.clone(),
+        });
 
-        let result = workflow.process_chapter(&chapter_path, filename).await;
+        let mut workflow = WorkflowManager::new(config, Box::new(MobLlm), mock_tts).await?;
+        let chapter_path = input_dir.join(filename);
+        workflow.process_chapter(&chapter_path, filename, 1, 1).await?;
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4, "all four mob lines should have synthesized");
+
+        // Three distinct voices in the pool, round-robin over 4 segments
+        // means the 4th line reuses the 1st line's voice.
+        let distinct_voices: std::collections::HashSet<&str> =
+            seen.iter().map(|(v, _)| v.as_str()).collect();
+        assert_eq!(distinct_voices.len(), 3);
+        assert_eq!(seen[0].0, seen[3].0);
+        assert_ne!(seen[0].0, seen[1].0);
+        assert_ne!(seen[1].0, seen[2].0);
+
+        // Every pool voice should be excluded from the other pool members'
+        // (and any later) random selection.
+        for (voice, excluded) in seen.iter() {
+            for other in distinct_voices.iter() {
+                if *other != voice {
+                    assert!(
+                        excluded.contains(&other.to_string()),
+                        "{} should exclude pool member {}",
+                        voice,
+                        other
+                    );
+                }
+            }
+        }
 
-        assert!(
-            result.is_err(),
-            "Expected synthesis failure due to mock error"
-        );
+        Ok(())
+    }
 
-        assert_eq!(
-            *call_count.lock().unwrap(),
-            2,
-            "Should call LLM twice (Analysis + Script)"
-        );
+    fn epub_crc32(data: &[u8]) -> u32 {
+        let mut crc: u32 = 0xFFFFFFFF;
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                if crc & 1 != 0 {
+                    crc = (crc >> 1) ^ 0xEDB88320;
+                } else {
+                    crc >>= 1;
+                }
+            }
+        }
+        !crc
+    }
 
-        let segments_path = build_dir.join("chapter_1_txt").join("segments.json");
-        assert!(segments_path.exists(), "segments.json should be created");
+    /// Builds a minimal, stored-only (no compression) two-chapter EPUB byte
+    /// buffer by hand, since there's no `zip`-writing dependency to build
+    /// one with directly.
+    fn minimal_epub_bytes() -> Vec<u8> {
+        let entries: Vec<(&str, Vec<u8>)> = vec![
+            ("mimetype", b"application/epub+zip".to_vec()),
+            (
+                "META-INF/container.xml",
+                br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#
+                    .to_vec(),
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" unique-identifier="BookId" version="2.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Test Book</dc:title>
+    <dc:identifier id="BookId">urn:uuid:test-book</dc:identifier>
+  </metadata>
+  <manifest>
+    <item id="chap0" href="chap0.xhtml" media-type="application/xhtml+xml"/>
+    <item id="chap1" href="chap1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+  </manifest>
+  <spine toc="ncx">
+    <itemref idref="chap0"/>
+    <itemref idref="chap1"/>
+  </spine>
+</package>"#
+                    .to_vec(),
+            ),
+            (
+                "OEBPS/toc.ncx",
+                br#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head/>
+  <docTitle><text>Test Book</text></docTitle>
+  <navMap/>
+</ncx>"#
+                    .to_vec(),
+            ),
+            (
+                "OEBPS/chap0.xhtml",
+                br#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p>First chapter body.</p></body></html>"#
+                    .to_vec(),
+            ),
+            (
+                "OEBPS/chap1.xhtml",
+                br#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p>Second chapter body.</p></body></html>"#
+                    .to_vec(),
+            ),
+        ];
 
-        let content = fs::read_to_string(segments_path)?;
-        assert!(content.contains("Test audio"));
+        let mut buf = Vec::new();
+        let mut offsets = Vec::with_capacity(entries.len());
+
+        for (name, data) in &entries {
+            offsets.push(buf.len() as u32);
+            let crc = epub_crc32(data);
+            let name_bytes = name.as_bytes();
+
+            buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+            buf.extend_from_slice(&20u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&crc.to_le_bytes());
+            buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(name_bytes);
+            buf.extend_from_slice(data);
+        }
 
-        Ok(())
+        let mut central = Vec::new();
+        for ((name, data), &offset) in entries.iter().zip(offsets.iter()) {
+            let crc = epub_crc32(data);
+            let name_bytes = name.as_bytes();
+
+            central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&crc.to_le_bytes());
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u32.to_le_bytes());
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name_bytes);
+        }
+
+        let cd_offset = buf.len() as u32;
+        let cd_size = central.len() as u32;
+        buf.extend_from_slice(&central);
+
+        buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&cd_size.to_le_bytes());
+        buf.extend_from_slice(&cd_offset.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+
+        buf
     }
 
     #[tokio::test]
-    async fn test_flattened_output_structure() -> Result<()> {
+    async fn test_run_processes_epub_chapters_without_leaving_txt_files() -> Result<()> {
         let temp_dir = tempfile::tempdir()?;
         let test_root = temp_dir.path();
 
         let build_dir = test_root.join("build");
         let input_dir = test_root.join("input");
         let output_dir = test_root.join("output");
-
         fs::create_dir_all(&build_dir)?;
         fs::create_dir_all(&input_dir)?;
         fs::create_dir_all(&output_dir)?;
@@ -720,277 +6914,260 @@ mod tests {
             input_folder: input_dir.to_string_lossy().to_string(),
             output_folder: output_dir.to_string_lossy().to_string(),
             build_folder: build_dir.to_string_lossy().to_string(),
-            unattended: false,
+            unattended: true,
             llm: crate::services::llm::LlmConfig {
                 provider: "mock".to_string(),
                 retry_count: 0,
                 retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
                 gemini: None,
                 ollama: None,
                 openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
             },
             audio: crate::core::config::AudioConfig {
                 provider: "edge-tts".to_string(),
                 edge_tts: Some(Default::default()),
                 ..crate::core::config::AudioConfig::default()
             },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: Default::default(),
+            preprocessing: Default::default(),
         };
 
-        let filename = "chapter_flat.txt";
-        let chapter_path = input_dir.join(filename);
-        fs::write(&chapter_path, "Text")?;
-
-        // Pre-populate segments to skip LLM
-        let chapter_build_dir = build_dir.join("chapter_flat_txt");
-        fs::create_dir_all(&chapter_build_dir)?;
-        let segments_path = chapter_build_dir.join("segments.json");
-        let cached_segments = vec![AudioSegment {
-            speaker: Some("Narrator".to_string()),
-            text: "Audio".to_string(),
-            style: None,
-            voice_id: None,
-        }];
-        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
+        fs::write(input_dir.join("book.epub"), minimal_epub_bytes())?;
 
         let mock_llm = Box::new(MockLlmClient::new());
         let mock_tts = Box::new(MockTtsClient { should_fail: false });
 
-        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts)?;
-        workflow.process_chapter(&chapter_path, filename).await?;
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.run().await?;
 
-        // Check output
-        let output_file = output_dir.join("chapter_flat.mp3");
-        assert!(
-            output_file.exists(),
-            "Output file should exist at root of output folder"
+        assert_eq!(
+            workflow.state.completed_chapters.len(),
+            2,
+            "Both EPUB chapters should have been processed"
         );
+        assert!(workflow.state.completed_chapters[0].contains("0000"));
+        assert!(workflow.state.completed_chapters[1].contains("0001"));
 
-        let sub_dir = output_dir.join("chapter_flat_txt");
-        assert!(
-            !sub_dir.exists(),
-            "Subdirectory should NOT exist in output folder"
+        let mut input_listing = fs::read_dir(&input_dir)?
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        input_listing.sort();
+        assert_eq!(
+            input_listing,
+            vec!["book.epub".to_string()],
+            "No .txt files should be left behind in the input folder"
         );
 
         Ok(())
     }
 
+    fn ffmpeg_available() -> bool {
+        std::process::Command::new("ffmpeg")
+            .arg("-version")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    /// Builds `frame_count` back-to-back MPEG1 Layer III frames at 44100Hz /
+    /// 128kbps, zero-padded to their exact frame size, matching the helper
+    /// in `utils::audio`'s own tests.
+    fn dummy_mp3_bytes(frame_count: usize) -> Vec<u8> {
+        const FRAME_SIZE: usize = 417; // 144 * 128000 / 44100
+        let mut buf = Vec::new();
+        for _ in 0..frame_count {
+            buf.extend_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+            buf.resize(buf.len() + FRAME_SIZE - 4, 0);
+        }
+        buf
+    }
+
     #[tokio::test]
-    async fn test_cache_hit_skips_llm() -> Result<()> {
+    async fn test_combine_to_m4b_produces_valid_mp4_container() -> Result<()> {
+        if !ffmpeg_available() {
+            eprintln!("skipping test_combine_to_m4b_produces_valid_mp4_container: ffmpeg not found on PATH");
+            return Ok(());
+        }
+
         let temp_dir = tempfile::tempdir()?;
         let test_root = temp_dir.path();
-
         let build_dir = test_root.join("build");
         let input_dir = test_root.join("input");
         let output_dir = test_root.join("output");
-
         fs::create_dir_all(&build_dir)?;
         fs::create_dir_all(&input_dir)?;
         fs::create_dir_all(&output_dir)?;
 
+        fs::write(output_dir.join("chapter_1.mp3"), dummy_mp3_bytes(20))?;
+        fs::write(output_dir.join("chapter_2.mp3"), dummy_mp3_bytes(30))?;
+
         let config = Config {
             input_folder: input_dir.to_string_lossy().to_string(),
             output_folder: output_dir.to_string_lossy().to_string(),
             build_folder: build_dir.to_string_lossy().to_string(),
-            unattended: false,
+            unattended: true,
             llm: crate::services::llm::LlmConfig {
                 provider: "mock".to_string(),
                 retry_count: 0,
                 retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
                 gemini: None,
                 ollama: None,
                 openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
             },
             audio: crate::core::config::AudioConfig {
                 provider: "edge-tts".to_string(),
                 edge_tts: Some(Default::default()),
                 ..crate::core::config::AudioConfig::default()
             },
+            workflow: Default::default(),
+            book_metadata: crate::core::config::BookMetadata {
+                title: "Test Book".to_string(),
+                author: "Test Author".to_string(),
+                cover_image_path: None,
+            },
+            output: crate::core::config::OutputConfig {
+                format: crate::core::config::OutputFormat::M4bSingleFile,
+                ..Default::default()
+            },
+            preprocessing: Default::default(),
         };
 
-        let filename = "chapter_2.txt";
-        let chapter_path = input_dir.join(filename);
-        fs::write(&chapter_path, "Some story text.")?;
-
-        let chapter_build_dir = build_dir.join("chapter_2_txt");
-        fs::create_dir_all(&chapter_build_dir)?;
-        let segments_path = chapter_build_dir.join("segments.json");
-
-        let cached_segments = vec![AudioSegment {
-            speaker: Some("Narrator".to_string()),
-            text: "Cached audio".to_string(),
-            style: None,
-            voice_id: None,
-        }];
-        fs::write(&segments_path, serde_json::to_string(&cached_segments)?)?;
-
-        let chunk_path = chapter_build_dir.join("chunk_0000.mp3");
-        fs::write(&chunk_path, b"fake mp3 data")?;
-
         let mock_llm = Box::new(MockLlmClient::new());
-        let call_count = mock_llm.call_count.clone();
-
         let mock_tts = Box::new(MockTtsClient { should_fail: false });
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.state.completed_chapters =
+            vec!["chapter_1.txt".to_string(), "chapter_2.txt".to_string()];
 
-        let mut workflow = WorkflowManager::new(config.clone(), mock_llm, mock_tts)?;
-
-        let result = workflow.process_chapter(&chapter_path, filename).await;
-
-        assert!(result.is_ok(), "Should complete successfully");
+        workflow.combine_to_m4b().await?;
 
+        let m4b_path = output_dir.join("audiobook.m4b");
+        let data = fs::read(&m4b_path).context("ffmpeg should have produced audiobook.m4b")?;
+        assert!(data.len() > 8);
         assert_eq!(
-            *call_count.lock().unwrap(),
-            0,
-            "Should use cache and NOT call LLM"
+            &data[4..8],
+            b"ftyp",
+            "output should be a valid MP4/M4B container"
         );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_voice_filtering_in_analysis_prompt() -> Result<()> {
+    async fn test_combine_chapters_concatenates_and_skips_missing() -> Result<()> {
         let temp_dir = tempfile::tempdir()?;
         let test_root = temp_dir.path();
-
         let build_dir = test_root.join("build");
         let input_dir = test_root.join("input");
         let output_dir = test_root.join("output");
-
         fs::create_dir_all(&build_dir)?;
         fs::create_dir_all(&input_dir)?;
         fs::create_dir_all(&output_dir)?;
 
+        fs::write(output_dir.join("chapter_1.mp3"), b"FIRST")?;
+        fs::write(output_dir.join("chapter_2.mp3"), b"SECOND")?;
+        // chapter_3.mp3 is deliberately never written, simulating a chapter
+        // that failed processing.
+
         let config = Config {
             input_folder: input_dir.to_string_lossy().to_string(),
             output_folder: output_dir.to_string_lossy().to_string(),
             build_folder: build_dir.to_string_lossy().to_string(),
-            unattended: false,
+            unattended: true,
             llm: crate::services::llm::LlmConfig {
                 provider: "mock".to_string(),
                 retry_count: 0,
                 retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
                 gemini: None,
                 ollama: None,
                 openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
             },
             audio: crate::core::config::AudioConfig {
                 provider: "edge-tts".to_string(),
-                language: "zh".to_string(),
-                exclude_locales: vec!["zh-HK".to_string()],
                 edge_tts: Some(Default::default()),
                 ..crate::core::config::AudioConfig::default()
             },
-        };
-
-        let filename = "chapter_filter.txt";
-        let chapter_path = input_dir.join(filename);
-        fs::write(&chapter_path, "Text")?;
-
-        // Setup Mock LLM to capture prompt
-        #[derive(Debug)]
-        struct CapturingLlmClient {
-            prompts: Arc<Mutex<Vec<String>>>,
-        }
-        #[async_trait]
-        impl LlmClient for CapturingLlmClient {
-            async fn chat(&self, _system: &str, user: &str) -> Result<String> {
-                self.prompts.lock().unwrap().push(user.to_string());
-                // Return valid JSON to proceed
-                Ok(r#"{"characters": []}"#.to_string())
-            }
-        }
-        let prompts_store = Arc::new(Mutex::new(Vec::new()));
-        let mock_llm = Box::new(CapturingLlmClient {
-            prompts: prompts_store.clone(),
-        });
-
-        // Setup Mock TTS with voices
-        struct MockTts {
-            voices: Vec<crate::services::tts::Voice>,
-        }
-        #[async_trait]
-        impl TtsClient for MockTts {
-            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
-                Ok(self.voices.clone())
-            }
-            async fn synthesize(
-                &self,
-                _: &AudioSegment,
-                _: &CharacterMap,
-                _: &[String],
-            ) -> Result<Vec<u8>> {
-                Ok(vec![])
-            }
-            async fn get_random_voice(&self, _: Option<&str>, _: &[String]) -> Result<String> {
-                Ok("mock".to_string())
-            }
-            fn get_narrator_voice_id(&self) -> String {
-                "mock_narrator".to_string()
-            }
-            fn is_mob_enabled(&self) -> bool {
-                true
-            }
-            fn format_voice_list_for_analysis(&self, voices: &[crate::services::tts::Voice]) -> String {
-                // Return specific format to verify test expectations if needed, or just a mock
-                // The test checks if specific voice names are in the prompt.
-                // The `format_voice_list_for_analysis` should return string containing voice names.
-                voices
-                    .iter()
-                    .map(|v| v.short_name.clone())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            }
-            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
-                Box::new(JsonScriptGenerator::new())
-            }
-        }
-
-        let voices = vec![
-            crate::services::tts::Voice {
-                short_name: "zh-TW-A".to_string(),
-                gender: "Male".to_string(),
-                locale: "zh-TW".to_string(),
-                name: "A".to_string(),
-                friendly_name: None,
-            },
-            crate::services::tts::Voice {
-                short_name: "zh-HK-B".to_string(),
-                gender: "Female".to_string(),
-                locale: "zh-HK".to_string(),
-                name: "B".to_string(),
-                friendly_name: None,
+            workflow: Default::default(),
+            book_metadata: crate::core::config::BookMetadata {
+                title: "Test Book".to_string(),
+                author: "Test Author".to_string(),
+                cover_image_path: None,
             },
-            crate::services::tts::Voice {
-                short_name: "zh-CN-C".to_string(),
-                gender: "Male".to_string(),
-                locale: "zh-CN".to_string(),
-                name: "C".to_string(),
-                friendly_name: None,
+            output: crate::core::config::OutputConfig {
+                combine: true,
+                // The chapters here are dummy byte strings, not real MP3
+                // frames, so the sample-rate-matched gap clip this feature
+                // would otherwise insert has nothing valid to read from.
+                chapter_gap_ms: 0,
+                ..Default::default()
             },
-        ];
-        let mock_tts = Box::new(MockTts { voices });
+            preprocessing: Default::default(),
+        };
 
-        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts)?;
-        let _ = workflow.process_chapter(&chapter_path, filename).await;
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.state.completed_chapters = vec![
+            "chapter_1.txt".to_string(),
+            "chapter_2.txt".to_string(),
+            "chapter_3.txt".to_string(),
+        ];
 
-        let prompts = prompts_store.lock().unwrap();
-        let analysis_prompt = &prompts[0];
+        workflow.combine_chapters().await?;
 
-        // Assertions
-        assert!(analysis_prompt.contains("zh-TW-A"));
-        assert!(analysis_prompt.contains("zh-CN-C"));
-        assert!(
-            !analysis_prompt.contains("zh-HK-B"),
-            "Excluded locale voice should not be in prompt"
+        let combined_path = output_dir.join("Test Book.mp3");
+        let tag = id3::Tag::read_from_path(&combined_path)?;
+        assert_eq!(tag.title(), Some("Test Book"));
+        assert_eq!(tag.artist(), Some("Test Author"));
+        assert_eq!(
+            tag.track(),
+            Some(2),
+            "should only count the two chapters whose MP3 actually exists"
         );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_protagonist_exclusion_and_chapter_mob() -> Result<()> {
+    async fn test_combine_chapters_inserts_configured_silence_gaps() -> Result<()> {
         let temp_dir = tempfile::tempdir()?;
         let test_root = temp_dir.path();
-
         let build_dir = test_root.join("build");
         let input_dir = test_root.join("input");
         let output_dir = test_root.join("output");
@@ -998,125 +7175,152 @@ mod tests {
         fs::create_dir_all(&input_dir)?;
         fs::create_dir_all(&output_dir)?;
 
+        let chapter_mp3 = crate::utils::audio::encode_to_mp3(
+            &crate::utils::audio::generate_silence_wav(1000, 44100, 2),
+            128,
+        )?;
+        fs::write(output_dir.join("chapter_1.mp3"), &chapter_mp3)?;
+        fs::write(output_dir.join("chapter_2.mp3"), &chapter_mp3)?;
+
         let config = Config {
             input_folder: input_dir.to_string_lossy().to_string(),
             output_folder: output_dir.to_string_lossy().to_string(),
             build_folder: build_dir.to_string_lossy().to_string(),
-            unattended: false,
+            unattended: true,
             llm: crate::services::llm::LlmConfig {
                 provider: "mock".to_string(),
                 retry_count: 0,
                 retry_delay_seconds: 0,
+                truncate_analysis_context: false,
+                max_context_chars: 10000,
+                window_long_chapters: false,
+                window_overlap_chars: 500,
+                use_llm_cache: false,
+                stream: false,
                 gemini: None,
                 ollama: None,
                 openai: None,
+                claude: None,
+                max_total_tokens: None,
+                max_retry_turns: 2,
+                providers: Vec::new(),
+                fallback_strategy: FallbackStrategy::Sequential,
             },
             audio: crate::core::config::AudioConfig {
                 provider: "edge-tts".to_string(),
-                edge_tts: Some(crate::services::tts::edge::EdgeTtsConfig {
-                    narrator_voice: Some("Voice_Narrator".to_string()),
-                    ..Default::default()
-                }),
+                edge_tts: Some(Default::default()),
+                ..crate::core::config::AudioConfig::default()
+            },
+            workflow: Default::default(),
+            book_metadata: Default::default(),
+            output: crate::core::config::OutputConfig {
+                combine: true,
+                chapter_gap_ms: 500,
+                before_first_chapter_ms: 300,
                 ..Default::default()
             },
+            preprocessing: Default::default(),
         };
 
-        let filename = "chapter_test.txt";
-        fs::write(input_dir.join(filename), "Text")?;
+        let mock_llm = Box::new(MockLlmClient::new());
+        let mock_tts = Box::new(MockTtsClient { should_fail: false });
+        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts).await?;
+        workflow.state.completed_chapters =
+            vec!["chapter_1.txt".to_string(), "chapter_2.txt".to_string()];
 
-        // Mock LLM: Returns Protag
-        #[derive(Debug)]
-        struct ProtagLlm;
-        #[async_trait]
-        impl LlmClient for ProtagLlm {
-            async fn chat(&self, _: &str, user: &str) -> Result<String> {
-                if user.contains("請分析以下文本") {
-                    return Ok(r#"{
-                        "characters": [
-                            { "name": "Hero", "gender": "Male", "is_protagonist": true, "voice_id": "Voice_Hero" },
-                            { "name": "章節路人(男)", "gender": "Male", "voice_id": "placeholder_chapter_mob_male" }
-                        ]
-                    }"#.to_string());
-                }
-                // Script gen
-                Ok(r#"[
-                    {"speaker": "Hero", "text": "I am hero.", "voice_id": null},
-                    {"speaker": "章節路人(男)", "text": "I am mob.", "voice_id": null}
-                ]"#
-                .to_string())
-            }
+        workflow.combine_chapters().await?;
+
+        let combined_path = output_dir.join("combined.mp3");
+        let combined_duration_ms = crate::utils::audio::mp3_duration_ms(&fs::read(&combined_path)?)?;
+
+        // 2 chapters * 1000ms + one 500ms inter-chapter gap + a 300ms
+        // before-first-chapter gap.
+        let expected_ms = 2000 + 500 + 300;
+        let tolerance_ms = 100;
+        assert!(
+            (combined_duration_ms as i64 - expected_ms as i64).abs() <= tolerance_ms,
+            "expected combined duration near {}ms, got {}ms",
+            expected_ms,
+            combined_duration_ms
+        );
+
+        Ok(())
+    }
+
+    #[derive(Debug, Default)]
+    struct JsonRetryLlmClient {
+        chat_calls: Arc<Mutex<usize>>,
+        multi_turn_calls: Arc<Mutex<Vec<Vec<(String, String)>>>>,
+    }
+
+    #[async_trait]
+    impl LlmClient for JsonRetryLlmClient {
+        async fn chat(&self, _system: &str, _user: &str) -> Result<String> {
+            *self.chat_calls.lock().unwrap() += 1;
+            Ok("not json at all".to_string())
         }
 
-        // Mock TTS: Captures exclusions
-        struct VerifyingTts {
-            exclusions: Arc<Mutex<Vec<String>>>,
+        async fn chat_multi_turn(&self, _system: &str, history: &[(String, String)]) -> Result<String> {
+            self.multi_turn_calls.lock().unwrap().push(history.to_vec());
+            Ok(r#"[{"speaker": "旁白", "text": "Fixed on retry"}]"#.to_string())
         }
-        #[async_trait]
-        impl TtsClient for VerifyingTts {
-            async fn list_voices(&self) -> Result<Vec<crate::services::tts::Voice>> {
-                Ok(vec![])
-            }
-            async fn synthesize(
-                &self,
-                segment: &AudioSegment,
-                map: &CharacterMap,
-                excluded: &[String],
-            ) -> Result<Vec<u8>> {
-                let mut ex = self.exclusions.lock().unwrap();
-                *ex = excluded.to_vec();
+    }
 
-                // Verify Chapter Mob resolution
-                if matches!(segment.speaker.as_deref(), Some("章節路人(男)")) {
-                    let info = map.characters.get("章節路人(男)").unwrap();
-                    assert_eq!(info.voice_id.as_deref(), Some("Voice_Mob_Male_Fixed"));
-                }
+    #[tokio::test]
+    async fn test_generate_script_segments_retries_invalid_json_via_multi_turn() {
+        let llm = JsonRetryLlmClient::default();
+        let script_generator = JsonScriptGenerator::new();
+
+        let (response, segments) = generate_script_segments(
+            &llm,
+            &script_generator,
+            "system prompt",
+            "user prompt",
+            false,
+            2,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response, r#"[{"speaker": "旁白", "text": "Fixed on retry"}]"#);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "Fixed on retry");
+        assert_eq!(*llm.chat_calls.lock().unwrap(), 1);
+
+        let multi_turn_calls = llm.multi_turn_calls.lock().unwrap();
+        assert_eq!(multi_turn_calls.len(), 1, "should succeed on the first retry turn");
+        assert_eq!(multi_turn_calls[0][0], ("user prompt".to_string(), "not json at all".to_string()));
+    }
 
-                Ok(vec![])
-            }
-            async fn get_random_voice(
-                &self,
-                gender: Option<&str>,
-                excluded: &[String],
-            ) -> Result<String> {
-                // Verify exclusion list is passed here too
-                assert!(excluded.contains(&"Voice_Narrator".to_string()));
-                assert!(excluded.contains(&"Voice_Hero".to_string()));
+    #[derive(Debug, Default)]
+    struct AlwaysInvalidLlmClient;
 
-                if gender == Some("Male") {
-                    Ok("Voice_Mob_Male_Fixed".to_string())
-                } else {
-                    Ok("Voice_Mob_Female_Fixed".to_string())
-                }
-            }
-            fn get_narrator_voice_id(&self) -> String {
-                "Voice_Narrator".to_string()
-            }
-            fn is_mob_enabled(&self) -> bool {
-                true
-            }
-            fn format_voice_list_for_analysis(&self, _voices: &[crate::services::tts::Voice]) -> String {
-                "".to_string()
-            }
-            fn get_script_generator(&self) -> Box<dyn ScriptGenerator> {
-                Box::new(JsonScriptGenerator::new())
-            }
+    #[async_trait]
+    impl LlmClient for AlwaysInvalidLlmClient {
+        async fn chat(&self, _system: &str, _user: &str) -> Result<String> {
+            Ok("still not json".to_string())
         }
 
-        let exclusions = Arc::new(Mutex::new(Vec::new()));
-        let mock_tts = Box::new(VerifyingTts {
-            exclusions: exclusions.clone(),
-        });
-        let mock_llm = Box::new(ProtagLlm);
-
-        let mut workflow = WorkflowManager::new(config, mock_llm, mock_tts)?;
-        workflow
-            .process_chapter(&input_dir.join(filename), filename)
-            .await?;
+        async fn chat_multi_turn(&self, _system: &str, _history: &[(String, String)]) -> Result<String> {
+            Ok("still not json".to_string())
+        }
+    }
 
-        let ex = exclusions.lock().unwrap();
-        assert!(ex.contains(&"Voice_Narrator".to_string()));
-        assert!(ex.contains(&"Voice_Hero".to_string()));
+    #[tokio::test]
+    async fn test_generate_script_segments_gives_up_after_max_retry_turns() {
+        let llm = AlwaysInvalidLlmClient;
+        let script_generator = JsonScriptGenerator::new();
+
+        let result = generate_script_segments(
+            &llm,
+            &script_generator,
+            "system prompt",
+            "user prompt",
+            false,
+            2,
+        )
+        .await;
 
-        Ok(())
+        assert!(result.is_err());
     }
 }
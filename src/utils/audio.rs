@@ -1,8 +1,193 @@
 use anyhow::{anyhow, Context, Result};
+use id3::frame::{Picture, PictureType};
+use id3::{Tag, TagLike, Version};
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+struct WavFmt {
+    channels: u16,
+    bits_per_sample: u16,
+    sample_rate: u32,
+}
+
+/// Parses a 16-bit PCM WAV buffer in memory, returning its format along with
+/// the byte range of the `data` chunk within `audio`. `chunk_size` comes
+/// straight from the (possibly truncated or malformed) input, so every
+/// chunk body and the `fmt` fields within it are bounds-checked against
+/// `audio.len()` before being sliced, rather than trusting the declared
+/// size - a short or corrupt response from a TTS provider should surface as
+/// an `Err`, not panic the task.
+fn parse_wav_bytes(audio: &[u8]) -> Result<(WavFmt, usize, usize)> {
+    if audio.len() < 12 || &audio[0..4] != b"RIFF" || &audio[8..12] != b"WAVE" {
+        return Err(anyhow!("Not a RIFF/WAVE buffer"));
+    }
+
+    let mut fmt: Option<WavFmt> = None;
+    let mut data_range: Option<(usize, usize)> = None;
+    let mut pos = 12;
+
+    while pos + 8 <= audio.len() {
+        let chunk_id = &audio[pos..pos + 4];
+        let chunk_size = u32::from_le_bytes(audio[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body_start = pos + 8;
+        let body_end = body_start
+            .checked_add(chunk_size)
+            .ok_or_else(|| anyhow!("WAV chunk size overflows"))?;
+        if body_end > audio.len() {
+            return Err(anyhow!(
+                "WAV chunk at offset {} claims {} bytes, past the end of a {}-byte buffer",
+                pos,
+                chunk_size,
+                audio.len()
+            ));
+        }
+
+        if chunk_id == b"fmt " {
+            if chunk_size < 16 {
+                return Err(anyhow!("WAV fmt chunk is too short: {} bytes", chunk_size));
+            }
+            let body = &audio[body_start..body_end];
+            fmt = Some(WavFmt {
+                channels: u16::from_le_bytes(body[2..4].try_into().unwrap()),
+                sample_rate: u32::from_le_bytes(body[4..8].try_into().unwrap()),
+                bits_per_sample: u16::from_le_bytes(body[14..16].try_into().unwrap()),
+            });
+        } else if chunk_id == b"data" {
+            data_range = Some((body_start, body_end));
+            break;
+        }
+
+        pos = body_end;
+    }
+
+    let fmt = fmt.ok_or_else(|| anyhow!("Missing fmt chunk"))?;
+    let data_range = data_range.ok_or_else(|| anyhow!("Missing data chunk"))?;
+    if fmt.bits_per_sample != 16 {
+        return Err(anyhow!(
+            "split_wav_at_silence only supports 16-bit PCM WAV, got {} bits",
+            fmt.bits_per_sample
+        ));
+    }
+
+    Ok((fmt, data_range.0, data_range.1))
+}
+
+/// Scans a 16-bit PCM WAV buffer for natural pauses and returns the byte
+/// offset ranges (into `audio`) of the non-silent regions between them.
+///
+/// A frame is considered silent when the peak amplitude across all channels
+/// stays below `threshold_db` (relative to full scale) for at least
+/// `min_silence_duration_ms`. Intended as a pre-split step for very long
+/// segments that some TTS engines render poorly in one pass.
+pub fn split_wav_at_silence(
+    audio: &[u8],
+    min_silence_duration_ms: u32,
+    threshold_db: f32,
+) -> Result<Vec<(u64, u64)>> {
+    let (fmt, data_start, data_end) = parse_wav_bytes(audio)?;
+    let frame_size = fmt.channels as usize * 2;
+    if frame_size == 0 || data_end <= data_start {
+        return Ok(vec![]);
+    }
+
+    let threshold_amplitude = (10f32.powf(threshold_db / 20.0) * i16::MAX as f32).abs() as i16;
+    let min_silence_frames =
+        (min_silence_duration_ms as u64 * fmt.sample_rate as u64 / 1000) as usize;
+
+    let frame_count = (data_end - data_start) / frame_size;
+    let min_silence_frames = min_silence_frames.max(1);
+    let mut ranges = Vec::new();
+    let mut region_start: Option<usize> = None;
+    let mut last_loud_frame: usize = 0;
+    let mut silence_run = 0usize;
+
+    for frame in 0..frame_count {
+        let offset = data_start + frame * frame_size;
+        let peak = (0..fmt.channels as usize)
+            .map(|c| {
+                let sample_offset = offset + c * 2;
+                i16::from_le_bytes(audio[sample_offset..sample_offset + 2].try_into().unwrap())
+                    .unsigned_abs()
+            })
+            .max()
+            .unwrap_or(0);
+
+        if (peak as i32) < threshold_amplitude as i32 {
+            silence_run += 1;
+            if silence_run >= min_silence_frames {
+                if let Some(start) = region_start.take() {
+                    ranges.push((
+                        (data_start + start * frame_size) as u64,
+                        (data_start + (last_loud_frame + 1) * frame_size) as u64,
+                    ));
+                }
+            }
+        } else {
+            if region_start.is_none() {
+                region_start = Some(frame);
+            }
+            last_loud_frame = frame;
+            silence_run = 0;
+        }
+    }
+
+    if let Some(start) = region_start {
+        ranges.push((
+            (data_start + start * frame_size) as u64,
+            data_end as u64,
+        ));
+    }
+
+    Ok(ranges)
+}
+
+/// Splits `wav` into standalone clips at the silence gaps `split_wav_at_silence`
+/// detects, each a complete, independently playable WAV file carrying the
+/// source's own sample rate/channel count. Unlike `split_wav_at_silence`
+/// (which only reports byte ranges for `post_synthesis_split`'s diagnostic
+/// logging), this is for providers that return one long audio blob per
+/// request instead of per-sentence audio, so the caller can treat each
+/// silence-delimited region as its own segment.
+pub fn split_wav_at_silence_into_clips(
+    wav: &[u8],
+    min_silence_duration_ms: u32,
+    threshold_db: f32,
+) -> Result<Vec<Vec<u8>>> {
+    let (fmt, ..) = parse_wav_bytes(wav)?;
+    let ranges = split_wav_at_silence(wav, min_silence_duration_ms, threshold_db)?;
+
+    Ok(ranges
+        .into_iter()
+        .map(|(start, end)| wrap_pcm_as_wav(&wav[start as usize..end as usize], fmt.sample_rate, fmt.channels))
+        .collect())
+}
+
+/// Wraps raw 16-bit PCM samples in a standard 44-byte RIFF/WAVE header.
+fn wrap_pcm_as_wav(pcm: &[u8], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let bits_per_sample: u16 = 16;
+    let block_align = channels * (bits_per_sample / 8);
+    let byte_rate = sample_rate * block_align as u32;
+    let data_size = pcm.len() as u32;
+
+    let mut buf = Vec::with_capacity(44 + pcm.len());
+    buf.extend_from_slice(b"RIFF");
+    buf.extend_from_slice(&(36 + data_size).to_le_bytes());
+    buf.extend_from_slice(b"WAVE");
+    buf.extend_from_slice(b"fmt ");
+    buf.extend_from_slice(&16u32.to_le_bytes());
+    buf.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    buf.extend_from_slice(&channels.to_le_bytes());
+    buf.extend_from_slice(&sample_rate.to_le_bytes());
+    buf.extend_from_slice(&byte_rate.to_le_bytes());
+    buf.extend_from_slice(&block_align.to_le_bytes());
+    buf.extend_from_slice(&bits_per_sample.to_le_bytes());
+    buf.extend_from_slice(b"data");
+    buf.extend_from_slice(&data_size.to_le_bytes());
+    buf.extend_from_slice(pcm);
+    buf
+}
+
 /// Merges multiple audio files via simple binary concatenation.
 /// Suitable for MP3 or other stream-based formats.
 pub fn merge_binary_files(input_paths: &[std::path::PathBuf], output_path: &Path) -> Result<()> {
@@ -74,6 +259,176 @@ fn scan_wav(path: &Path) -> Result<WavInfo> {
     })
 }
 
+/// Returns the duration in seconds of an audio file. Exact for WAV (parsed
+/// from the `fmt`/`data` chunks); for other formats (e.g. MP3) this only has
+/// the file size to go on, so it falls back to a rough estimate assuming a
+/// typical ~24kbps TTS bitrate.
+pub fn audio_duration_secs(path: &Path) -> Result<f64> {
+    if let Ok(info) = scan_wav(path) {
+        let channels = u16::from_le_bytes(info.fmt_content[2..4].try_into().unwrap()) as f64;
+        let sample_rate = u32::from_le_bytes(info.fmt_content[4..8].try_into().unwrap()) as f64;
+        let bits_per_sample =
+            u16::from_le_bytes(info.fmt_content[14..16].try_into().unwrap()) as f64;
+        let bytes_per_second = channels * sample_rate * (bits_per_sample / 8.0);
+        if bytes_per_second <= 0.0 {
+            return Err(anyhow!("Invalid WAV format for {:?}", path));
+        }
+        return Ok(info.data_size as f64 / bytes_per_second);
+    }
+
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {:?}", path))?
+        .len();
+    const ASSUMED_BITRATE_BYTES_PER_SEC: f64 = 24_000.0 / 8.0;
+    Ok(size as f64 / ASSUMED_BITRATE_BYTES_PER_SEC)
+}
+
+/// MPEG bitrate table in kbps, indexed by `[is_mpeg1][layer_index][bitrate_index]`.
+/// `layer_index` is `3 - layer_bits` (Layer I -> 0, Layer II -> 1, Layer III -> 2).
+/// Index 0 ("free") and 15 ("bad") are not usable frame bitrates and map to 0.
+const MPEG1_BITRATES_KBPS: [[u32; 16]; 3] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0],
+];
+const MPEG2_BITRATES_KBPS: [[u32; 16]; 3] = [
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0],
+];
+
+/// Sample rates in Hz, indexed by `[version_index][sample_rate_bits]` where
+/// `version_index` is 0 for MPEG1, 1 for MPEG2, 2 for MPEG2.5.
+const SAMPLE_RATES_HZ: [[u32; 3]; 3] = [
+    [44100, 48000, 32000],
+    [22050, 24000, 16000],
+    [11025, 12000, 8000],
+];
+
+/// One parsed MPEG audio frame header, enough to compute the frame's byte
+/// length and the number of audio samples it encodes.
+struct Mp3FrameHeader {
+    frame_size: usize,
+    samples_per_frame: u32,
+    sample_rate: u32,
+}
+
+/// Parses a 4-byte MPEG audio frame header at `data[0..4]`, returning
+/// `None` if it doesn't look like a valid header (no frame sync, or a
+/// reserved version/layer/bitrate/sample-rate field).
+fn parse_mp3_frame_header(data: &[u8]) -> Option<Mp3FrameHeader> {
+    if data.len() < 4 {
+        return None;
+    }
+    if data[0] != 0xFF || data[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+
+    let version_bits = (data[1] >> 3) & 0x03;
+    let layer_bits = (data[1] >> 1) & 0x03;
+    let bitrate_index = (data[2] >> 4) as usize;
+    let sample_rate_bits = (data[2] >> 2) & 0x03;
+    let padding = (data[2] >> 1) & 0x01;
+
+    if layer_bits == 0 || sample_rate_bits == 0x03 || bitrate_index == 0 || bitrate_index == 15 {
+        return None;
+    }
+
+    let is_mpeg1 = version_bits == 0x03;
+    let version_index = match version_bits {
+        0x03 => 0, // MPEG1
+        0x02 => 1, // MPEG2
+        0x00 => 2, // MPEG2.5
+        _ => return None,
+    };
+    let layer_index = (3 - layer_bits) as usize; // Layer I=11->0, II=10->1, III=01->2
+
+    let sample_rate = SAMPLE_RATES_HZ[version_index][sample_rate_bits as usize];
+    let bitrate_kbps = if is_mpeg1 {
+        MPEG1_BITRATES_KBPS[layer_index][bitrate_index]
+    } else {
+        MPEG2_BITRATES_KBPS[layer_index][bitrate_index]
+    };
+    let bitrate_bps = bitrate_kbps * 1000;
+
+    let samples_per_frame = if layer_index == 0 {
+        384
+    } else if layer_index == 1 || is_mpeg1 {
+        1152
+    } else {
+        576
+    };
+
+    let frame_size = if layer_index == 0 {
+        (12 * bitrate_bps / sample_rate + padding as u32) * 4
+    } else {
+        144 * bitrate_bps / sample_rate + padding as u32
+    };
+
+    if frame_size == 0 {
+        return None;
+    }
+
+    Some(Mp3FrameHeader {
+        frame_size: frame_size as usize,
+        samples_per_frame,
+        sample_rate,
+    })
+}
+
+/// Computes the exact duration of an MP3 file by walking its frame headers
+/// and summing `samples_per_frame / sample_rate` per frame, rather than
+/// estimating from the file size and an assumed bitrate. Used for M4B
+/// chapter marker timestamps, where `audio_duration_secs`'s file-size
+/// estimate isn't accurate enough to keep chapters in sync with the audio.
+pub fn mp3_duration_ms(data: &[u8]) -> Result<u64> {
+    let mut pos = existing_id3v2_len(data);
+    let mut total_samples: f64 = 0.0;
+    let mut sample_rate: u32 = 0;
+
+    while pos + 4 <= data.len() {
+        match parse_mp3_frame_header(&data[pos..]) {
+            Some(frame) => {
+                total_samples += frame.samples_per_frame as f64;
+                sample_rate = frame.sample_rate;
+                pos += frame.frame_size;
+            }
+            None => {
+                pos += 1;
+            }
+        }
+    }
+
+    if sample_rate == 0 {
+        return Err(anyhow!("No valid MPEG frames found"));
+    }
+
+    Ok((total_samples / sample_rate as f64 * 1000.0).round() as u64)
+}
+
+/// Same computation as [`mp3_duration_ms`], but reads the MP3 from disk and
+/// returns seconds. Used for M4B chapter marker timestamps, where
+/// `audio_duration_secs`'s file-size estimate isn't accurate enough to keep
+/// chapters in sync with the audio.
+pub fn mp3_duration_secs(path: &Path) -> Result<f64> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let ms = mp3_duration_ms(&data).with_context(|| format!("No valid MPEG frames found in {:?}", path))?;
+    Ok(ms as f64 / 1000.0)
+}
+
+/// Sample rate of an MP3's first valid frame. Used to generate a silence
+/// clip that matches a chapter's audio rather than guessing a rate.
+pub fn mp3_sample_rate(data: &[u8]) -> Result<u32> {
+    let mut pos = existing_id3v2_len(data);
+    while pos + 4 <= data.len() {
+        match parse_mp3_frame_header(&data[pos..]) {
+            Some(frame) => return Ok(frame.sample_rate),
+            None => pos += 1,
+        }
+    }
+    Err(anyhow!("No valid MPEG frames found"))
+}
+
 /// Merges multiple WAV files by parsing headers and concatenating data chunks.
 /// Ensures all files have compatible format (fmt chunk).
 pub fn merge_wav_files(input_paths: &[std::path::PathBuf], output_path: &Path) -> Result<()> {
@@ -137,6 +492,295 @@ pub fn merge_wav_files(input_paths: &[std::path::PathBuf], output_path: &Path) -
     Ok(())
 }
 
+/// Reads the `(sample_rate, channels)` pair from a WAV file's `fmt` chunk,
+/// for matching a generated silence clip to a provider's real output format.
+pub fn wav_format(path: &Path) -> Result<(u32, u16)> {
+    let info = scan_wav(path)?;
+    let channels = u16::from_le_bytes(info.fmt_content[2..4].try_into().unwrap());
+    let sample_rate = u32::from_le_bytes(info.fmt_content[4..8].try_into().unwrap());
+    Ok((sample_rate, channels))
+}
+
+/// Applies a linear peak-normalizing gain to a 16-bit PCM WAV buffer so its
+/// loudest sample sits at `target_db` dBFS (clamped to 0 dB max). Returns a
+/// new buffer; silent input (peak of 0) is returned unchanged since there's
+/// no gain that would help it.
+pub fn normalize_wav_peak(data: &[u8], target_db: f32) -> Result<Vec<u8>> {
+    let (_fmt, data_start, data_end) = parse_wav_bytes(data)?;
+
+    let mut samples: Vec<i16> = data[data_start..data_end]
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+
+    let peak = samples.iter().map(|&s| (s as i32).unsigned_abs()).max().unwrap_or(0);
+    if peak == 0 {
+        return Ok(data.to_vec());
+    }
+
+    let target_db = target_db.min(0.0);
+    let target_peak = i16::MAX as f32 * 10f32.powf(target_db / 20.0);
+    let gain = target_peak / peak as f32;
+
+    for sample in samples.iter_mut() {
+        let scaled = (*sample as f32 * gain).round();
+        *sample = scaled.clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..data_start]);
+    for s in &samples {
+        out.extend_from_slice(&s.to_le_bytes());
+    }
+    out.extend_from_slice(&data[data_end..]);
+    Ok(out)
+}
+
+/// Linearly ramps amplitude up from silence over the first `duration_ms` of
+/// a 16-bit PCM WAV buffer, so a chapter's opening segment doesn't start
+/// abruptly. `duration_ms` of `0` is a no-op; a buffer shorter than the
+/// requested duration fades over its entire length instead of erroring.
+pub fn apply_fade_in(wav: &[u8], duration_ms: u32) -> Result<Vec<u8>> {
+    apply_fade(wav, duration_ms, Fade::In)
+}
+
+/// Mirror of `apply_fade_in`: linearly ramps amplitude down to silence over
+/// the last `duration_ms` of the buffer.
+pub fn apply_fade_out(wav: &[u8], duration_ms: u32) -> Result<Vec<u8>> {
+    apply_fade(wav, duration_ms, Fade::Out)
+}
+
+enum Fade {
+    In,
+    Out,
+}
+
+fn apply_fade(wav: &[u8], duration_ms: u32, direction: Fade) -> Result<Vec<u8>> {
+    if duration_ms == 0 {
+        return Ok(wav.to_vec());
+    }
+
+    let (fmt, data_start, data_end) = parse_wav_bytes(wav)?;
+    let channels = fmt.channels as usize;
+    if channels == 0 || data_end <= data_start {
+        return Ok(wav.to_vec());
+    }
+
+    let mut samples: Vec<i16> = wav[data_start..data_end]
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+
+    let frame_count = samples.len() / channels;
+    let fade_frames = ((duration_ms as u64 * fmt.sample_rate as u64 / 1000) as usize)
+        .min(frame_count)
+        .max(1);
+
+    for frame in 0..fade_frames {
+        let gain = match direction {
+            Fade::In => frame as f32 / fade_frames as f32,
+            Fade::Out => (fade_frames - frame) as f32 / fade_frames as f32,
+        };
+        let target_frame = match direction {
+            Fade::In => frame,
+            Fade::Out => frame_count - fade_frames + frame,
+        };
+        for channel in 0..channels {
+            let sample = &mut samples[target_frame * channels + channel];
+            *sample = (*sample as f32 * gain).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+
+    let mut out = Vec::with_capacity(wav.len());
+    out.extend_from_slice(&wav[..data_start]);
+    for s in &samples {
+        out.extend_from_slice(&s.to_le_bytes());
+    }
+    out.extend_from_slice(&wav[data_end..]);
+    Ok(out)
+}
+
+/// Transcodes a 16-bit PCM WAV buffer to a constant-bitrate MP3 via LAME.
+/// `bitrate_kbps` is snapped down to the nearest bitrate LAME supports.
+pub fn encode_to_mp3(wav_data: &[u8], bitrate_kbps: u32) -> Result<Vec<u8>> {
+    let (fmt, data_start, data_end) = parse_wav_bytes(wav_data)?;
+    let samples: Vec<i16> = wav_data[data_start..data_end]
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+
+    let mut builder = mp3lame_encoder::Builder::new()
+        .ok_or_else(|| anyhow!("Failed to create LAME encoder"))?;
+    builder
+        .set_num_channels(fmt.channels as u8)
+        .map_err(|e| anyhow!("Failed to set MP3 channel count: {:?}", e))?;
+    builder
+        .set_sample_rate(fmt.sample_rate)
+        .map_err(|e| anyhow!("Failed to set MP3 sample rate: {:?}", e))?;
+    builder
+        .set_brate(bitrate_to_lame(bitrate_kbps))
+        .map_err(|e| anyhow!("Failed to set MP3 bitrate: {:?}", e))?;
+    let mut encoder = builder
+        .build()
+        .map_err(|e| anyhow!("Failed to initialize LAME encoder: {:?}", e))?;
+
+    let mut mp3_out = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(samples.len()));
+    let encoded_size = if fmt.channels == 1 {
+        encoder
+            .encode(mp3lame_encoder::MonoPcm(&samples), mp3_out.spare_capacity_mut())
+            .map_err(|e| anyhow!("Failed to encode MP3: {:?}", e))?
+    } else {
+        encoder
+            .encode(
+                mp3lame_encoder::InterleavedPcm(&samples),
+                mp3_out.spare_capacity_mut(),
+            )
+            .map_err(|e| anyhow!("Failed to encode MP3: {:?}", e))?
+    };
+    unsafe {
+        mp3_out.set_len(mp3_out.len() + encoded_size);
+    }
+
+    let flushed_size = encoder
+        .flush::<mp3lame_encoder::FlushNoGap>(mp3_out.spare_capacity_mut())
+        .map_err(|e| anyhow!("Failed to flush MP3 encoder: {:?}", e))?;
+    unsafe {
+        mp3_out.set_len(mp3_out.len() + flushed_size);
+    }
+
+    Ok(mp3_out)
+}
+
+fn bitrate_to_lame(kbps: u32) -> mp3lame_encoder::Bitrate {
+    use mp3lame_encoder::Bitrate;
+    match kbps {
+        0..=8 => Bitrate::Kbps8,
+        9..=16 => Bitrate::Kbps16,
+        17..=24 => Bitrate::Kbps24,
+        25..=32 => Bitrate::Kbps32,
+        33..=40 => Bitrate::Kbps40,
+        41..=48 => Bitrate::Kbps48,
+        49..=56 => Bitrate::Kbps56,
+        57..=64 => Bitrate::Kbps64,
+        65..=80 => Bitrate::Kbps80,
+        81..=96 => Bitrate::Kbps96,
+        97..=112 => Bitrate::Kbps112,
+        113..=128 => Bitrate::Kbps128,
+        129..=160 => Bitrate::Kbps160,
+        161..=192 => Bitrate::Kbps192,
+        193..=224 => Bitrate::Kbps224,
+        225..=256 => Bitrate::Kbps256,
+        _ => Bitrate::Kbps320,
+    }
+}
+
+/// Transcodes a 16-bit PCM WAV buffer to OGG Vorbis. `quality` is clamped to
+/// `0.0`-`1.0`, matching `vorbis_encoder`'s own range (higher is better
+/// quality and larger files).
+pub fn encode_to_ogg(wav_data: &[u8], quality: f32) -> Result<Vec<u8>> {
+    let (fmt, data_start, data_end) = parse_wav_bytes(wav_data)?;
+    let samples: Vec<i16> = wav_data[data_start..data_end]
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+
+    let mut encoder = vorbis_encoder::Encoder::new(
+        fmt.channels as u8,
+        fmt.sample_rate as u64,
+        quality.clamp(0.0, 1.0),
+    )
+    .map_err(|e| anyhow!("Failed to create OGG Vorbis encoder: {:?}", e))?;
+
+    let mut ogg_out = encoder
+        .encode(&samples)
+        .map_err(|e| anyhow!("Failed to encode OGG Vorbis: {:?}", e))?;
+    ogg_out.extend(
+        encoder
+            .flush()
+            .map_err(|e| anyhow!("Failed to flush OGG Vorbis encoder: {:?}", e))?,
+    );
+
+    Ok(ogg_out)
+}
+
+/// Generates a silent 16-bit PCM WAV buffer of `duration_ms`, for the gaps
+/// `WorkflowManager` inserts between synthesized segments.
+pub fn generate_silence_wav(duration_ms: u32, sample_rate: u32, channels: u16) -> Vec<u8> {
+    let bits_per_sample: u16 = 16;
+    let block_align = channels * (bits_per_sample / 8);
+    let byte_rate = sample_rate * block_align as u32;
+    let num_samples = (sample_rate as u64 * duration_ms as u64) / 1000;
+    let data_size = (num_samples as u32).saturating_mul(block_align as u32);
+
+    let mut buf = Vec::with_capacity(44 + data_size as usize);
+    buf.extend_from_slice(b"RIFF");
+    buf.extend_from_slice(&(36 + data_size).to_le_bytes());
+    buf.extend_from_slice(b"WAVE");
+    buf.extend_from_slice(b"fmt ");
+    buf.extend_from_slice(&16u32.to_le_bytes());
+    buf.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    buf.extend_from_slice(&channels.to_le_bytes());
+    buf.extend_from_slice(&sample_rate.to_le_bytes());
+    buf.extend_from_slice(&byte_rate.to_le_bytes());
+    buf.extend_from_slice(&block_align.to_le_bytes());
+    buf.extend_from_slice(&bits_per_sample.to_le_bytes());
+    buf.extend_from_slice(b"data");
+    buf.extend_from_slice(&data_size.to_le_bytes());
+    buf.resize(buf.len() + data_size as usize, 0);
+    buf
+}
+
+/// Returns the byte length of an existing ID3v2 header at the start of
+/// `data` (10-byte header plus its declared tag size), or 0 if `data`
+/// doesn't start with one.
+fn existing_id3v2_len(data: &[u8]) -> usize {
+    if data.len() < 10 || &data[0..3] != b"ID3" {
+        return 0;
+    }
+    let size = ((data[6] as u32 & 0x7f) << 21)
+        | ((data[7] as u32 & 0x7f) << 14)
+        | ((data[8] as u32 & 0x7f) << 7)
+        | (data[9] as u32 & 0x7f);
+    10 + size as usize
+}
+
+/// Embeds `TIT2`/`TPE1`/`TALB`/`TRCK` ID3v2 frames (and an optional cover
+/// image) into an MP3 buffer in place. Any existing ID3v2 tag at the start
+/// of `mp3` is replaced rather than stacked on top of.
+pub fn embed_id3_tags(
+    mp3: &mut Vec<u8>,
+    title: &str,
+    artist: &str,
+    album: &str,
+    track: u32,
+    cover_jpeg: Option<&[u8]>,
+) -> Result<()> {
+    let mut tag = Tag::new();
+    tag.set_title(title);
+    tag.set_artist(artist);
+    tag.set_album(album);
+    tag.set_track(track);
+
+    if let Some(jpeg) = cover_jpeg {
+        tag.add_frame(Picture {
+            mime_type: "image/jpeg".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: jpeg.to_vec(),
+        });
+    }
+
+    let audio_start = existing_id3v2_len(mp3);
+    let mut tagged = Cursor::new(Vec::new());
+    tag.write_to(&mut tagged, Version::Id3v24)
+        .context("failed to write ID3 tag")?;
+    let mut tagged = tagged.into_inner();
+    tagged.extend_from_slice(&mp3[audio_start..]);
+    *mp3 = tagged;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,6 +809,31 @@ mod tests {
         buf
     }
 
+    #[test]
+    fn test_audio_duration_secs_wav() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("1.wav");
+        // 16-bit mono PCM at 44100Hz, 1 second of audio.
+        std::fs::write(&path, create_dummy_wav(44100 * 2, 44100))?;
+
+        let duration = audio_duration_secs(&path)?;
+        assert!((duration - 1.0).abs() < 0.01, "duration was {}", duration);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audio_duration_secs_non_wav_falls_back_to_size_estimate() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("1.mp3");
+        std::fs::write(&path, vec![0u8; 3000])?;
+
+        let duration = audio_duration_secs(&path)?;
+        assert!((duration - 1.0).abs() < 0.01, "duration was {}", duration);
+
+        Ok(())
+    }
+
     #[test]
     fn test_merge_wav_files() -> Result<()> {
         let temp_dir = tempfile::tempdir()?;
@@ -183,7 +852,292 @@ mod tests {
         let info = scan_wav(&output)?;
         assert_eq!(info.data_size, 30);
         assert_eq!(info.fmt_content.len(), 16);
-        
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_silence_wav_size_matches_duration() {
+        // 500ms at 44100Hz, stereo, 16-bit: 44100 * 0.5 * 2 channels * 2 bytes
+        let wav = generate_silence_wav(500, 44100, 2);
+        let (fmt, data_start, data_end) = parse_wav_bytes(&wav).unwrap();
+        assert_eq!(fmt.sample_rate, 44100);
+        assert_eq!(fmt.channels, 2);
+        assert_eq!(fmt.bits_per_sample, 16);
+        assert_eq!(data_end - data_start, 44100 / 2 * 2 * 2);
+        assert!(wav[data_start..data_end].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_encode_to_mp3_produces_smaller_valid_mp3() -> Result<()> {
+        let wav = generate_silence_wav(2000, 44100, 2);
+
+        let mp3 = encode_to_mp3(&wav, 128)?;
+
+        assert!(
+            mp3.len() >= 2 && mp3[0] == 0xFF && (mp3[1] & 0xE0) == 0xE0,
+            "encoded output should start with an MP3 frame sync word"
+        );
+        assert!(
+            mp3.len() < wav.len(),
+            "MP3 output ({} bytes) should be smaller than the source WAV ({} bytes)",
+            mp3.len(),
+            wav.len()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_to_ogg_produces_valid_ogg() -> Result<()> {
+        let wav = generate_silence_wav(2000, 44100, 2);
+
+        let ogg = encode_to_ogg(&wav, 0.5)?;
+
+        assert!(
+            ogg.starts_with(b"OggS"),
+            "encoded output should start with the OGG page magic bytes"
+        );
+
+        Ok(())
+    }
+
+    fn create_wav_with_peak(peak: i16, sample_count: usize, sample_rate: u32) -> Vec<u8> {
+        let mut samples = vec![0i16; sample_count];
+        samples[0] = peak;
+        samples[sample_count / 2] = -peak;
+        create_wav_with_samples(&samples, sample_rate)
+    }
+
+    #[test]
+    fn test_normalize_wav_peak_reaches_target_level() -> Result<()> {
+        // A quiet clip peaking at roughly -20 dBFS.
+        let quiet_peak = (i16::MAX as f32 * 10f32.powf(-20.0 / 20.0)) as i16;
+        let wav = create_wav_with_peak(quiet_peak, 100, 44100);
+
+        let normalized = normalize_wav_peak(&wav, -3.0)?;
+
+        let (_, data_start, data_end) = parse_wav_bytes(&normalized)?;
+        let peak_after = normalized[data_start..data_end]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]).unsigned_abs())
+            .max()
+            .unwrap();
+
+        let expected_peak = i16::MAX as f32 * 10f32.powf(-3.0 / 20.0);
+        assert!(
+            (peak_after as f32 - expected_peak).abs() < expected_peak * 0.02,
+            "peak_after = {}, expected ~{}",
+            peak_after,
+            expected_peak
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_wav_peak_clamps_target_above_zero_db() -> Result<()> {
+        let wav = create_wav_with_peak(i16::MAX / 2, 10, 44100);
+
+        let normalized = normalize_wav_peak(&wav, 6.0)?;
+
+        let (_, data_start, data_end) = parse_wav_bytes(&normalized)?;
+        let peak_after = normalized[data_start..data_end]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]).unsigned_abs())
+            .max()
+            .unwrap();
+
+        assert!(
+            peak_after as i32 <= i16::MAX as i32,
+            "peak should be clamped at 0 dBFS, got {}",
+            peak_after
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_wav_peak_leaves_silent_clip_unchanged() -> Result<()> {
+        let wav = create_wav_with_samples(&vec![0i16; 10], 44100);
+
+        let normalized = normalize_wav_peak(&wav, -3.0)?;
+
+        assert_eq!(normalized, wav);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_fade_in_ramps_up_from_silence() -> Result<()> {
+        // 100 samples at 1000Hz = 100ms, constant full-scale amplitude.
+        let wav = create_wav_with_samples(&vec![i16::MAX; 100], 1000);
+
+        let faded = apply_fade_in(&wav, 50)?;
+
+        let (_, data_start, data_end) = parse_wav_bytes(&faded)?;
+        let samples: Vec<i16> = faded[data_start..data_end]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+
+        assert_eq!(samples[0], 0, "first sample should start at silence");
+        for pair in samples[..50].windows(2) {
+            assert!(
+                pair[1] >= pair[0],
+                "fade-in samples should be non-decreasing: {:?}",
+                pair
+            );
+        }
+        // Samples after the fade window keep the original full amplitude.
+        assert_eq!(samples[99], i16::MAX);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_fade_out_ramps_down_to_silence() -> Result<()> {
+        let wav = create_wav_with_samples(&vec![i16::MAX; 100], 1000);
+
+        let faded = apply_fade_out(&wav, 50)?;
+
+        let (_, data_start, data_end) = parse_wav_bytes(&faded)?;
+        let samples: Vec<i16> = faded[data_start..data_end]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+
+        assert_eq!(samples[0], i16::MAX, "samples before the fade window are untouched");
+        for pair in samples[50..].windows(2) {
+            assert!(
+                pair[1] <= pair[0],
+                "fade-out samples should be non-increasing: {:?}",
+                pair
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_fade_zero_duration_is_a_no_op() -> Result<()> {
+        let wav = create_wav_with_samples(&vec![i16::MAX; 10], 1000);
+
+        assert_eq!(apply_fade_in(&wav, 0)?, wav);
+        assert_eq!(apply_fade_out(&wav, 0)?, wav);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wav_format_reads_sample_rate_and_channels() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("1.wav");
+        std::fs::write(&path, create_dummy_wav(100, 32000))?;
+
+        let (sample_rate, channels) = wav_format(&path)?;
+        assert_eq!(sample_rate, 32000);
+        assert_eq!(channels, 1);
+
+        Ok(())
+    }
+
+    fn create_wav_with_samples(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+        let size = (samples.len() * 2) as u32;
+        let mut buf = Vec::new();
+        buf.write_all(b"RIFF").unwrap();
+        let total_size = 36 + size;
+        buf.write_all(&total_size.to_le_bytes()).unwrap();
+        buf.write_all(b"WAVE").unwrap();
+
+        buf.write_all(b"fmt ").unwrap();
+        buf.write_all(&16u32.to_le_bytes()).unwrap();
+        buf.write_all(&1u16.to_le_bytes()).unwrap();
+        buf.write_all(&1u16.to_le_bytes()).unwrap();
+        buf.write_all(&sample_rate.to_le_bytes()).unwrap();
+        buf.write_all(&(sample_rate * 2).to_le_bytes()).unwrap();
+        buf.write_all(&2u16.to_le_bytes()).unwrap();
+        buf.write_all(&16u16.to_le_bytes()).unwrap();
+
+        buf.write_all(b"data").unwrap();
+        buf.write_all(&size.to_le_bytes()).unwrap();
+        for s in samples {
+            buf.write_all(&s.to_le_bytes()).unwrap();
+        }
+
+        buf
+    }
+
+    #[test]
+    fn test_split_wav_at_silence_finds_single_loud_region() -> Result<()> {
+        let sample_rate = 1000;
+        let mut samples = vec![0i16; 50]; // 50ms silence
+        samples.extend(vec![20000i16; 100]); // 100ms loud
+        samples.extend(vec![0i16; 50]); // 50ms silence
+
+        let wav = create_wav_with_samples(&samples, sample_rate);
+        let regions = split_wav_at_silence(&wav, 30, -40.0)?;
+
+        assert_eq!(regions.len(), 1);
+        let data_start = 44u64; // standard 44-byte header for this layout
+        let (start, end) = regions[0];
+        assert_eq!(start, data_start + 50 * 2);
+        assert_eq!(end, data_start + 150 * 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_wav_at_silence_all_silent_returns_no_regions() -> Result<()> {
+        let wav = create_wav_with_samples(&vec![0i16; 200], 1000);
+        let regions = split_wav_at_silence(&wav, 30, -40.0)?;
+        assert!(regions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_wav_at_silence_rejects_truncated_data_chunk_instead_of_panicking() {
+        let mut wav = create_wav_with_samples(&vec![20000i16; 100], 1000);
+        // Claim a data chunk far larger than what's actually in the buffer.
+        // The data chunk's 4-byte size field sits right before its payload.
+        let data_size_field = wav.len() - 100 * 2 - 4;
+        wav[data_size_field..data_size_field + 4].copy_from_slice(&(1_000_000u32).to_le_bytes());
+
+        let result = split_wav_at_silence(&wav, 30, -40.0);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_split_wav_at_silence_rejects_truncated_fmt_chunk_instead_of_panicking() {
+        let mut wav = create_wav_with_samples(&vec![20000i16; 10], 1000);
+        // fmt chunk size is the u32 right after b"fmt " at offset 16.
+        wav[16..20].copy_from_slice(&(2u32).to_le_bytes());
+        wav.truncate(20 + 2);
+
+        let result = split_wav_at_silence(&wav, 30, -40.0);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_split_wav_at_silence_into_clips_returns_standalone_wavs() -> Result<()> {
+        let sample_rate = 1000;
+        let mut samples = vec![20000i16; 100]; // 100ms loud
+        samples.extend(vec![0i16; 50]); // 50ms silence
+        samples.extend(vec![20000i16; 100]); // 100ms loud
+
+        let wav = create_wav_with_samples(&samples, sample_rate);
+        let clips = split_wav_at_silence_into_clips(&wav, 30, -40.0)?;
+
+        assert_eq!(clips.len(), 2);
+        for clip in &clips {
+            let (fmt, data_start, data_end) = parse_wav_bytes(clip)?;
+            assert_eq!(fmt.sample_rate, sample_rate);
+            assert_eq!(fmt.channels, 1);
+            assert!(data_end > data_start);
+        }
+
         Ok(())
     }
 
@@ -203,4 +1157,109 @@ mod tests {
         assert_eq!(content, b"HelloWorld");
         Ok(())
     }
+
+    #[test]
+    fn test_embed_id3_tags_round_trips_text_frames() -> Result<()> {
+        let mut mp3 = b"not real mpeg data, just a placeholder".to_vec();
+
+        embed_id3_tags(&mut mp3, "Chapter One", "Jane Author", "My Book", 1, None)?;
+
+        let tag = Tag::read_from(&mp3[..])?;
+        assert_eq!(tag.title(), Some("Chapter One"));
+        assert_eq!(tag.artist(), Some("Jane Author"));
+        assert_eq!(tag.album(), Some("My Book"));
+        assert_eq!(tag.track(), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_embed_id3_tags_round_trips_cover_image() -> Result<()> {
+        let mut mp3 = b"placeholder mpeg data".to_vec();
+        let cover = vec![0xFFu8, 0xD8, 0xFF, 0xD9]; // minimal fake JPEG bytes
+
+        embed_id3_tags(&mut mp3, "Title", "Artist", "Album", 2, Some(&cover))?;
+
+        let tag = Tag::read_from(&mp3[..])?;
+        let picture = tag
+            .pictures()
+            .next()
+            .expect("cover picture should be present");
+        assert_eq!(picture.mime_type, "image/jpeg");
+        assert_eq!(picture.data, cover);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_embed_id3_tags_replaces_existing_tag_instead_of_stacking() -> Result<()> {
+        let mut mp3 = b"audio bytes".to_vec();
+        embed_id3_tags(&mut mp3, "First", "A", "B", 1, None)?;
+        let first_len = mp3.len();
+
+        embed_id3_tags(&mut mp3, "Second", "A", "B", 1, None)?;
+
+        let tag = Tag::read_from(&mp3[..])?;
+        assert_eq!(tag.title(), Some("Second"));
+        assert!(
+            (mp3.len() as i64 - first_len as i64).abs() < 64,
+            "Re-tagging shouldn't stack a second ID3 header on top of the first"
+        );
+
+        Ok(())
+    }
+
+    /// Builds `frame_count` back-to-back MPEG1 Layer III frames at 44100Hz /
+    /// 128kbps, zero-padded to their exact frame size.
+    fn create_dummy_mp3(frame_count: usize) -> Vec<u8> {
+        const FRAME_SIZE: usize = 417; // 144 * 128000 / 44100
+        let mut buf = Vec::new();
+        for _ in 0..frame_count {
+            buf.extend_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+            buf.resize(buf.len() + FRAME_SIZE - 4, 0);
+        }
+        buf
+    }
+
+    #[test]
+    fn test_mp3_duration_secs() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("test.mp3");
+        std::fs::write(&path, create_dummy_mp3(10))?;
+
+        let duration = mp3_duration_secs(&path)?;
+        let expected = 10.0 * 1152.0 / 44100.0;
+        assert!(
+            (duration - expected).abs() < 0.001,
+            "expected ~{expected}s, got {duration}s"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mp3_duration_ms_matches_mp3_duration_secs() -> Result<()> {
+        let data = create_dummy_mp3(10);
+        let expected_ms = (10.0 * 1152.0 / 44100.0 * 1000.0).round() as u64;
+        assert_eq!(mp3_duration_ms(&data)?, expected_ms);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mp3_duration_secs_skips_leading_id3_tag() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("tagged.mp3");
+        let mut data = create_dummy_mp3(4);
+        embed_id3_tags(&mut data, "Title", "Artist", "Album", 1, None)?;
+        std::fs::write(&path, data)?;
+
+        let duration = mp3_duration_secs(&path)?;
+        let expected = 4.0 * 1152.0 / 44100.0;
+        assert!(
+            (duration - expected).abs() < 0.001,
+            "expected ~{expected}s, got {duration}s"
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,256 @@
+// EPUB chapter extraction helpers.
+use anyhow::{anyhow, Result};
+use epub::doc::EpubDoc;
+use std::io::Cursor;
+
+/// Extracts the chapter text from an EPUB file given as raw bytes, in the
+/// order the EPUB's spine defines. Each returned pair is
+/// `(synthetic_filename, chapter_text)`; the filename is only used to name
+/// the temporary `.txt` file the chapter gets written to before being
+/// processed like any other input chapter, so it doesn't need to match
+/// anything inside the EPUB itself. It's derived purely from the spine
+/// index (not the manifest item id, which is attacker-controlled content
+/// from the untrusted EPUB and could otherwise smuggle a `/` or `..` into
+/// the path the caller joins it onto).
+pub fn extract_chapters(bytes: &[u8]) -> Result<Vec<(String, String)>> {
+    let mut doc = EpubDoc::from_reader(Cursor::new(bytes.to_vec()))
+        .map_err(|e| anyhow!("Failed to open EPUB: {}", e))?;
+
+    let spine = doc.spine.clone();
+    let mut chapters = Vec::with_capacity(spine.len());
+
+    for (i, id) in spine.iter().enumerate() {
+        let Some((content, _mime)) = doc.get_resource_str(id) else {
+            continue;
+        };
+
+        let text = html2text::from_read(content.as_bytes(), usize::MAX);
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+
+        let filename = format!("{:04}.txt", i);
+        chapters.push((filename, text));
+    }
+
+    Ok(chapters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc: u32 = 0xFFFFFFFF;
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                if crc & 1 != 0 {
+                    crc = (crc >> 1) ^ 0xEDB88320;
+                } else {
+                    crc >>= 1;
+                }
+            }
+        }
+        !crc
+    }
+
+    struct ZipEntry {
+        name: &'static str,
+        data: Vec<u8>,
+    }
+
+    /// Hand-rolls a minimal, stored-only (no compression) ZIP archive so the
+    /// EPUB parsing tests don't need a `zip`-writing dependency of their own.
+    fn build_zip(entries: &[ZipEntry]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut offsets = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            offsets.push(buf.len() as u32);
+            let crc = crc32(&entry.data);
+            let name_bytes = entry.name.as_bytes();
+
+            buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+            buf.extend_from_slice(&20u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&crc.to_le_bytes());
+            buf.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(name_bytes);
+            buf.extend_from_slice(&entry.data);
+        }
+
+        let mut central = Vec::new();
+        for (entry, &offset) in entries.iter().zip(offsets.iter()) {
+            let crc = crc32(&entry.data);
+            let name_bytes = entry.name.as_bytes();
+
+            central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&crc.to_le_bytes());
+            central.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u16.to_le_bytes());
+            central.extend_from_slice(&0u32.to_le_bytes());
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name_bytes);
+        }
+
+        let cd_offset = buf.len() as u32;
+        let cd_size = central.len() as u32;
+        buf.extend_from_slice(&central);
+
+        buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&cd_size.to_le_bytes());
+        buf.extend_from_slice(&cd_offset.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+
+        buf
+    }
+
+    fn minimal_epub(chapter_bodies: &[&str]) -> Vec<u8> {
+        let container_xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#
+            .to_vec();
+
+        let manifest_items: String = chapter_bodies
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                format!(
+                    r#"<item id="chap{i}" href="chap{i}.xhtml" media-type="application/xhtml+xml"/>"#,
+                    i = i
+                )
+            })
+            .collect();
+        let spine_items: String = chapter_bodies
+            .iter()
+            .enumerate()
+            .map(|(i, _)| format!(r#"<itemref idref="chap{i}"/>"#, i = i))
+            .collect();
+
+        let content_opf = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" unique-identifier="BookId" version="2.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Test Book</dc:title>
+    <dc:language>en</dc:language>
+    <dc:identifier id="BookId">urn:uuid:test-book</dc:identifier>
+  </metadata>
+  <manifest>
+    {manifest_items}
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+  </manifest>
+  <spine toc="ncx">
+    {spine_items}
+  </spine>
+</package>"#,
+            manifest_items = manifest_items,
+            spine_items = spine_items
+        )
+        .into_bytes();
+
+        let toc_ncx = br#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head/>
+  <docTitle><text>Test Book</text></docTitle>
+  <navMap/>
+</ncx>"#
+            .to_vec();
+
+        let mut entries = vec![
+            ZipEntry {
+                name: "mimetype",
+                data: b"application/epub+zip".to_vec(),
+            },
+            ZipEntry {
+                name: "META-INF/container.xml",
+                data: container_xml,
+            },
+            ZipEntry {
+                name: "OEBPS/content.opf",
+                data: content_opf,
+            },
+            ZipEntry {
+                name: "OEBPS/toc.ncx",
+                data: toc_ncx,
+            },
+        ];
+
+        // Leaked names so `ZipEntry::name` (a `&'static str`) can reference
+        // per-chapter strings built at runtime.
+        for (i, body) in chapter_bodies.iter().enumerate() {
+            let xhtml = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head><title>Chapter {i}</title></head>
+<body><h1>Chapter {i}</h1><p>{body}</p></body>
+</html>"#,
+                i = i,
+                body = body
+            )
+            .into_bytes();
+            let name: &'static str = Box::leak(format!("OEBPS/chap{}.xhtml", i).into_boxed_str());
+            entries.push(ZipEntry { name, data: xhtml });
+        }
+
+        build_zip(&entries)
+    }
+
+    #[test]
+    fn test_extract_chapters_respects_spine_order() {
+        let bytes = minimal_epub(&[
+            "This is the first chapter text.",
+            "This is the second chapter text.",
+        ]);
+
+        let chapters = extract_chapters(&bytes).unwrap();
+
+        assert_eq!(chapters.len(), 2);
+        assert!(chapters[0].1.contains("first chapter"));
+        assert!(chapters[1].1.contains("second chapter"));
+    }
+
+    #[test]
+    fn test_extract_chapters_filenames_are_unique_and_ordered() {
+        let bytes = minimal_epub(&["One.", "Two.", "Three."]);
+
+        let chapters = extract_chapters(&bytes).unwrap();
+
+        assert_eq!(chapters.len(), 3);
+        let filenames: Vec<&str> = chapters.iter().map(|(name, _)| name.as_str()).collect();
+        let mut sorted = filenames.clone();
+        sorted.sort();
+        assert_eq!(filenames, sorted, "Filenames should sort in spine order");
+    }
+
+    #[test]
+    fn test_extract_chapters_rejects_non_epub_bytes() {
+        let result = extract_chapters(b"not an epub file");
+        assert!(result.is_err());
+    }
+}
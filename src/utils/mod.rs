@@ -1,2 +1,8 @@
 // Utility functions module
 pub mod audio;
+pub mod epub;
+pub mod ssml;
+pub mod storage;
+pub mod subtitle;
+pub mod template;
+pub mod text;
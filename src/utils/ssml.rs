@@ -0,0 +1,54 @@
+//! Small SSML string-building helpers shared across TTS providers.
+//!
+//! Per-character speaking rate is already threaded end-to-end via
+//! `core::state::CharacterInfo::speed` and `services::tts::edge::apply_prosody`,
+//! which looks the rate up from the character map by speaker name and wraps
+//! the segment in a percentage-based `<prosody rate='+NN%'>` tag. This module
+//! adds [`rate_to_prosody_tag`] as the named-bucket (`slow`/`medium`/`fast`)
+//! convenience for call sites that prefer semantic SSML rate keywords over a
+//! raw percentage; it does not replace the existing percentage-based path.
+
+/// Converts a speaking rate multiplier (`1.0` = normal) into the value of an
+/// Edge TTS `<prosody rate="...">` attribute. Rates close to the named Edge
+/// TTS presets collapse to those keywords (`slow`, `medium`, `fast`);
+/// anything else falls back to a signed percentage, matching how Edge TTS
+/// itself documents the `rate` attribute.
+pub fn rate_to_prosody_tag(rate: f32) -> String {
+    if (rate - 0.8).abs() < f32::EPSILON {
+        return "slow".to_string();
+    }
+    if (rate - 1.0).abs() < f32::EPSILON {
+        return "medium".to_string();
+    }
+    if (rate - 1.5).abs() < f32::EPSILON {
+        return "fast".to_string();
+    }
+
+    format!("{:+.0}%", (rate - 1.0) * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_to_prosody_tag_slow_preset() {
+        assert_eq!(rate_to_prosody_tag(0.8), "slow");
+    }
+
+    #[test]
+    fn test_rate_to_prosody_tag_normal_preset() {
+        assert_eq!(rate_to_prosody_tag(1.0), "medium");
+    }
+
+    #[test]
+    fn test_rate_to_prosody_tag_fast_preset() {
+        assert_eq!(rate_to_prosody_tag(1.5), "fast");
+    }
+
+    #[test]
+    fn test_rate_to_prosody_tag_falls_back_to_percentage() {
+        assert_eq!(rate_to_prosody_tag(1.2), "+20%");
+        assert_eq!(rate_to_prosody_tag(0.9), "-10%");
+    }
+}
@@ -0,0 +1,144 @@
+// Sandboxed filesystem access, so a maliciously crafted `config.yml` or LLM
+// output containing a `../../`-style path can't read or write outside the
+// project directory it was handed.
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves every `read`/`write` path against `root` and rejects any that
+/// would escape it. Resolution is purely lexical (`.`/`..` components are
+/// collapsed without touching the filesystem, so it also works for `write`
+/// targets that don't exist yet); it doesn't follow symlinks, so a symlink
+/// planted inside `root` that points outside of it is not caught.
+///
+/// Reads and writes go straight through `std::fs` on native builds. There's
+/// no `Storage`/`WebStorage` abstraction in this crate yet (see the
+/// top-of-crate comment in `lib.rs`), so under wasm32 `read`/`write` just
+/// return an error and `exists` reports `false`.
+#[derive(Debug, Clone)]
+pub struct NativeStorage {
+    root: PathBuf,
+}
+
+impl NativeStorage {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: normalize_path(root),
+        }
+    }
+
+    fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        let candidate = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+        let resolved = normalize_path(&candidate);
+        if !resolved.starts_with(&self.root) {
+            return Err(anyhow!("Path traversal detected: {:?}", path));
+        }
+        Ok(resolved)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read(&self, path: &Path) -> Result<String> {
+        let resolved = self.resolve(path)?;
+        Ok(fs::read_to_string(resolved)?)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn read(&self, path: &Path) -> Result<String> {
+        self.resolve(path)?;
+        Err(anyhow!("NativeStorage is not available under wasm32"))
+    }
+
+    /// `true` if `path` both stays within `root` and exists on disk. Always
+    /// `false` under wasm32, where there's no filesystem to check.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn exists(&self, path: &Path) -> bool {
+        self.resolve(path).is_ok_and(|resolved| resolved.exists())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn exists(&self, _path: &Path) -> bool {
+        false
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write(&self, path: &Path, content: &str) -> Result<()> {
+        let resolved = self.resolve(path)?;
+        if let Some(parent) = resolved.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(fs::write(resolved, content)?)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn write(&self, path: &Path, _content: &str) -> Result<()> {
+        self.resolve(path)?;
+        Err(anyhow!("NativeStorage is not available under wasm32"))
+    }
+}
+
+/// Collapses `.`/`..` components lexically (no filesystem access), so a
+/// `..` can pop an already-joined path back out of `root` entirely.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                result.pop();
+            }
+            Component::CurDir => {}
+            other => result.push(other.as_os_str()),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_roundtrip_within_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = NativeStorage::new(temp_dir.path());
+
+        storage.write(Path::new("chapter1/segments.json"), "hello").unwrap();
+        let content = storage.read(Path::new("chapter1/segments.json")).unwrap();
+
+        assert_eq!(content, "hello");
+    }
+
+    #[test]
+    fn test_write_rejects_path_traversal_outside_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = NativeStorage::new(temp_dir.path());
+
+        let result = storage.write(Path::new("../../../etc/passwd"), "evil");
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Path traversal detected"));
+    }
+
+    #[test]
+    fn test_read_rejects_path_traversal_outside_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = NativeStorage::new(temp_dir.path());
+
+        let result = storage.read(Path::new("../../../etc/passwd"));
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Path traversal detected"));
+    }
+
+    #[test]
+    fn test_relative_project_paths_succeed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = NativeStorage::new(temp_dir.path());
+
+        assert!(storage.write(Path::new("state.json"), "{}").is_ok());
+        assert!(storage.write(Path::new("./character_map.json"), "{}").is_ok());
+    }
+}
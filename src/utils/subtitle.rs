@@ -0,0 +1,178 @@
+// SRT subtitle generation helpers.
+use crate::services::script::AudioSegment;
+use anyhow::Result;
+
+/// Computes `(original_segment_index, start_secs, end_secs)` for each of
+/// `indexed_audio_files`, back to back in order, using each file's exact
+/// duration. MP3 files are measured by parsing their frame headers
+/// (`utils::audio::mp3_duration_secs`); any file that isn't a parseable MP3
+/// (e.g. a WAV segment from a non-MP3 TTS provider) falls back to
+/// `utils::audio::audio_duration_secs`, which parses WAV headers exactly.
+///
+/// Carrying the original segment index (rather than just a duration list)
+/// keeps `generate_srt` correctly paired with `segments` even when
+/// `WorkflowConfig::continue_on_error` has dropped some segments from
+/// `indexed_audio_files` - the same pairing `workflow::build_segment_timings`
+/// already relies on.
+pub fn estimate_segment_timings(
+    indexed_audio_files: &[(usize, std::path::PathBuf)],
+) -> Result<Vec<(usize, f64, f64)>> {
+    let mut timings = Vec::with_capacity(indexed_audio_files.len());
+    let mut cursor = 0.0;
+
+    for (index, path) in indexed_audio_files {
+        let duration = crate::utils::audio::mp3_duration_secs(path)
+            .or_else(|_| crate::utils::audio::audio_duration_secs(path))?;
+        timings.push((*index, cursor, cursor + duration));
+        cursor += duration;
+    }
+
+    Ok(timings)
+}
+
+/// Formats seconds as an SRT timestamp: `HH:MM:SS,mmm`.
+fn format_timestamp(secs: f64) -> String {
+    let total_ms = (secs * 1000.0).round() as u64;
+    let ms = total_ms % 1000;
+    let total_secs = total_ms / 1000;
+    let s = total_secs % 60;
+    let m = (total_secs / 60) % 60;
+    let h = total_secs / 3600;
+    format!("{:02}:{:02}:{:02},{:03}", h, m, s, ms)
+}
+
+/// Builds a well-formed SRT subtitle string from `segments` and their
+/// `timings`, each tagged with the original `segments` index it belongs to
+/// (as produced by `estimate_segment_timings`). Looking a segment up by its
+/// own index, rather than zipping the two lists positionally, keeps text
+/// and timestamps correctly paired even when `timings` is shorter than
+/// `segments` because `WorkflowConfig::continue_on_error` dropped one or
+/// more non-trailing segments.
+pub fn generate_srt(segments: &[AudioSegment], timings: &[(usize, f64, f64)]) -> String {
+    let mut srt = String::new();
+
+    for (i, (index, start, end)) in timings.iter().enumerate() {
+        srt.push_str(&format!("{}\n", i + 1));
+        srt.push_str(&format!(
+            "{} --> {}\n",
+            format_timestamp(*start),
+            format_timestamp(*end)
+        ));
+        srt.push_str(&segments[*index].text);
+        srt.push_str("\n\n");
+    }
+
+    srt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_dummy_mp3(frame_count: usize) -> Vec<u8> {
+        const FRAME_SIZE: usize = 417; // MPEG1 Layer III, 128kbps, 44100Hz
+        let mut buf = Vec::new();
+        for _ in 0..frame_count {
+            buf.extend_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+            buf.resize(buf.len() + FRAME_SIZE - 4, 0);
+        }
+        buf
+    }
+
+    fn segment(text: &str) -> AudioSegment {
+        AudioSegment {
+            text: text.to_string(),
+            speaker: None,
+            style: None,
+            voice_id: None,
+            detected_language: None,
+            confidence: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0.0), "00:00:00,000");
+        assert_eq!(format_timestamp(61.5), "00:01:01,500");
+        assert_eq!(format_timestamp(3661.001), "01:01:01,001");
+    }
+
+    #[test]
+    fn test_generate_srt_well_formed() {
+        let segments = vec![segment("Hello there."), segment("General Kenobi.")];
+        let timings = vec![(0, 0.0, 1.5), (1, 1.5, 3.2)];
+
+        let srt = generate_srt(&segments, &timings);
+
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:01,500\nHello there.\n\n\
+             2\n00:00:01,500 --> 00:00:03,200\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_srt_skips_dropped_non_trailing_segment() {
+        let segments = vec![
+            segment("First."),
+            segment("Second, failed synthesis."),
+            segment("Third."),
+        ];
+        // Segment 1 was dropped by `continue_on_error`; only 0 and 2 survive.
+        let timings = vec![(0, 0.0, 1.0), (2, 1.0, 2.0)];
+
+        let srt = generate_srt(&segments, &timings);
+
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:01,000\nFirst.\n\n\
+             2\n00:00:01,000 --> 00:00:02,000\nThird.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_estimate_segment_timings_monotonically_increasing() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let paths = vec![
+            temp_dir.path().join("seg0.mp3"),
+            temp_dir.path().join("seg1.mp3"),
+            temp_dir.path().join("seg2.mp3"),
+        ];
+        std::fs::write(&paths[0], create_dummy_mp3(10))?;
+        std::fs::write(&paths[1], create_dummy_mp3(20))?;
+        std::fs::write(&paths[2], create_dummy_mp3(5))?;
+
+        let indexed_audio_files: Vec<(usize, std::path::PathBuf)> =
+            paths.into_iter().enumerate().collect();
+        let timings = estimate_segment_timings(&indexed_audio_files)?;
+
+        assert_eq!(timings.len(), 3);
+        let mut last_end = 0.0;
+        for (_index, start, end) in &timings {
+            assert!(*start >= last_end - f64::EPSILON);
+            assert!(*end > *start);
+            last_end = *end;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_segment_timings_preserves_original_indices_with_gaps() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path0 = temp_dir.path().join("seg0.mp3");
+        let path2 = temp_dir.path().join("seg2.mp3");
+        std::fs::write(&path0, create_dummy_mp3(10))?;
+        std::fs::write(&path2, create_dummy_mp3(5))?;
+
+        // Segment 1 was dropped by `continue_on_error`.
+        let indexed_audio_files = vec![(0, path0), (2, path2)];
+        let timings = estimate_segment_timings(&indexed_audio_files)?;
+
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].0, 0);
+        assert_eq!(timings[1].0, 2);
+
+        Ok(())
+    }
+}
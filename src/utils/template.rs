@@ -0,0 +1,56 @@
+// Output filename templating helpers, used by `OutputConfig::filename_template`.
+use std::collections::HashMap;
+
+/// Replaces every `{key}` placeholder in `tpl` with `vars[key]`, leaving
+/// placeholders with no matching key untouched. Doesn't sanitize the
+/// result; callers writing the output to disk should run it through
+/// `sanitize_filename` first.
+pub fn render_template(tpl: &str, vars: &HashMap<&str, &str>) -> String {
+    let mut rendered = tpl.to_string();
+    for (key, value) in vars {
+        rendered = rendered.replace(&format!("{{{}}}", key), value);
+    }
+    rendered
+}
+
+/// Replaces path-traversal-sensitive characters (`/`, `\`, `:`) with `-`, so
+/// a rendered template can't escape the output folder or be misread as a
+/// drive/volume prefix on Windows.
+pub fn sanitize_filename(name: &str) -> String {
+    name.replace(['/', '\\', ':'], "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template_substitutes_all_variables() {
+        let vars = HashMap::from([
+            ("stem", "chapter_001"),
+            ("ext", "mp3"),
+            ("index", "1"),
+            ("total", "12"),
+            ("title", "My Book"),
+        ]);
+
+        let rendered = render_template(
+            "{title} - {index}_{total} - {stem}.{ext}",
+            &vars,
+        );
+
+        assert_eq!(rendered, "My Book - 1_12 - chapter_001.mp3");
+    }
+
+    #[test]
+    fn test_render_template_leaves_unknown_placeholders_untouched() {
+        let vars = HashMap::from([("stem", "chapter_001")]);
+        assert_eq!(render_template("{stem}.{ext}", &vars), "chapter_001.{ext}");
+    }
+
+    #[test]
+    fn test_sanitize_filename_strips_dangerous_characters() {
+        assert_eq!(sanitize_filename("Vol1/../../etc-passwd"), "Vol1-..-..-etc-passwd");
+        assert_eq!(sanitize_filename(r"C:\evil\path"), "C--evil-path");
+    }
+}
@@ -0,0 +1,383 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Chinese script variant detected in a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZhVariant {
+    Traditional,
+    Simplified,
+    Unknown,
+}
+
+/// User-selected Chinese conversion target for `convert_zh_variant`, as
+/// opposed to `ZhVariant` above which is a *detected* script. Named
+/// differently from `ZhVariant` to avoid colliding with it: this enum's
+/// `None` means "don't convert", which has no equivalent in `ZhVariant`'s
+/// `Unknown` (a detection outcome, not a user choice).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZhConversionMode {
+    Simplified,
+    Traditional,
+    None,
+}
+
+/// Converts `text` to the requested Chinese script via `zhconv`, or returns
+/// it unchanged for `ZhConversionMode::None`. Used by `Qwen3TtsClient` in
+/// place of a hardcoded Simplified-Chinese conversion, so Traditional
+/// Chinese novels can be sent to TTS without being silently rewritten.
+pub fn convert_zh_variant(text: &str, variant: ZhConversionMode) -> String {
+    match variant {
+        ZhConversionMode::Simplified => zhconv::zhconv(text, zhconv::Variant::ZhCN),
+        ZhConversionMode::Traditional => zhconv::zhconv(text, zhconv::Variant::ZhTW),
+        ZhConversionMode::None => text.to_string(),
+    }
+}
+
+// A small sample of characters that only exist in one of the two scripts.
+// This is not exhaustive, but scanning a handful of common, unambiguous
+// characters is enough to classify real novel text.
+const TRADITIONAL_ONLY: &[char] = &[
+    '繁', '體', '說', '話', '這', '個', '們', '來', '時', '國', '會', '對', '沒', '種', '還',
+    '裡', '後', '讓', '過', '樣', '與', '實', '應', '開', '現', '關', '經',
+];
+const SIMPLIFIED_ONLY: &[char] = &[
+    '简', '体', '说', '话', '这', '个', '们', '来', '时', '国', '会', '对', '没', '种', '还',
+    '里', '后', '让', '过', '样', '与', '实', '应', '开', '现', '关', '经',
+];
+
+/// Scans up to the first 200 characters of `text` and decides whether it is
+/// written in Traditional or Simplified Chinese, based on characters that
+/// only appear in one of the two scripts.
+pub fn detect_zh_variant(text: &str) -> ZhVariant {
+    let traditional_set: HashSet<char> = TRADITIONAL_ONLY.iter().copied().collect();
+    let simplified_set: HashSet<char> = SIMPLIFIED_ONLY.iter().copied().collect();
+
+    let mut traditional_hits = 0usize;
+    let mut simplified_hits = 0usize;
+
+    for c in text.chars().take(200) {
+        if traditional_set.contains(&c) {
+            traditional_hits += 1;
+        } else if simplified_set.contains(&c) {
+            simplified_hits += 1;
+        }
+    }
+
+    match traditional_hits.cmp(&simplified_hits) {
+        std::cmp::Ordering::Greater => ZhVariant::Traditional,
+        std::cmp::Ordering::Less => ZhVariant::Simplified,
+        std::cmp::Ordering::Equal => ZhVariant::Unknown,
+    }
+}
+
+/// Classifies `text` as CJK or Latin-script by counting Unicode code points
+/// in the CJK Unified Ideographs / Hiragana / Katakana / Hangul blocks
+/// against ASCII letters, returning `"zh"`/`"en"` for whichever dominates
+/// (ties and punctuation-only/empty text return `None`, i.e. "same as the
+/// chapter's primary language"). Used by `process_chapter` to tag
+/// `AudioSegment::detected_language` for mixed-language chapters (e.g. an
+/// English quote inside a Chinese novel), so `AudioConfig::additional_languages`
+/// providers can pick a matching voice instead of the primary one.
+pub fn detect_script_language(text: &str) -> Option<String> {
+    let mut cjk_hits = 0usize;
+    let mut latin_hits = 0usize;
+
+    for c in text.chars() {
+        if c.is_ascii_alphabetic() {
+            latin_hits += 1;
+        } else if matches!(c,
+            '\u{4E00}'..='\u{9FFF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{3040}'..='\u{30FF}'
+            | '\u{AC00}'..='\u{D7A3}'
+        ) {
+            cjk_hits += 1;
+        }
+    }
+
+    match cjk_hits.cmp(&latin_hits) {
+        std::cmp::Ordering::Greater => Some("zh".to_string()),
+        std::cmp::Ordering::Less if latin_hits > 0 => Some("en".to_string()),
+        _ => None,
+    }
+}
+
+/// Escapes characters that are illegal inside SSML/XML text content, so
+/// dialogue containing `<`, `>`, `&`, `"`, or `'` doesn't produce invalid XML.
+/// Shared by the SSML-based TTS providers (`tts::edge`, `tts::azure`).
+pub fn escape_ssml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Replaces each key in `corrections` with its value in `text`, for
+/// plain-text TTS providers (GPT-SoVITS, Qwen3, Polly, ElevenLabs) that
+/// would otherwise mispronounce a character name. Keys are tried longest
+/// first at each position, so a correction for a longer phrase takes
+/// priority over one that happens to match a shorter substring of it.
+pub fn apply_phonetic_corrections(text: &str, corrections: &HashMap<String, String>) -> String {
+    if corrections.is_empty() {
+        return text.to_string();
+    }
+
+    let mut keys: Vec<&str> = corrections.keys().map(String::as_str).collect();
+    keys.sort_by_key(|k| std::cmp::Reverse(k.len()));
+
+    let mut result = String::new();
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        for key in &keys {
+            if let Some(stripped) = rest.strip_prefix(key) {
+                result.push_str(&corrections[*key]);
+                rest = stripped;
+                continue 'outer;
+            }
+        }
+        let mut chars = rest.chars();
+        result.push(chars.next().unwrap());
+        rest = chars.as_str();
+    }
+    result
+}
+
+/// Same matching rules as `apply_phonetic_corrections`, but for SSML-based
+/// providers: rather than substituting plain text, wraps each matched span
+/// in a `<phoneme alphabet="ipa" ph="...">` tag so the original characters
+/// are still shown while the TTS engine pronounces the corrected reading.
+/// Text outside of matches, and the correction text itself, are both
+/// escaped via `escape_ssml` so a correction value containing `<`/`&`
+/// can't break the surrounding SSML document.
+pub fn apply_phonetic_corrections_ssml(text: &str, corrections: &HashMap<String, String>) -> String {
+    if corrections.is_empty() {
+        return escape_ssml(text);
+    }
+
+    let mut keys: Vec<&str> = corrections.keys().map(String::as_str).collect();
+    keys.sort_by_key(|k| std::cmp::Reverse(k.len()));
+
+    let mut result = String::new();
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        for key in &keys {
+            if let Some(stripped) = rest.strip_prefix(key) {
+                result.push_str(&format!(
+                    "<phoneme alphabet=\"ipa\" ph=\"{}\">{}</phoneme>",
+                    escape_ssml(&corrections[*key]),
+                    escape_ssml(key)
+                ));
+                rest = stripped;
+                continue 'outer;
+            }
+        }
+        let mut chars = rest.chars();
+        result.push_str(&escape_ssml(&chars.next().unwrap().to_string()));
+        rest = chars.as_str();
+    }
+    result
+}
+
+/// Returns the entry in `existing_names` that `name` should be merged into,
+/// if any is within Levenshtein edit distance 2 (e.g. "英雄" vs "英雄男"),
+/// so that a chapter's character analysis re-identifying a known character
+/// under a slightly different spelling doesn't fork the character map.
+/// Returns `None` both when `name` already matches an existing entry
+/// exactly (no rename needed) and when nothing is close enough.
+pub fn find_fuzzy_character_match<'a>(
+    name: &str,
+    existing_names: &'a [String],
+) -> Option<&'a str> {
+    existing_names
+        .iter()
+        .find(|existing| existing.as_str() != name && edit_distance::edit_distance(name, existing) <= 2)
+        .map(String::as_str)
+}
+
+/// Strips a leading UTF-8 BOM (`EF BB BF`, decodes to U+FEFF) from `bytes`,
+/// if present. Only the UTF-8 form is checked: GBK/Big5 text read by chapter
+/// scrapers doesn't carry a BOM in practice.
+fn strip_utf8_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes)
+}
+
+/// Decodes `bytes` to a `String`, for chapter `.txt` files that aren't
+/// UTF-8 (common for scraped Chinese novels saved as GBK or Big5). Valid
+/// UTF-8 is returned as-is; otherwise this tries GBK and Big5 and keeps
+/// whichever produces fewer U+FFFD replacement characters, since
+/// `encoding_rs` substitutes rather than errors on an invalid byte
+/// sequence. Prefer `decode_bytes_with_encoding` when the caller knows the
+/// actual encoding (see `WorkflowConfig::input_encoding`).
+pub fn decode_bytes(bytes: &[u8]) -> Result<String> {
+    let bytes = strip_utf8_bom(bytes);
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok(text.to_string());
+    }
+
+    let best = [encoding_rs::GBK, encoding_rs::BIG5]
+        .iter()
+        .map(|encoding| {
+            let (decoded, _, _) = encoding.decode(bytes);
+            let replacement_count = decoded.chars().filter(|&c| c == '\u{FFFD}').count();
+            (replacement_count, decoded.into_owned())
+        })
+        .min_by_key(|(replacement_count, _)| *replacement_count)
+        .map(|(_, decoded)| decoded)
+        .unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned());
+
+    Ok(best)
+}
+
+/// Mirror of `decode_bytes` that honors an explicit encoding label (e.g.
+/// `"gbk"`, `"big5"`) instead of auto-detecting, for a source collection
+/// known to use one consistent legacy encoding. `None` falls back to
+/// `decode_bytes`'s auto-detection.
+pub fn decode_bytes_with_encoding(bytes: &[u8], encoding: Option<&str>) -> Result<String> {
+    let Some(label) = encoding else {
+        return decode_bytes(bytes);
+    };
+
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| anyhow!("Unknown input_encoding: {}", label))?;
+    let (decoded, _, _) = encoding.decode(strip_utf8_bom(bytes));
+    Ok(decoded.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_traditional() {
+        let text = "這個故事發生在一個沒有國家的世界裡，他們還是這樣說話。";
+        assert_eq!(detect_zh_variant(text), ZhVariant::Traditional);
+    }
+
+    #[test]
+    fn test_detect_simplified() {
+        let text = "这个故事发生在一个没有国家的世界里，他们还是这样说话。";
+        assert_eq!(detect_zh_variant(text), ZhVariant::Simplified);
+    }
+
+    #[test]
+    fn test_detect_unknown_for_ascii() {
+        assert_eq!(detect_zh_variant("Hello world"), ZhVariant::Unknown);
+    }
+
+    #[test]
+    fn test_convert_zh_variant_none_leaves_traditional_text_unchanged() {
+        let text = "這個故事發生在一個沒有國家的世界裡。";
+        assert_eq!(convert_zh_variant(text, ZhConversionMode::None), text);
+    }
+
+    #[test]
+    fn test_convert_zh_variant_simplified_converts_traditional_characters() {
+        assert_eq!(convert_zh_variant("國", ZhConversionMode::Simplified), "国");
+    }
+
+    #[test]
+    fn test_escape_ssml_leaves_chinese_text_unchanged() {
+        let text = "他說：「這真是太好了！」她笑著回答。";
+        assert_eq!(escape_ssml(text), text);
+    }
+
+    #[test]
+    fn test_apply_phonetic_corrections_is_a_no_op_with_no_corrections() {
+        let corrections = HashMap::new();
+        assert_eq!(apply_phonetic_corrections("長公主殿下", &corrections), "長公主殿下");
+    }
+
+    #[test]
+    fn test_apply_phonetic_corrections_prefers_longer_overlapping_match() {
+        let mut corrections = HashMap::new();
+        corrections.insert("長".to_string(), "chang2".to_string());
+        corrections.insert("長公主".to_string(), "zhang3 gong1 zhu3".to_string());
+
+        assert_eq!(
+            apply_phonetic_corrections("長公主駕到，長老請起", &corrections),
+            "zhang3 gong1 zhu3駕到，chang2老請起"
+        );
+    }
+
+    #[test]
+    fn test_apply_phonetic_corrections_ssml_wraps_phoneme_and_escapes() {
+        let mut corrections = HashMap::new();
+        corrections.insert("長".to_string(), "zhang3".to_string());
+
+        assert_eq!(
+            apply_phonetic_corrections_ssml("她說<長>很奇怪", &corrections),
+            "她說&lt;<phoneme alphabet=\"ipa\" ph=\"zhang3\">長</phoneme>&gt;很奇怪"
+        );
+    }
+
+    #[test]
+    fn test_apply_phonetic_corrections_ssml_escapes_with_no_corrections() {
+        let corrections = HashMap::new();
+        assert_eq!(
+            apply_phonetic_corrections_ssml("<a> & \"b\"", &corrections),
+            "&lt;a&gt; &amp; &quot;b&quot;"
+        );
+    }
+
+    #[test]
+    fn test_find_fuzzy_character_match_merges_near_duplicate_name() {
+        let existing_names = vec!["英雄男".to_string(), "路人".to_string()];
+        assert_eq!(
+            find_fuzzy_character_match("英雄", &existing_names),
+            Some("英雄男")
+        );
+    }
+
+    #[test]
+    fn test_find_fuzzy_character_match_returns_none_for_exact_match() {
+        let existing_names = vec!["英雄".to_string()];
+        assert_eq!(find_fuzzy_character_match("英雄", &existing_names), None);
+    }
+
+    #[test]
+    fn test_find_fuzzy_character_match_returns_none_when_too_different() {
+        let existing_names = vec!["長公主".to_string()];
+        assert_eq!(find_fuzzy_character_match("路人甲", &existing_names), None);
+    }
+
+    #[test]
+    fn test_decode_bytes_passes_through_valid_utf8() -> Result<()> {
+        assert_eq!(decode_bytes("你好世界".as_bytes())?, "你好世界");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bytes_strips_utf8_bom() -> Result<()> {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice("你好".as_bytes());
+        assert_eq!(decode_bytes(&bytes)?, "你好");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bytes_auto_detects_gbk() -> Result<()> {
+        let (gbk_bytes, _, had_errors) = encoding_rs::GBK.encode("你好世界");
+        assert!(!had_errors);
+        assert_eq!(decode_bytes(&gbk_bytes)?, "你好世界");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bytes_with_encoding_forces_big5() -> Result<()> {
+        let (big5_bytes, _, had_errors) = encoding_rs::BIG5.encode("你好世界");
+        assert!(!had_errors);
+        assert_eq!(
+            decode_bytes_with_encoding(&big5_bytes, Some("big5"))?,
+            "你好世界"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bytes_with_encoding_rejects_unknown_label() {
+        assert!(decode_bytes_with_encoding(b"hi", Some("not-a-real-encoding")).is_err());
+    }
+}